@@ -0,0 +1,93 @@
+//! Command logs record the commands sent to a stroker, one JSON object per line, e.g.
+//!
+//! ```text
+//! {"time_ms":0,"command":"movement","axis_kind":"stroke","target":0.5,"ramp_time_milliseconds":250}
+//! {"time_ms":250,"command":"stop"}
+//! ```
+
+use std::path::Path;
+
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use strokers_core::AxisKind;
+
+/// One line of a command log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoggedCommand {
+    /// When the command was sent, in milliseconds since the start of the log.
+    pub time_ms: u64,
+
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// A command sent to a stroker.
+///
+/// Axes are identified by their kind rather than their [`strokers_core::AxisId`],
+/// so that logs can be replayed on a different stroker.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    Movement {
+        axis_kind: AxisKind,
+        target: f32,
+        ramp_time_milliseconds: u32,
+    },
+    Stop,
+}
+
+/// Parse the text of a command log.
+///
+/// Blank lines are ignored.
+pub fn parse_command_log(text: &str) -> eyre::Result<Vec<LoggedCommand>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line).with_context(|| format!("bad command on line {}", idx + 1))
+        })
+        .collect()
+}
+
+/// Read and parse the command log at the given path.
+pub async fn read_command_log(path: &Path) -> eyre::Result<Vec<LoggedCommand>> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("failed to read {path:?}"))?;
+    parse_command_log(&text)
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::AxisKind;
+
+    use super::{parse_command_log, Command, LoggedCommand};
+
+    #[test]
+    fn test_parse_command_log() {
+        let text = r#"{"time_ms":0,"command":"movement","axis_kind":"stroke","target":0.5,"ramp_time_milliseconds":250}
+
+{"time_ms":250,"command":"stop"}
+"#;
+        assert_eq!(
+            parse_command_log(text).unwrap(),
+            vec![
+                LoggedCommand {
+                    time_ms: 0,
+                    command: Command::Movement {
+                        axis_kind: AxisKind::Stroke,
+                        target: 0.5,
+                        ramp_time_milliseconds: 250
+                    }
+                },
+                LoggedCommand {
+                    time_ms: 250,
+                    command: Command::Stop
+                }
+            ]
+        );
+
+        let err = parse_command_log("{\"time_ms\":0,\"command\":\"stop\"}\n{}").unwrap_err();
+        assert_eq!(err.to_string(), "bad command on line 2");
+    }
+}
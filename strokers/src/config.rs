@@ -1,12 +1,51 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
 
 use serde::{Deserialize, Serialize};
 use strokers_core::AxisKind;
+use strokers_device_debug::Verbosity;
+use strokers_funscript::{generate::Pattern, processing::Easing};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RootConfig {
     pub stroker: StrokerConfig,
     pub limits: BTreeMap<AxisKind, LimitsConfig>,
+    #[serde(default)]
+    pub playback: PlaybackConfig,
+    /// Named sets of limits to switch between, e.g. `[presets.gentle.stroke]`.
+    #[serde(default)]
+    pub presets: BTreeMap<String, PresetConfig>,
+    #[serde(default)]
+    pub heatmap: HeatmapConfig,
+    #[serde(default)]
+    pub fallback: FallbackConfig,
+    #[serde(default)]
+    pub pause: PauseConfig,
+}
+
+/// Limits for some axes, to switch to all at once. Axes left out keep their limits.
+pub type PresetConfig = BTreeMap<AxisKind, PresetLimitsConfig>;
+
+/// The limits of an axis in a preset. Any left out keep their current values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetLimitsConfig {
+    /// Speed limit in full-scales per second
+    #[serde(default)]
+    pub speed: Option<f32>,
+
+    /// Minimum limit of the axis.
+    #[serde(default)]
+    pub min: Option<f32>,
+
+    /// Maximum limit of the axis.
+    #[serde(default)]
+    pub max: Option<f32>,
+
+    /// Whether to turn the axis's motion upside down.
+    #[serde(default)]
+    pub invert: Option<bool>,
 }
 
 /// Specify how to connect to the stroker.
@@ -29,9 +68,129 @@ pub enum StrokerConfig {
         baud: u32,
     },
 
+    /// Send [T-Code] commands as UDP datagrams, one command per datagram.
+    /// Some WiFi-enabled firmwares support this for minimal latency.
+    ///
+    /// [T-Code]: https://github.com/multiaxis/TCode-Specification
+    #[serde(rename = "tcode_udp")]
+    TCodeUdp {
+        /// The hostname or IP address of the T-Code device.
+        host: String,
+
+        /// The UDP port the T-Code device listens on.
+        port: u16,
+
+        /// The T-Code names of the device's axes, e.g. `["L0", "R0"]`.
+        /// If empty (the default), the axes of an OSR2/SR6-style device are assumed.
+        #[serde(default)]
+        axes: Vec<String>,
+
+        /// How many times to send each command, to mitigate packet loss.
+        /// Defaults to 1.
+        #[serde(default = "default_tcode_udp_repeat")]
+        repeat: u32,
+
+        /// Whether to ask the device to identify itself and describe its axes.
+        /// Responses over UDP are unreliable, so this defaults to false.
+        #[serde(default)]
+        handshake: bool,
+    },
+
+    /// Connect to a Fleshlight Launch or Kiiroo Onyx over Bluetooth LE.
+    /// Requires the `kiiroo` feature.
+    #[serde(rename = "kiiroo_launch")]
+    KiirooLaunch {
+        /// The name the device advertises itself with, e.g. `Launch`.
+        device_name: String,
+    },
+
+    /// Combine several strokers into one, with each axis driven by one of them.
+    #[serde(rename = "composite")]
+    Composite {
+        /// The strokers to combine, by name.
+        devices: BTreeMap<String, StrokerConfig>,
+
+        /// Which device (by name) drives each kind of axis.
+        /// Only needed for kinds of axis that more than one device has.
+        #[serde(default)]
+        routes: BTreeMap<AxisKind, String>,
+    },
+
+    /// Wrap another stroker along with a command log that can be replayed on it,
+    /// with the original timing between commands.
+    #[serde(rename = "replay")]
+    Replay {
+        /// The path to the command log (JSON lines).
+        path: PathBuf,
+
+        /// The stroker to replay the commands on.
+        inner: Box<StrokerConfig>,
+    },
+
+    /// Don't connect to a stroker, but check every command against the axes and limits,
+    /// logging any problems found.
+    /// Useful for validating funscripts without a device.
+    #[serde(rename = "dry_run")]
+    DryRun {
+        /// The kinds of axes that the pretend stroker has.
+        /// Defaults to those of an OSR2/SR6-style device.
+        #[serde(default = "default_pretend_axes")]
+        axes: Vec<AxisKind>,
+    },
+
+    /// Wrap another stroker whose vibration (or similar) axes can only be switched on or off,
+    /// pulsing them on and off so that the proportion of time spent on matches the requested intensity.
+    #[serde(rename = "pulsed")]
+    Pulsed {
+        /// The stroker to wrap.
+        inner: Box<StrokerConfig>,
+
+        /// The kinds of axis to pulse.
+        /// Defaults to just `vibration`.
+        #[serde(default = "default_pulsed_axes")]
+        axes: BTreeSet<AxisKind>,
+
+        /// The length of one on/off cycle, in milliseconds.
+        /// Defaults to 200.
+        #[serde(default = "default_pulsed_period_ms")]
+        period_ms: u64,
+    },
+
+    /// Don't connect to a stroker, but write every command as a line of JSON,
+    /// for other programs to act on.
+    /// See [`crate::command_log`] for the format.
+    #[serde(rename = "json_lines")]
+    JsonLines {
+        /// The file or named pipe to append the lines to.
+        /// If not specified, lines are written to standard output.
+        #[serde(default)]
+        path: Option<PathBuf>,
+
+        /// The kinds of axes that the pretend stroker has.
+        /// Defaults to those of an OSR2/SR6-style device.
+        #[serde(default = "default_pretend_axes")]
+        axes: Vec<AxisKind>,
+    },
+
     /// Don't connect to a stroker, just emit debug information to the log.
     #[serde(rename = "debug")]
-    Debug,
+    Debug {
+        /// The kinds of axes that the pretend stroker has.
+        /// Defaults to those of an OSR2/SR6-style device.
+        #[serde(default = "default_pretend_axes")]
+        axes: Vec<AxisKind>,
+
+        /// Whether to log every command (`every_command`, the default)
+        /// or only a summary every few seconds (`summary`).
+        #[serde(default)]
+        verbosity: Verbosity,
+
+        /// How long each command takes, in milliseconds,
+        /// to roughly simulate the latency of a real device.
+        /// Defaults to 0.
+        #[serde(default)]
+        command_delay_ms: u64,
+    },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -46,8 +205,579 @@ pub struct LimitsConfig {
     /// Default maximum limit of the axis.
     /// Note that this can often be controlled dynamically later on.
     pub default_max: f32,
+
+    /// Whether to turn the axis's motion upside down, e.g. for a stroker mounted the other way up.
+    /// (Setting `default_min` above `default_max` used to be the way to do this, and still works.)
+    #[serde(default)]
+    pub invert: bool,
+
+    /// How the stroker's firmware eases each movement along this axis, used to work out
+    /// where it is part-way through one (which the speed limit is worked out from).
+    /// Defaults to linear.
+    #[serde(default)]
+    pub device_easing: Easing,
+
+    /// If set, once the axis hasn't been sent anywhere for this many milliseconds whilst the video
+    /// plays (e.g. in a long gap in the funscript, or after its last action), it moves slowly
+    /// to the middle of its limits to rest, rather than staying wherever the last stroke ended.
+    #[serde(default)]
+    pub idle_timeout_ms: Option<u32>,
+}
+
+/// How funscripts are found and played back.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaybackConfig {
+    /// If set, add actions at this fixed interval (in milliseconds) between the actions
+    /// of each funscript, so that sparse funscripts don't become long, lifeless ramps.
+    #[serde(default)]
+    pub resample_interval_ms: Option<u32>,
+
+    /// How positions are interpolated when resampling.
+    /// Defaults to linear.
+    #[serde(default)]
+    pub resample_easing: Easing,
+
+    /// Subdirectories of the video's directory to also look for funscripts in,
+    /// in order of preference.
+    /// Funscripts beside the video are preferred over those in these subdirectories.
+    /// Defaults to `funscripts` and `scripts`.
+    #[serde(default = "default_funscript_subdirectories")]
+    pub funscript_subdirectories: Vec<String>,
+
+    /// Axes to make up motion for from the stroke funscript, when the video has no funscript
+    /// for them, e.g. `["roll", "twist"]`.
+    /// See [`strokers_funscript::processing::derive_secondary`]. None by default.
+    #[serde(default)]
+    pub synthesize_axes: Vec<AxisKind>,
+
+    /// Whether to log each movement alongside the action in the funscript file it came from,
+    /// before normalising and limiting, to debug motion that doesn't match the script.
+    /// Costs some memory per action, so off by default.
+    #[serde(default)]
+    pub trace_raw_positions: bool,
+
+    /// Whether to also look for funscripts named a little differently to the video
+    /// (e.g. `My Video.funscript` for `My.Video.2160p.h265.mkv`) when none match its name.
+    /// See [`strokers_funscript::search_path::scan_for_funscripts_fuzzy`]. Off by default.
+    #[serde(default)]
+    pub fuzzy_funscript_matching: bool,
+
+    /// Directories of funscripts kept apart from the videos, e.g. `["~/Scripts"]`,
+    /// searched (with their subdirectories) when no funscripts are found beside the video.
+    /// None by default.
+    #[serde(default)]
+    pub script_library_dirs: Vec<String>,
+
+    /// Whether to keep the sync offset (set with the `sync_offset` keybinding) when a new
+    /// video starts, rather than going back to no offset. Off by default.
+    #[serde(default)]
+    pub keep_sync_offset: bool,
+
+    /// Whether to leave the stroker alone for videos without funscripts:
+    /// it is stopped once when such a video starts, and then left be
+    /// (e.g. not stopped again on pause) until funscripts load. Off by default.
+    #[serde(default)]
+    pub require_funscript: bool,
+
+    /// If set, instead of moving once per action, move this many times a second
+    /// to where the funscript is at each moment, for smoother motion on sparse funscripts.
+    /// Slowed down to suit devices that can't take commands so often. Off by default.
+    #[serde(default)]
+    pub interpolation_hz: Option<f32>,
+
+    /// If set, a file (e.g. `~/.local/state/strokers/video_state.json`) to remember
+    /// the limits, sync offset and funscript cluster that were chosen for each video in,
+    /// to go back to when the video is played again. Off by default.
+    #[serde(default)]
+    pub video_state_file: Option<String>,
+
+    /// How many milliseconds ahead of the video to play every funscript, to make up for
+    /// how long the stroker takes to carry out movements. The `calibrate` keybinding helps
+    /// find this, and with `video_state_file` the latency found is used instead. 0 by default.
+    #[serde(default)]
+    pub latency_ms: i32,
+
+    /// Whether to watch the funscript files in use and reload them as they're saved,
+    /// e.g. whilst editing them alongside the video. Off by default.
+    #[serde(default)]
+    pub watch_funscripts: bool,
+
+    /// The only axes to drive, e.g. to leave a valve or suction axis alone whatever funscripts
+    /// there are for it. Funscripts for the others are ignored and they're never moved.
+    /// All of the stroker's axes by default.
+    #[serde(default)]
+    pub driven_axes: Option<Vec<AxisKind>>,
+
+    /// If set, whilst the video plays slower than this (e.g. 0.75 for 75% of normal speed),
+    /// the funscripts are interpolated as with `interpolation_hz` (at 20 Hz, unless that's set),
+    /// as otherwise the actions are so far apart in real time that each movement becomes
+    /// a long, even ramp and the rhythm is lost. Off by default.
+    #[serde(default)]
+    pub interpolate_below_speed: Option<f32>,
+
+    /// If set, how often (in milliseconds) to send the state shown on the status page as JSON
+    /// to other scripts, as `script-message strokers-status <json>`, e.g. for an overlay in OBS.
+    /// Otherwise it's only sent when asked for. Off by default.
+    #[serde(default)]
+    pub status_broadcast_interval_ms: Option<u32>,
+}
+
+/// How often to interpolate below `interpolate_below_speed`, if `interpolation_hz` isn't set.
+const SLOW_INTERPOLATION_HZ: f32 = 20.0;
+
+impl PlaybackConfig {
+    /// The paths of [`Self::script_library_dirs`], with a leading `~` standing for the home directory.
+    pub fn script_library_paths(&self) -> Vec<PathBuf> {
+        self.script_library_dirs
+            .iter()
+            .map(|dir| expand_home(dir, dirs::home_dir()))
+            .collect()
+    }
+
+    /// Whether the axis is one to drive, as [`Self::driven_axes`].
+    pub fn drives(&self, axis_kind: AxisKind) -> bool {
+        self.driven_axes
+            .as_ref()
+            .map_or(true, |driven_axes| driven_axes.contains(&axis_kind))
+    }
+
+    /// How often to interpolate the funscripts whilst the video plays at `speed`
+    /// (as a multiple of normal speed): always at [`Self::interpolation_hz`] if set,
+    /// otherwise only below [`Self::interpolate_below_speed`].
+    pub fn interpolation_hz_at(&self, speed: f64) -> Option<f32> {
+        self.interpolation_hz.or_else(|| {
+            self.interpolate_below_speed
+                .filter(|&below_speed| speed < below_speed as f64)
+                .map(|_| SLOW_INTERPOLATION_HZ)
+        })
+    }
+
+    /// The path of [`Self::video_state_file`], with a leading `~` standing for the home directory.
+    pub fn video_state_path(&self) -> Option<PathBuf> {
+        self.video_state_file
+            .as_deref()
+            .map(|file| expand_home(file, dirs::home_dir()))
+    }
+}
+
+/// Expands a leading `~` (on its own or followed by a separator) to `home`, if there is one.
+fn expand_home(path: &str, home: Option<PathBuf>) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with(['/', '\\']) => &rest[1..],
+        _ => return PathBuf::from(path),
+    };
+    match home {
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+impl Default for PlaybackConfig {
+    fn default() -> Self {
+        PlaybackConfig {
+            resample_interval_ms: None,
+            resample_easing: Easing::default(),
+            funscript_subdirectories: default_funscript_subdirectories(),
+            synthesize_axes: Vec::new(),
+            trace_raw_positions: false,
+            fuzzy_funscript_matching: false,
+            script_library_dirs: Vec::new(),
+            keep_sync_offset: false,
+            require_funscript: false,
+            interpolation_hz: None,
+            video_state_file: None,
+            latency_ms: 0,
+            watch_funscripts: false,
+            driven_axes: None,
+            interpolate_below_speed: None,
+            status_broadcast_interval_ms: None,
+        }
+    }
 }
 
 fn default_tcode_baud_rate() -> u32 {
     115200
 }
+
+fn default_tcode_udp_repeat() -> u32 {
+    1
+}
+
+fn default_pretend_axes() -> Vec<AxisKind> {
+    vec![
+        AxisKind::Stroke,
+        AxisKind::Surge,
+        AxisKind::Sway,
+        AxisKind::Twist,
+        AxisKind::Roll,
+        AxisKind::Pitch,
+    ]
+}
+
+fn default_pulsed_axes() -> BTreeSet<AxisKind> {
+    BTreeSet::from([AxisKind::Vibration])
+}
+
+fn default_pulsed_period_ms() -> u64 {
+    200
+}
+
+/// How the heatmap overlay (shown with the `toggle_heatmap` keybinding) is laid out.
+///
+/// Positions and sizes are on a 1280×720 canvas, scaled to fit the video window.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HeatmapConfig {
+    /// How many slices of the video the intensity is shown for.
+    /// 100 by default.
+    #[serde(default = "default_heatmap_bucket_count")]
+    pub bucket_count: usize,
+
+    /// Distance of the left of the bar from the left of the canvas.
+    /// 40 by default.
+    #[serde(default = "default_heatmap_x")]
+    pub x: u32,
+
+    /// Distance of the top of the bar from the top of the canvas.
+    /// 660 by default, near the bottom.
+    #[serde(default = "default_heatmap_y")]
+    pub y: u32,
+
+    /// Width of the bar. 1200 by default.
+    #[serde(default = "default_heatmap_width")]
+    pub width: u32,
+
+    /// Height of the bar, which the most intense slices fill. 30 by default.
+    #[serde(default = "default_heatmap_height")]
+    pub height: u32,
+}
+
+impl Default for HeatmapConfig {
+    fn default() -> Self {
+        HeatmapConfig {
+            bucket_count: default_heatmap_bucket_count(),
+            x: default_heatmap_x(),
+            y: default_heatmap_y(),
+            width: default_heatmap_width(),
+            height: default_heatmap_height(),
+        }
+    }
+}
+
+fn default_heatmap_bucket_count() -> usize {
+    100
+}
+
+fn default_heatmap_x() -> u32 {
+    40
+}
+
+fn default_heatmap_y() -> u32 {
+    660
+}
+
+fn default_heatmap_width() -> u32 {
+    1200
+}
+
+fn default_heatmap_height() -> u32 {
+    30
+}
+
+/// Motion made up for videos that no funscripts are found for.
+///
+/// It follows the clock rather than the video, but stops whilst the video is paused.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FallbackConfig {
+    /// Whether to make up motion at all. Off by default.
+    /// Can also be switched on and off with the `toggle_fallback` keybinding.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The pattern to move in: `sine`, `triangle`, `sawtooth` or `random_walk`.
+    /// `sine` by default.
+    #[serde(default)]
+    pub pattern: Pattern,
+
+    /// How long one cycle of the pattern takes, in milliseconds.
+    /// 2000 by default. Can be changed whilst playing with the `fallback_period` keybinding.
+    #[serde(default = "default_fallback_period_ms")]
+    pub period_ms: u32,
+
+    /// Lowest position of the pattern, within the stroke axis's limits. 0.0 by default.
+    #[serde(default)]
+    pub min: f32,
+
+    /// Highest position of the pattern, within the stroke axis's limits. 1.0 by default.
+    #[serde(default = "default_fallback_max")]
+    pub max: f32,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        FallbackConfig {
+            enabled: false,
+            pattern: Pattern::default(),
+            period_ms: default_fallback_period_ms(),
+            min: 0.0,
+            max: default_fallback_max(),
+        }
+    }
+}
+
+fn default_fallback_period_ms() -> u32 {
+    2000
+}
+
+fn default_fallback_max() -> f32 {
+    1.0
+}
+
+/// What to do with the stroker whilst the video is paused, besides stopping it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PauseConfig {
+    /// Where to move the axes to after stopping: `"none"` to leave them where they stopped,
+    /// `"neutral"` for the middle of their limits, or e.g. `{ position = 0.2, over_ms = 1500 }`.
+    /// `"none"` by default.
+    #[serde(default)]
+    pub park: ParkConfig,
+
+    /// The axes to move to the park position. Just the stroke axis by default.
+    #[serde(default = "default_park_axes")]
+    pub axes: Vec<AxisKind>,
+
+    /// Whether the axes follow the video as it's stepped through frame by frame whilst paused,
+    /// moving gently into place as after a seek. Off by default.
+    #[serde(default)]
+    pub track_while_paused: bool,
+}
+
+impl Default for PauseConfig {
+    fn default() -> Self {
+        PauseConfig {
+            park: ParkConfig::default(),
+            axes: default_park_axes(),
+            track_while_paused: false,
+        }
+    }
+}
+
+/// Where to move axes to whilst the video is paused.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParkConfig {
+    /// `"none"` or `"neutral"`
+    Named(ParkName),
+    /// A position within the axis's limits (0.0 for the bottom to 1.0 for the top),
+    /// moved to over the given time, 1500 ms by default.
+    Position {
+        position: f32,
+        #[serde(default = "default_park_over_ms")]
+        over_ms: u32,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParkName {
+    /// Leave the axes where they stopped
+    None,
+    /// The middle of the axes' limits
+    Neutral,
+}
+
+impl Default for ParkConfig {
+    fn default() -> Self {
+        ParkConfig::Named(ParkName::None)
+    }
+}
+
+impl ParkConfig {
+    /// The position to park at and how long to take getting there, if parking at all.
+    pub fn target(&self) -> Option<(f32, u32)> {
+        match *self {
+            ParkConfig::Named(ParkName::None) => None,
+            ParkConfig::Named(ParkName::Neutral) => Some((0.5, default_park_over_ms())),
+            ParkConfig::Position { position, over_ms } => Some((position.clamp(0.0, 1.0), over_ms)),
+        }
+    }
+}
+
+fn default_park_axes() -> Vec<AxisKind> {
+    vec![AxisKind::Stroke]
+}
+
+fn default_park_over_ms() -> u32 {
+    1500
+}
+
+fn default_funscript_subdirectories() -> Vec<String> {
+    vec!["funscripts".to_owned(), "scripts".to_owned()]
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::AxisKind;
+    use strokers_device_debug::Verbosity;
+    use strokers_funscript::generate::Pattern;
+
+    use std::path::PathBuf;
+
+    use super::{expand_home, ParkConfig, PauseConfig, PlaybackConfig, RootConfig, StrokerConfig};
+
+    #[test]
+    fn test_bare_debug_config() {
+        let config: StrokerConfig = toml::from_str(r#"type = "debug""#).unwrap();
+        let StrokerConfig::Debug {
+            axes,
+            verbosity,
+            command_delay_ms,
+        } = config
+        else {
+            panic!("not a debug config: {config:?}");
+        };
+        assert_eq!(axes.len(), 6);
+        assert_eq!(axes[0], AxisKind::Stroke);
+        assert_eq!(verbosity, Verbosity::EveryCommand);
+        assert_eq!(command_delay_ms, 0);
+    }
+
+    #[test]
+    fn test_script_library_dirs() {
+        let config: PlaybackConfig =
+            toml::from_str(r#"script_library_dirs = ["~/Scripts", "/mnt/scripts"]"#).unwrap();
+        assert_eq!(config.script_library_paths().len(), 2);
+        assert_eq!(
+            config.script_library_paths()[1],
+            PathBuf::from("/mnt/scripts")
+        );
+
+        let home = Some(PathBuf::from("/home/me"));
+        assert_eq!(
+            expand_home("~/Scripts", home.clone()),
+            PathBuf::from("/home/me/Scripts")
+        );
+        assert_eq!(expand_home("~", home.clone()), PathBuf::from("/home/me"));
+        // Only the current user's home directory
+        assert_eq!(
+            expand_home("~other/Scripts", home.clone()),
+            PathBuf::from("~other/Scripts")
+        );
+        assert_eq!(expand_home("~/Scripts", None), PathBuf::from("~/Scripts"));
+    }
+
+    #[test]
+    fn test_driven_axes() {
+        let config = PlaybackConfig::default();
+        assert!(config.drives(AxisKind::Valve));
+
+        let config: PlaybackConfig =
+            toml::from_str(r#"driven_axes = ["stroke", "twist", "roll"]"#).unwrap();
+        assert!(config.drives(AxisKind::Twist));
+        assert!(!config.drives(AxisKind::Valve));
+        assert!(!config.drives(AxisKind::Suction));
+    }
+
+    #[test]
+    fn test_interpolation_hz_at() {
+        let config = PlaybackConfig::default();
+        assert_eq!(config.interpolation_hz_at(0.25), None);
+
+        let config: PlaybackConfig = toml::from_str("interpolate_below_speed = 0.75").unwrap();
+        assert_eq!(config.interpolation_hz_at(0.5), Some(20.0));
+        assert_eq!(config.interpolation_hz_at(0.75), None);
+        assert_eq!(config.interpolation_hz_at(1.0), None);
+
+        // Interpolating all the time anyway
+        let config: PlaybackConfig =
+            toml::from_str("interpolation_hz = 30\ninterpolate_below_speed = 0.75").unwrap();
+        assert_eq!(config.interpolation_hz_at(0.5), Some(30.0));
+        assert_eq!(config.interpolation_hz_at(1.0), Some(30.0));
+    }
+
+    #[test]
+    fn test_presets() {
+        let config: RootConfig = toml::from_str(
+            r#"
+            [stroker]
+            type = "debug"
+
+            [limits.stroke]
+            speed = 0.5
+            default_min = 0.45
+            default_max = 0.55
+
+            [presets.gentle.stroke]
+            speed = 0.5
+            min = 0.3
+            max = 0.7
+
+            [presets.full.stroke]
+            min = 0.0
+            max = 1.0
+
+            [presets.full.twist]
+            speed = 2.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.presets.keys().collect::<Vec<_>>(),
+            vec!["full", "gentle"]
+        );
+        let full = &config.presets["full"];
+        assert_eq!(
+            (full[&AxisKind::Stroke].min, full[&AxisKind::Stroke].speed),
+            (Some(0.0), None)
+        );
+        assert_eq!(full[&AxisKind::Twist].speed, Some(2.0));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let config: RootConfig = toml::from_str(
+            r#"
+            [stroker]
+            type = "debug"
+
+            [limits.stroke]
+            speed = 0.5
+            default_min = 0.45
+            default_max = 0.55
+
+            [fallback]
+            enabled = true
+            pattern = "triangle"
+            "#,
+        )
+        .unwrap();
+        assert!(config.fallback.enabled);
+        assert_eq!(config.fallback.pattern, Pattern::Triangle);
+        assert_eq!(
+            (
+                config.fallback.period_ms,
+                config.fallback.min,
+                config.fallback.max
+            ),
+            (2000, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_pause_park() {
+        let park = |toml: &str| toml::from_str::<PauseConfig>(toml).unwrap().park.target();
+        assert_eq!(park(""), None);
+        assert_eq!(park(r#"park = "none""#), None);
+        assert_eq!(park(r#"park = "neutral""#), Some((0.5, 1500)));
+        assert_eq!(
+            park("park = { position = 0.2, over_ms = 3000 }"),
+            Some((0.2, 3000))
+        );
+        assert_eq!(park("park = { position = 1.5 }"), Some((1.0, 1500)));
+        assert!(toml::from_str::<PauseConfig>(r#"park = "somewhere""#).is_err());
+
+        let config: PauseConfig = toml::from_str(r#"axes = ["stroke", "pitch"]"#).unwrap();
+        assert_eq!(config.axes, vec![AxisKind::Stroke, AxisKind::Pitch]);
+        assert_eq!(PauseConfig::default().park, ParkConfig::default());
+    }
+}
@@ -0,0 +1,291 @@
+//! Saving changes back into the configuration file, keeping its comments and layout.
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::Permissions,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use strokers_core::AxisKind;
+use toml_edit::{DocumentMut, Item, Table, TableLike, Value};
+
+use crate::{config::LimitsConfig, StrokersError};
+
+/// Saves the speed, default minimum and maximum and inversion of each of the given axes into
+/// the `limits` section of the configuration file at `path`, adding any axes it's missing.
+/// Other axes, and everything else in the file (including comments), are left as they were.
+pub async fn save_limits(
+    path: &Path,
+    limits: &BTreeMap<AxisKind, LimitsConfig>,
+) -> Result<(), StrokersError> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .map_err(StrokersError::IoError)?;
+    let mut doc: DocumentMut = text
+        .parse()
+        .map_err(|err| StrokersError::ConfigEditError(err, path.to_owned()))?;
+    set_limits(&mut doc, limits)?;
+
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .map_err(StrokersError::IoError)?;
+    if metadata.permissions().readonly() {
+        return Err(StrokersError::ReadOnlyConfig(path.to_owned()));
+    }
+    // The file itself is replaced, rather than whatever link leads to it
+    let target = tokio::fs::canonicalize(path)
+        .await
+        .map_err(StrokersError::IoError)?;
+    replace_file(&target, doc.to_string(), metadata.permissions())
+        .await
+        .map_err(|err| match err.kind() {
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                StrokersError::ReadOnlyConfig(path.to_owned())
+            }
+            _ => StrokersError::IoError(err),
+        })
+}
+
+/// Replaces the file at `path` with one with the given contents and permissions.
+///
+/// The contents are written to a file alongside it first, which is then moved over it,
+/// so that the file is never left half-written (e.g. if the disk is full).
+async fn replace_file(
+    path: &Path,
+    contents: String,
+    permissions: Permissions,
+) -> std::io::Result<()> {
+    let temp_path = temp_path_for(path);
+    let result = async {
+        tokio::fs::write(&temp_path, contents).await?;
+        tokio::fs::set_permissions(&temp_path, permissions).await?;
+        tokio::fs::rename(&temp_path, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+    result
+}
+
+/// Where to write a file's new contents before moving them over it, e.g. `.config.toml.1234.tmp`
+/// for `config.toml` (in the same directory, as a file can't be moved across filesystems).
+fn temp_path_for(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Sets the limits of each of the given axes in the document, as [`save_limits`].
+fn set_limits(
+    doc: &mut DocumentMut,
+    limits: &BTreeMap<AxisKind, LimitsConfig>,
+) -> Result<(), StrokersError> {
+    let limits_item = doc.entry("limits").or_insert_with(|| {
+        // Only the `[limits.<axis>]` headers are needed
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    // New axes are added the same way as the rest, e.g. `limits = { stroke = { ... } }`
+    let inline = limits_item.is_inline_table();
+    let limits_table = limits_item
+        .as_table_like_mut()
+        .ok_or_else(|| StrokersError::Configuration("`limits` isn't a table".to_owned()))?;
+
+    for (&axis_kind, axis_limits) in limits {
+        let key = axis_key(axis_kind)?;
+        let axis_table = limits_table
+            .entry(&key)
+            .or_insert_with(|| {
+                if inline {
+                    Item::Value(Value::InlineTable(Default::default()))
+                } else {
+                    Item::Table(Table::new())
+                }
+            })
+            .as_table_like_mut()
+            .ok_or_else(|| StrokersError::Configuration(format!("`limits.{key}` isn't a table")))?;
+        set_value(axis_table, "speed", rounded(axis_limits.speed));
+        set_value(axis_table, "default_min", rounded(axis_limits.default_min));
+        set_value(axis_table, "default_max", rounded(axis_limits.default_max));
+        if axis_limits.invert {
+            set_value(axis_table, "invert", true);
+        } else {
+            axis_table.remove("invert");
+        }
+    }
+    Ok(())
+}
+
+/// Sets a value in the table, keeping any comment alongside the value it replaces.
+fn set_value(table: &mut dyn TableLike, key: &str, value: impl Into<Value>) {
+    let mut value = value.into();
+    if let Some(old_value) = table.get(key).and_then(Item::as_value) {
+        *value.decor_mut() = old_value.decor().clone();
+    }
+    table.insert(key, Item::Value(value));
+}
+
+/// The axis's key in the `limits` section, e.g. `stroke`.
+fn axis_key(axis_kind: AxisKind) -> Result<String, StrokersError> {
+    match serde_json::to_value(axis_kind) {
+        Ok(serde_json::Value::String(key)) => Ok(key),
+        other => Err(StrokersError::Unexpected(eyre::eyre!(
+            "{axis_kind:?} isn't named as expected: {other:?}"
+        ))),
+    }
+}
+
+/// A limit as it's worth writing down, without the noise of it having been an `f32`
+/// (e.g. 0.45 rather than 0.44999998807907104).
+fn rounded(limit: f32) -> f64 {
+    (limit as f64 * 10_000.0).round() / 10_000.0
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    use strokers_core::AxisKind;
+    use strokers_funscript::processing::Easing;
+    use toml_edit::DocumentMut;
+
+    use super::{save_limits, set_limits};
+    use crate::{config::LimitsConfig, StrokersError};
+
+    fn limits(speed: f32, default_min: f32, default_max: f32, invert: bool) -> LimitsConfig {
+        LimitsConfig {
+            speed,
+            default_min,
+            default_max,
+            invert,
+            device_easing: Easing::Linear,
+            idle_timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_set_limits() {
+        let mut doc: DocumentMut = r#"# My stroker
+[stroker]
+type = "debug"
+
+[limits.stroke]
+speed = 0.5 # full strokes a second
+default_min = 0.45
+default_max = 0.55
+invert = true
+device_easing = "cosine"
+
+# Not in use, so left alone
+[limits.twist]
+speed = 1.0
+default_min = 0.0
+default_max = 1.0
+"#
+        .parse()
+        .unwrap();
+        let new_limits = BTreeMap::from([
+            (AxisKind::Stroke, limits(0.75, 0.1, 0.9, false)),
+            (AxisKind::Roll, limits(0.5, 0.3, 0.7, true)),
+        ]);
+        set_limits(&mut doc, &new_limits).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            r#"# My stroker
+[stroker]
+type = "debug"
+
+[limits.stroke]
+speed = 0.75 # full strokes a second
+default_min = 0.1
+default_max = 0.9
+device_easing = "cosine"
+
+# Not in use, so left alone
+[limits.twist]
+speed = 1.0
+default_min = 0.0
+default_max = 1.0
+
+[limits.roll]
+speed = 0.5
+default_min = 0.3
+default_max = 0.7
+invert = true
+"#
+        );
+
+        // A file without any limits yet gets them
+        let mut doc: DocumentMut = "[stroker]\ntype = \"debug\"\n".parse().unwrap();
+        set_limits(
+            &mut doc,
+            &BTreeMap::from([(AxisKind::Stroke, limits(0.5, 0.45, 0.55, false))]),
+        )
+        .unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[stroker]\ntype = \"debug\"\n\n\
+             [limits.stroke]\nspeed = 0.5\ndefault_min = 0.45\ndefault_max = 0.55\n"
+        );
+
+        // Nor is anything made up if the limits aren't where they're expected
+        let mut doc: DocumentMut = "limits = 5\n".parse().unwrap();
+        assert!(matches!(
+            set_limits(
+                &mut doc,
+                &BTreeMap::from([(AxisKind::Stroke, limits(0.5, 0.45, 0.55, false))]),
+            ),
+            Err(StrokersError::Configuration(_))
+        ));
+    }
+
+    /// A directory of its own for a test, removed afterwards even if the test fails.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> TestDir {
+            let dir = std::env::temp_dir().join(format!("{name}_{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TestDir(dir)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_limits() {
+        let dir = TestDir::new("strokers_save");
+        let path = dir.0.join("config.toml");
+        std::fs::write(&path, "# Mine\n[limits.stroke]\nspeed = 0.5\n").unwrap();
+        let new_limits = BTreeMap::from([(AxisKind::Stroke, limits(0.25, 0.4, 0.6, false))]);
+        save_limits(&path, &new_limits).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Mine\n[limits.stroke]\nspeed = 0.25\ndefault_min = 0.4\ndefault_max = 0.6\n"
+        );
+        // Nothing is left behind from writing it
+        let names: Vec<_> = std::fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["config.toml"]);
+
+        // Refused, rather than failing part-way, if the file can't be written to
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        assert!(matches!(
+            save_limits(&path, &new_limits).await,
+            Err(StrokersError::ReadOnlyConfig(read_only)) if read_only == path
+        ));
+    }
+}
@@ -1,22 +1,49 @@
 use async_trait::async_trait;
 use strokers_core::{AxisDescriptor, Movement, Stroker};
+
+use crate::lock::StrokerLock;
 pub use strokers_device_debug as debug;
+#[cfg(feature = "kiiroo")]
+pub use strokers_device_kiiroo as kiiroo;
 pub use strokers_device_tcode as tcode;
 
+mod composite;
+mod dry_run;
+mod json_lines;
+mod pulsed;
+mod remapped;
+mod replay;
+
+pub use composite::CompositeStroker;
+pub use dry_run::{DryRunStroker, Violation, ViolationKind};
+pub use json_lines::JsonLinesStroker;
+pub use pulsed::PulsedStroker;
+pub use remapped::RemappedStroker;
+pub use replay::{ReplayOptions, ReplayStroker, ReplaySummary};
+
 /// Wrapper for a [`Box`]ed [`Stroker`].
 /// This makes it easier to support any type of stroker in your application.
 ///
 /// Get one with [`crate::open_stroker`].
 pub struct AnyStroker {
     inner: Box<dyn Stroker + Send + 'static>,
+    /// The lock keeping other programs off the stroker, if it's locked
+    lock: Option<StrokerLock>,
 }
 
 impl AnyStroker {
     pub fn new(stroker: impl Stroker + Send + 'static) -> AnyStroker {
         AnyStroker {
             inner: Box::new(stroker),
+            lock: None,
         }
     }
+
+    /// Holds the given lock on the stroker for as long as this lives.
+    pub fn with_lock(mut self, lock: Option<StrokerLock>) -> AnyStroker {
+        self.lock = lock;
+        self
+    }
 }
 
 #[async_trait]
@@ -36,4 +63,8 @@ impl Stroker for AnyStroker {
     fn description(&mut self) -> eyre::Result<Option<String>> {
         self.inner.description()
     }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        self.inner.min_movement_interval_ms()
+    }
 }
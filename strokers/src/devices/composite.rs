@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use eyre::ContextCompat;
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+
+use crate::{devices::AnyStroker, StrokersError};
+
+/// Where a composite axis is routed to.
+struct Route {
+    device_name: String,
+    inner_axis_id: AxisId,
+    axis_kind: AxisKind,
+}
+
+/// A stroker made up of several other strokers, each one driving some of the axes.
+///
+/// The axes are re-numbered so that the composite stroker has one axis per [`AxisKind`].
+pub struct CompositeStroker {
+    devices: BTreeMap<String, AnyStroker>,
+    routes: BTreeMap<AxisId, Route>,
+}
+
+impl CompositeStroker {
+    /// Build a composite stroker out of the given named devices.
+    ///
+    /// `explicit_routes` chooses which device drives an axis kind.
+    /// Axis kinds without an explicit route are driven by the only device that has that kind of axis;
+    /// if more than one device has that kind of axis, this is a configuration error.
+    pub fn new(
+        mut devices: BTreeMap<String, AnyStroker>,
+        explicit_routes: &BTreeMap<AxisKind, String>,
+    ) -> Result<CompositeStroker, StrokersError> {
+        for (axis_kind, device_name) in explicit_routes {
+            if !devices.contains_key(device_name) {
+                return Err(StrokersError::Configuration(format!(
+                    "{axis_kind:?} is routed to {device_name:?} but there is no such device"
+                )));
+            }
+        }
+
+        // For each kind of axis, all the devices that have it.
+        let mut candidates: BTreeMap<AxisKind, Vec<(String, AxisId)>> = BTreeMap::new();
+        for (device_name, device) in &mut devices {
+            for axis in device.axes() {
+                let device_candidates = candidates.entry(axis.axis_kind).or_default();
+                if device_candidates
+                    .iter()
+                    .any(|(name, _)| name == device_name)
+                {
+                    // Device has more than one axis of this kind; only use the first.
+                    continue;
+                }
+                device_candidates.push((device_name.clone(), axis.axis_id));
+            }
+        }
+
+        for (axis_kind, device_name) in explicit_routes {
+            let has_axis = candidates
+                .get(axis_kind)
+                .is_some_and(|c| c.iter().any(|(name, _)| name == device_name));
+            if !has_axis {
+                return Err(StrokersError::Configuration(format!(
+                    "{axis_kind:?} is routed to {device_name:?} but that device has no such axis"
+                )));
+            }
+        }
+
+        let mut routes = BTreeMap::new();
+        for (axis_kind, device_candidates) in candidates {
+            let (device_name, inner_axis_id) = match explicit_routes.get(&axis_kind) {
+                Some(routed_name) => device_candidates
+                    .into_iter()
+                    .find(|(name, _)| name == routed_name)
+                    .expect("explicit routes checked above"),
+                None => {
+                    if device_candidates.len() > 1 {
+                        let names: Vec<&String> =
+                            device_candidates.iter().map(|(name, _)| name).collect();
+                        return Err(StrokersError::Configuration(format!(
+                            "{axis_kind:?} is provided by multiple devices {names:?}; add an explicit route for it"
+                        )));
+                    }
+                    device_candidates
+                        .into_iter()
+                        .next()
+                        .expect("every candidate list has at least one entry")
+                }
+            };
+            routes.insert(
+                AxisId(routes.len() as u32),
+                Route {
+                    device_name,
+                    inner_axis_id,
+                    axis_kind,
+                },
+            );
+        }
+
+        Ok(CompositeStroker { devices, routes })
+    }
+}
+
+#[async_trait]
+impl Stroker for CompositeStroker {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.routes
+            .iter()
+            .map(|(&axis_id, route)| AxisDescriptor {
+                axis_id,
+                axis_kind: route.axis_kind,
+            })
+            .collect()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        // Try to stop every device, even if one of them fails.
+        let mut errors = Vec::new();
+        for (device_name, device) in &mut self.devices {
+            if let Err(err) = device.stop().await {
+                errors.push(err.wrap_err(format!("failed to stop {device_name:?}")));
+            }
+        }
+        // The first failure is returned (so that e.g. a disconnection can still be recognised),
+        // telling of the rest too
+        let mut errors = errors.into_iter();
+        let Some(first) = errors.next() else {
+            return Ok(());
+        };
+        let others: Vec<String> = errors.map(|err| format!("{err:#}")).collect();
+        if others.is_empty() {
+            return Err(first);
+        }
+        Err(first.wrap_err(format!(
+            "failed to stop {} devices (also: {})",
+            others.len() + 1,
+            others.join("; ")
+        )))
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        let route = self
+            .routes
+            .get(&movement.axis())
+            .with_context(|| format!("no such axis: {:?}", movement.axis()))?;
+        let device = self
+            .devices
+            .get_mut(&route.device_name)
+            .context("routed to missing device")?;
+        let inner_movement = Movement::new(
+            route.inner_axis_id,
+            movement.target(),
+            movement.ramp_time_milliseconds(),
+        )
+        .context("failed to re-target movement")?;
+        device.movement(inner_movement).await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        let mut descriptions = Vec::with_capacity(self.devices.len());
+        for (device_name, device) in &mut self.devices {
+            match device.description()? {
+                Some(description) => descriptions.push(format!("{device_name}: {description}")),
+                None => descriptions.push(device_name.clone()),
+            }
+        }
+        Ok(Some(format!("Composite [{}]", descriptions.join(", "))))
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        // The slowest device sets the pace for all of them.
+        self.devices
+            .values_mut()
+            .filter_map(|device| device.min_movement_interval_ms())
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use async_trait::async_trait;
+    use strokers_core::{AxisDescriptor, AxisId, AxisKind, Disconnected, Movement, Stroker};
+    use strokers_device_debug::DebugStroker;
+
+    use crate::{devices::AnyStroker, StrokersError};
+
+    use super::CompositeStroker;
+
+    fn devices() -> BTreeMap<String, AnyStroker> {
+        let mut devices = BTreeMap::new();
+        devices.insert(
+            "a".to_owned(),
+            AnyStroker::new(DebugStroker::with_axes(vec![
+                (AxisId(1), AxisKind::Stroke),
+                (AxisId(2), AxisKind::Twist),
+            ])),
+        );
+        devices.insert(
+            "b".to_owned(),
+            AnyStroker::new(DebugStroker::with_axes(vec![
+                (AxisId(7), AxisKind::Stroke),
+                (AxisId(8), AxisKind::Vibration),
+            ])),
+        );
+        devices
+    }
+
+    #[test]
+    fn test_overlapping_axes_need_route() {
+        let result = CompositeStroker::new(devices(), &BTreeMap::new());
+        assert!(matches!(result, Err(StrokersError::Configuration(_))));
+    }
+
+    #[test]
+    fn test_route_to_device_without_axis() {
+        let mut routes = BTreeMap::new();
+        routes.insert(AxisKind::Stroke, "a".to_owned());
+        routes.insert(AxisKind::Vibration, "a".to_owned());
+        let result = CompositeStroker::new(devices(), &routes);
+        assert!(matches!(result, Err(StrokersError::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_routing() {
+        let mut routes = BTreeMap::new();
+        routes.insert(AxisKind::Stroke, "b".to_owned());
+        let mut composite = CompositeStroker::new(devices(), &routes).unwrap();
+
+        let axes: Vec<(AxisId, AxisKind)> = composite
+            .axes()
+            .into_iter()
+            .map(|axis| (axis.axis_id, axis.axis_kind))
+            .collect();
+        assert_eq!(
+            axes,
+            vec![
+                (AxisId(0), AxisKind::Stroke),
+                (AxisId(1), AxisKind::Twist),
+                (AxisId(2), AxisKind::Vibration),
+            ]
+        );
+
+        let routed: Vec<(&str, AxisId)> = composite
+            .routes
+            .values()
+            .map(|route| (route.device_name.as_str(), route.inner_axis_id))
+            .collect();
+        assert_eq!(
+            routed,
+            vec![("b", AxisId(7)), ("a", AxisId(2)), ("b", AxisId(8))]
+        );
+
+        for axis_id in [AxisId(0), AxisId(1), AxisId(2)] {
+            composite
+                .movement(Movement::new(axis_id, 0.5, 100).unwrap())
+                .await
+                .unwrap();
+        }
+        assert!(composite
+            .movement(Movement::new(AxisId(3), 0.5, 100).unwrap())
+            .await
+            .is_err());
+        composite.stop().await.unwrap();
+    }
+
+    /// Says it's been disconnected, whatever it's asked to do.
+    struct UnpluggedStroker(AxisKind);
+
+    #[async_trait]
+    impl Stroker for UnpluggedStroker {
+        fn axes(&mut self) -> Vec<AxisDescriptor> {
+            vec![AxisDescriptor {
+                axis_id: AxisId(1),
+                axis_kind: self.0,
+            }]
+        }
+
+        async fn stop(&mut self) -> eyre::Result<()> {
+            Err(Disconnected.into())
+        }
+
+        async fn movement(&mut self, _movement: Movement) -> eyre::Result<()> {
+            Err(Disconnected.into())
+        }
+
+        fn description(&mut self) -> eyre::Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_failures() {
+        let mut devices = devices();
+        devices.insert(
+            "c".to_owned(),
+            AnyStroker::new(UnpluggedStroker(AxisKind::Roll)),
+        );
+        devices.insert(
+            "d".to_owned(),
+            AnyStroker::new(UnpluggedStroker(AxisKind::Pitch)),
+        );
+        let routes = BTreeMap::from([(AxisKind::Stroke, "a".to_owned())]);
+        let mut composite = CompositeStroker::new(devices, &routes).unwrap();
+
+        // Each failure is told of, and the disconnection can still be recognised
+        let err = composite.stop().await.unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("failed to stop \"c\""), "{message}");
+        assert!(message.contains("failed to stop \"d\""), "{message}");
+        assert!(err.downcast_ref::<Disconnected>().is_some());
+    }
+}
@@ -0,0 +1,363 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use async_trait::async_trait;
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+use strokers_device_tcode::encode_tcode_command;
+use strokers_funscript::processing::Easing;
+use tokio::time::Instant;
+use tracing::warn;
+
+use crate::config::LimitsConfig;
+
+/// Slack allowed when comparing against limits, to avoid complaining about rounding errors.
+const TOLERANCE: f32 = 0.001;
+
+/// A problem found with a command sent to a [`DryRunStroker`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// Index of the offending command, counting every command sent to the stroker from 0.
+    pub command_index: usize,
+    pub kind: ViolationKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    /// The movement was for an axis that the stroker doesn't have.
+    NoSuchAxis { axis_id: AxisId },
+    /// The movement's target was outside of 0.0 to 1.0.
+    TargetOutOfRange { axis_kind: AxisKind, target: f32 },
+    /// The movement's target was outside of the configured limits of the axis.
+    OutsideLimits {
+        axis_kind: AxisKind,
+        target: f32,
+        min: f32,
+        max: f32,
+    },
+    /// The movement was faster than the configured speed limit of the axis.
+    TooFast {
+        axis_kind: AxisKind,
+        /// Speed in full-scales per second
+        speed: f32,
+        speed_limit: f32,
+    },
+    /// The movement could not be encoded as T-Code.
+    NotEncodable { axis_kind: AxisKind },
+}
+
+/// An axis's last movement, as a stroker easing it with `easing` would make it.
+struct Motion {
+    from: f32,
+    to: f32,
+    started_at: Instant,
+    ramp_time: Duration,
+    easing: Easing,
+}
+
+impl Motion {
+    fn position_at(&self, at: Instant) -> f32 {
+        let elapsed = at.saturating_duration_since(self.started_at);
+        let progress = if elapsed >= self.ramp_time {
+            1.0
+        } else {
+            elapsed.as_secs_f32() / self.ramp_time.as_secs_f32()
+        };
+        self.from + (self.to - self.from) * self.easing.apply(progress)
+    }
+}
+
+/// Performs the checks that a real stroker would, but never sends anything anywhere.
+///
+/// Rather than failing, problems are collected as [`Violation`]s
+/// so that a whole session can be validated in one go.
+/// It also keeps track of where each axis would be, moving as the configured
+/// `device_easing` says the stroker does (see [`DryRunStroker::position_at`]).
+pub struct DryRunStroker {
+    axes: Vec<AxisDescriptor>,
+    limits: BTreeMap<AxisKind, LimitsConfig>,
+    /// The last target of each axis, if any.
+    last_targets: BTreeMap<AxisId, f32>,
+    /// The last movement of each axis, if any.
+    motions: BTreeMap<AxisId, Motion>,
+    command_count: usize,
+    violations: Vec<Violation>,
+}
+
+impl DryRunStroker {
+    /// Create a dry-run stroker with the given axes.
+    ///
+    /// Movements are checked against the `limits` of their kind of axis, if there are any.
+    pub fn new(
+        axes: Vec<AxisDescriptor>,
+        limits: BTreeMap<AxisKind, LimitsConfig>,
+    ) -> DryRunStroker {
+        DryRunStroker {
+            axes,
+            limits,
+            last_targets: BTreeMap::new(),
+            motions: BTreeMap::new(),
+            command_count: 0,
+            violations: Vec::new(),
+        }
+    }
+
+    /// All the problems found so far, in order.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// Where the axis would be at `at` (which should be no earlier than its last movement),
+    /// or `None` if it hasn't been moved yet.
+    pub fn position_at(&self, axis_id: AxisId, at: Instant) -> Option<f32> {
+        self.motions
+            .get(&axis_id)
+            .map(|motion| motion.position_at(at))
+    }
+
+    /// Starts the axis moving from wherever it is (or, the first time, from its target).
+    fn start_motion(&mut self, axis_kind: AxisKind, movement: &Movement) {
+        let now = Instant::now();
+        let from = self
+            .position_at(movement.axis(), now)
+            .unwrap_or(movement.target());
+        let easing = self
+            .limits
+            .get(&axis_kind)
+            .map_or(Easing::Linear, |limits| limits.device_easing);
+        self.motions.insert(
+            movement.axis(),
+            Motion {
+                from,
+                to: movement.target(),
+                started_at: now,
+                ramp_time: Duration::from_millis(movement.ramp_time_milliseconds().into()),
+                easing,
+            },
+        );
+    }
+
+    fn violation(&mut self, command_index: usize, kind: ViolationKind) {
+        warn!("dry run: command {command_index}: {kind:?}");
+        self.violations.push(Violation {
+            command_index,
+            kind,
+        });
+    }
+
+    fn check_movement(&mut self, command_index: usize, movement: &Movement) {
+        let Some(axis_kind) = self
+            .axes
+            .iter()
+            .find(|axis| axis.axis_id == movement.axis())
+            .map(|axis| axis.axis_kind)
+        else {
+            self.violation(
+                command_index,
+                ViolationKind::NoSuchAxis {
+                    axis_id: movement.axis(),
+                },
+            );
+            return;
+        };
+
+        let target = movement.target();
+        if !(0.0..=1.0).contains(&target) {
+            self.violation(
+                command_index,
+                ViolationKind::TargetOutOfRange { axis_kind, target },
+            );
+        }
+
+        if let Some(limits) = self.limits.get(&axis_kind).cloned() {
+            let min = limits.default_min.min(limits.default_max);
+            let max = limits.default_min.max(limits.default_max);
+            if target < min - TOLERANCE || max + TOLERANCE < target {
+                self.violation(
+                    command_index,
+                    ViolationKind::OutsideLimits {
+                        axis_kind,
+                        target,
+                        min,
+                        max,
+                    },
+                );
+            }
+
+            if let Some(&last_target) = self.last_targets.get(&movement.axis()) {
+                let speed = (target - last_target).abs()
+                    / (movement.ramp_time_milliseconds().max(1) as f32 * 0.001);
+                let speed_limit = limits.speed;
+                if speed > speed_limit + TOLERANCE {
+                    self.violation(
+                        command_index,
+                        ViolationKind::TooFast {
+                            axis_kind,
+                            speed,
+                            speed_limit,
+                        },
+                    );
+                }
+            }
+        }
+
+        match axis_kind.tcode_name() {
+            Some(tcode_axis_name) => {
+                // Encoding can't currently fail, but this makes sure it doesn't panic either.
+                encode_tcode_command(tcode_axis_name, movement);
+            }
+            None => {
+                self.violation(command_index, ViolationKind::NotEncodable { axis_kind });
+            }
+        }
+
+        self.last_targets.insert(movement.axis(), target);
+        self.start_motion(axis_kind, movement);
+    }
+}
+
+#[async_trait]
+impl Stroker for DryRunStroker {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.axes.clone()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.command_count += 1;
+        // Each axis stops where it is
+        let now = Instant::now();
+        for motion in self.motions.values_mut() {
+            let position = motion.position_at(now);
+            motion.from = position;
+            motion.to = position;
+        }
+        Ok(())
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        let command_index = self.command_count;
+        self.command_count += 1;
+        self.check_movement(command_index, &movement);
+        Ok(())
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(Some(format!(
+            "DryRunStroker ({} violations)",
+            self.violations.len()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+    use strokers_funscript::processing::Easing;
+    use tokio::time::Instant;
+
+    use crate::config::LimitsConfig;
+
+    use super::{DryRunStroker, Violation, ViolationKind};
+
+    #[tokio::test]
+    async fn test_violations() {
+        let mut limits = BTreeMap::new();
+        limits.insert(
+            AxisKind::Stroke,
+            LimitsConfig {
+                speed: 1.0,
+                default_min: 0.2,
+                default_max: 0.8,
+                invert: false,
+                device_easing: Easing::Linear,
+                idle_timeout_ms: None,
+            },
+        );
+        let mut stroker = DryRunStroker::new(
+            vec![AxisDescriptor {
+                axis_id: AxisId(0),
+                axis_kind: AxisKind::Stroke,
+            }],
+            limits,
+        );
+
+        // fine
+        stroker
+            .movement(Movement::new(AxisId(0), 0.5, 100).unwrap())
+            .await
+            .unwrap();
+        // outside limits and too fast
+        stroker
+            .movement(Movement::new(AxisId(0), 0.9, 100).unwrap())
+            .await
+            .unwrap();
+        // no such axis
+        stroker
+            .movement(Movement::new(AxisId(1), 0.5, 100).unwrap())
+            .await
+            .unwrap();
+        // fine
+        stroker
+            .movement(Movement::new(AxisId(0), 0.8, 1000).unwrap())
+            .await
+            .unwrap();
+
+        let violations = stroker.violations();
+        assert_eq!(violations.len(), 3);
+        assert_eq!(
+            violations[0],
+            Violation {
+                command_index: 1,
+                kind: ViolationKind::OutsideLimits {
+                    axis_kind: AxisKind::Stroke,
+                    target: 0.9,
+                    min: 0.2,
+                    max: 0.8
+                }
+            }
+        );
+        assert!(matches!(
+            violations[1],
+            Violation {
+                command_index: 1,
+                kind: ViolationKind::TooFast { speed, .. }
+            } if (speed - 4.0).abs() < 0.001
+        ));
+        assert_eq!(
+            violations[2],
+            Violation {
+                command_index: 2,
+                kind: ViolationKind::NoSuchAxis { axis_id: AxisId(1) }
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_position() {
+        let mut stroker = DryRunStroker::new(
+            vec![AxisDescriptor {
+                axis_id: AxisId(0),
+                axis_kind: AxisKind::Stroke,
+            }],
+            BTreeMap::new(),
+        );
+        assert_eq!(stroker.position_at(AxisId(0), Instant::now()), None);
+
+        // Without limits, movements are taken to be linear
+        stroker
+            .movement(Movement::new(AxisId(0), 0.2, 0).unwrap())
+            .await
+            .unwrap();
+        stroker
+            .movement(Movement::new(AxisId(0), 1.0, 400).unwrap())
+            .await
+            .unwrap();
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert_eq!(stroker.position_at(AxisId(0), Instant::now()), Some(0.4));
+
+        // Stopped where it is
+        stroker.stop().await.unwrap();
+        tokio::time::advance(Duration::from_millis(100)).await;
+        assert_eq!(stroker.position_at(AxisId(0), Instant::now()), Some(0.4));
+    }
+}
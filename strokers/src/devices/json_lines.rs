@@ -0,0 +1,159 @@
+use std::{path::Path, time::Instant};
+
+use async_trait::async_trait;
+use eyre::{Context, ContextCompat};
+use strokers_core::{AxisDescriptor, Movement, Stroker};
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncWrite, AsyncWriteExt},
+};
+
+use crate::command_log::{Command, LoggedCommand};
+
+/// Writes every command as a line of JSON, in the format described in [`crate::command_log`],
+/// so that other programs can act on them.
+///
+/// Times are counted from when the stroker was created.
+pub struct JsonLinesStroker {
+    axes: Vec<AxisDescriptor>,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    /// Describes where the lines are going.
+    sink: String,
+    started: Instant,
+}
+
+impl JsonLinesStroker {
+    /// Create a stroker with the given axes, writing lines to `writer`.
+    ///
+    /// `sink` describes where the lines are going, for [`Stroker::description`].
+    pub fn new(
+        axes: Vec<AxisDescriptor>,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+        sink: String,
+    ) -> JsonLinesStroker {
+        JsonLinesStroker {
+            axes,
+            writer: Box::new(writer),
+            sink,
+            started: Instant::now(),
+        }
+    }
+
+    /// Create a stroker with the given axes, writing lines to standard output.
+    pub fn stdout(axes: Vec<AxisDescriptor>) -> JsonLinesStroker {
+        JsonLinesStroker::new(axes, tokio::io::stdout(), "stdout".to_owned())
+    }
+
+    /// Create a stroker with the given axes, appending lines to the file (or named pipe) at `path`.
+    pub async fn open(axes: Vec<AxisDescriptor>, path: &Path) -> eyre::Result<JsonLinesStroker> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("failed to open {path:?}"))?;
+        Ok(JsonLinesStroker::new(axes, file, format!("{path:?}")))
+    }
+
+    async fn write_command(&mut self, command: Command) -> eyre::Result<()> {
+        let logged = LoggedCommand {
+            time_ms: self.started.elapsed().as_millis() as u64,
+            command,
+        };
+        let mut line = serde_json::to_string(&logged).context("failed to serialise command")?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .context("failed to write command")?;
+        self.writer.flush().await.context("failed to flush")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Stroker for JsonLinesStroker {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.axes.clone()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.write_command(Command::Stop).await
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        let axis_kind = self
+            .axes
+            .iter()
+            .find(|axis| axis.axis_id == movement.axis())
+            .with_context(|| format!("no such axis: {:?}", movement.axis()))?
+            .axis_kind;
+        self.write_command(Command::Movement {
+            axis_kind,
+            target: movement.target(),
+            ramp_time_milliseconds: movement.ramp_time_milliseconds(),
+        })
+        .await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(Some(format!("JSON lines to {}", self.sink)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+    use tokio::io::AsyncReadExt;
+
+    use crate::command_log::{parse_command_log, Command};
+
+    use super::JsonLinesStroker;
+
+    #[tokio::test]
+    async fn test_lines_parse_back() {
+        let (writer, mut reader) = tokio::io::duplex(4096);
+        let mut stroker = JsonLinesStroker::new(
+            vec![AxisDescriptor {
+                axis_id: AxisId(3),
+                axis_kind: AxisKind::Twist,
+            }],
+            writer,
+            "test".to_owned(),
+        );
+        assert_eq!(
+            stroker.description().unwrap().as_deref(),
+            Some("JSON lines to test")
+        );
+
+        stroker
+            .movement(Movement::new(AxisId(3), 0.25, 500).unwrap())
+            .await
+            .unwrap();
+        assert!(stroker
+            .movement(Movement::new(AxisId(4), 0.25, 500).unwrap())
+            .await
+            .is_err());
+        stroker.stop().await.unwrap();
+        drop(stroker);
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await.unwrap();
+        let commands: Vec<Command> = parse_command_log(&text)
+            .unwrap()
+            .into_iter()
+            .map(|logged| logged.command)
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                Command::Movement {
+                    axis_kind: AxisKind::Twist,
+                    target: 0.25,
+                    ramp_time_milliseconds: 500
+                },
+                Command::Stop
+            ]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use eyre::eyre;
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+use tokio::{
+    sync::{watch, Mutex},
+    task::JoinHandle,
+    time::{timeout, Instant},
+};
+use tracing::error;
+
+/// Wraps a stroker whose vibration (or similar) axes can only be switched on or off,
+/// turning proportional intensities into on/off pulses.
+///
+/// Each pulsed axis is switched on for a proportion of every `period` equal to the requested intensity
+/// (like PWM), by a timer task of its own.
+/// Other axes are passed through unchanged.
+pub struct PulsedStroker<S> {
+    inner: Arc<Mutex<S>>,
+    axes: Vec<AxisDescriptor>,
+    /// The description, worked out up front, as the timer tasks can be holding the inner lock
+    /// whenever it's asked for (the error, if any, as its message)
+    description: Result<Option<String>, String>,
+    /// The inner stroker's, worked out up front for the same reason
+    min_movement_interval_ms: Option<u32>,
+    /// Requested intensity of each pulsed axis.
+    levels: BTreeMap<AxisId, watch::Sender<f32>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<S: Stroker + Send + 'static> PulsedStroker<S> {
+    /// Wrap `inner`, pulsing all of its axes whose kind is in `pulsed_kinds`
+    /// with the given `period`.
+    ///
+    /// Must be called within a Tokio runtime, as this spawns the timer tasks.
+    pub fn new(mut inner: S, pulsed_kinds: &BTreeSet<AxisKind>, period: Duration) -> Self {
+        let axes = inner.axes();
+        let description = inner
+            .description()
+            .map(|description| description.map(|description| format!("{description} (pulsed)")))
+            .map_err(|err| format!("{err:#}"));
+        let min_movement_interval_ms = inner.min_movement_interval_ms();
+        let inner = Arc::new(Mutex::new(inner));
+
+        let mut levels = BTreeMap::new();
+        let mut tasks = Vec::new();
+        for axis in &axes {
+            if !pulsed_kinds.contains(&axis.axis_kind) {
+                continue;
+            }
+            let (level_tx, level_rx) = watch::channel(0.0);
+            levels.insert(axis.axis_id, level_tx);
+            tasks.push(tokio::spawn(pulse_task(
+                inner.clone(),
+                axis.axis_id,
+                period,
+                level_rx,
+            )));
+        }
+
+        PulsedStroker {
+            inner,
+            axes,
+            description,
+            min_movement_interval_ms,
+            levels,
+            tasks,
+        }
+    }
+}
+
+impl<S> Drop for PulsedStroker<S> {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Switches the axis fully on or off.
+async fn switch<S: Stroker>(inner: &Mutex<S>, axis_id: AxisId, on: bool) {
+    let target = if on { 1.0 } else { 0.0 };
+    let movement = Movement::new(axis_id, target, 0).expect("0.0 and 1.0 are valid targets");
+    if let Err(err) = inner.lock().await.movement(movement).await {
+        error!("failed to switch pulsed axis {axis_id:?}: {err:?}");
+    }
+}
+
+/// Waits until `deadline`, or until the level drops to zero.
+///
+/// Returns false if the level sender has gone away and the task should end.
+async fn wait_unless_stopped(level_rx: &mut watch::Receiver<f32>, deadline: Instant) -> bool {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    match timeout(remaining, level_rx.wait_for(|&level| level <= 0.0)).await {
+        // Deadline reached
+        Err(_) => true,
+        // Level dropped to zero
+        Ok(Ok(_)) => true,
+        // Sender dropped
+        Ok(Err(_)) => false,
+    }
+}
+
+async fn pulse_task<S: Stroker>(
+    inner: Arc<Mutex<S>>,
+    axis_id: AxisId,
+    period: Duration,
+    mut level_rx: watch::Receiver<f32>,
+) {
+    let mut is_on = false;
+    loop {
+        let level = *level_rx.borrow_and_update();
+
+        if level <= 0.0 {
+            if is_on {
+                switch(&inner, axis_id, false).await;
+                is_on = false;
+            }
+            if level_rx.changed().await.is_err() {
+                return;
+            }
+            continue;
+        }
+
+        let period_start = Instant::now();
+        let on_until = period_start + period.mul_f32(level.min(1.0));
+        let period_end = period_start + period;
+
+        if !is_on {
+            switch(&inner, axis_id, true).await;
+            is_on = true;
+        }
+        if !wait_unless_stopped(&mut level_rx, on_until).await {
+            return;
+        }
+
+        if on_until < period_end {
+            switch(&inner, axis_id, false).await;
+            is_on = false;
+            if !wait_unless_stopped(&mut level_rx, period_end).await {
+                return;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Stroker + Send + 'static> Stroker for PulsedStroker<S> {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.axes.clone()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        for level_tx in self.levels.values() {
+            level_tx.send_replace(0.0);
+        }
+        self.inner.lock().await.stop().await
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        match self.levels.get(&movement.axis()) {
+            Some(level_tx) => {
+                level_tx.send_replace(movement.target());
+                Ok(())
+            }
+            None => self.inner.lock().await.movement(movement).await,
+        }
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        self.description.clone().map_err(|err| eyre!(err))
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        self.min_movement_interval_ms
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, time::Duration};
+
+    use strokers_core::{AxisId, AxisKind, Movement, Stroker};
+    use strokers_device_debug::testing::{ChannelStroker, StrokerCommand};
+
+    use super::PulsedStroker;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_duty_cycle() {
+        let (inner, rx) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Vibration)]);
+        let mut stroker = PulsedStroker::new(
+            inner,
+            &BTreeSet::from([AxisKind::Vibration]),
+            Duration::from_millis(200),
+        );
+
+        stroker
+            .movement(Movement::new(AxisId(1), 0.25, 0).unwrap())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        assert_eq!(
+            stroker.description().unwrap().as_deref(),
+            Some("ChannelStroker (pulsed)")
+        );
+        stroker.stop().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let record: Vec<_> = rx
+            .drain()
+            .filter_map(StrokerCommand::into_movement)
+            .map(|(at, movement)| (at, movement.target()))
+            .collect();
+        // on, off, on, off, on, off, on, off
+        assert_eq!(record.len(), 8, "{record:?}");
+        for (idx, pair) in record.windows(2).enumerate() {
+            let [(on_at, on_target), (off_at, off_target)] = pair else {
+                unreachable!()
+            };
+            if idx % 2 != 0 {
+                continue;
+            }
+            assert_eq!(*on_target, 1.0);
+            assert_eq!(*off_target, 0.0);
+            // To within the timer's millisecond resolution
+            if idx < 6 {
+                let on_millis = (*off_at - *on_at).as_millis();
+                assert!((50..=51).contains(&on_millis), "on for {on_millis} ms");
+            }
+        }
+        let period_millis = (record[2].0 - record[0].0).as_millis();
+        assert!(
+            (200..=201).contains(&period_millis),
+            "period {period_millis} ms"
+        );
+
+        // After stopping, nothing more happens
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            rx.drain().filter_map(StrokerCommand::into_movement).count(),
+            0
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use eyre::ContextCompat;
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+use tracing::warn;
+
+/// Where a remapped axis is sent to.
+struct Mapping {
+    inner_axis_id: AxisId,
+    /// The kind of axis presented to callers.
+    axis_kind: AxisKind,
+}
+
+/// Wraps a stroker, presenting its axes as different kinds of axis.
+///
+/// For example, mapping [`AxisKind::Twist`] to [`AxisKind::Roll`] makes the inner stroker's
+/// roll axis appear as a twist axis, so that twist scripts get played on roll.
+///
+/// Several kinds of axis can be mapped to the same inner kind, in which case whichever
+/// was moved last wins.
+/// Only the mapped kinds are presented; any other axes of the inner stroker are hidden.
+/// Map a kind to itself to keep it.
+///
+/// The axes are re-numbered so that the remapped stroker has one axis per mapped [`AxisKind`].
+pub struct RemappedStroker<S> {
+    inner: S,
+    mappings: BTreeMap<AxisId, Mapping>,
+}
+
+impl<S: Stroker> RemappedStroker<S> {
+    /// Wrap `inner`, where `map` gives, for each kind of axis to present,
+    /// the kind of the inner stroker's axis that it drives.
+    ///
+    /// Kinds that map to a kind of axis the inner stroker doesn't have are left out.
+    pub fn new(mut inner: S, map: &BTreeMap<AxisKind, AxisKind>) -> RemappedStroker<S> {
+        let inner_axes = inner.axes();
+
+        let mut mappings = BTreeMap::new();
+        for (&axis_kind, &inner_axis_kind) in map {
+            // If the inner stroker has more than one axis of this kind, only use the first.
+            let Some(inner_axis) = inner_axes
+                .iter()
+                .find(|axis| axis.axis_kind == inner_axis_kind)
+            else {
+                warn!("can't map {axis_kind:?} to {inner_axis_kind:?}: stroker has no such axis");
+                continue;
+            };
+            mappings.insert(
+                AxisId(mappings.len() as u32),
+                Mapping {
+                    inner_axis_id: inner_axis.axis_id,
+                    axis_kind,
+                },
+            );
+        }
+
+        RemappedStroker { inner, mappings }
+    }
+}
+
+#[async_trait]
+impl<S: Stroker + Send> Stroker for RemappedStroker<S> {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.mappings
+            .iter()
+            .map(|(&axis_id, mapping)| AxisDescriptor {
+                axis_id,
+                axis_kind: mapping.axis_kind,
+            })
+            .collect()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.inner.stop().await
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        let mapping = self
+            .mappings
+            .get(&movement.axis())
+            .with_context(|| format!("no such axis: {:?}", movement.axis()))?;
+        let inner_movement = Movement::new(
+            mapping.inner_axis_id,
+            movement.target(),
+            movement.ramp_time_milliseconds(),
+        )
+        .context("failed to re-target movement")?;
+        self.inner.movement(inner_movement).await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(self
+            .inner
+            .description()?
+            .map(|description| format!("{description} (remapped)")))
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        self.inner.min_movement_interval_ms()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use strokers_core::{AxisId, AxisKind, Movement, Stroker};
+    use strokers_device_debug::testing::{ChannelStroker, StrokerCommand};
+
+    use super::RemappedStroker;
+
+    #[tokio::test]
+    async fn test_remapping() {
+        let (inner, rx) = ChannelStroker::with_axes(vec![
+            (AxisId(1), AxisKind::Stroke),
+            (AxisId(4), AxisKind::Twist),
+            (AxisId(5), AxisKind::Roll),
+        ]);
+        let mut map = BTreeMap::new();
+        map.insert(AxisKind::Stroke, AxisKind::Stroke);
+        // Both twist and roll drive the roll axis; the twist axis is dropped.
+        map.insert(AxisKind::Twist, AxisKind::Roll);
+        map.insert(AxisKind::Roll, AxisKind::Roll);
+        // The inner stroker has no vibration axis, so this is left out.
+        map.insert(AxisKind::Vibration, AxisKind::Vibration);
+        let mut remapped = RemappedStroker::new(inner, &map);
+
+        let axes: Vec<(AxisId, AxisKind)> = remapped
+            .axes()
+            .into_iter()
+            .map(|axis| (axis.axis_id, axis.axis_kind))
+            .collect();
+        assert_eq!(
+            axes,
+            vec![
+                (AxisId(0), AxisKind::Stroke),
+                (AxisId(1), AxisKind::Twist),
+                (AxisId(2), AxisKind::Roll),
+            ]
+        );
+
+        for (axis_id, target) in [(AxisId(0), 0.1), (AxisId(1), 0.2), (AxisId(2), 0.3)] {
+            remapped
+                .movement(Movement::new(axis_id, target, 100).unwrap())
+                .await
+                .unwrap();
+        }
+        assert!(remapped
+            .movement(Movement::new(AxisId(3), 0.5, 100).unwrap())
+            .await
+            .is_err());
+
+        let received: Vec<(AxisId, f32)> = rx
+            .drain()
+            .filter_map(StrokerCommand::into_movement)
+            .map(|(_, movement)| (movement.axis(), movement.target()))
+            .collect();
+        assert_eq!(
+            received,
+            vec![(AxisId(1), 0.1), (AxisId(5), 0.2), (AxisId(5), 0.3)]
+        );
+    }
+}
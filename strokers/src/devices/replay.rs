@@ -0,0 +1,266 @@
+use std::{path::Path, time::Duration};
+
+use async_trait::async_trait;
+use eyre::{bail, ContextCompat};
+use strokers_core::{AxisDescriptor, Movement, Stroker};
+use tokio::time::{sleep_until, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::command_log::{read_command_log, Command, LoggedCommand};
+
+/// How to replay a command log.
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    /// How much faster than the original to replay the commands.
+    /// Ramp times are scaled too.
+    pub time_scale: f32,
+
+    /// Skip the commands before this time in the log, in milliseconds.
+    pub start_at_ms: u64,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions {
+            time_scale: 1.0,
+            start_at_ms: 0,
+        }
+    }
+}
+
+/// What happened during a replay.
+///
+/// Drift is how late each command was sent compared to when it should have been.
+#[derive(Clone, Debug, Default)]
+pub struct ReplaySummary {
+    pub commands_sent: usize,
+    /// Commands for kinds of axis that the stroker doesn't have.
+    pub commands_skipped: usize,
+    /// Whether the replay was cancelled before the end of the log.
+    pub cancelled: bool,
+    pub max_drift: Duration,
+    pub mean_drift: Duration,
+}
+
+/// Wraps a stroker along with a command log that can be replayed on it
+/// with [`ReplayStroker::replay`].
+///
+/// Otherwise, commands are passed through to the inner stroker unchanged.
+pub struct ReplayStroker<S> {
+    inner: S,
+    commands: Vec<LoggedCommand>,
+}
+
+impl<S: Stroker + Send> ReplayStroker<S> {
+    pub fn new(inner: S, commands: Vec<LoggedCommand>) -> ReplayStroker<S> {
+        ReplayStroker { inner, commands }
+    }
+
+    /// Read the command log at `path`, to be replayed on `inner`.
+    pub async fn open(inner: S, path: &Path) -> eyre::Result<ReplayStroker<S>> {
+        let commands = read_command_log(path).await?;
+        Ok(ReplayStroker::new(inner, commands))
+    }
+
+    /// The commands in the log.
+    pub fn commands(&self) -> &[LoggedCommand] {
+        &self.commands
+    }
+
+    /// Send the logged commands to the inner stroker, keeping the original delays between them.
+    ///
+    /// If `ctoken` is cancelled, the inner stroker is stopped and the replay ends early.
+    pub async fn replay(
+        &mut self,
+        options: &ReplayOptions,
+        ctoken: &CancellationToken,
+    ) -> eyre::Result<ReplaySummary> {
+        if !(options.time_scale.is_finite() && options.time_scale > 0.0) {
+            bail!("time scale must be positive, not {}", options.time_scale);
+        }
+
+        let axes = self.inner.axes();
+        let mut summary = ReplaySummary::default();
+        let mut total_drift = Duration::ZERO;
+
+        let replay_start = Instant::now();
+        for logged in &self.commands {
+            if logged.time_ms < options.start_at_ms {
+                continue;
+            }
+            let offset = Duration::from_millis(logged.time_ms - options.start_at_ms)
+                .div_f32(options.time_scale);
+            let intended_at = replay_start + offset;
+
+            tokio::select! {
+                _ = sleep_until(intended_at) => {}
+                _ = ctoken.cancelled() => {
+                    info!("replay cancelled");
+                    summary.cancelled = true;
+                    self.inner.stop().await?;
+                    break;
+                }
+            }
+
+            let drift = Instant::now().saturating_duration_since(intended_at);
+            match &logged.command {
+                Command::Movement {
+                    axis_kind,
+                    target,
+                    ramp_time_milliseconds,
+                } => {
+                    let Some(axis) = axes.iter().find(|axis| axis.axis_kind == *axis_kind) else {
+                        warn!("can't replay movement for {axis_kind:?}: stroker has no such axis");
+                        summary.commands_skipped += 1;
+                        continue;
+                    };
+                    let ramp_time_milliseconds =
+                        (*ramp_time_milliseconds as f32 / options.time_scale).round() as u32;
+                    let movement = Movement::new(axis.axis_id, *target, ramp_time_milliseconds)
+                        .context("invalid movement in command log")?;
+                    self.inner.movement(movement).await?;
+                }
+                Command::Stop => {
+                    self.inner.stop().await?;
+                }
+            }
+
+            summary.commands_sent += 1;
+            summary.max_drift = summary.max_drift.max(drift);
+            total_drift += drift;
+        }
+
+        if summary.commands_sent > 0 {
+            summary.mean_drift = total_drift / summary.commands_sent as u32;
+        }
+        info!("replay finished: {summary:?}");
+        Ok(summary)
+    }
+}
+
+#[async_trait]
+impl<S: Stroker + Send> Stroker for ReplayStroker<S> {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.inner.axes()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.inner.stop().await
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        self.inner.movement(movement).await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(self.inner.description()?.map(|description| {
+            format!("{description} (replaying {} commands)", self.commands.len())
+        }))
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        self.inner.min_movement_interval_ms()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use strokers_core::{AxisId, AxisKind};
+    use strokers_device_debug::testing::{ChannelStroker, StrokerCommand};
+    use tokio_util::sync::CancellationToken;
+
+    use crate::command_log::{Command, LoggedCommand};
+
+    use super::{ReplayOptions, ReplayStroker};
+
+    fn commands() -> Vec<LoggedCommand> {
+        [
+            (0, AxisKind::Stroke),
+            (100, AxisKind::Twist),
+            (300, AxisKind::Stroke),
+        ]
+        .into_iter()
+        .map(|(time_ms, axis_kind)| LoggedCommand {
+            time_ms,
+            command: Command::Movement {
+                axis_kind,
+                target: 0.5,
+                ramp_time_milliseconds: 100,
+            },
+        })
+        .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_timing() {
+        let (inner, rx) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let mut stroker = ReplayStroker::new(inner, commands());
+
+        let summary = stroker
+            .replay(
+                &ReplayOptions {
+                    time_scale: 2.0,
+                    start_at_ms: 0,
+                },
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(summary.commands_sent, 2);
+        assert_eq!(summary.commands_skipped, 1);
+        assert!(!summary.cancelled);
+        // Only as late as the timer's millisecond resolution
+        assert!(summary.max_drift <= Duration::from_millis(1), "{summary:?}");
+
+        let record: Vec<_> = rx
+            .drain()
+            .filter_map(StrokerCommand::into_movement)
+            .collect();
+        assert_eq!(record.len(), 2);
+        // The second movement is due 150 ms after the first, at double speed
+        let gap = record[1].0 - record[0].0;
+        assert!(
+            (Duration::from_millis(150)..=Duration::from_millis(151)).contains(&gap),
+            "gap of {gap:?}"
+        );
+        assert_eq!(record[1].1.ramp_time_milliseconds(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_replay_seek_and_cancel() {
+        let (inner, rx) = ChannelStroker::with_axes(vec![
+            (AxisId(1), AxisKind::Stroke),
+            (AxisId(4), AxisKind::Twist),
+        ]);
+        let mut stroker = ReplayStroker::new(inner, commands());
+
+        let ctoken = CancellationToken::new();
+        let canceller = ctoken.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+        let summary = stroker
+            .replay(
+                &ReplayOptions {
+                    time_scale: 1.0,
+                    start_at_ms: 100,
+                },
+                &ctoken,
+            )
+            .await
+            .unwrap();
+        assert_eq!(summary.commands_sent, 1);
+        assert!(summary.cancelled);
+
+        let record: Vec<_> = rx
+            .drain()
+            .filter_map(StrokerCommand::into_movement)
+            .collect();
+        assert_eq!(record.len(), 1);
+        assert_eq!(record[0].1.axis(), AxisId(4));
+    }
+}
@@ -3,7 +3,7 @@
 //! This library lets you control strokers (adult toys).
 //!
 //! Currently this library only supports strokers that implement
-//! the T-Code protocol over a serial port,
+//! the T-Code protocol over a serial port (or UDP),
 //! such as the [Tempest MAx] OSR2, OSR2+, SR6, SSR1 and similar derivatives.
 //!
 //! (These machines are self-built using a 3D printer and relatively accessible hardware.
@@ -13,20 +13,35 @@
 //!
 //! [Tempest MAx]: https://www.patreon.com/tempestvr
 
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use config::{RootConfig, StrokerConfig};
-use devices::AnyStroker;
+use config::{LimitsConfig, RootConfig, StrokerConfig};
+use devices::{
+    AnyStroker, CompositeStroker, DryRunStroker, JsonLinesStroker, PulsedStroker, ReplayStroker,
+};
 use eyre::ContextCompat;
+use lock::StrokerLock;
 pub use strokers_core as core;
+use strokers_core::{AxisDescriptor, AxisId, AxisKind};
 use strokers_device_debug::DebugStroker;
-use strokers_device_tcode::SerialTCodeStroker;
+use strokers_device_tcode::{SerialTCodeStroker, UdpTCodeStroker};
 use thiserror::Error;
+use tracing::warn;
+
+pub mod command_log;
 
 pub mod config;
 
+pub mod config_edit;
+
 pub mod devices;
 
+pub mod lock;
+
 #[derive(Debug, Error)]
 pub enum StrokersError {
     #[error("i/o error: {0}")]
@@ -35,9 +50,24 @@ pub enum StrokersError {
     #[error("failed to deserialise TOML config at {1:?}: {0}")]
     ConfigDeserialisationError(toml::de::Error, PathBuf),
 
+    #[error("failed to parse TOML config at {1:?} to save to it: {0}")]
+    ConfigEditError(toml_edit::TomlError, PathBuf),
+
+    #[error("config at {0:?} is read-only, so can't be saved to")]
+    ReadOnlyConfig(PathBuf),
+
+    #[error("invalid configuration: {0}")]
+    Configuration(String),
+
+    #[error("failed to read command log at {1:?}: {0:?}")]
+    CommandLog(eyre::Error, PathBuf),
+
     #[error("failed to connect to stroker: {0:?}")]
     Connection(eyre::Error),
 
+    #[error("stroker {0} is in use by another program")]
+    InUse(String),
+
     #[error("unexpected error: {0:?}")]
     Unexpected(eyre::Error),
 }
@@ -48,15 +78,18 @@ pub enum StrokersError {
 ///
 /// In any case the environment variable `STROKERS_CONFIG` overrides this location.
 pub async fn load_config() -> Result<RootConfig, StrokersError> {
+    load_config_from_path(&config_path()?).await
+}
+
+/// Where [`load_config`] loads the Strokers configuration from.
+pub fn config_path() -> Result<PathBuf, StrokersError> {
     if let Ok(env_var) = std::env::var("STROKERS_CONFIG") {
-        let path = PathBuf::from(env_var);
-        load_config_from_path(&path).await
+        Ok(PathBuf::from(env_var))
     } else {
         let config_dir = dirs::config_dir()
             .context("can't find config_dir()")
             .map_err(StrokersError::Unexpected)?;
-        let path = config_dir.join("strokers.toml");
-        load_config_from_path(&path).await
+        Ok(config_dir.join("strokers.toml"))
     }
 }
 
@@ -72,14 +105,168 @@ pub async fn load_config_from_path(path: &Path) -> Result<RootConfig, StrokersEr
 }
 
 /// Attempt to open a stroker from its configuration.
+///
+/// Prefer [`open_configured_stroker`] if you have the whole configuration,
+/// as some strokers (e.g. `dry_run`) make use of the limits.
+///
+/// Strokers on serial ports are locked (see [`lock::StrokerLock`]) for as long as they're open,
+/// failing with [`StrokersError::InUse`] if another program has one open already.
 pub async fn open_stroker(config: &StrokerConfig) -> Result<AnyStroker, StrokersError> {
+    open_stroker_with_limits(config, &BTreeMap::new()).await
+}
+
+/// Attempt to open the stroker described by the whole configuration.
+pub async fn open_configured_stroker(config: &RootConfig) -> Result<AnyStroker, StrokersError> {
+    open_stroker_with_limits(&config.stroker, &config.limits).await
+}
+
+/// Attempt to open the `replay` stroker described by the whole configuration,
+/// so that its command log can be replayed with [`ReplayStroker::replay`].
+pub async fn open_configured_replay(
+    config: &RootConfig,
+) -> Result<ReplayStroker<AnyStroker>, StrokersError> {
+    let StrokerConfig::Replay { path, inner } = &config.stroker else {
+        return Err(StrokersError::Configuration(
+            "the configured stroker is not a replay stroker".to_owned(),
+        ));
+    };
+    open_replay(path, inner, &config.limits).await
+}
+
+async fn open_replay(
+    path: &Path,
+    inner: &StrokerConfig,
+    limits: &BTreeMap<AxisKind, LimitsConfig>,
+) -> Result<ReplayStroker<AnyStroker>, StrokersError> {
+    // Boxed because of the recursion
+    let inner = Box::pin(open_stroker_with_limits(inner, limits)).await?;
+    ReplayStroker::open(inner, path)
+        .await
+        .map_err(|err| StrokersError::CommandLog(err, path.to_owned()))
+}
+
+async fn open_stroker_with_limits(
+    config: &StrokerConfig,
+    limits: &BTreeMap<AxisKind, LimitsConfig>,
+) -> Result<AnyStroker, StrokersError> {
     match config {
         StrokerConfig::TCodeSerial { serial_port, baud } => {
+            let lock = lock_stroker(serial_port)?;
             let stroker = SerialTCodeStroker::connect(serial_port, *baud)
                 .await
                 .map_err(StrokersError::Connection)?;
+            Ok(AnyStroker::new(stroker).with_lock(lock))
+        }
+        StrokerConfig::TCodeUdp {
+            host,
+            port,
+            axes,
+            repeat,
+            handshake,
+        } => {
+            let stroker =
+                UdpTCodeStroker::connect((host.as_str(), *port), axes, *repeat, *handshake)
+                    .await
+                    .map_err(StrokersError::Connection)?;
+            Ok(AnyStroker::new(stroker))
+        }
+        #[cfg(feature = "kiiroo")]
+        StrokerConfig::KiirooLaunch { device_name } => {
+            let stroker = strokers_device_kiiroo::KiirooLaunchStroker::connect(device_name)
+                .await
+                .map_err(StrokersError::Connection)?;
+            Ok(AnyStroker::new(stroker))
+        }
+        #[cfg(not(feature = "kiiroo"))]
+        StrokerConfig::KiirooLaunch { .. } => Err(StrokersError::Configuration(
+            "kiiroo_launch strokers need strokers to be built with the `kiiroo` feature".to_owned(),
+        )),
+        StrokerConfig::Composite { devices, routes } => {
+            let mut opened = BTreeMap::new();
+            for (device_name, device_config) in devices {
+                // Boxed because of the recursion
+                let device = Box::pin(open_stroker_with_limits(device_config, limits)).await?;
+                opened.insert(device_name.clone(), device);
+            }
+            Ok(AnyStroker::new(CompositeStroker::new(opened, routes)?))
+        }
+        StrokerConfig::Pulsed {
+            inner,
+            axes,
+            period_ms,
+        } => {
+            if *period_ms == 0 {
+                return Err(StrokersError::Configuration(
+                    "pulsed stroker's period_ms must not be 0".to_owned(),
+                ));
+            }
+            // Boxed because of the recursion
+            let inner = Box::pin(open_stroker_with_limits(inner, limits)).await?;
+            Ok(AnyStroker::new(PulsedStroker::new(
+                inner,
+                axes,
+                Duration::from_millis(*period_ms),
+            )))
+        }
+        StrokerConfig::Replay { path, inner } => Ok(AnyStroker::new(
+            Box::pin(open_replay(path, inner, limits)).await?,
+        )),
+        StrokerConfig::DryRun { axes } => Ok(AnyStroker::new(DryRunStroker::new(
+            pretend_axes(axes),
+            limits.clone(),
+        ))),
+        StrokerConfig::JsonLines { path, axes } => {
+            let stroker = match path {
+                Some(path) => JsonLinesStroker::open(pretend_axes(axes), path)
+                    .await
+                    .map_err(StrokersError::Connection)?,
+                None => JsonLinesStroker::stdout(pretend_axes(axes)),
+            };
             Ok(AnyStroker::new(stroker))
         }
-        StrokerConfig::Debug => Ok(AnyStroker::new(DebugStroker::new())),
+        StrokerConfig::Debug {
+            axes,
+            verbosity,
+            command_delay_ms,
+        } => {
+            // Numbered from 1, like the debug stroker's default axes
+            let axes = axes
+                .iter()
+                .zip(1..)
+                .map(|(&axis_kind, id)| (AxisId(id), axis_kind))
+                .collect();
+            Ok(AnyStroker::new(
+                DebugStroker::builder()
+                    .axes(axes)
+                    .verbosity(*verbosity)
+                    .command_delay(Duration::from_millis(*command_delay_ms))
+                    .build(),
+            ))
+        }
     }
 }
+
+/// Locks the stroker on the given device, or carries on without a lock (with a warning)
+/// if it can't be locked for any reason but being in use.
+fn lock_stroker(device: &str) -> Result<Option<StrokerLock>, StrokersError> {
+    match StrokerLock::try_acquire(device) {
+        Ok(lock) => Ok(Some(lock)),
+        Err(err @ StrokersError::InUse(_)) => Err(err),
+        Err(err) => {
+            warn!("can't lock stroker {device}, so not keeping other programs off it: {err}");
+            Ok(None)
+        }
+    }
+}
+
+/// Number the given kinds of axes, for strokers that don't have real axes.
+fn pretend_axes(axis_kinds: &[AxisKind]) -> Vec<AxisDescriptor> {
+    axis_kinds
+        .iter()
+        .enumerate()
+        .map(|(idx, &axis_kind)| AxisDescriptor {
+            axis_id: AxisId(idx as u32),
+            axis_kind,
+        })
+        .collect()
+}
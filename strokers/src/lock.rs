@@ -0,0 +1,106 @@
+//! Advisory locks on strokers, so that two programs (e.g. two mpv windows) don't both
+//! drive the same stroker at once.
+
+use std::{fs::File, path::PathBuf};
+
+use fs4::{FileExt, TryLockError};
+
+use crate::StrokersError;
+
+/// A lock on a stroker, held until dropped.
+///
+/// The lock is on a file in the temporary directory named after the device, so it only keeps out
+/// other programs that take the same lock (such as anything else using this library).
+/// It's freed by the OS when the program holding it exits, however that happens.
+#[derive(Debug)]
+pub struct StrokerLock {
+    _file: File,
+}
+
+impl StrokerLock {
+    /// Locks the stroker on the given device (e.g. a serial port's path),
+    /// failing with [`StrokersError::InUse`] if something else already has it locked.
+    pub fn try_acquire(device: &str) -> Result<StrokerLock, StrokersError> {
+        let path = lock_path(device);
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(StrokersError::IoError)?;
+        // Not `file.try_lock()`, which would be the standard library's on newer toolchains
+        match FileExt::try_lock(&file) {
+            Ok(()) => Ok(StrokerLock { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(StrokersError::InUse(device.to_owned())),
+            Err(TryLockError::Error(err)) => Err(StrokersError::IoError(err)),
+        }
+    }
+}
+
+/// The lock file for a device, with anything that can't go in a file name replaced.
+///
+/// Where the device is a path to something that exists, it's resolved first, so that the
+/// same stroker reached through a link (e.g. `/dev/serial/by-id/...`) gets the same lock.
+fn lock_path(device: &str) -> PathBuf {
+    let device = match std::fs::canonicalize(device) {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(_) => device.to_owned(),
+    };
+    let name: String = device
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    std::env::temp_dir().join(format!("strokers-{name}.lock"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::StrokersError;
+
+    use super::{lock_path, StrokerLock};
+
+    #[test]
+    fn test_lock() {
+        let device = format!("/dev/ttyTEST{}", std::process::id());
+        let lock = StrokerLock::try_acquire(&device).unwrap();
+        assert!(matches!(
+            StrokerLock::try_acquire(&device),
+            Err(StrokersError::InUse(in_use)) if in_use == device
+        ));
+        // Other devices are separate
+        let other = StrokerLock::try_acquire(&format!("{device}x")).unwrap();
+
+        // Free again once the first is done with it
+        drop(lock);
+        let lock = StrokerLock::try_acquire(&device).unwrap();
+
+        drop((lock, other));
+        let _ = std::fs::remove_file(lock_path(&device));
+        let _ = std::fs::remove_file(lock_path(&format!("{device}x")));
+    }
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            lock_path("/dev/ttyUSB0").file_name().unwrap(),
+            "strokers-_dev_ttyUSB0.lock"
+        );
+        assert_eq!(lock_path("COM5").file_name().unwrap(), "strokers-COM5.lock");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_path_through_link() {
+        let dir = std::env::temp_dir().join(format!("strokers_lock_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let device = dir.join("ttyTEST0");
+        let link = dir.join("by-id-stroker");
+        std::fs::write(&device, "").unwrap();
+        std::os::unix::fs::symlink(&device, &link).unwrap();
+
+        let lock_of = |path: &std::path::Path| lock_path(path.to_str().unwrap());
+        assert_eq!(lock_of(&link), lock_of(&device));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,8 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod time;
 
 #[async_trait]
 pub trait Stroker {
@@ -14,8 +17,23 @@ pub trait Stroker {
     /// Returns a human-readable description of the stroker device.
     /// Returns None if this device doesn't support that.
     fn description(&mut self) -> eyre::Result<Option<String>>;
+
+    /// The shortest time, in milliseconds, that the device needs between movements,
+    /// if it can't keep up with movements as often as funscripts can have actions.
+    /// Callers should thin out actions that are closer together than this.
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        None
+    }
 }
 
+/// The stroker has lost its connection to the device.
+///
+/// Strokers return this (wrapped in an [`eyre::Report`]) so that callers can tell
+/// connection loss apart from other errors using `report.downcast_ref::<Disconnected>()`.
+#[derive(Clone, Debug, Error)]
+#[error("stroker disconnected")]
+pub struct Disconnected;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct AxisId(pub u32);
 
@@ -53,6 +71,43 @@ pub enum AxisKind {
     Lubricant,
 }
 
+impl AxisKind {
+    /// Converts a T-Code axis name (e.g. `L0`) to the corresponding kind of axis.
+    /// Returns `None` if the axis name is not recognised.
+    pub fn from_tcode_name(tcode_axis_name: &str) -> Option<AxisKind> {
+        Some(match tcode_axis_name {
+            "L0" => AxisKind::Stroke,
+            "L1" => AxisKind::Surge,
+            "L2" => AxisKind::Sway,
+            "R0" => AxisKind::Twist,
+            "R1" => AxisKind::Roll,
+            "R2" => AxisKind::Pitch,
+            "V0" => AxisKind::Vibration,
+            "A0" => AxisKind::Valve,
+            "A1" => AxisKind::Suction,
+            "A2" => AxisKind::Lubricant,
+            _ => return None,
+        })
+    }
+
+    /// The conventional T-Code axis name (e.g. `L0`) for this kind of axis.
+    /// Returns `None` if there is no T-Code axis for that kind of axis.
+    pub fn tcode_name(self) -> Option<&'static str> {
+        Some(match self {
+            AxisKind::Stroke => "L0",
+            AxisKind::Surge => "L1",
+            AxisKind::Sway => "L2",
+            AxisKind::Twist => "R0",
+            AxisKind::Roll => "R1",
+            AxisKind::Pitch => "R2",
+            AxisKind::Vibration => "V0",
+            AxisKind::Valve => "A0",
+            AxisKind::Suction => "A1",
+            AxisKind::Lubricant => "A2",
+        })
+    }
+}
+
 /// Describes a desired movement.
 #[derive(Clone, Debug)]
 pub struct Movement {
@@ -76,7 +131,7 @@ impl Movement {
     /// Returns the Movement or `None` if the specified parameters were not valid.
     pub fn new(axis: AxisId, target: f32, ramp_time_milliseconds: u32) -> Option<Movement> {
         const MAX_RAMP_TIME_MS: u32 = 9999999;
-        if target < 0.0 || target > 1.0 {
+        if !(0.0..=1.0).contains(&target) {
             return None;
         };
         if !target.is_finite() {
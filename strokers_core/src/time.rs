@@ -0,0 +1,38 @@
+//! Times as people write them, e.g. in funscripts' metadata.
+
+use eyre::{bail, Context, ContextCompat};
+
+/// Parses `[hh:]mm:ss[.fff]` (or plain milliseconds, in a string) into milliseconds.
+///
+/// Used for the times in funscripts' metadata and the timestamps in T-Code scripts.
+pub fn parse_time_text(text: &str) -> eyre::Result<u32> {
+    let text = text.trim();
+    if !text.contains(':') {
+        return text.parse().context("bad millisecond time");
+    }
+
+    let (clock, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() > 3 {
+        bail!("too many parts in time {text:?}");
+    }
+    let mut seconds = 0u32;
+    for part in parts {
+        let value: u32 = part.parse().context("bad time")?;
+        seconds = seconds
+            .checked_mul(60)
+            .and_then(|seconds| seconds.checked_add(value))
+            .with_context(|| format!("time {text:?} out of range"))?;
+    }
+    let millis: u32 = if fraction.is_empty() {
+        0
+    } else {
+        // Only the first 3 digits are significant
+        let digits: String = fraction.chars().chain("000".chars()).take(3).collect();
+        digits.parse().context("bad fraction of a second")?
+    };
+    seconds
+        .checked_mul(1000)
+        .and_then(|seconds_millis| seconds_millis.checked_add(millis))
+        .with_context(|| format!("time {text:?} out of range"))
+}
@@ -1,15 +1,127 @@
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 use eyre::anyhow;
+use serde::{Deserialize, Serialize};
 use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
-use tracing::{debug, error};
+use tracing::{debug, error, info};
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+/// How often a summary is logged with [`Verbosity::Summary`].
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How much a [`DebugStroker`] logs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Log every command.
+    #[default]
+    EveryCommand,
+    /// Only log how many movements there have been, every few seconds.
+    /// Stops are still logged individually.
+    Summary,
+}
 
 /// Does not connect to a real device; only emits log lines.
-#[non_exhaustive]
-pub struct DebugStroker {}
+///
+/// Use [`DebugStroker::builder`] to customise it.
+pub struct DebugStroker {
+    axes: Vec<(AxisId, AxisKind)>,
+    verbosity: Verbosity,
+    command_delay: Duration,
+    /// Movements since the last summary
+    movements_since_summary: usize,
+    last_summary: Instant,
+}
 
 impl DebugStroker {
     pub fn new() -> DebugStroker {
-        DebugStroker {}
+        DebugStroker::builder().build()
+    }
+
+    /// Create a debug stroker with the given axes instead of the default ones.
+    pub fn with_axes(axes: Vec<(AxisId, AxisKind)>) -> DebugStroker {
+        DebugStroker::builder().axes(axes).build()
+    }
+
+    pub fn builder() -> DebugStrokerBuilder {
+        DebugStrokerBuilder::default()
+    }
+
+    async fn delay(&self) {
+        if !self.command_delay.is_zero() {
+            tokio::time::sleep(self.command_delay).await;
+        }
+    }
+
+    /// Counts a movement, logging a summary if one is due.
+    fn count_movement(&mut self) {
+        self.movements_since_summary += 1;
+        let elapsed = self.last_summary.elapsed();
+        if elapsed >= SUMMARY_INTERVAL {
+            info!(
+                "{} movements in the last {:.1} s",
+                self.movements_since_summary,
+                elapsed.as_secs_f32()
+            );
+            self.movements_since_summary = 0;
+            self.last_summary = Instant::now();
+        }
+    }
+}
+
+impl Default for DebugStroker {
+    fn default() -> Self {
+        DebugStroker::new()
+    }
+}
+
+/// Builds a [`DebugStroker`].
+#[derive(Clone, Debug)]
+pub struct DebugStrokerBuilder {
+    axes: Vec<(AxisId, AxisKind)>,
+    verbosity: Verbosity,
+    command_delay: Duration,
+}
+
+impl Default for DebugStrokerBuilder {
+    fn default() -> Self {
+        DebugStrokerBuilder {
+            axes: AXES.to_vec(),
+            verbosity: Verbosity::default(),
+            command_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl DebugStrokerBuilder {
+    /// Use the given axes instead of the default ones (those of an OSR2/SR6-style device).
+    pub fn axes(mut self, axes: Vec<(AxisId, AxisKind)>) -> Self {
+        self.axes = axes;
+        self
+    }
+
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Wait this long before completing each command, to roughly simulate the latency of a real device.
+    pub fn command_delay(mut self, command_delay: Duration) -> Self {
+        self.command_delay = command_delay;
+        self
+    }
+
+    pub fn build(self) -> DebugStroker {
+        DebugStroker {
+            axes: self.axes,
+            verbosity: self.verbosity,
+            command_delay: self.command_delay,
+            movements_since_summary: 0,
+            last_summary: Instant::now(),
+        }
     }
 }
 
@@ -25,8 +137,9 @@ const AXES: &[(AxisId, AxisKind)] = &[
 #[async_trait]
 impl Stroker for DebugStroker {
     fn axes(&mut self) -> Vec<AxisDescriptor> {
-        let result = AXES
-            .into_iter()
+        let result = self
+            .axes
+            .iter()
             .cloned()
             .map(|(axis_id, axis_kind)| AxisDescriptor { axis_id, axis_kind })
             .collect();
@@ -36,18 +149,23 @@ impl Stroker for DebugStroker {
 
     async fn stop(&mut self) -> eyre::Result<()> {
         debug!("stop()");
+        self.delay().await;
         Ok(())
     }
 
     async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
-        match AXES.into_iter().find(|(id, _)| *id == movement.axis()) {
+        match self.axes.iter().find(|(id, _)| *id == movement.axis()) {
             Some((_, axis_kind)) => {
-                debug!(
-                    "movement({axis_kind:?}={:?} to {:.4} in {} ms)",
-                    movement.axis(),
-                    movement.target(),
-                    movement.ramp_time_milliseconds()
-                );
+                match self.verbosity {
+                    Verbosity::EveryCommand => debug!(
+                        "movement({axis_kind:?}={:?} to {:.4} in {} ms)",
+                        movement.axis(),
+                        movement.target(),
+                        movement.ramp_time_milliseconds()
+                    ),
+                    Verbosity::Summary => self.count_movement(),
+                }
+                self.delay().await;
                 Ok(())
             }
             None => {
@@ -68,3 +186,35 @@ impl Stroker for DebugStroker {
         Ok(Some(result))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use strokers_core::{AxisId, AxisKind, Movement, Stroker};
+
+    use crate::{DebugStroker, Verbosity};
+
+    #[tokio::test]
+    async fn test_builder() {
+        let mut stroker = DebugStroker::builder()
+            .axes(vec![(AxisId(7), AxisKind::Vibration)])
+            .verbosity(Verbosity::Summary)
+            .command_delay(Duration::from_millis(50))
+            .build();
+        assert_eq!(stroker.axes().len(), 1);
+
+        let start = Instant::now();
+        stroker
+            .movement(Movement::new(AxisId(7), 0.5, 0).unwrap())
+            .await
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(stroker.movements_since_summary, 1);
+
+        assert!(stroker
+            .movement(Movement::new(AxisId(1), 0.5, 0).unwrap())
+            .await
+            .is_err());
+    }
+}
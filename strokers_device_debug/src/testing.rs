@@ -0,0 +1,113 @@
+//! Strokers for use in tests.
+
+use async_trait::async_trait;
+use eyre::{anyhow, bail};
+use flume::{Receiver, Sender};
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker};
+use tokio::time::Instant;
+
+use crate::AXES;
+
+/// A command received by a [`ChannelStroker`], along with when it was received
+/// (by the Tokio clock, so that tests can pause it).
+#[derive(Clone, Debug)]
+pub enum StrokerCommand {
+    Stop { at: Instant },
+    Movement { at: Instant, movement: Movement },
+}
+
+impl StrokerCommand {
+    /// The movement and when it was received, if this is one rather than a stop.
+    pub fn into_movement(self) -> Option<(Instant, Movement)> {
+        match self {
+            StrokerCommand::Stop { .. } => None,
+            StrokerCommand::Movement { at, movement } => Some((at, movement)),
+        }
+    }
+}
+
+/// Does not connect to a real device, but sends every command it receives
+/// (as a [`StrokerCommand`]) down a channel so that tests can check them.
+pub struct ChannelStroker {
+    axes: Vec<(AxisId, AxisKind)>,
+    tx: Sender<StrokerCommand>,
+}
+
+impl ChannelStroker {
+    /// Create a channel stroker with the same axes as [`crate::DebugStroker::new`],
+    /// along with the receiving end of its channel.
+    pub fn new() -> (ChannelStroker, Receiver<StrokerCommand>) {
+        ChannelStroker::with_axes(AXES.to_vec())
+    }
+
+    /// Create a channel stroker with the given axes, along with the receiving end of its channel.
+    pub fn with_axes(axes: Vec<(AxisId, AxisKind)>) -> (ChannelStroker, Receiver<StrokerCommand>) {
+        let (tx, rx) = flume::unbounded();
+        (ChannelStroker { axes, tx }, rx)
+    }
+
+    fn send(&self, command: StrokerCommand) -> eyre::Result<()> {
+        self.tx
+            .send(command)
+            .map_err(|_| anyhow!("ChannelStroker's receiver has gone away"))
+    }
+}
+
+#[async_trait]
+impl Stroker for ChannelStroker {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.axes
+            .iter()
+            .map(|&(axis_id, axis_kind)| AxisDescriptor { axis_id, axis_kind })
+            .collect()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.send(StrokerCommand::Stop { at: Instant::now() })
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        if !self.axes.iter().any(|(id, _)| *id == movement.axis()) {
+            bail!("Invalid axis");
+        }
+        self.send(StrokerCommand::Movement {
+            at: Instant::now(),
+            movement,
+        })
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(Some("ChannelStroker".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::{AxisId, Movement, Stroker};
+
+    use super::{ChannelStroker, StrokerCommand};
+
+    #[tokio::test]
+    async fn test_commands_are_forwarded() {
+        let (mut stroker, rx) = ChannelStroker::new();
+
+        stroker
+            .movement(Movement::new(AxisId(1), 0.25, 100).unwrap())
+            .await
+            .unwrap();
+        assert!(stroker
+            .movement(Movement::new(AxisId(99), 0.25, 100).unwrap())
+            .await
+            .is_err());
+        stroker.stop().await.unwrap();
+
+        let commands: Vec<StrokerCommand> = rx.drain().collect();
+        assert!(matches!(
+            &commands[..],
+            [
+                StrokerCommand::Movement { movement, .. },
+                StrokerCommand::Stop { .. }
+            ] if movement.axis() == AxisId(1) && movement.target() == 0.25
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use btleplug::{
+    api::{Central, Characteristic, Manager as _, Peripheral as _, ScanFilter, WriteType},
+    platform::{Manager, Peripheral},
+};
+use eyre::{bail, Context, ContextCompat};
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Disconnected, Movement, Stroker};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, warn};
+use uuid::{uuid, Uuid};
+
+use crate::protocol::{duration_for, encode_movement, MIN_SPEED};
+
+/// Characteristic that position+speed commands are written to.
+const TX_CHARACTERISTIC: Uuid = uuid!("88f80581-0000-01e6-aace-0002a5d5c51b");
+
+/// Characteristic that switches the device into command mode.
+const CMD_CHARACTERISTIC: Uuid = uuid!("88f80583-0000-01e6-aace-0002a5d5c51b");
+
+/// How long to look for the device before giving up.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check for newly-discovered devices whilst scanning.
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The only axis.
+const STROKE_AXIS: AxisId = AxisId(0);
+
+/// Shortest time between commands that the Launch copes with over BLE;
+/// it stutters or drops commands that come faster.
+const MIN_COMMAND_INTERVAL_MS: u32 = 100;
+
+/// The last movement that was sent, to estimate where the device is now.
+struct Motion {
+    from: u8,
+    to: u8,
+    started: Instant,
+    duration_ms: u32,
+}
+
+impl Motion {
+    /// Estimates the current position, assuming constant speed.
+    fn position_now(&self) -> u8 {
+        let elapsed_ms = self.started.elapsed().as_millis() as f32;
+        if self.duration_ms == 0 || elapsed_ms >= self.duration_ms as f32 {
+            return self.to;
+        }
+        let progress = elapsed_ms / self.duration_ms as f32;
+        (self.from as f32 + (self.to as f32 - self.from as f32) * progress).round() as u8
+    }
+}
+
+/// A Fleshlight Launch or Kiiroo Onyx, connected over Bluetooth LE.
+///
+/// These have a single stroke axis, which is moved by asking for a position and a speed.
+pub struct KiirooLaunchStroker {
+    peripheral: Peripheral,
+    tx: Characteristic,
+    device_name: String,
+    motion: Motion,
+}
+
+impl KiirooLaunchStroker {
+    /// Scan for a device advertising itself as `device_name` (e.g. `Launch`) and connect to it.
+    pub async fn connect(device_name: &str) -> eyre::Result<KiirooLaunchStroker> {
+        let manager = Manager::new()
+            .await
+            .context("failed to start Bluetooth manager")?;
+        let adapter = manager
+            .adapters()
+            .await
+            .context("failed to list Bluetooth adapters")?
+            .into_iter()
+            .next()
+            .context("no Bluetooth adapter")?;
+
+        adapter
+            .start_scan(ScanFilter::default())
+            .await
+            .context("failed to start scanning")?;
+        let peripheral = find_peripheral(&adapter, device_name).await;
+        if let Err(err) = adapter.stop_scan().await {
+            warn!("failed to stop scanning: {err:?}");
+        }
+        let peripheral = peripheral?;
+
+        peripheral
+            .connect()
+            .await
+            .with_context(|| format!("failed to connect to {device_name:?}"))?;
+        peripheral
+            .discover_services()
+            .await
+            .context("failed to discover services")?;
+
+        let characteristics = peripheral.characteristics();
+        let tx = characteristics
+            .iter()
+            .find(|c| c.uuid == TX_CHARACTERISTIC)
+            .context("device has no command characteristic; is it really a Launch?")?
+            .clone();
+        let cmd = characteristics
+            .iter()
+            .find(|c| c.uuid == CMD_CHARACTERISTIC)
+            .context("device has no mode characteristic; is it really a Launch?")?;
+        peripheral
+            .write(cmd, &[0x00], WriteType::WithResponse)
+            .await
+            .context("failed to switch device into command mode")?;
+        debug!("connected to {device_name:?}");
+
+        // We don't know where the device is; assume it's at the bottom, as it is when switched on.
+        Ok(KiirooLaunchStroker {
+            peripheral,
+            tx,
+            device_name: device_name.to_owned(),
+            motion: Motion {
+                from: 0,
+                to: 0,
+                started: Instant::now(),
+                duration_ms: 0,
+            },
+        })
+    }
+
+    async fn send(&mut self, position: u8, speed: u8) -> eyre::Result<()> {
+        let from = self.motion.position_now();
+        if let Err(err) = self
+            .peripheral
+            .write(&self.tx, &[position, speed], WriteType::WithoutResponse)
+            .await
+        {
+            if !self.peripheral.is_connected().await.unwrap_or(false) {
+                return Err(Disconnected.into());
+            }
+            return Err(err).context("failed to send command");
+        }
+        self.motion = Motion {
+            from,
+            to: position,
+            started: Instant::now(),
+            duration_ms: duration_for(position.abs_diff(from), speed),
+        };
+        Ok(())
+    }
+}
+
+/// Polls the adapter's discovered devices until one with the given name turns up.
+async fn find_peripheral(
+    adapter: &impl Central<Peripheral = Peripheral>,
+    device_name: &str,
+) -> eyre::Result<Peripheral> {
+    let deadline = Instant::now() + SCAN_TIMEOUT;
+    while Instant::now() < deadline {
+        for peripheral in adapter
+            .peripherals()
+            .await
+            .context("failed to list discovered devices")?
+        {
+            let Ok(Some(properties)) = peripheral.properties().await else {
+                continue;
+            };
+            if properties.local_name.as_deref() == Some(device_name) {
+                return Ok(peripheral);
+            }
+        }
+        sleep(SCAN_POLL_INTERVAL).await;
+    }
+    bail!("no device called {device_name:?} found within {SCAN_TIMEOUT:?}");
+}
+
+#[async_trait]
+impl Stroker for KiirooLaunchStroker {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        vec![AxisDescriptor {
+            axis_id: STROKE_AXIS,
+            axis_kind: AxisKind::Stroke,
+        }]
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        // There is no stop command, but moving to where we are now has much the same effect.
+        let position = self.motion.position_now();
+        self.send(position, MIN_SPEED).await
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        if movement.axis() != STROKE_AXIS {
+            bail!("no such axis: {:?}", movement.axis());
+        }
+        let [position, speed] = encode_movement(self.motion.position_now(), &movement);
+        self.send(position, speed).await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(Some(format!(
+            "Kiiroo Launch {:?} ({})",
+            self.device_name,
+            self.peripheral.address()
+        )))
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        Some(MIN_COMMAND_INTERVAL_MS)
+    }
+}
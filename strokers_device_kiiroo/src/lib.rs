@@ -0,0 +1,4 @@
+mod launch;
+pub mod protocol;
+
+pub use launch::KiirooLaunchStroker;
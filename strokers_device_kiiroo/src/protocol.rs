@@ -0,0 +1,120 @@
+//! The Launch protocol: each command is two bytes, a position (0–99) and a speed (0–99).
+//!
+//! The device doesn't take a duration, so we have to work out the speed that
+//! covers the distance in the requested time. The relationship between speed, distance and
+//! duration was measured by the community (see funjack's `launchcontrol`):
+//!
+//! `duration_ms = (speed / 25000) ^ -0.95 * distance / 90`,
+//! where `distance` is in positions.
+
+use strokers_core::Movement;
+
+/// Highest position; positions range from 0 (bottom) to this (top).
+pub const MAX_POSITION: u8 = 99;
+
+/// Slowest speed we send.
+/// The Launch stalls or moves jerkily at speeds much below this.
+pub const MIN_SPEED: u8 = 20;
+
+/// Fastest speed the Launch accepts.
+pub const MAX_SPEED: u8 = 99;
+
+/// Converts a normalised target (0.0 to 1.0) into a position.
+pub fn position_from_target(target: f32) -> u8 {
+    (target.clamp(0.0, 1.0) * MAX_POSITION as f32).round() as u8
+}
+
+/// Converts a position into a normalised target (0.0 to 1.0).
+pub fn target_from_position(position: u8) -> f32 {
+    position.min(MAX_POSITION) as f32 / MAX_POSITION as f32
+}
+
+/// The speed needed to move `distance` positions in `duration_ms` milliseconds,
+/// clamped to what the Launch can do.
+pub fn speed_for(distance: u8, duration_ms: u32) -> u8 {
+    if distance == 0 {
+        return MIN_SPEED;
+    }
+    if duration_ms == 0 {
+        return MAX_SPEED;
+    }
+    let speed = 25000.0 * (duration_ms as f64 * 90.0 / distance as f64).powf(-1.05);
+    speed.round().clamp(MIN_SPEED as f64, MAX_SPEED as f64) as u8
+}
+
+/// How long, in milliseconds, it takes to move `distance` positions at `speed`.
+pub fn duration_for(distance: u8, speed: u8) -> u32 {
+    if distance == 0 {
+        return 0;
+    }
+    let speed = speed.clamp(1, MAX_SPEED) as f64;
+    ((speed / 25000.0).powf(-0.95) * distance as f64 / 90.0).round() as u32
+}
+
+/// Encodes the command to perform `movement`, starting from `current_position`.
+pub fn encode_movement(current_position: u8, movement: &Movement) -> [u8; 2] {
+    let position = position_from_target(movement.target());
+    let speed = speed_for(
+        position.abs_diff(current_position),
+        movement.ramp_time_milliseconds(),
+    );
+    [position, speed]
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::{AxisId, Movement};
+
+    use super::{
+        duration_for, encode_movement, position_from_target, speed_for, target_from_position,
+        MAX_SPEED, MIN_SPEED,
+    };
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(position_from_target(0.0), 0);
+        assert_eq!(position_from_target(0.5), 50);
+        assert_eq!(position_from_target(1.0), 99);
+        assert_eq!(target_from_position(99), 1.0);
+        assert_eq!(target_from_position(0), 0.0);
+    }
+
+    #[test]
+    fn test_speed_for() {
+        // Known values from the measured curve
+        assert_eq!(speed_for(90, 500), 37);
+        assert_eq!(speed_for(45, 250), 37);
+        assert_eq!(speed_for(99, 300), 69);
+
+        // Out of range: clamped
+        assert_eq!(speed_for(99, 10), MAX_SPEED);
+        assert_eq!(speed_for(99, 0), MAX_SPEED);
+        assert_eq!(speed_for(5, 10_000), MIN_SPEED);
+        assert_eq!(speed_for(0, 100), MIN_SPEED);
+    }
+
+    #[test]
+    fn test_speed_duration_round_trip() {
+        for distance in [10, 50, 99] {
+            for duration_ms in [300, 500, 1000] {
+                let speed = speed_for(distance, duration_ms);
+                if speed == MIN_SPEED || speed == MAX_SPEED {
+                    continue;
+                }
+                let round_trip = duration_for(distance, speed) as f64;
+                // One step of speed is worth a few percent of duration
+                let error = (round_trip - duration_ms as f64).abs() / duration_ms as f64;
+                assert!(
+                    error < 0.05,
+                    "{distance} positions in {duration_ms} ms -> speed {speed} -> {round_trip} ms"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_movement() {
+        let movement = Movement::new(AxisId(0), 1.0, 500).unwrap();
+        assert_eq!(encode_movement(9, &movement), [99, 37]);
+    }
+}
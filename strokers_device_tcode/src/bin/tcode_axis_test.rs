@@ -3,6 +3,7 @@ use std::time::Duration;
 use eyre::Context;
 use strokers_core::{Movement, Stroker};
 use strokers_device_tcode::SerialTCodeStroker;
+use strokers_funscript::generate::{triangle, PatternParams};
 use tracing::info;
 use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -23,16 +24,29 @@ async fn main() -> eyre::Result<()> {
         .context("failed to connect to serial port T-Code device")?;
     info!("connected to t-code device");
 
+    // Down to the bottom, then up to the top, then back down, 2 seconds each
+    let pattern = triangle(&PatternParams {
+        duration_ms: 4000,
+        period_ms: 4000,
+        min: 0.0,
+        max: 1.0,
+        sample_interval_ms: 2000,
+    });
+
     for axis in stroker.axes() {
         info!("trying axis: {axis:?}");
-        stroker
-            .movement(Movement::new(axis.axis_id, 0.0, 2000).unwrap())
-            .await?;
-        tokio::time::sleep(Duration::from_secs(2)).await;
-        stroker
-            .movement(Movement::new(axis.axis_id, 1.0, 2000).unwrap())
-            .await?;
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        let mut previous_at = 0;
+        for action in &pattern {
+            let ramp_ms = match action.at - previous_at {
+                0 => 2000,
+                ramp_ms => ramp_ms,
+            };
+            previous_at = action.at;
+            stroker
+                .movement(Movement::new(axis.axis_id, action.norm_pos, ramp_ms).unwrap())
+                .await?;
+            tokio::time::sleep(Duration::from_millis(ramp_ms as u64)).await;
+        }
     }
 
     Ok(())
@@ -0,0 +1,91 @@
+//! Plays a T-Code script on a serial port T-Code device.
+//!
+//! Usage: `tcode_play <serial port> <script> [untimed interval in ms]`
+//!
+//! Whilst playing, type a command and press enter:
+//! `p` to pause, `r` to resume, `s <seconds>` to seek, `q` to abort.
+
+use std::time::Duration;
+
+use eyre::{Context, ContextCompat};
+use strokers_device_tcode::{
+    player::{parse_tcode_script, play_tcode_script, PlayerControl},
+    SerialTCodeStroker,
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::mpsc,
+};
+use tracing::{info, warn};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Baud rate of the serial port.
+const BAUD: u32 = 115200;
+
+/// Default time between lines that have no timestamp.
+const DEFAULT_UNTIMED_INTERVAL_MS: u64 = 100;
+
+fn parse_control(line: &str) -> Option<PlayerControl> {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        "p" => Some(PlayerControl::Pause),
+        "r" => Some(PlayerControl::Resume),
+        "q" => Some(PlayerControl::Abort),
+        "s" => {
+            let seconds: f64 = words.next()?.parse().ok()?;
+            Some(PlayerControl::Seek(
+                Duration::try_from_secs_f64(seconds).ok()?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> eyre::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "strokers=debug,info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE))
+        .init();
+
+    let mut args = std::env::args().skip(1);
+    let serial_port = args.next().context("no serial port given")?;
+    let script_path = args.next().context("no script given")?;
+    let untimed_interval_ms = match args.next() {
+        Some(arg) => arg.parse().context("bad untimed interval")?,
+        None => DEFAULT_UNTIMED_INTERVAL_MS,
+    };
+
+    let text = tokio::fs::read_to_string(&script_path)
+        .await
+        .with_context(|| format!("failed to read {script_path:?}"))?;
+    let commands = parse_tcode_script(&text, Duration::from_millis(untimed_interval_ms))
+        .context("failed to parse T-Code script")?;
+    info!("loaded {} commands", commands.len());
+
+    let mut stroker = SerialTCodeStroker::connect(&serial_port, BAUD)
+        .await
+        .context("failed to connect to serial port T-Code device")?;
+
+    let (control_tx, control_rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match parse_control(&line) {
+                Some(control) => {
+                    if control_tx.send(control).await.is_err() {
+                        break;
+                    }
+                }
+                None => warn!("unknown command {line:?}; try p, r, s <seconds> or q"),
+            }
+        }
+    });
+
+    let outcome = play_tcode_script(&mut stroker, &commands, control_rx).await?;
+    info!("{outcome:?}");
+    Ok(())
+}
@@ -1,5 +1,9 @@
 mod tcode;
 
+pub mod player;
 mod serial;
+mod udp;
 
 pub use serial::SerialTCodeStroker;
+pub use tcode::encode_tcode_command;
+pub use udp::UdpTCodeStroker;
@@ -0,0 +1,310 @@
+//! Plays back T-Code scripts: text files of T-Code commands, one per line,
+//! optionally with a leading timestamp, e.g.
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! 0 L05000I0500
+//! 500 L09999I0500 R05000I0500
+//! 00:01.500 L00000I1000
+//! ```
+//!
+//! Timestamps are either milliseconds or `[hh:]mm:ss[.fff]`.
+//! Lines without a timestamp are sent a fixed interval after the previous line.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use eyre::{bail, Context, ContextCompat};
+use strokers_core::time::parse_time_text;
+use tokio::{
+    sync::mpsc,
+    time::{sleep_until, Instant},
+};
+use tracing::{debug, info};
+
+/// A T-Code command (line) and when to send it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedCommand {
+    /// Time since the start of the script.
+    pub at: Duration,
+    pub tcode: String,
+}
+
+/// Something that raw T-Code commands can be sent to.
+#[async_trait]
+pub trait RawTCodeSink {
+    /// Sends one T-Code command (line), without the trailing newline.
+    async fn send_raw(&mut self, tcode: &str) -> eyre::Result<()>;
+}
+
+/// Controls a T-Code script that is being played by [`play_tcode_script`].
+#[derive(Clone, Debug)]
+pub enum PlayerControl {
+    /// Stop the device and wait for [`PlayerControl::Resume`].
+    Pause,
+    Resume,
+    /// Carry on from the given time in the script.
+    Seek(Duration),
+    /// Stop the device and stop playing.
+    Abort,
+}
+
+/// How playing a T-Code script ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayOutcome {
+    Finished,
+    Aborted,
+}
+
+/// Parses the text of a T-Code script into timed commands.
+///
+/// Lines without a timestamp are timed `untimed_interval` after the previous line
+/// (the first line without a timestamp being at the start).
+pub fn parse_tcode_script(
+    text: &str,
+    untimed_interval: Duration,
+) -> eyre::Result<Vec<TimedCommand>> {
+    let mut commands: Vec<TimedCommand> = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+
+        // T-Code commands always start with a letter, so a leading digit means a timestamp.
+        let (at, tcode) = if line.starts_with(|c: char| c.is_ascii_digit()) {
+            let (timestamp, tcode) = line
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("line {}: timestamp without a command", idx + 1))?;
+            let at_millis = parse_time_text(timestamp)
+                .with_context(|| format!("line {}: bad timestamp {timestamp:?}", idx + 1))?;
+            (Duration::from_millis(at_millis.into()), tcode.trim())
+        } else {
+            let at = match commands.last() {
+                Some(previous) => previous.at + untimed_interval,
+                None => Duration::ZERO,
+            };
+            (at, line)
+        };
+
+        if let Some(previous) = commands.last() {
+            if at < previous.at {
+                bail!("line {}: timestamp goes backwards", idx + 1);
+            }
+        }
+        commands.push(TimedCommand {
+            at,
+            tcode: tcode.to_owned(),
+        });
+    }
+    Ok(commands)
+}
+
+/// Sends the commands to `sink` at their times, until the end of the script or until aborted.
+///
+/// `control` can be used to pause, seek or abort; if it is closed, playing simply carries on.
+pub async fn play_tcode_script(
+    sink: &mut (impl RawTCodeSink + Send),
+    commands: &[TimedCommand],
+    mut control: mpsc::Receiver<PlayerControl>,
+) -> eyre::Result<PlayOutcome> {
+    let mut next_idx = 0;
+    // Script time at `started_at`
+    let mut script_base = Duration::ZERO;
+    let mut started_at = Instant::now();
+    let mut paused = false;
+    let mut control_open = true;
+
+    loop {
+        let Some(next) = commands.get(next_idx) else {
+            info!("T-Code script finished");
+            return Ok(PlayOutcome::Finished);
+        };
+        let send_at = started_at + next.at.saturating_sub(script_base);
+
+        let message = tokio::select! {
+            _ = sleep_until(send_at), if !paused => {
+                sink.send_raw(&next.tcode).await?;
+                next_idx += 1;
+                continue;
+            }
+            message = control.recv(), if control_open => message,
+        };
+
+        let Some(message) = message else {
+            control_open = false;
+            if paused {
+                // Nothing can ever resume us
+                return Ok(PlayOutcome::Aborted);
+            }
+            continue;
+        };
+        debug!("T-Code player: {message:?}");
+
+        let now = Instant::now();
+        match message {
+            PlayerControl::Pause => {
+                if !paused {
+                    script_base += now - started_at;
+                    paused = true;
+                    sink.send_raw("DSTOP").await?;
+                }
+            }
+            PlayerControl::Resume => {
+                if paused {
+                    started_at = now;
+                    paused = false;
+                }
+            }
+            PlayerControl::Seek(to) => {
+                script_base = to;
+                started_at = now;
+                next_idx = commands.partition_point(|command| command.at < to);
+            }
+            PlayerControl::Abort => {
+                sink.send_raw("DSTOP").await?;
+                return Ok(PlayOutcome::Aborted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use tokio::{sync::mpsc, time::Instant};
+
+    use super::{
+        parse_tcode_script, play_tcode_script, PlayOutcome, PlayerControl, RawTCodeSink,
+        TimedCommand,
+    };
+
+    const TIMESTAMPED: &str = include_str!("../fixtures/timestamped.tcode");
+    const UNTIMED: &str = include_str!("../fixtures/untimed.tcode");
+
+    /// Records the commands sent to it and when.
+    struct RecordingSink {
+        start: Instant,
+        sent: Vec<(u128, String)>,
+    }
+
+    #[async_trait]
+    impl RawTCodeSink for RecordingSink {
+        async fn send_raw(&mut self, tcode: &str) -> eyre::Result<()> {
+            self.sent
+                .push((self.start.elapsed().as_millis(), tcode.to_owned()));
+            Ok(())
+        }
+    }
+
+    fn timed(at_ms: u64, tcode: &str) -> TimedCommand {
+        TimedCommand {
+            at: Duration::from_millis(at_ms),
+            tcode: tcode.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamped() {
+        assert_eq!(
+            parse_tcode_script(TIMESTAMPED, Duration::from_millis(100)).unwrap(),
+            vec![
+                timed(0, "L05000I0500"),
+                timed(500, "L09999I0500 R05000I0500"),
+                timed(1500, "L00000I1000"),
+                timed(1600, "L05000I0100"),
+                timed(62_250, "DSTOP"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_untimed() {
+        assert_eq!(
+            parse_tcode_script(UNTIMED, Duration::from_millis(100)).unwrap(),
+            vec![
+                timed(0, "L05000"),
+                timed(100, "L09999I0100"),
+                timed(200, "L00000I0100"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_tcode_script("500\n", Duration::ZERO).is_err());
+        assert!(parse_tcode_script("1x L05000\n", Duration::ZERO).is_err());
+        assert!(parse_tcode_script("500 L05000\n100 L09999\n", Duration::ZERO).is_err());
+        // Out of range, rather than overflowing
+        assert!(parse_tcode_script("99999999:00 L05000\n", Duration::ZERO).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_play_timing() {
+        let commands = parse_tcode_script(UNTIMED, Duration::from_millis(100)).unwrap();
+        let mut sink = RecordingSink {
+            start: Instant::now(),
+            sent: Vec::new(),
+        };
+        let (_control_tx, control_rx) = mpsc::channel(1);
+        let outcome = play_tcode_script(&mut sink, &commands, control_rx)
+            .await
+            .unwrap();
+        assert_eq!(outcome, PlayOutcome::Finished);
+
+        assert_eq!(
+            sink.sent,
+            vec![
+                (0, "L05000".to_owned()),
+                (100, "L09999I0100".to_owned()),
+                (200, "L00000I0100".to_owned()),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_seek_abort() {
+        let commands = vec![
+            timed(0, "A"),
+            timed(100, "B"),
+            timed(200, "C"),
+            timed(300, "D"),
+            timed(500, "E"),
+        ];
+        let mut sink = RecordingSink {
+            start: Instant::now(),
+            sent: Vec::new(),
+        };
+        let (control_tx, control_rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            control_tx.send(PlayerControl::Pause).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            // Skip C
+            control_tx
+                .send(PlayerControl::Seek(Duration::from_millis(250)))
+                .await
+                .unwrap();
+            control_tx.send(PlayerControl::Resume).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            control_tx.send(PlayerControl::Abort).await.unwrap();
+        });
+        let outcome = play_tcode_script(&mut sink, &commands, control_rx)
+            .await
+            .unwrap();
+        assert_eq!(outcome, PlayOutcome::Aborted);
+
+        // D is 50 ms into the script after seeking at 250 ms
+        assert_eq!(
+            sink.sent,
+            vec![
+                (0, "A".to_owned()),
+                (50, "DSTOP".to_owned()),
+                (300, "D".to_owned()),
+                (350, "DSTOP".to_owned()),
+            ]
+        );
+    }
+}
@@ -1,7 +1,52 @@
 use std::{cmp::min, collections::BTreeMap, str::FromStr};
 
 use eyre::{bail, Context, ContextCompat};
-use strokers_core::{AxisId, Movement};
+use strokers_core::{AxisDescriptor, AxisId, AxisKind, Movement};
+use tracing::warn;
+
+/// T-Code axis names that are assumed to exist when we can't ask the device.
+/// This is the set of axes of an OSR2/SR6-style device.
+pub(crate) const ASSUMED_TCODE_AXES: &[&str] = &["L0", "L1", "L2", "R0", "R1", "R2"];
+
+/// Lists the axes in an axis map, skipping any T-Code axes we don't recognise.
+pub(crate) fn axes_from_axis_map(
+    axis_map: &BTreeMap<AxisId, DiscoveredAxisInfo>,
+) -> Vec<AxisDescriptor> {
+    let mut result = Vec::with_capacity(axis_map.len());
+    for (&axis_id, axis) in axis_map {
+        let Some(axis_kind) = AxisKind::from_tcode_name(&axis.tcode_axis_name) else {
+            warn!(
+                "Unrecognised T-Code axis: {:?}; ignoring.",
+                axis.tcode_axis_name
+            );
+            continue;
+        };
+        result.push(AxisDescriptor { axis_id, axis_kind });
+    }
+    result
+}
+
+/// Builds an axis map from a list of T-Code axis names, without asking the device.
+/// Each axis is assumed to use the full range.
+pub(crate) fn axis_map_from_names(
+    tcode_axis_names: &[impl AsRef<str>],
+) -> BTreeMap<AxisId, DiscoveredAxisInfo> {
+    tcode_axis_names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            (
+                AxisId(idx as u32),
+                DiscoveredAxisInfo {
+                    tcode_axis_name: name.as_ref().to_owned(),
+                    preferred_min: 0,
+                    preferred_max: 9999,
+                    identified_name: name.as_ref().to_owned(),
+                },
+            )
+        })
+        .collect()
+}
 
 /// Converts a [`Movement`] to a T-Code command
 /// Axis IDs are converted to T-Code axis names by using the `axis_map`.
@@ -14,17 +59,23 @@ pub(crate) fn movement_to_tcode(
         .with_context(|| format!("no such axis: {:?}", movement.axis()))?
         .tcode_axis_name;
 
+    Ok(encode_tcode_command(axis_name, movement))
+}
+
+/// Encodes a [`Movement`] as a T-Code command for the axis with the given T-Code name.
+/// The axis ID in the movement is ignored.
+pub fn encode_tcode_command(tcode_axis_name: &str, movement: &Movement) -> String {
     assert!(movement.target() >= 0.0);
     let target_int = min((movement.target() * 10000.0) as u16, 9999);
 
     let ramp_int = movement.ramp_time_milliseconds();
 
-    Ok(format!("{axis_name}{target_int:04}I{ramp_int:04}"))
+    format!("{tcode_axis_name}{target_int:04}I{ramp_int:04}")
 }
 
 /// The parsed format of a D2 response line.
 /// e.g. each one of these is a line in the response
-/// ```
+/// ```text
 /// L0 0 9999 Up
 /// R0 0 9999 Twist
 /// R1 0 9999 Roll
@@ -0,0 +1,275 @@
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use async_trait::async_trait;
+use eyre::{bail, Context};
+use strokers_core::{AxisId, Stroker};
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    time::timeout,
+};
+use tracing::{debug, error, warn};
+
+use crate::{
+    player::RawTCodeSink,
+    tcode::{
+        axes_from_axis_map, axis_map_from_names, movement_to_tcode, DiscoveredAxisInfo,
+        ASSUMED_TCODE_AXES,
+    },
+};
+
+/// How many times a `DSTOP` command is sent, at minimum.
+/// Stopping is important enough that we don't want to rely on a single datagram getting through.
+const STOP_REPEATS: u32 = 3;
+
+/// How long to wait for a response to a handshake command before giving up.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A T-Code device that accepts commands as UDP datagrams, one command per datagram.
+///
+/// As UDP is fire-and-forget, we don't learn whether commands were received.
+/// Each command can optionally be sent multiple times to mitigate packet loss.
+pub struct UdpTCodeStroker {
+    socket: UdpSocket,
+    axis_map: BTreeMap<AxisId, DiscoveredAxisInfo>,
+    description: String,
+    repeat: u32,
+}
+
+impl UdpTCodeStroker {
+    /// Connect to a T-Code device listening for UDP datagrams at `addr`.
+    ///
+    /// - `tcode_axes`: the T-Code names of the axes of the device (e.g. `L0`).
+    ///   If empty, the axes of an OSR2/SR6-style device are assumed.
+    /// - `repeat`: how many times each command is sent. Values below 1 are treated as 1.
+    /// - `handshake`: if true, ask the device to identify itself (`D0`, `D1`) and describe its axes (`D2`).
+    ///   As UDP responses are unreliable, we fall back to `tcode_axes` if there is no answer.
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        tcode_axes: &[String],
+        repeat: u32,
+        handshake: bool,
+    ) -> eyre::Result<UdpTCodeStroker> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .context("failed to bind UDP socket")?;
+        socket
+            .connect(addr)
+            .await
+            .context("failed to connect UDP socket")?;
+        let peer = socket.peer_addr().context("no peer address")?;
+
+        let mut axis_map = if tcode_axes.is_empty() {
+            axis_map_from_names(ASSUMED_TCODE_AXES)
+        } else {
+            axis_map_from_names(tcode_axes)
+        };
+        let mut description = format!("T-Code over UDP ({peer})");
+
+        if handshake {
+            match identify(&socket).await {
+                Ok((d0_resp, d1_resp, discovered_axis_map)) => {
+                    description = format!("{d0_resp} ({d1_resp}) over UDP ({peer})");
+                    if discovered_axis_map.is_empty() {
+                        warn!("T-Code device over UDP did not describe any axes; using configured axes");
+                    } else {
+                        axis_map = discovered_axis_map;
+                    }
+                }
+                Err(err) => {
+                    warn!("T-Code handshake over UDP failed; using configured axes: {err:?}");
+                }
+            }
+        }
+
+        Ok(UdpTCodeStroker {
+            socket,
+            axis_map,
+            description,
+            repeat: repeat.max(1),
+        })
+    }
+
+    /// Sends a command as its own datagram, `times` times over.
+    async fn send_command(&self, command: &str, times: u32) -> eyre::Result<()> {
+        let datagram = format!("{command}\n");
+        for _ in 0..times {
+            self.socket
+                .send(datagram.as_bytes())
+                .await
+                .with_context(|| format!("failed to send {command:?} datagram"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives one datagram and returns its lines.
+async fn recv_lines(socket: &UdpSocket) -> eyre::Result<Vec<String>> {
+    let mut buf = [0u8; 1500];
+    let len = timeout(HANDSHAKE_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .context("timed out waiting for response")?
+        .context("failed to receive datagram")?;
+    let text = std::str::from_utf8(&buf[..len]).context("response is not UTF-8")?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Performs the `D0`, `D1`, `D2` handshake over UDP.
+///
+/// Returns the `D0` and `D1` responses and the axis map described by the `D2` response.
+async fn identify(
+    socket: &UdpSocket,
+) -> eyre::Result<(String, String, BTreeMap<AxisId, DiscoveredAxisInfo>)> {
+    debug!("attempting to identify T-Code device over UDP");
+
+    socket
+        .send(b"D0\n")
+        .await
+        .context("failed to send D0 command")?;
+    let Some(d0_resp) = recv_lines(socket)
+        .await
+        .context("failed to read D0 response")?
+        .into_iter()
+        .next()
+    else {
+        bail!("empty D0 response");
+    };
+    debug!("D0: {d0_resp}");
+
+    socket
+        .send(b"D1\n")
+        .await
+        .context("failed to send D1 command")?;
+    let Some(d1_resp) = recv_lines(socket)
+        .await
+        .context("failed to read D1 response")?
+        .into_iter()
+        .next()
+    else {
+        bail!("empty D1 response");
+    };
+    debug!("D1: {d1_resp}");
+
+    socket
+        .send(b"D2\n")
+        .await
+        .context("failed to send D2 command")?;
+
+    let mut axis_map = BTreeMap::new();
+    let mut axis_id_generator = 0;
+    // The axes may come in one datagram or several; keep reading until the device goes quiet.
+    while let Ok(lines) = recv_lines(socket).await {
+        for next_line in lines {
+            debug!("D2 response line: {next_line:?}");
+            match DiscoveredAxisInfo::from_str(&next_line) {
+                Ok(axis) => {
+                    axis_map.insert(AxisId(axis_id_generator), axis);
+                }
+                Err(err) => {
+                    error!(
+                        "D2 axis description response {next_line:?} could not be parsed: {err:?}"
+                    );
+                }
+            }
+            axis_id_generator += 1;
+        }
+    }
+
+    Ok((d0_resp, d1_resp, axis_map))
+}
+
+#[async_trait]
+impl RawTCodeSink for UdpTCodeStroker {
+    async fn send_raw(&mut self, tcode: &str) -> eyre::Result<()> {
+        self.send_command(tcode, self.repeat).await
+    }
+}
+
+#[async_trait]
+impl Stroker for UdpTCodeStroker {
+    fn axes(&mut self) -> Vec<strokers_core::AxisDescriptor> {
+        axes_from_axis_map(&self.axis_map)
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        self.send_command("DSTOP", self.repeat.max(STOP_REPEATS))
+            .await
+    }
+
+    async fn movement(&mut self, movement: strokers_core::Movement) -> eyre::Result<()> {
+        let tcode = movement_to_tcode(&self.axis_map, &movement)
+            .with_context(|| format!("failed to encode T-Code for {movement:?}"))?;
+        self.send_command(&tcode, self.repeat).await
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(Some(self.description.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::{AxisKind, Movement, Stroker};
+    use tokio::net::UdpSocket;
+
+    use crate::UdpTCodeStroker;
+
+    async fn recv_datagram(socket: &UdpSocket) -> String {
+        let mut buf = [0u8; 1500];
+        let len = socket.recv(&mut buf).await.unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_udp_datagrams() {
+        let device = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let device_addr = device.local_addr().unwrap();
+
+        let mut stroker = UdpTCodeStroker::connect(device_addr, &[], 2, false)
+            .await
+            .unwrap();
+
+        let axes = stroker.axes();
+        assert_eq!(axes.len(), 6);
+        let stroke = axes
+            .iter()
+            .find(|axis| axis.axis_kind == AxisKind::Stroke)
+            .unwrap();
+
+        stroker
+            .movement(Movement::new(stroke.axis_id, 0.75, 42).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(recv_datagram(&device).await, "L07500I0042\n");
+        assert_eq!(recv_datagram(&device).await, "L07500I0042\n");
+
+        stroker.stop().await.unwrap();
+        for _ in 0..3 {
+            assert_eq!(recv_datagram(&device).await, "DSTOP\n");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_udp_configured_axes_without_handshake_answer() {
+        let device = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let device_addr = device.local_addr().unwrap();
+
+        // The device never answers the handshake, so we should fall back to the configured axes.
+        let mut stroker =
+            UdpTCodeStroker::connect(device_addr, &["R0".to_owned(), "V0".to_owned()], 1, true)
+                .await
+                .unwrap();
+        assert_eq!(recv_datagram(&device).await, "D0\n");
+
+        let axis_kinds: Vec<AxisKind> = stroker
+            .axes()
+            .into_iter()
+            .map(|axis| axis.axis_kind)
+            .collect();
+        assert_eq!(axis_kinds, vec![AxisKind::Twist, AxisKind::Vibration]);
+    }
+}
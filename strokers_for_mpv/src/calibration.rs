@@ -0,0 +1,218 @@
+use std::{sync::Arc, time::Duration};
+
+use mpv_client::osd;
+use strokers::{
+    config::LimitsConfig,
+    core::{AxisId, Stroker},
+};
+use strokers_funscript::processing::NormalisedAction;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tracing::error;
+
+use crate::{mpv::Mpv, playstate::AxisPlaystate};
+
+/// Time between the beats of the metronome.
+const BEAT_MS: u32 = 1000;
+
+/// How many beats are counted down on the OSD before the first thump.
+const COUNTDOWN_BEATS: u32 = 3;
+
+/// How long the OSD flashes for on each beat.
+const FLASH_DURATION: Duration = Duration::from_millis(150);
+
+/// How often the metronome is moved along, which is also how late a flash can be.
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A metronome played on the stroke axis, reaching the bottom on each beat,
+/// with the OSD flashing on the beat too.
+///
+/// Movements are sent `latency_ms` early; the user nudges that until the stroker
+/// reaches the bottom as the OSD flashes, at which point it's how late the stroker is.
+pub(crate) struct Calibration {
+    axis_id: AxisId,
+    latency_ms: i32,
+    started_at: Instant,
+    /// The metronome's motion, looping every beat
+    playstate: AxisPlaystate,
+    /// Whether the stroker has started moving, after the countdown
+    moving: bool,
+    /// The last beat shown on the OSD, counting from the start of the countdown
+    last_beat: Option<u32>,
+    /// Whether the video was playing before calibrating, to carry on with afterwards
+    pub was_playing: bool,
+    ticker: Interval,
+}
+
+impl Calibration {
+    /// Starts the countdown to the metronome, starting from the given latency.
+    pub fn new(
+        axis_id: AxisId,
+        limits: &LimitsConfig,
+        latency_ms: i32,
+        was_playing: bool,
+    ) -> Calibration {
+        let mut playstate = AxisPlaystate::new(
+            Arc::new(metronome_actions()),
+            limits.speed,
+            limits.default_min,
+            limits.default_max,
+        );
+        playstate.limiter.apply_config(limits);
+        playstate.set_loop(true, Some(BEAT_MS));
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Calibration {
+            axis_id,
+            latency_ms,
+            started_at: Instant::now(),
+            playstate,
+            moving: false,
+            last_beat: None,
+            was_playing,
+            ticker,
+        }
+    }
+
+    pub fn latency_ms(&self) -> i32 {
+        self.latency_ms
+    }
+
+    /// Changes how early movements are sent, moving into place straight away.
+    pub async fn set_latency_ms(
+        &mut self,
+        latency_ms: i32,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        self.latency_ms = latency_ms;
+        if let Some(metronome_millis) = self.metronome_millis() {
+            self.playstate
+                .seek(metronome_millis, false, self.axis_id, stroker)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// How far into the metronome's motion to be now (with the latency made up for),
+    /// or `None` during the countdown.
+    fn metronome_millis(&self) -> Option<u32> {
+        let elapsed_millis = self.started_at.elapsed().as_millis().min(u32::MAX as u128) as u32;
+        // The stroker moves into place over the last beat of the countdown
+        if elapsed_millis < (COUNTDOWN_BEATS - 1) * BEAT_MS {
+            return None;
+        }
+        Some(elapsed_millis.saturating_add_signed(self.latency_ms))
+    }
+
+    /// Waits until it's time to move along again.
+    pub async fn next_tick(&mut self) {
+        self.ticker.tick().await;
+    }
+
+    /// Counts down or flashes the OSD on each beat, and moves the stroker along the metronome.
+    pub async fn tick(
+        &mut self,
+        client: &mut impl Mpv,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        let beat = (self.started_at.elapsed().as_millis() / BEAT_MS as u128) as u32;
+        if self.last_beat != Some(beat) {
+            self.last_beat = Some(beat);
+            let shown = if beat < COUNTDOWN_BEATS {
+                osd!(
+                    client,
+                    Duration::from_millis(BEAT_MS.into()),
+                    "Calibrating in {}…\nMatch the stroker to the flashes with the sync offset keys",
+                    COUNTDOWN_BEATS - beat
+                )
+            } else {
+                osd!(
+                    client,
+                    FLASH_DURATION,
+                    "████████\nLatency: {} ms",
+                    self.latency_ms
+                )
+            };
+            if let Err(err) = shown {
+                error!("Failed to display OSD: {err:?}");
+            }
+        }
+
+        let Some(metronome_millis) = self.metronome_millis() else {
+            return Ok(());
+        };
+        if !self.moving {
+            self.moving = true;
+            return self
+                .playstate
+                .seek(metronome_millis, false, self.axis_id, stroker)
+                .await;
+        }
+        self.playstate
+            .tick(metronome_millis, self.axis_id, stroker)
+            .await
+    }
+}
+
+/// A beat of the metronome: down to the bottom on the beat, back up halfway between beats.
+fn metronome_actions() -> Vec<NormalisedAction> {
+    vec![
+        NormalisedAction {
+            at: 0,
+            norm_pos: 0.0,
+        },
+        NormalisedAction {
+            at: BEAT_MS / 2,
+            norm_pos: 1.0,
+        },
+        NormalisedAction {
+            at: BEAT_MS,
+            norm_pos: 0.0,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use strokers::{
+        config::LimitsConfig,
+        core::{AxisId, AxisKind},
+        devices::debug::testing::{ChannelStroker, StrokerCommand},
+    };
+    use strokers_funscript::processing::Easing;
+
+    use super::{Calibration, BEAT_MS, COUNTDOWN_BEATS};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let limits = LimitsConfig {
+            speed: 1000.0,
+            default_min: 0.0,
+            default_max: 1.0,
+            invert: false,
+            device_easing: Easing::Linear,
+            idle_timeout_ms: None,
+        };
+        let mut calibration = Calibration::new(axis_id, &limits, 0, true);
+        // Still counting down
+        assert_eq!(calibration.metronome_millis(), None);
+
+        tokio::time::advance(Duration::from_millis(
+            ((COUNTDOWN_BEATS - 1) * BEAT_MS + BEAT_MS / 4).into(),
+        ))
+        .await;
+        assert_eq!(calibration.metronome_millis(), Some(2250));
+
+        // Sent early to make up for a slow stroker: on the way down already
+        calibration.set_latency_ms(300, &mut stroker).await.unwrap();
+        assert_eq!(calibration.metronome_millis(), Some(2550));
+        assert!(matches!(
+            rx.drain().collect::<Vec<_>>()[..],
+            [StrokerCommand::Movement { .. }]
+        ));
+        assert_eq!(calibration.latency_ms(), 300);
+    }
+}
@@ -0,0 +1,192 @@
+use tokio::time::Instant;
+
+/// The most an update from mpv moves the clock at once, in milliseconds, when it disagrees
+/// with the estimate a little (e.g. arriving late), so that the time doesn't jump about.
+const MAX_CORRECTION_MS: f64 = 20.0;
+
+/// How far an update from mpv can be from the estimate before it's taken as it is,
+/// as the video has jumped (e.g. looped back to the start without a seek).
+const MAX_DRIFT_MS: f64 = 500.0;
+
+/// The playback time, estimated between mpv's updates of it.
+///
+/// mpv's `time-pos` updates come at irregular intervals (often with the frames), so anything
+/// timed by them alone comes in clumps. Between updates, the clock carries on from the last
+/// one at the playback speed; each update then corrects the clock towards it, a little at a time.
+pub(crate) struct PlaybackClock {
+    /// The estimated video time, in milliseconds, as of when, if known
+    base: Option<(f64, Instant)>,
+    /// How fast the video is playing, as a multiple of normal speed
+    speed: f64,
+    paused: bool,
+}
+
+impl PlaybackClock {
+    pub fn new() -> PlaybackClock {
+        PlaybackClock {
+            base: None,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    /// The estimated video time at `at` (normally now), in milliseconds, if known.
+    pub fn now_at(&self, at: Instant) -> Option<u32> {
+        self.estimate(at)
+            .map(|millis| millis.round().clamp(0.0, u32::MAX as f64) as u32)
+    }
+
+    fn estimate(&self, at: Instant) -> Option<f64> {
+        let (base_millis, base_at) = self.base?;
+        if self.paused {
+            return Some(base_millis);
+        }
+        let elapsed_millis = at.saturating_duration_since(base_at).as_secs_f64() * 1000.0;
+        Some(base_millis + elapsed_millis * self.speed)
+    }
+
+    /// mpv says the video is at `now_millis`, as of `at`.
+    /// Whilst playing, the clock is only corrected towards that by [`MAX_CORRECTION_MS`] at most,
+    /// unless it's so far off that the video must have jumped.
+    pub fn update(&mut self, now_millis: u32, at: Instant) {
+        let reported = now_millis as f64;
+        let millis = match self.estimate(at) {
+            Some(estimate) if !self.paused && (reported - estimate).abs() <= MAX_DRIFT_MS => {
+                estimate + (reported - estimate).clamp(-MAX_CORRECTION_MS, MAX_CORRECTION_MS)
+            }
+            _ => reported,
+        };
+        self.base = Some((millis, at));
+    }
+
+    /// The video seeked to `now_millis`, which is taken as it is.
+    pub fn seek(&mut self, now_millis: u32, at: Instant) {
+        self.base = Some((now_millis as f64, at));
+    }
+
+    /// Stops or starts the clock where it is at `at`.
+    pub fn set_paused(&mut self, paused: bool, at: Instant) {
+        self.rebase(at);
+        self.paused = paused;
+    }
+
+    /// Runs the clock at `speed` times normal speed from `at`. Nonsensical speeds
+    /// are taken as normal speed.
+    pub fn set_speed(&mut self, speed: f64, at: Instant) {
+        self.rebase(at);
+        self.speed = if speed.is_finite() && speed > 0.0 {
+            speed
+        } else {
+            1.0
+        };
+    }
+
+    /// Forgets the time, e.g. as a new video starts.
+    pub fn clear(&mut self) {
+        self.base = None;
+    }
+
+    fn rebase(&mut self, at: Instant) {
+        if let Some(millis) = self.estimate(at) {
+            self.base = Some((millis, at));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::PlaybackClock;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_between_updates() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new();
+        assert_eq!(clock.now_at(start), None);
+
+        clock.update(10_000, start);
+        assert_eq!(clock.now_at(start), Some(10_000));
+        assert_eq!(clock.now_at(start + ms(25)), Some(10_025));
+
+        // An update right on time changes nothing
+        clock.update(10_040, start + ms(40));
+        assert_eq!(clock.now_at(start + ms(60)), Some(10_060));
+
+        // Nor does asking about a moment before the latest update
+        assert_eq!(clock.now_at(start), Some(10_040));
+    }
+
+    #[test]
+    fn test_late_updates() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new();
+        clock.update(10_000, start);
+
+        // Arriving 50 ms late only pulls the clock back a little at a time
+        clock.update(10_050, start + ms(100));
+        assert_eq!(clock.now_at(start + ms(100)), Some(10_080));
+        clock.update(10_100, start + ms(150));
+        assert_eq!(clock.now_at(start + ms(150)), Some(10_110));
+        clock.update(10_160, start + ms(200));
+        assert_eq!(clock.now_at(start + ms(200)), Some(10_160));
+
+        // Running behind is caught up with as gradually
+        clock.update(10_300, start + ms(250));
+        assert_eq!(clock.now_at(start + ms(250)), Some(10_230));
+
+        // But a jump is taken as it is
+        clock.update(0, start + ms(300));
+        assert_eq!(clock.now_at(start + ms(300)), Some(0));
+
+        // As is a seek
+        clock.seek(10_200, start + ms(300));
+        assert_eq!(clock.now_at(start + ms(310)), Some(10_210));
+    }
+
+    #[test]
+    fn test_pause() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new();
+        clock.update(10_000, start);
+
+        clock.set_paused(true, start + ms(30));
+        assert_eq!(clock.now_at(start + ms(1000)), Some(10_030));
+
+        // Stepped through whilst paused, the updates are taken as they are
+        clock.update(10_070, start + ms(1000));
+        assert_eq!(clock.now_at(start + ms(2000)), Some(10_070));
+
+        // Carries on from there
+        clock.set_paused(false, start + ms(2000));
+        assert_eq!(clock.now_at(start + ms(2010)), Some(10_080));
+    }
+
+    #[test]
+    fn test_speed_change() {
+        let start = Instant::now();
+        let mut clock = PlaybackClock::new();
+        clock.update(10_000, start);
+
+        // Slower from the change on, without a jump
+        clock.set_speed(0.25, start + ms(100));
+        assert_eq!(clock.now_at(start + ms(100)), Some(10_100));
+        assert_eq!(clock.now_at(start + ms(200)), Some(10_125));
+
+        // So updates at that speed are on time
+        clock.update(10_150, start + ms(300));
+        assert_eq!(clock.now_at(start + ms(300)), Some(10_150));
+
+        clock.set_speed(2.0, start + ms(300));
+        assert_eq!(clock.now_at(start + ms(310)), Some(10_170));
+
+        clock.set_speed(f64::NAN, start + ms(310));
+        assert_eq!(clock.now_at(start + ms(320)), Some(10_180));
+    }
+}
@@ -0,0 +1,261 @@
+use std::{future::Future, pin::Pin, time::Duration};
+
+use async_trait::async_trait;
+use strokers::{
+    core::{AxisDescriptor, Movement, Stroker},
+    StrokersError,
+};
+use tracing::{debug, warn};
+
+use crate::writer::Writer;
+
+/// How long to wait before trying to connect again after the first failure.
+pub const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest to wait between attempts to connect, however many have failed.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How long to wait between attempts to connect whilst another program has the stroker,
+/// so that it's taken over soon after that program is done with it.
+pub const IN_USE_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// An attempt to connect to the stroker, waiting first if it's a retry.
+pub type ConnectAttempt<S> = Pin<Box<dyn Future<Output = eyre::Result<S>>>>;
+
+/// How long to wait before the next attempt to connect, after waiting `delay` before the last.
+/// Each failure waits twice as long as the last, up to a limit.
+pub fn next_retry_delay(delay: Duration) -> Duration {
+    (delay * 2).clamp(INITIAL_RETRY_DELAY, MAX_RETRY_DELAY)
+}
+
+/// Whether connecting failed because another program (e.g. another mpv) has the stroker.
+pub fn is_in_use(err: &eyre::Report) -> bool {
+    matches!(
+        err.downcast_ref::<StrokersError>(),
+        Some(StrokersError::InUse(_))
+    )
+}
+
+/// The stroker, if connected to.
+///
+/// Whilst not connected, it has no axes and movements go nowhere, so that playback
+/// can be kept track of until it is. If the stroker says it has been disconnected,
+/// it is dropped, to connect to again.
+///
+/// Once connected, commands are written by a [`Writer`], so they're queued rather than waited for.
+///
+/// Movements also go nowhere whilst the user has disabled the stroker, though it can still be stopped.
+pub(crate) struct Device {
+    writer: Option<Writer>,
+    /// The connected stroker's, found out as it connects
+    axes: Vec<AxisDescriptor>,
+    description: Option<String>,
+    min_movement_interval_ms: Option<u32>,
+    enabled: bool,
+}
+
+impl Device {
+    pub fn disconnected() -> Device {
+        Device {
+            writer: None,
+            axes: Vec::new(),
+            description: None,
+            min_movement_interval_ms: None,
+            enabled: true,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables or disables movements, e.g. at the user's request.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Starts using a newly connected stroker.
+    pub fn attach<S: Stroker + Send + 'static>(&mut self, mut stroker: S) {
+        self.axes = stroker.axes();
+        self.description = stroker.description().unwrap_or_else(|err| {
+            warn!("failed to get the stroker's description: {err:?}");
+            None
+        });
+        self.min_movement_interval_ms = stroker.min_movement_interval_ms();
+        self.writer = Some(Writer::spawn(stroker));
+    }
+
+    /// Stops using the stroker, once the commands already queued have been written,
+    /// and waits until it's been let go of, so that it can be connected to afresh.
+    pub async fn release(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            writer.close().await;
+        }
+        self.detach();
+    }
+
+    /// Stops using the stroker. Commands already queued are still written.
+    pub fn detach(&mut self) {
+        self.writer = None;
+        self.axes.clear();
+        self.description = None;
+        self.min_movement_interval_ms = None;
+    }
+
+    /// Whether the connection has been lost, in which case the stroker is dropped.
+    pub fn take_lost(&mut self) -> bool {
+        if !self.writer.as_ref().is_some_and(Writer::is_lost) {
+            return false;
+        }
+        self.detach();
+        true
+    }
+
+    /// Waits until the connection is lost, or forever if not connected.
+    pub async fn lost(&self) {
+        match &self.writer {
+            Some(writer) => writer.lost().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Waits until the commands queued so far have been written, e.g. before shutting down.
+    pub async fn flush(&self) {
+        if let Some(writer) = &self.writer {
+            writer.flush().await;
+        }
+    }
+}
+
+#[async_trait]
+impl Stroker for Device {
+    fn axes(&mut self) -> Vec<AxisDescriptor> {
+        self.axes.clone()
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        if let Some(writer) = &self.writer {
+            writer.stop();
+        }
+        Ok(())
+    }
+
+    async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+        let Some(writer) = &self.writer else {
+            debug!("not connected, so not moving: {movement:?}");
+            return Ok(());
+        };
+        if self.enabled {
+            writer.movement(movement);
+        }
+        Ok(())
+    }
+
+    fn description(&mut self) -> eyre::Result<Option<String>> {
+        Ok(self.description.clone())
+    }
+
+    fn min_movement_interval_ms(&mut self) -> Option<u32> {
+        self.min_movement_interval_ms
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use eyre::Context;
+    use strokers::{
+        core::{AxisId, AxisKind, Movement, Stroker},
+        devices::debug::testing::{ChannelStroker, StrokerCommand},
+        StrokersError,
+    };
+
+    use super::{is_in_use, next_retry_delay, Device, INITIAL_RETRY_DELAY};
+    use crate::writer::test::UnpluggedStroker;
+
+    #[test]
+    fn test_next_retry_delay() {
+        let delays: Vec<Duration> = std::iter::successors(Some(INITIAL_RETRY_DELAY), |&delay| {
+            Some(next_retry_delay(delay))
+        })
+        .take(9)
+        .collect();
+        assert_eq!(
+            delays.iter().map(Duration::as_secs).collect::<Vec<_>>(),
+            vec![1, 2, 4, 8, 16, 32, 60, 60, 60]
+        );
+        assert_eq!(next_retry_delay(Duration::ZERO), INITIAL_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_is_in_use() {
+        // As given by the connection to the stroker, with context
+        let in_use: eyre::Result<()> = Err(StrokersError::InUse("/dev/ttyUSB0".to_owned()))
+            .context("failed to connect to Stroker");
+        assert!(is_in_use(&in_use.unwrap_err()));
+        let unplugged: eyre::Result<()> =
+            Err(StrokersError::Connection(eyre::eyre!("no such device")))
+                .context("failed to connect to Stroker");
+        assert!(!is_in_use(&unplugged.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn test_device() {
+        let axis_id = AxisId(1);
+        let mut device = Device::disconnected();
+        assert!(device.axes().is_empty());
+        // Goes nowhere, but isn't an error
+        device
+            .movement(Movement::new(axis_id, 0.5, 100).unwrap())
+            .await
+            .unwrap();
+
+        let (stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        device.attach(stroker);
+        assert!(device.is_connected());
+        assert_eq!(device.axes().len(), 1);
+        device
+            .movement(Movement::new(axis_id, 0.5, 100).unwrap())
+            .await
+            .unwrap();
+        device.flush().await;
+        assert_eq!(rx.drain().count(), 1);
+
+        // Disabled, it can stop but not move
+        device.set_enabled(false);
+        device
+            .movement(Movement::new(axis_id, 0.5, 100).unwrap())
+            .await
+            .unwrap();
+        device.stop().await.unwrap();
+        device.flush().await;
+        assert!(matches!(
+            rx.drain().collect::<Vec<_>>()[..],
+            [StrokerCommand::Stop { .. }]
+        ));
+        device.set_enabled(true);
+
+        // Other errors don't lose the stroker
+        device
+            .movement(Movement::new(AxisId(99), 0.5, 100).unwrap())
+            .await
+            .unwrap();
+        device.flush().await;
+        assert!(!device.take_lost());
+        assert!(device.is_connected());
+
+        // Disconnection is dealt with by dropping the stroker
+        device.attach(UnpluggedStroker);
+        device.stop().await.unwrap();
+        device.lost().await;
+        assert!(device.take_lost());
+        assert!(!device.is_connected());
+        assert!(device.axes().is_empty());
+        assert!(!device.take_lost());
+    }
+}
@@ -0,0 +1,296 @@
+use std::{sync::Arc, time::Duration};
+
+use eyre::Context;
+use strokers::{
+    config::{FallbackConfig, LimitsConfig},
+    core::{AxisId, Stroker},
+};
+use strokers_funscript::{
+    generate::{Pattern, PatternParams},
+    processing::{enforce_min_interval, NormalisedAction},
+};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+use crate::playstate::{AxisLimiter, AxisPlaystate};
+
+/// The shortest cycle the generated motion can be nudged down to.
+pub const MIN_PERIOD_MS: u32 = 200;
+
+/// The longest cycle the generated motion can be nudged up to.
+pub const MAX_PERIOD_MS: u32 = 60_000;
+
+/// Time between the positions generated along the pattern.
+const SAMPLE_INTERVAL_MS: u32 = 100;
+
+/// How often the generated motion is moved along.
+const TICK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many cycles of a random walk are generated before it repeats.
+/// The other patterns repeat every cycle anyway.
+const RANDOM_WALK_CYCLES: u32 = 32;
+
+/// Motion made up for the stroke axis whilst a video without funscripts plays,
+/// following the clock from when it started (less any time paused).
+pub(crate) struct FallbackMotion {
+    config: FallbackConfig,
+    period_ms: u32,
+    axis_id: AxisId,
+    min_movement_interval_ms: Option<u32>,
+    seed: u64,
+    /// The generated actions, looping
+    playstate: AxisPlaystate,
+    /// How long the generated actions last before they loop
+    loop_millis: u32,
+    /// How far into the generated actions the motion was when it last paused
+    paused_at_millis: u32,
+    /// When the motion last resumed, or `None` whilst paused
+    resumed_at: Option<Instant>,
+    ticker: Interval,
+}
+
+impl FallbackMotion {
+    /// Makes up motion for the given axis, starting paused.
+    /// The seed picks which random walk to take, if that's the pattern.
+    pub fn new(
+        config: FallbackConfig,
+        period_ms: u32,
+        axis_id: AxisId,
+        limits: &LimitsConfig,
+        min_movement_interval_ms: Option<u32>,
+        seed: u64,
+    ) -> FallbackMotion {
+        let (actions, loop_millis) =
+            generate_actions(&config, period_ms, min_movement_interval_ms, seed);
+        let mut playstate = AxisPlaystate::new(
+            Arc::new(actions),
+            limits.speed,
+            limits.default_min,
+            limits.default_max,
+        );
+        playstate.limiter.apply_config(limits);
+        playstate.set_loop(true, Some(loop_millis));
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        FallbackMotion {
+            config,
+            period_ms,
+            axis_id,
+            min_movement_interval_ms,
+            seed,
+            playstate,
+            loop_millis,
+            paused_at_millis: 0,
+            resumed_at: None,
+            ticker,
+        }
+    }
+
+    /// The limits the motion is kept within, e.g. to apply a preset to.
+    pub fn limiter_mut(&mut self) -> &mut AxisLimiter {
+        &mut self.playstate.limiter
+    }
+
+    /// Describes the motion, for the OSD.
+    pub fn describe(&self) -> String {
+        format!(
+            "Generated motion: {:?}, {} ms per cycle",
+            self.config.pattern, self.period_ms
+        )
+    }
+
+    /// How far into the generated actions the motion is now.
+    fn pattern_millis(&self) -> u32 {
+        let elapsed_millis = self
+            .resumed_at
+            .map_or(0, |resumed_at| resumed_at.elapsed().as_millis());
+        (self.paused_at_millis as u128 + elapsed_millis).min(u32::MAX as u128) as u32
+    }
+
+    /// Waits until it's time to move along again.
+    pub async fn next_tick(&mut self) {
+        self.ticker.tick().await;
+    }
+
+    /// Moves along the pattern, if it's time for the next position.
+    pub async fn tick(&mut self, stroker: &mut impl Stroker) -> eyre::Result<()> {
+        if self.resumed_at.is_none() {
+            return Ok(());
+        }
+        self.playstate
+            .tick(self.pattern_millis(), self.axis_id, stroker)
+            .await
+    }
+
+    /// Stops the clock, e.g. as the video pauses. The stroker isn't stopped.
+    pub fn pause(&mut self) {
+        self.paused_at_millis = self.pattern_millis();
+        self.resumed_at = None;
+    }
+
+    /// Moves slowly to `norm_pos` over `over_millis`, e.g. whilst paused.
+    pub async fn park(
+        &mut self,
+        norm_pos: f32,
+        over_millis: u32,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        self.playstate
+            .park(norm_pos, over_millis, self.axis_id, stroker)
+            .await
+    }
+
+    /// Starts the clock again and moves into place, e.g. as the video resumes.
+    pub async fn resume(&mut self, stroker: &mut impl Stroker) -> eyre::Result<()> {
+        if self.resumed_at.is_none() {
+            self.resumed_at = Some(Instant::now());
+        }
+        self.playstate
+            .seek(self.pattern_millis(), false, self.axis_id, stroker)
+            .await
+    }
+
+    /// Changes how long a cycle of the pattern takes, carrying on from the same point in the cycle.
+    pub async fn set_period(
+        &mut self,
+        period_ms: u32,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        let (actions, loop_millis) = generate_actions(
+            &self.config,
+            period_ms,
+            self.min_movement_interval_ms,
+            self.seed,
+        );
+        let progress = (self.pattern_millis() % self.loop_millis) as f64 / self.loop_millis as f64;
+        let running = self.resumed_at.is_some();
+
+        let limiter = std::mem::replace(
+            &mut self.playstate,
+            AxisPlaystate::new(Arc::new(actions), 0.0, 0.0, 1.0),
+        )
+        .limiter;
+        self.playstate.limiter = limiter;
+        self.playstate.set_loop(true, Some(loop_millis));
+        self.period_ms = period_ms;
+        self.loop_millis = loop_millis;
+        self.paused_at_millis = (progress * loop_millis as f64) as u32;
+        self.resumed_at = None;
+
+        if running {
+            self.resume(stroker)
+                .await
+                .context("failed to move into place after changing the period")?;
+        }
+        Ok(())
+    }
+}
+
+/// The actions to loop through for the motion, and how long they last.
+fn generate_actions(
+    config: &FallbackConfig,
+    period_ms: u32,
+    min_movement_interval_ms: Option<u32>,
+    seed: u64,
+) -> (Vec<NormalisedAction>, u32) {
+    let period_ms = period_ms.clamp(MIN_PERIOD_MS, MAX_PERIOD_MS);
+    let cycles = match config.pattern {
+        Pattern::RandomWalk => RANDOM_WALK_CYCLES,
+        Pattern::Sine | Pattern::Triangle | Pattern::Sawtooth => 1,
+    };
+    let params = PatternParams {
+        duration_ms: period_ms.saturating_mul(cycles),
+        period_ms,
+        min: config.min,
+        max: config.max,
+        sample_interval_ms: SAMPLE_INTERVAL_MS.max(min_movement_interval_ms.unwrap_or(0)),
+    };
+    let mut actions = config.pattern.generate(&params, seed);
+    if let Some(min_interval_ms) = min_movement_interval_ms {
+        actions = enforce_min_interval(&actions, min_interval_ms);
+    }
+    (actions, params.duration_ms)
+}
+
+#[cfg(test)]
+mod test {
+    use strokers::{
+        config::{FallbackConfig, LimitsConfig},
+        core::{AxisId, AxisKind},
+        devices::debug::testing::{ChannelStroker, StrokerCommand},
+    };
+    use strokers_funscript::{generate::Pattern, processing::Easing};
+
+    use super::{generate_actions, FallbackMotion, MAX_PERIOD_MS, MIN_PERIOD_MS};
+
+    #[test]
+    fn test_generate_actions() {
+        let config = FallbackConfig {
+            min: 0.25,
+            max: 0.75,
+            ..FallbackConfig::default()
+        };
+        let (actions, loop_millis) = generate_actions(&config, 1000, None, 0);
+        assert_eq!(loop_millis, 1000);
+        assert_eq!((actions.len(), actions[5].norm_pos), (11, 0.75));
+        assert!(actions
+            .iter()
+            .all(|action| (0.25..=0.75).contains(&action.norm_pos)));
+
+        // Not more often than the device can take
+        let (actions, _) = generate_actions(&config, 1000, Some(250), 0);
+        assert!(actions
+            .windows(2)
+            .all(|pair| pair[1].at - pair[0].at >= 250));
+
+        let random_walk = FallbackConfig {
+            pattern: Pattern::RandomWalk,
+            ..FallbackConfig::default()
+        };
+        let (_, loop_millis) = generate_actions(&random_walk, 1000, None, 0);
+        assert_eq!(loop_millis, 32_000);
+
+        let (_, loop_millis) = generate_actions(&config, 10, None, 0);
+        assert_eq!(loop_millis, MIN_PERIOD_MS);
+        let (_, loop_millis) = generate_actions(&random_walk, u32::MAX, None, 0);
+        assert_eq!(loop_millis, MAX_PERIOD_MS * 32);
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_period() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let limits = LimitsConfig {
+            speed: 1000.0,
+            default_min: 0.0,
+            default_max: 1.0,
+            invert: false,
+            device_easing: Easing::Linear,
+            idle_timeout_ms: None,
+        };
+        let mut motion =
+            FallbackMotion::new(FallbackConfig::default(), 2000, axis_id, &limits, None, 0);
+
+        // Nothing moves until resumed
+        motion.tick(&mut stroker).await.unwrap();
+        assert!(rx.drain().next().is_none());
+
+        motion.resume(&mut stroker).await.unwrap();
+        assert!(matches!(
+            rx.drain().collect::<Vec<_>>()[..],
+            [StrokerCommand::Movement { .. }]
+        ));
+
+        motion.pause();
+        let paused_at_millis = motion.pattern_millis();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(motion.pattern_millis(), paused_at_millis);
+        motion.tick(&mut stroker).await.unwrap();
+        assert!(rx.drain().next().is_none());
+
+        // Halfway through a cycle stays halfway through
+        motion.paused_at_millis = 1000;
+        motion.set_period(1000, &mut stroker).await.unwrap();
+        assert_eq!((motion.pattern_millis(), motion.loop_millis), (500, 1000));
+        assert!(motion.describe().contains("1000 ms"));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+use strokers::config::HeatmapConfig;
+use strokers_funscript::{processing::NormalisedAction, stats::heatmap};
+use tokio::time::Instant;
+use tracing::error;
+
+use crate::mpv::Mpv;
+
+/// Our ID for the overlay, as given to mpv's `osd-overlay` command.
+/// Each client has its own IDs, so this can't clash with other scripts'.
+const OVERLAY_ID: &str = "1";
+
+/// Size of the canvas that the overlay is drawn on, which mpv scales to fit the window.
+const CANVAS_WIDTH: u32 = 1280;
+const CANVAS_HEIGHT: u32 = 720;
+
+/// How often the marker for the current position is moved along.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How far the marker sticks out above and below the bar.
+const MARKER_OVERHANG: u32 = 4;
+
+/// Colours (red, green, blue) of the least and most intense parts of the video.
+const CALM_COLOUR: (u8, u8, u8) = (0, 128, 255);
+const INTENSE_COLOUR: (u8, u8, u8) = (255, 32, 0);
+
+/// A bar along the video, drawn on the OSD, showing how intense the funscript is
+/// throughout and where in the video playback is.
+pub(crate) struct HeatmapOverlay {
+    config: HeatmapConfig,
+    /// Whether the user wants to see the overlay
+    shown: bool,
+    /// The actions of the funscript to show the intensity of
+    actions: Option<Arc<Vec<NormalisedAction>>>,
+    duration_millis: Option<u32>,
+    /// Intensity of each bucket, from 0.0 to 1.0, from the start of the video to `end_millis`
+    buckets: Vec<f32>,
+    end_millis: u32,
+    drawn_at: Option<Instant>,
+}
+
+impl HeatmapOverlay {
+    pub fn new(config: HeatmapConfig) -> HeatmapOverlay {
+        HeatmapOverlay {
+            config,
+            shown: false,
+            actions: None,
+            duration_millis: None,
+            buckets: Vec::new(),
+            end_millis: 0,
+            drawn_at: None,
+        }
+    }
+
+    /// Whether there are any actions to show the intensity of.
+    pub fn has_actions(&self) -> bool {
+        self.actions.is_some()
+    }
+
+    /// Shows the overlay if it's hidden, or hides it if it's shown.
+    /// Returns whether it's now shown.
+    pub fn toggle(&mut self, client: &mut impl Mpv, now_millis: Option<u32>) -> bool {
+        self.shown = !self.shown;
+        if self.shown {
+            self.redraw(client, now_millis);
+        } else {
+            self.remove(client);
+        }
+        self.shown
+    }
+
+    /// Shows the intensity of the given actions, e.g. those of the stroke funscript.
+    pub fn set_actions(
+        &mut self,
+        client: &mut impl Mpv,
+        actions: Arc<Vec<NormalisedAction>>,
+        now_millis: Option<u32>,
+    ) {
+        self.actions = Some(actions);
+        self.update_buckets();
+        self.redraw(client, now_millis);
+    }
+
+    /// Sets the length of the video, which the bar spans, if known.
+    pub fn set_duration(&mut self, duration_millis: Option<u32>) {
+        self.duration_millis = duration_millis;
+        self.update_buckets();
+        // Drawn again at the next tick
+        self.drawn_at = None;
+    }
+
+    /// Forgets the funscript and takes the bar off the screen, e.g. as a new video starts.
+    /// If shown, the overlay reappears once there are actions again.
+    pub fn clear(&mut self, client: &mut impl Mpv) {
+        self.actions = None;
+        self.buckets.clear();
+        self.remove(client);
+    }
+
+    /// Moves the marker along to the current position, every so often.
+    pub fn tick(&mut self, client: &mut impl Mpv, now_millis: u32) {
+        let due = self
+            .drawn_at
+            .map_or(true, |drawn_at| drawn_at.elapsed() >= REDRAW_INTERVAL);
+        if due {
+            self.redraw(client, Some(now_millis));
+        }
+    }
+
+    /// Draws the bar again now, e.g. after a seek.
+    pub fn redraw(&mut self, client: &mut impl Mpv, now_millis: Option<u32>) {
+        if !self.shown || self.actions.is_none() {
+            return;
+        }
+        let progress = now_millis
+            .filter(|_| self.end_millis > 0)
+            .map(|now_millis| (now_millis as f32 / self.end_millis as f32).clamp(0.0, 1.0));
+        let events = render(&self.buckets, progress, &self.config);
+        if let Err(err) = client.command([
+            "osd-overlay".to_owned(),
+            OVERLAY_ID.to_owned(),
+            "ass-events".to_owned(),
+            events,
+            CANVAS_WIDTH.to_string(),
+            CANVAS_HEIGHT.to_string(),
+        ]) {
+            error!("Failed to draw heatmap: {err:?}");
+        }
+        self.drawn_at = Some(Instant::now());
+    }
+
+    fn remove(&mut self, client: &mut impl Mpv) {
+        if let Err(err) = client.command(["osd-overlay", OVERLAY_ID, "none", ""]) {
+            error!("Failed to remove heatmap: {err:?}");
+        }
+        self.drawn_at = None;
+    }
+
+    fn update_buckets(&mut self) {
+        let Some(actions) = &self.actions else {
+            return;
+        };
+        (self.buckets, self.end_millis) =
+            video_heatmap(actions, self.duration_millis, self.config.bucket_count);
+    }
+}
+
+/// The intensity of the actions in each of `bucket_count` slices of the video,
+/// from its start to its end (or the last action, if later or the length isn't known),
+/// along with the time of that end.
+fn video_heatmap(
+    actions: &[NormalisedAction],
+    duration_millis: Option<u32>,
+    bucket_count: usize,
+) -> (Vec<f32>, u32) {
+    let (Some(&first), Some(&last)) = (actions.first(), actions.last()) else {
+        return (vec![0.0; bucket_count], 0);
+    };
+    let end_millis = duration_millis.unwrap_or(0).max(last.at);
+    // Held still before the first action and after the last, so that the buckets span the video
+    let mut padded = Vec::with_capacity(actions.len() + 2);
+    padded.push(NormalisedAction { at: 0, ..first });
+    padded.extend_from_slice(actions);
+    padded.push(NormalisedAction {
+        at: end_millis,
+        ..last
+    });
+    (heatmap(&padded, bucket_count), end_millis)
+}
+
+/// The ASS events drawing the bar: a dim background, a column for each bucket
+/// (taller and redder the more intense it is) and a marker at `progress` through the video.
+fn render(buckets: &[f32], progress: Option<f32>, config: &HeatmapConfig) -> String {
+    let HeatmapConfig {
+        x,
+        y,
+        width,
+        height,
+        ..
+    } = *config;
+    let mut events = String::new();
+    draw_rect(&mut events, (0, 0, 0), 0x80, x, y, x + width, y + height);
+
+    for (idx, &intensity) in buckets.iter().enumerate() {
+        let column_height = (intensity * height as f32).round() as u32;
+        if column_height == 0 {
+            continue;
+        }
+        let left = x + (idx as u64 * width as u64 / buckets.len() as u64) as u32;
+        let right = x + ((idx as u64 + 1) * width as u64 / buckets.len() as u64) as u32;
+        let colour = blend(CALM_COLOUR, INTENSE_COLOUR, intensity);
+        draw_rect(
+            &mut events,
+            colour,
+            0,
+            left,
+            y + height - column_height,
+            right,
+            y + height,
+        );
+    }
+
+    if let Some(progress) = progress {
+        let marker_x = x + (progress * width as f32).round() as u32;
+        draw_rect(
+            &mut events,
+            (255, 255, 255),
+            0,
+            marker_x.saturating_sub(1),
+            y.saturating_sub(MARKER_OVERHANG),
+            marker_x + 1,
+            y + height + MARKER_OVERHANG,
+        );
+    }
+    events
+}
+
+/// Adds an ASS event drawing a filled rectangle, `alpha` being 0 for opaque to 255 for invisible.
+fn draw_rect(
+    events: &mut String,
+    (red, green, blue): (u8, u8, u8),
+    alpha: u8,
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+) {
+    // ASS colours are written blue, green, red
+    let _ = writeln!(
+        events,
+        r"{{\an7\pos(0,0)\bord0\shad0\1c&H{blue:02X}{green:02X}{red:02X}&\1a&H{alpha:02X}&\p1}}m {left} {top} l {right} {top} {right} {bottom} {left} {bottom}{{\p0}}"
+    );
+}
+
+/// The colour `fraction` of the way from `from` to `to`.
+fn blend(from: (u8, u8, u8), to: (u8, u8, u8), fraction: f32) -> (u8, u8, u8) {
+    let channel = |from: u8, to: u8| -> u8 {
+        (from as f32 + (to as f32 - from as f32) * fraction.clamp(0.0, 1.0)).round() as u8
+    };
+    (
+        channel(from.0, to.0),
+        channel(from.1, to.1),
+        channel(from.2, to.2),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use strokers::config::HeatmapConfig;
+    use strokers_funscript::processing::NormalisedAction;
+
+    use super::{render, video_heatmap};
+
+    fn action(at: u32, norm_pos: f32) -> NormalisedAction {
+        NormalisedAction { at, norm_pos }
+    }
+
+    #[test]
+    fn test_video_heatmap() {
+        // Moving only in the middle half of the video
+        let actions = [action(1000, 0.0), action(1500, 1.0), action(3000, 0.0)];
+        let (buckets, end_millis) = video_heatmap(&actions, Some(4000), 4);
+        assert_eq!(end_millis, 4000);
+        assert_eq!(buckets[0], 0.0);
+        assert_eq!(buckets[3], 0.0);
+        assert!(buckets[1] == 1.0 && buckets[2] > 0.0, "{buckets:?}");
+
+        // Without the video's length, up to the last action
+        let (buckets, end_millis) = video_heatmap(&actions, None, 3);
+        assert_eq!((buckets[0], end_millis), (0.0, 3000));
+
+        let (buckets, end_millis) = video_heatmap(&[], Some(4000), 2);
+        assert_eq!((buckets, end_millis), (vec![0.0, 0.0], 0));
+    }
+
+    #[test]
+    fn test_render() {
+        let config = HeatmapConfig {
+            bucket_count: 2,
+            x: 10,
+            y: 100,
+            width: 200,
+            height: 20,
+        };
+        let events = render(&[0.0, 1.0], Some(0.25), &config);
+        let lines: Vec<&str> = events.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                // Background
+                r"{\an7\pos(0,0)\bord0\shad0\1c&H000000&\1a&H80&\p1}m 10 100 l 210 100 210 120 10 120{\p0}",
+                // The intense half (the calm one isn't drawn)
+                r"{\an7\pos(0,0)\bord0\shad0\1c&H0020FF&\1a&H00&\p1}m 110 100 l 210 100 210 120 110 120{\p0}",
+                // Marker
+                r"{\an7\pos(0,0)\bord0\shad0\1c&HFFFFFF&\1a&H00&\p1}m 59 96 l 61 96 61 124 59 124{\p0}",
+            ]
+        );
+        assert_eq!(render(&[0.5], None, &config).lines().count(), 2);
+    }
+}
@@ -1,13 +1,140 @@
 use eyre::{bail, Context};
-use serde::Deserialize;
+use serde::{
+    de::{Error, Unexpected},
+    Deserialize, Deserializer,
+};
 use strokers::core::AxisKind;
 
 #[derive(Clone, Debug)]
 pub enum KeyCommand {
     AxisLimitChange(AxisLimitChangeCommand),
+    /// Shows or hides a page with the state of the stroker and of each axis,
+    /// e.g. the progress through its funscript, kept up to date whilst shown.
+    Status,
+    /// Shows or hides a bar along the video showing how intense the funscript is.
+    ToggleHeatmap,
+    /// Changes how far ahead of the video the funscripts are played.
+    SyncOffset(SyncOffsetCommand),
+    /// Switches to the next preset of limits, after the last going back to the first.
+    CyclePreset,
+    /// Switches to the named preset of limits.
+    SetPreset(SetPresetCommand),
+    /// Searches for the video's funscripts again and reloads them from disk,
+    /// e.g. after editing them.
+    ReloadFunscripts,
+    /// Switches to the next cluster of funscripts (e.g. `video.soft.funscript`),
+    /// after the last going back to the main one.
+    CycleCluster,
+    /// Switches to the named cluster of funscripts.
+    SetCluster(SetClusterCommand),
+    /// Disables the stroker if it's enabled, or enables it if it's disabled.
+    /// Whilst disabled, it doesn't move, but playback is still kept track of.
+    ToggleEnabled,
+    /// Enables or disables the stroker.
+    SetEnabled(SetEnabledCommand),
+    /// Connects to the stroker again, e.g. after plugging it in,
+    /// rather than waiting for the next attempt.
+    Reconnect,
+    /// Switches making up motion for videos without funscripts on or off.
+    ToggleFallback,
+    /// Changes how long a cycle of the made-up motion takes.
+    FallbackPeriod(FallbackPeriodCommand),
+    /// Starts playing a metronome on the stroker to match up with the OSD using the sync offset
+    /// keybindings, or if it's playing, stops and keeps the latency that was matched up.
+    Calibrate,
+    /// Stops the metronome without keeping the latency.
+    CancelCalibration,
+    /// Holds an axis still at a position, whilst the others carry on following their funscripts,
+    /// or lets it follow its funscript again.
+    HoldAxis(HoldAxisCommand),
+    /// Sends the state shown on the status page as JSON to another script.
+    GetStatus(GetStatusCommand),
+    /// Saves the limits of the axes in use, as adjusted, into the configuration file.
+    SaveLimits,
+}
+
+/// Changes the sync offset: how many milliseconds ahead of the video the funscripts are played.
+/// Negative to play them behind the video.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncOffsetCommand {
+    /// Change the offset by the given number of milliseconds.
+    pub by: Option<i32>,
+    /// Change the offset to the given number of milliseconds.
+    pub new: Option<i32>,
+}
+
+/// Enables or disables the stroker.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetEnabledCommand {
+    pub enabled: bool,
+}
+
+/// Switches to a preset of limits, from the `presets` in the configuration.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetPresetCommand {
+    /// The name of the preset, e.g. `gentle` for `[presets.gentle]`.
+    pub name: String,
+}
+
+/// Switches to a cluster of funscripts.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SetClusterCommand {
+    /// The name of the cluster, e.g. `soft` for `video.soft.funscript`,
+    /// or `main` for the funscripts named just after the video.
+    pub name: String,
+}
+
+/// Changes how many milliseconds a cycle of the motion made up for videos without funscripts takes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FallbackPeriodCommand {
+    /// Change the period by the given number of milliseconds.
+    pub by: Option<i32>,
+    /// Change the period to the given number of milliseconds.
+    pub new: Option<u32>,
+}
+
+/// Holds an axis at a position, or releases it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoldAxisCommand {
+    /// The axis to hold
+    pub axis: AxisKind,
+    /// Where to hold the axis, within its limits. The middle (0.5) if not given.
+    pub position: Option<f32>,
+    /// Release the axis if it's held, rather than holding it (again).
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub toggle: bool,
+}
+
+/// Asks for the state shown on the status page, as JSON, e.g. for an overlay in OBS.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GetStatusCommand {
+    /// What to reply with: the report is sent as `script-message <reply_to> <json>`.
+    pub reply_to: String,
+}
+
+/// Reads a flag given as `1` or `0` as well as `true` or `false`.
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        other => Err(D::Error::invalid_value(
+            Unexpected::Str(other),
+            &"1, 0, true or false",
+        )),
+    }
 }
 
 /// Changes the limit on an axis.
+///
+/// Unlike the other commands, arguments it doesn't take are ignored, as they always have been,
+/// so that bindings written with extra ones keep working.
 #[derive(Clone, Debug, Deserialize)]
 pub struct AxisLimitChangeCommand {
     /// The axis to change the limit of
@@ -20,6 +147,58 @@ pub struct AxisLimitChangeCommand {
     pub max_by: Option<f32>,
     /// Change the axis maximum limit to the given amount.
     pub max_new: Option<f32>,
+    /// Turn the axis's motion upside down, or back the right way up.
+    pub invert: Option<InvertChange>,
+}
+
+/// How to change whether an axis is inverted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvertChange {
+    /// Inverts the axis if it isn't, or puts it back the right way up if it is.
+    Toggle,
+    On,
+    Off,
+}
+
+/// The names of the actions, for telling the user what they could have meant.
+const ACTION_NAMES: &[&str] = &[
+    "axis_limit",
+    "status",
+    "toggle_heatmap",
+    "sync_offset",
+    "cycle_preset",
+    "set_preset",
+    "reload_funscripts",
+    "cycle_cluster",
+    "set_cluster",
+    "toggle_enabled",
+    "set_enabled",
+    "reconnect",
+    "toggle_fallback",
+    "fallback_period",
+    "calibrate",
+    "cancel_calibration",
+    "hold_axis",
+    "get_status",
+    "save_limits",
+];
+
+/// How much of a mistyped action to show on the OSD, in characters.
+const MAX_SHOWN_ACTION_CHARS: usize = 60;
+
+/// Parses an action's arguments, saying which are accepted if they can't be.
+fn parse_args<'de, T: Deserialize<'de>>(
+    action_name: &str,
+    args: &'de str,
+    accepted: &[&str],
+) -> eyre::Result<T> {
+    serde_qs::from_str(args).with_context(|| {
+        format!(
+            "failed to parse {action_name} cmd (it takes {})",
+            accepted.join(", ")
+        )
+    })
 }
 
 pub fn parse_action(action: &str) -> eyre::Result<KeyCommand> {
@@ -27,12 +206,275 @@ pub fn parse_action(action: &str) -> eyre::Result<KeyCommand> {
 
     match action_name {
         "axis_limit" => {
-            let cmd =
-                serde_qs::from_str(action_args_qs).context("failed to parse axis_limit cmd")?;
+            let cmd = parse_args(
+                action_name,
+                action_args_qs,
+                &["axis", "min_by", "min_new", "max_by", "max_new", "invert"],
+            )?;
             Ok(KeyCommand::AxisLimitChange(cmd))
         }
+        "status" => Ok(KeyCommand::Status),
+        "toggle_heatmap" => Ok(KeyCommand::ToggleHeatmap),
+        "sync_offset" => {
+            let cmd = parse_args(action_name, action_args_qs, &["by", "new"])?;
+            Ok(KeyCommand::SyncOffset(cmd))
+        }
+        "cycle_preset" => Ok(KeyCommand::CyclePreset),
+        "set_preset" => {
+            let cmd = parse_args(action_name, action_args_qs, &["name"])?;
+            Ok(KeyCommand::SetPreset(cmd))
+        }
+        "reload_funscripts" => Ok(KeyCommand::ReloadFunscripts),
+        "cycle_cluster" => Ok(KeyCommand::CycleCluster),
+        "set_cluster" => {
+            let cmd = parse_args(action_name, action_args_qs, &["name"])?;
+            Ok(KeyCommand::SetCluster(cmd))
+        }
+        "toggle_enabled" => Ok(KeyCommand::ToggleEnabled),
+        "set_enabled" => {
+            let cmd = parse_args(action_name, action_args_qs, &["enabled"])?;
+            Ok(KeyCommand::SetEnabled(cmd))
+        }
+        "reconnect" => Ok(KeyCommand::Reconnect),
+        "toggle_fallback" => Ok(KeyCommand::ToggleFallback),
+        "fallback_period" => {
+            let cmd = parse_args(action_name, action_args_qs, &["by", "new"])?;
+            Ok(KeyCommand::FallbackPeriod(cmd))
+        }
+        "calibrate" => Ok(KeyCommand::Calibrate),
+        "cancel_calibration" => Ok(KeyCommand::CancelCalibration),
+        "hold_axis" => {
+            let cmd = parse_args(action_name, action_args_qs, &["axis", "position", "toggle"])?;
+            Ok(KeyCommand::HoldAxis(cmd))
+        }
+        "get_status" => {
+            let cmd = parse_args(action_name, action_args_qs, &["reply_to"])?;
+            Ok(KeyCommand::GetStatus(cmd))
+        }
+        "save_limits" => Ok(KeyCommand::SaveLimits),
         _ => {
-            bail!("unknown action: {action_name:?}");
+            bail!(
+                "unknown action: {action_name:?} (the actions are {})",
+                ACTION_NAMES.join(", ")
+            );
+        }
+    }
+}
+
+/// What to show on the OSD when an action couldn't be parsed: the action, as much of it
+/// as fits, and why.
+pub fn describe_parse_error(action: &str, err: &eyre::Report) -> String {
+    let shown: String = if action.chars().count() > MAX_SHOWN_ACTION_CHARS {
+        let start: String = action.chars().take(MAX_SHOWN_ACTION_CHARS - 1).collect();
+        format!("{start}…")
+    } else {
+        action.to_owned()
+    };
+    format!("strokers: can't do {shown:?}: {err:#}")
+}
+
+/// What other scripts send `script-message`s to us as, e.g. `script-message strokers status`.
+pub const SCRIPT_MESSAGE_TARGET: &str = "strokers";
+
+/// Asks for the status as JSON, in the form mpv scripts usually ask each other for things.
+const STATUS_REQUEST: &str = "get-status";
+
+fn get_status(reply_to: &str) -> KeyCommand {
+    KeyCommand::GetStatus(GetStatusCommand {
+        reply_to: reply_to.to_owned(),
+    })
+}
+
+/// Parses a `script-message` from another script (or from `input.conf`),
+/// if it's for us (if not, `None`): `strokers <action> [<args>...]`.
+///
+/// The actions and their arguments are the same as for keybindings, for example:
+/// - `axis_limit axis=stroke&min_by=-0.05` (arguments can also be given separately:
+///   `axis_limit axis=stroke min_by=-0.05`)
+/// - `sync_offset by=50`, `sync_offset new=0`
+/// - `toggle_enabled`, `set_enabled enabled=false`
+/// - `set_preset name=gentle`, `cycle_preset`
+/// - `reload_funscripts`
+/// - `status`, which shows or hides the status page on the OSD
+///
+/// Also `get-status <reply-target>` (as well as `get_status reply_to=<reply-target>`),
+/// in the form mpv scripts usually ask each other for things. It still needs the `strokers`
+/// first: mpv doesn't say whether a message was sent to us or to everyone, and another
+/// script's `get-status` broadcast isn't for us to answer.
+pub fn parse_script_message(args: &[&str]) -> Option<eyre::Result<KeyCommand>> {
+    let (&target, args) = args.split_first()?;
+    if target != SCRIPT_MESSAGE_TARGET {
+        return None;
+    }
+    if let [STATUS_REQUEST, reply_to] = args {
+        return Some(Ok(get_status(reply_to)));
+    }
+    let Some((&action_name, action_args)) = args.split_first() else {
+        return Some(Err(eyre::eyre!(
+            "script-message {SCRIPT_MESSAGE_TARGET} needs an action"
+        )));
+    };
+    let action = if action_args.is_empty() {
+        action_name.to_owned()
+    } else {
+        format!("{action_name} {}", action_args.join("&"))
+    };
+    Some(parse_action(&action))
+}
+
+#[cfg(test)]
+mod test {
+    use strokers::core::AxisKind;
+
+    use super::{
+        describe_parse_error, parse_action, parse_script_message, KeyCommand, ACTION_NAMES,
+    };
+
+    #[test]
+    fn test_parse_hold_axis() {
+        let Ok(KeyCommand::HoldAxis(cmd)) = parse_action("hold_axis axis=twist&position=0.25")
+        else {
+            panic!();
+        };
+        assert_eq!(
+            (cmd.axis, cmd.position, cmd.toggle),
+            (AxisKind::Twist, Some(0.25), false)
+        );
+
+        for toggle in ["toggle=1", "toggle=true"] {
+            let Ok(KeyCommand::HoldAxis(cmd)) =
+                parse_action(&format!("hold_axis axis=roll&{toggle}"))
+            else {
+                panic!("{toggle}");
+            };
+            assert_eq!(
+                (cmd.axis, cmd.position, cmd.toggle),
+                (AxisKind::Roll, None, true)
+            );
+        }
+
+        assert!(parse_action("hold_axis position=0.5").is_err());
+        assert!(parse_action("hold_axis axis=twist&toggle=maybe").is_err());
+        assert!(parse_action("hold_axis axis=twist&position=middle").is_err());
+    }
+
+    #[test]
+    fn test_parse_script_message() {
+        let parsed = parse_script_message(&["strokers", "sync_offset", "by=-50"]);
+        assert!(matches!(
+            parsed,
+            Some(Ok(KeyCommand::SyncOffset(cmd))) if cmd.by == Some(-50) && cmd.new.is_none()
+        ));
+
+        // Arguments can be given together or separately
+        for args in [
+            &[
+                "strokers",
+                "axis_limit",
+                "axis=twist&min_new=0.2&max_new=0.8",
+            ][..],
+            &[
+                "strokers",
+                "axis_limit",
+                "axis=twist",
+                "min_new=0.2",
+                "max_new=0.8",
+            ][..],
+        ] {
+            let Some(Ok(KeyCommand::AxisLimitChange(cmd))) = parse_script_message(args) else {
+                panic!("{args:?}");
+            };
+            assert_eq!(cmd.axis, AxisKind::Twist);
+            assert_eq!((cmd.min_new, cmd.max_new), (Some(0.2), Some(0.8)));
+        }
+
+        assert!(matches!(
+            parse_script_message(&["strokers", "status"]),
+            Some(Ok(KeyCommand::Status))
+        ));
+        assert!(matches!(
+            parse_script_message(&["strokers", "set_enabled", "enabled=false"]),
+            Some(Ok(KeyCommand::SetEnabled(cmd))) if !cmd.enabled
+        ));
+        assert!(matches!(
+            parse_script_message(&["strokers", "set_preset", "name=gentle"]),
+            Some(Ok(KeyCommand::SetPreset(cmd))) if cmd.name == "gentle"
+        ));
+        assert!(matches!(
+            parse_script_message(&["strokers", "save_limits"]),
+            Some(Ok(KeyCommand::SaveLimits))
+        ));
+
+        // Asking for the status, either way
+        for args in [
+            &["strokers", "get-status", "overlay-status"][..],
+            &["strokers", "get_status", "reply_to=overlay-status"][..],
+        ] {
+            assert!(matches!(
+                parse_script_message(args),
+                Some(Ok(KeyCommand::GetStatus(cmd))) if cmd.reply_to == "overlay-status"
+            ));
+        }
+
+        // Mistakes are errors, to tell the sender about
+        assert!(matches!(
+            parse_script_message(&["strokers", "jump"]),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            parse_script_message(&["strokers", "sync_offset", "by=lots"]),
+            Some(Err(_))
+        ));
+        assert!(matches!(parse_script_message(&["strokers"]), Some(Err(_))));
+
+        // Messages for other scripts aren't ours to parse
+        assert!(parse_script_message(&["osc-visibility", "always"]).is_none());
+        assert!(parse_script_message(&["get-status", "overlay-status"]).is_none());
+        assert!(parse_script_message(&[]).is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let message = |action: &str| {
+            let err = parse_action(action).expect_err(action);
+            describe_parse_error(action, &err)
+        };
+
+        let typo = message("axis_limit axis=stroek&min_by=0.1");
+        assert!(
+            typo.starts_with("strokers: can't do \"axis_limit axis=stroek&min_by=0.1\""),
+            "{typo}"
+        );
+        assert!(typo.contains("stroek"), "{typo}");
+        assert!(
+            typo.contains("it takes axis, min_by, min_new, max_by, max_new, invert"),
+            "{typo}"
+        );
+
+        // Arguments that don't exist aren't ignored, except by `axis_limit` as they always were
+        assert!(matches!(
+            parse_action("axis_limit axis=stroke&min_by=0.1&min=0.2"),
+            Ok(KeyCommand::AxisLimitChange(cmd)) if cmd.min_by == Some(0.1)
+        ));
+        let unknown_arg = message("sync_offset by=50&step=10");
+        assert!(unknown_arg.contains("it takes by, new"), "{unknown_arg}");
+
+        let unknown = message("jump");
+        assert!(unknown.contains("unknown action: \"jump\""), "{unknown}");
+        assert!(unknown.contains("axis_limit, status,"), "{unknown}");
+
+        // Long actions are cut short
+        let long = format!("set_preset name={}", "x".repeat(200));
+        let err = eyre::eyre!("oops");
+        let shown = describe_parse_error(&long, &err);
+        assert!(shown.contains("xxx…\": oops"), "{shown}");
+        assert!(shown.chars().count() < 100, "{shown}");
+
+        // Every action listed is known, given the right arguments or not
+        for name in ACTION_NAMES {
+            if let Err(err) = parse_action(name) {
+                assert!(!format!("{err:#}").contains("unknown action"), "{name}");
+            }
         }
     }
 }
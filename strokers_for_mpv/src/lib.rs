@@ -1,22 +1,54 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
 use eyre::Context;
 use flume::{Receiver, Sender};
-use mpv_client::{mpv_handle, Client, Event, Handle};
-use playthread::PlaythreadMessage;
+use mpv_client::{mpv_handle, osd, Client, Event, Handle, Node};
+use playthread::{NewVideo, PlaythreadMessage};
 use tracing::{debug, error, info};
 use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
 
-use crate::keybindings::parse_action;
+use crate::{
+    keybindings::{describe_parse_error, parse_action, parse_script_message},
+    script_opts::script_opts_from_node,
+};
 
+pub(crate) mod calibration;
+pub(crate) mod clock;
+pub(crate) mod connection;
+pub(crate) mod fallback;
+pub(crate) mod heatmap;
 pub(crate) mod keybindings;
+pub(crate) mod mpv;
 pub(crate) mod playstate;
 mod playthread;
+pub(crate) mod scan_problems;
+pub(crate) mod script_opts;
+pub(crate) mod status;
+pub(crate) mod video_state;
+pub(crate) mod watcher;
+pub(crate) mod writer;
 
 const PROP_TIME: &str = "time-pos/full";
 const REPLY_TIME: u64 = 1;
 const PROP_PAUSE: &str = "pause";
 const REPLY_PAUSE: u64 = 2;
+const PROP_LOOP_FILE: &str = "loop-file";
+const REPLY_LOOP_FILE: u64 = 3;
+const PROP_DURATION: &str = "duration";
+const REPLY_DURATION: u64 = 4;
+const PROP_SPEED: &str = "speed";
+const REPLY_SPEED: u64 = 5;
+const PROP_AB_LOOP_A: &str = "ab-loop-a";
+const REPLY_AB_LOOP_A: u64 = 6;
+const PROP_AB_LOOP_B: &str = "ab-loop-b";
+const REPLY_AB_LOOP_B: u64 = 7;
 
 const PROP_PATH: &str = "path";
+const PROP_WORKING_DIRECTORY: &str = "working-directory";
+const PROP_SCRIPT_OPTS: &str = "options/script-opts";
 
 #[no_mangle]
 extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
@@ -49,6 +81,10 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
     // - time-pos/full (current playback position in milliseconds)
     //   - playback-time/full is similar but clamped to the duration of the file. I don't think we want that
     // - pause
+    // - loop-file (whether the video will loop, so the funscript should too)
+    // - duration (length of the video, which is how long a loop lasts)
+    // - speed (playback speed, as funscript times are in video time)
+    // - ab-loop-a, ab-loop-b (the A-B loop, jumping back from B to A, if both are set)
 
     if let Err(err) = client.observe_property::<f64>(REPLY_TIME, PROP_TIME) {
         error!("can't register for {PROP_TIME}: {err:?}");
@@ -56,6 +92,25 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
     if let Err(err) = client.observe_property::<bool>(REPLY_PAUSE, PROP_PAUSE) {
         error!("can't register for {PROP_PAUSE}: {err:?}");
     }
+    if let Err(err) = client.observe_property::<String>(REPLY_LOOP_FILE, PROP_LOOP_FILE) {
+        error!("can't register for {PROP_LOOP_FILE}: {err:?}");
+    }
+    if let Err(err) = client.observe_property::<f64>(REPLY_DURATION, PROP_DURATION) {
+        error!("can't register for {PROP_DURATION}: {err:?}");
+    }
+    if let Err(err) = client.observe_property::<f64>(REPLY_SPEED, PROP_SPEED) {
+        error!("can't register for {PROP_SPEED}: {err:?}");
+    }
+    if let Err(err) = client.observe_property::<f64>(REPLY_AB_LOOP_A, PROP_AB_LOOP_A) {
+        error!("can't register for {PROP_AB_LOOP_A}: {err:?}");
+    }
+    if let Err(err) = client.observe_property::<f64>(REPLY_AB_LOOP_B, PROP_AB_LOOP_B) {
+        error!("can't register for {PROP_AB_LOOP_B}: {err:?}");
+    }
+
+    // The points of the A-B loop, each of which mpv tells us of separately
+    let mut ab_loop_a_millis: Option<u32> = None;
+    let mut ab_loop_b_millis: Option<u32> = None;
 
     loop {
         match client.wait_event(-1.) {
@@ -66,15 +121,44 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
             Event::StartFile(_) => match client.get_property::<String>(PROP_PATH) {
                 Ok(new_path) => {
                     info!("New video starting: {new_path:?}");
-                    let cwd = match std::env::current_dir() {
-                        Ok(cwd) => cwd,
-                        Err(err) => {
-                            error!("Could not determine current working directory: {err:?}");
+                    // mpv's working directory, which relative paths are relative to,
+                    // isn't necessarily the process's
+                    let working_directory =
+                        match client.get_property::<String>(PROP_WORKING_DIRECTORY) {
+                            Ok(working_directory) => Some(PathBuf::from(working_directory)),
+                            Err(err) => {
+                                debug!("no {PROP_WORKING_DIRECTORY}, using the process's: {err:?}");
+                                std::env::current_dir()
+                                .inspect_err(|err| {
+                                    error!("Could not determine current working directory: {err:?}")
+                                })
+                                .ok()
+                            }
+                        };
+                    let video = if is_url(&new_path) {
+                        // Only the funscripts given in the script options are played along
+                        NewVideo::Stream { url: new_path }
+                    } else {
+                        let Some(video_path) =
+                            resolve_video_path(&new_path, working_directory.as_deref())
+                        else {
+                            info!("Not looking for funscripts for {new_path:?}");
                             continue;
+                        };
+                        NewVideo::File(video_path)
+                    };
+                    // Unset unless the user gave some options, which is no reason not to play
+                    let script_opts = match client.get_property::<Node>(PROP_SCRIPT_OPTS) {
+                        Ok(node) => script_opts_from_node(node),
+                        Err(err) => {
+                            debug!("no {PROP_SCRIPT_OPTS}: {err:?}");
+                            Default::default()
                         }
                     };
                     if let Err(_) = tx.send(PlaythreadMessage::VideoStarting {
-                        video_path: cwd.join(new_path),
+                        video,
+                        script_opts,
+                        working_directory,
                     }) {
                         error!("New video loaded but can't send notification to playtask.")
                     }
@@ -83,6 +167,17 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
                     error!("New video starting but failed to get {PROP_PATH}: {err:?}");
                 }
             },
+            Event::EndFile(end_file) => {
+                let reason = end_file.reason();
+                if !ends_playback(reason) {
+                    debug!("File ended (reason {reason}), but something else will follow");
+                    continue;
+                }
+                info!("Playback ended (reason {reason})");
+                if tx.send(PlaythreadMessage::PlaybackEnded).is_err() {
+                    error!("Playback ended but can't send notification to playtask.");
+                }
+            }
             Event::PropertyChange(REPLY_TIME, time_prop) => {
                 let Some(time) = time_prop.data::<f64>() else {
                     error!("On change, can't read {PROP_TIME} as f64");
@@ -105,6 +200,58 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
                     error!("Couldn't send pause change status to playtask.");
                 }
             }
+            Event::PropertyChange(REPLY_LOOP_FILE, loop_prop) => {
+                let Some(loop_file) = loop_prop.data::<String>() else {
+                    error!("can't read {PROP_LOOP_FILE} as string");
+                    continue;
+                };
+                // Either "no", "inf" or a number of times to loop
+                let looping = loop_file != "no";
+                if tx.send(PlaythreadMessage::LoopChange { looping }).is_err() {
+                    error!("Couldn't send loop change status to playtask.");
+                }
+            }
+            Event::PropertyChange(REPLY_DURATION, duration_prop) => {
+                // Unavailable whilst no video is loaded
+                let duration_millis = duration_prop
+                    .data::<f64>()
+                    .and_then(|duration| ((duration * 1000.0) as i64).try_into().ok());
+                if tx
+                    .send(PlaythreadMessage::DurationChange { duration_millis })
+                    .is_err()
+                {
+                    error!("Couldn't send duration change to playtask.");
+                }
+            }
+            Event::PropertyChange(REPLY_SPEED, speed_prop) => {
+                let Some(speed) = speed_prop.data::<f64>() else {
+                    error!("can't read {PROP_SPEED} as f64");
+                    continue;
+                };
+                if tx.send(PlaythreadMessage::SpeedChange { speed }).is_err() {
+                    error!("Couldn't send speed change to playtask.");
+                }
+            }
+            Event::PropertyChange(reply @ (REPLY_AB_LOOP_A | REPLY_AB_LOOP_B), point_prop) => {
+                // "no" (so not a number) when unset
+                let point_millis = point_prop
+                    .data::<f64>()
+                    .and_then(|point| ((point * 1000.0) as i64).try_into().ok());
+                if reply == REPLY_AB_LOOP_A {
+                    ab_loop_a_millis = point_millis;
+                } else {
+                    ab_loop_b_millis = point_millis;
+                }
+                if tx
+                    .send(PlaythreadMessage::AbLoopChange {
+                        a_millis: ab_loop_a_millis,
+                        b_millis: ab_loop_b_millis,
+                    })
+                    .is_err()
+                {
+                    error!("Couldn't send A-B loop change to playtask.");
+                }
+            }
             Event::Seek => {
                 let Ok(time) = client.get_property::<f64>(PROP_TIME) else {
                     error!("On seek, can't fetch {PROP_TIME} as f64");
@@ -122,20 +269,34 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
             }
             Event::ClientMessage(client_message) => {
                 let args = client_message.args();
-                if args[0] != "key-binding" || &args[2][0..1] != "u" {
-                    // the message is either not a keybinding or not a released key
-                    continue;
-                }
+                // The action as given, to show if it's wrong
+                let (parsed, raw_action) = if args.first() == Some(&"key-binding") {
+                    if args.len() < 3 || !args[2].starts_with('u') {
+                        // not a released key
+                        continue;
+                    }
+                    (parse_action(args[1]), args[1].to_owned())
+                } else {
+                    // e.g. `script-message strokers status`, from another script
+                    let Some(parsed) = parse_script_message(&args) else {
+                        continue;
+                    };
+                    (parsed, args.join(" "))
+                };
 
-                match parse_action(&args[1]) {
+                match parsed {
                     Ok(action) => {
-                        debug!("Keybinding triggered: {action:?}");
+                        debug!("Action triggered: {action:?}");
                         if let Err(_) = tx.send(PlaythreadMessage::KeyCommand(action)) {
                             error!("Couldn't send key command to playtask.");
                         }
                     }
                     Err(err) => {
-                        error!("{err:?}");
+                        error!("can't do {raw_action:?}: {err:?}");
+                        let message = describe_parse_error(&raw_action, &err);
+                        if let Err(err) = osd!(client, Duration::from_secs(5), "{message}") {
+                            error!("Failed to display OSD: {err:?}");
+                        }
                     }
                 }
             }
@@ -146,6 +307,52 @@ extern "C" fn mpv_open_cplugin(handle: *mut mpv_handle) -> std::os::raw::c_int {
     }
 }
 
+/// mpv's reasons for a file ending (`mpv_end_file_reason`)
+const END_FILE_REASON_EOF: u32 = 0;
+const END_FILE_REASON_STOP: u32 = 2;
+const END_FILE_REASON_QUIT: u32 = 3;
+const END_FILE_REASON_ERROR: u32 = 4;
+const END_FILE_REASON_REDIRECT: u32 = 5;
+
+/// Whether a file ending for the given reason leaves nothing playing, so the device should stop.
+///
+/// Looping files never end. Quitting shuts the playtask down anyway,
+/// and a redirect (e.g. a playlist file) goes straight on to the next file.
+fn ends_playback(reason: u32) -> bool {
+    match reason {
+        END_FILE_REASON_EOF | END_FILE_REASON_STOP | END_FILE_REASON_ERROR => true,
+        END_FILE_REASON_QUIT | END_FILE_REASON_REDIRECT => false,
+        // Something newer than we know of, so be on the safe side
+        _ => true,
+    }
+}
+
+/// Where the video of mpv's `path` property is, resolving a relative path against
+/// mpv's working directory.
+///
+/// `None` for URLs (e.g. streams), which have no directory to look for funscripts in,
+/// and for relative paths if the working directory isn't known.
+fn resolve_video_path(path: &str, working_directory: Option<&Path>) -> Option<PathBuf> {
+    if is_url(path) {
+        return None;
+    }
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return Some(path.to_owned());
+    }
+    working_directory.map(|working_directory| working_directory.join(path))
+}
+
+/// Whether mpv would take the path as a URL, i.e. it starts with a protocol such as `https://`.
+pub(crate) fn is_url(path: &str) -> bool {
+    path.split_once("://").is_some_and(|(protocol, _)| {
+        !protocol.is_empty()
+            && protocol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn start_playtask(
     rx: Receiver<PlaythreadMessage>,
@@ -155,9 +362,64 @@ async fn start_playtask(
     let config = strokers::load_config()
         .await
         .context("failed to load Strokers configuration")?;
-    let stroker = strokers::open_stroker(&config.stroker)
-        .await
-        .context("failed to connect to Stroker")?;
-    playthread::playtask(stroker, config, rx, tx, weak_client).await?;
+    let connect = {
+        let config = config.clone();
+        move || {
+            let config = config.clone();
+            async move {
+                strokers::open_configured_stroker(&config)
+                    .await
+                    .context("failed to connect to Stroker")
+            }
+        }
+    };
+    playthread::playtask(connect, config, rx, tx, weak_client).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        ends_playback, resolve_video_path, END_FILE_REASON_EOF, END_FILE_REASON_QUIT,
+        END_FILE_REASON_REDIRECT,
+    };
+
+    #[test]
+    fn test_ends_playback() {
+        assert!(ends_playback(END_FILE_REASON_EOF));
+        assert!(!ends_playback(END_FILE_REASON_QUIT));
+        assert!(!ends_playback(END_FILE_REASON_REDIRECT));
+    }
+
+    #[test]
+    fn test_resolve_video_path() {
+        let working_directory = Some(Path::new("/home/me/videos"));
+        assert_eq!(
+            resolve_video_path("new/Video.mp4", working_directory),
+            Some(PathBuf::from("/home/me/videos/new/Video.mp4"))
+        );
+        assert_eq!(
+            resolve_video_path("/elsewhere/Video.mp4", working_directory),
+            Some(PathBuf::from("/elsewhere/Video.mp4"))
+        );
+        assert_eq!(
+            resolve_video_path("Video.mp4", None),
+            None,
+            "nothing to resolve against"
+        );
+        for url in [
+            "https://example.com/Video.mp4",
+            "ytdl://abc",
+            "file:///home/me/Video.mp4",
+        ] {
+            assert_eq!(resolve_video_path(url, working_directory), None, "{url}");
+        }
+        // Not URLs, just odd names
+        assert_eq!(
+            resolve_video_path("My Video: ://Part 2.mp4", working_directory),
+            Some(PathBuf::from("/home/me/videos/My Video: ://Part 2.mp4"))
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use eyre::eyre;
+use mpv_client::{Client, Handle};
+
+/// What playing along with a video needs of mpv: running commands (such as `show-text`,
+/// which [`osd!`](mpv_client::osd) runs) and pausing the video.
+///
+/// Implemented by mpv's [`Client`], and in tests by [`testing::RecordingMpv`],
+/// so that the playthread can be driven without mpv.
+pub(crate) trait Mpv {
+    /// Runs an mpv command, e.g. `["show-text", "Hello", "2000"]`.
+    fn command<I, S>(&mut self, args: I) -> eyre::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>;
+
+    /// Pauses or unpauses the video.
+    fn set_pause(&mut self, paused: bool) -> eyre::Result<()>;
+}
+
+impl Mpv for Client {
+    fn command<I, S>(&mut self, args: I) -> eyre::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<String> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_owned())
+            .collect();
+        Handle::command(self, args).map_err(|err| eyre!("{err:?}"))
+    }
+
+    fn set_pause(&mut self, paused: bool) -> eyre::Result<()> {
+        Handle::set_property(self, "pause", paused).map_err(|err| eyre!("{err:?}"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::sync::{Arc, Mutex};
+
+    use super::Mpv;
+
+    /// Stands in for mpv, keeping a record of the commands run.
+    #[derive(Clone, Default)]
+    pub(crate) struct RecordingMpv {
+        commands: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    impl RecordingMpv {
+        /// Takes the commands run so far.
+        pub fn take_commands(&self) -> Vec<Vec<String>> {
+            std::mem::take(&mut self.commands.lock().unwrap())
+        }
+    }
+
+    impl Mpv for RecordingMpv {
+        fn command<I, S>(&mut self, args: I) -> eyre::Result<()>
+        where
+            I: IntoIterator<Item = S>,
+            S: AsRef<str>,
+        {
+            let args = args
+                .into_iter()
+                .map(|arg| arg.as_ref().to_owned())
+                .collect();
+            self.commands.lock().unwrap().push(args);
+            Ok(())
+        }
+
+        fn set_pause(&mut self, paused: bool) -> eyre::Result<()> {
+            self.command(["set", "pause", if paused { "yes" } else { "no" }])
+        }
+    }
+}
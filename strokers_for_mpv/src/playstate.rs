@@ -1,21 +1,164 @@
-use std::{
-    collections::BTreeMap,
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use eyre::{Context, ContextCompat};
-use strokers::core::{AxisId, Movement, Stroker};
-use strokers_funscript::{playstate::FunscriptPlaystate, processing::NormalisedAction};
+use strokers::{
+    config::{LimitsConfig, PresetLimitsConfig},
+    core::{AxisId, Movement, Stroker},
+};
+use strokers_funscript::{
+    limits::limit_target,
+    playstate::{FunscriptPlaystate, PlaystateStatus, TracedAction, FIRST_ACTION_LEAD_IN_MS},
+    processing::{Easing, NormalisedAction},
+    schema::FunscriptAction,
+};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// How long an axis takes to move to where it's held, from wherever it was.
+pub const HOLD_MOVE_MS: u32 = 500;
+
+/// How long the position has to stay put after a seek before the axes move into place.
+pub const SEEK_SETTLE: Duration = Duration::from_millis(150);
+
+/// How long an axis takes to move to rest, after being idle for its `idle_timeout_ms`.
+pub const REST_MOVE_MS: u32 = 3000;
+
+/// How often the axes follow the video at most, as it's stepped through whilst paused.
+pub const STEP_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Default)]
 pub(crate) struct Playstate {
     pub by_axis: BTreeMap<AxisId, AxisPlaystate>,
 }
 
+impl Playstate {
+    /// Seeks every axis to the given time, as [`AxisPlaystate::seek`].
+    pub async fn seek(
+        &mut self,
+        now_millis: u32,
+        paused: bool,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        for (&axis_id, axis_playstate) in self.by_axis.iter_mut() {
+            axis_playstate
+                .seek(now_millis, paused, axis_id, stroker)
+                .await
+                .with_context(|| format!("failed to seek {axis_id:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Holds back moving into place after a seek until the position has settled, so that
+/// dragging the seek bar (dozens of seeks a second) doesn't send the axes after each one.
+#[derive(Default)]
+pub(crate) struct SeekDebounce {
+    /// The time seeked to last, and when to move into place if there's no seek after it
+    pending: Option<(u32, tokio::time::Instant)>,
+}
+
+impl SeekDebounce {
+    /// Holds back moving into place until there's been no seek for [`SEEK_SETTLE`].
+    pub fn seek(&mut self, now_millis: u32) {
+        self.pending = Some((now_millis, tokio::time::Instant::now() + SEEK_SETTLE));
+    }
+
+    /// Whether a seek is being held back, during which the axes shouldn't move along either.
+    pub fn is_settling(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// If playback has carried on from the time seeked to, the seeking is over,
+    /// so the axes can move into place straight away.
+    pub fn time_change(&mut self, now_millis: u32) {
+        if let Some((seek_millis, settle_at)) = self.pending.as_mut() {
+            if now_millis > *seek_millis {
+                *settle_at = tokio::time::Instant::now();
+            }
+        }
+    }
+
+    /// Waits until the axes should move into place, or forever if no seek is being held back.
+    pub async fn settled(&self) {
+        match self.pending {
+            Some((_, settle_at)) => tokio::time::sleep_until(settle_at).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Forgets the seek, e.g. as the axes move into place.
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
+/// With `track_while_paused`, holds back following the video as it's stepped through
+/// whilst paused, so that holding down the frame-step key doesn't send the axes
+/// after every frame. A step is followed straight away if there hasn't been one for
+/// [`STEP_INTERVAL`]; otherwise only the latest is, once that's up.
+#[derive(Default)]
+pub(crate) struct StepThrottle {
+    /// When the axes last followed a step
+    followed_at: Option<tokio::time::Instant>,
+    /// The latest time stepped to that's being held back, and when to follow it
+    pending: Option<(u32, tokio::time::Instant)>,
+}
+
+impl StepThrottle {
+    /// The video was stepped to `now_millis`. Gives it back if it's to be followed
+    /// straight away; if not, it's held back until [`due`](Self::due).
+    pub fn step(&mut self, now_millis: u32) -> Option<u32> {
+        let now = tokio::time::Instant::now();
+        match self.followed_at {
+            Some(followed_at) if now < followed_at + STEP_INTERVAL => {
+                self.pending = Some((now_millis, followed_at + STEP_INTERVAL));
+                None
+            }
+            _ => {
+                self.followed_at = Some(now);
+                self.pending = None;
+                Some(now_millis)
+            }
+        }
+    }
+
+    /// Waits until a step held back is to be followed, or forever if there isn't one.
+    pub async fn due(&self) {
+        match self.pending {
+            Some((_, follow_at)) => tokio::time::sleep_until(follow_at).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Takes the step held back, to follow now.
+    pub fn take(&mut self) -> Option<u32> {
+        let (now_millis, _) = self.pending.take()?;
+        self.followed_at = Some(tokio::time::Instant::now());
+        Some(now_millis)
+    }
+
+    /// Forgets any step held back, e.g. as the video resumes.
+    pub fn clear(&mut self) {
+        self.pending = None;
+    }
+}
+
 pub(crate) struct AxisPlaystate {
     funscript: FunscriptPlaystate,
+    /// The funscript's first action, to lead in to when interpolating
+    first_action: Option<NormalisedAction>,
+    /// When interpolating, the action being moved towards, to trace the movement reaching it
+    interpolating_to: Option<TracedAction>,
     pub limiter: AxisLimiter,
+    /// How fast the video is playing, as a multiple of normal speed
+    speed: f64,
+    /// Where the axis is held still at the user's request, if it is,
+    /// in which case it doesn't follow the funscript (though its place in it is kept track of)
+    held: Option<f32>,
+    /// With `idle_timeout_ms`, how long the axis can go without being sent anywhere before resting
+    idle_timeout: Option<Duration>,
+    /// When the axis was sent to rest, if that's the last place it was sent
+    rested_at: Option<Instant>,
 }
 
 impl AxisPlaystate {
@@ -26,24 +169,105 @@ impl AxisPlaystate {
         max: f32,
     ) -> AxisPlaystate {
         AxisPlaystate {
+            first_action: normalised_actions.first().copied(),
+            interpolating_to: None,
             funscript: FunscriptPlaystate::new(normalised_actions),
             limiter: AxisLimiter::new(speed_limit, min, max),
+            speed: 1.0,
+            held: None,
+            idle_timeout: None,
+            rested_at: None,
+        }
+    }
+
+    /// Lets the axis rest once it's been idle for `idle_timeout_ms`, as configured.
+    pub fn set_idle_timeout(&mut self, idle_timeout_ms: Option<u32>) {
+        self.idle_timeout = idle_timeout_ms.map(|ms| Duration::from_millis(ms as u64));
+    }
+
+    /// When the axis will have been idle long enough to rest, unless it's sent anywhere first.
+    /// `None` if it doesn't rest, is resting already or is held.
+    pub fn rest_due(&self) -> Option<Instant> {
+        let idle_timeout = self.idle_timeout?;
+        let last_sent_at = self.limiter.last_command_start_time;
+        if self.held.is_some() || self.rested_at == Some(last_sent_at) {
+            return None;
         }
+        Some(last_sent_at + idle_timeout)
+    }
+
+    /// Moves the axis slowly to the middle of its limits, having been idle for a while.
+    /// Once it's sent anywhere else (e.g. by the funscript's next action), it can rest again.
+    pub async fn rest(&mut self, axis_id: AxisId, stroker: &mut impl Stroker) -> eyre::Result<()> {
+        self.park(0.5, REST_MOVE_MS, axis_id, stroker).await?;
+        self.rested_at = Some(self.limiter.last_command_start_time);
+        Ok(())
+    }
+
+    /// Where the axis is held, if it is.
+    pub fn held(&self) -> Option<f32> {
+        self.held
+    }
+
+    /// Holds the axis at a position (within its limits), or with `None` lets it follow
+    /// the funscript again. Doesn't move the axis: [`park`](Self::park) it there,
+    /// or [`seek`](Self::seek) back into the funscript.
+    pub fn set_held(&mut self, held: Option<f32>) {
+        self.held = held;
+    }
+
+    /// Sets how fast the video is playing, e.g. 1.25 for 25% faster than normal,
+    /// so that movements take as long in real time as in the video. Nonsensical speeds
+    /// are taken as normal speed.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = if speed.is_finite() && speed > 0.0 {
+            speed
+        } else {
+            1.0
+        };
+    }
+
+    /// How long `video_millis` of the video takes to play, in real milliseconds.
+    fn real_duration(&self, video_millis: u32) -> u32 {
+        (video_millis as f64 / self.speed)
+            .round()
+            .min(u32::MAX as f64) as u32
     }
+
+    pub fn status(&self) -> PlaystateStatus {
+        self.funscript.status()
+    }
+
+    /// Enables or disables looping of the funscript, with a loop as long as the video if known.
+    pub fn set_loop(&mut self, enabled: bool, video_duration_millis: Option<u32>) {
+        self.funscript.set_loop(enabled);
+        self.funscript.set_loop_duration(video_duration_millis);
+    }
+
+    /// Gives the actions as they were in the funscript file,
+    /// so that each movement is logged alongside the action it came from.
+    pub fn set_raw_actions(&mut self, raw_actions: Arc<Vec<FunscriptAction>>) {
+        self.funscript.set_raw_actions(raw_actions);
+    }
+
     pub async fn tick(
         &mut self,
         now_millis: u32,
         axis_id: AxisId,
         stroker: &mut impl Stroker,
     ) -> eyre::Result<()> {
-        if let Some(action) = self.funscript.tick(now_millis) {
-            if action.at < now_millis {
+        if let Some(traced) = self.funscript.tick_traced(now_millis) {
+            let action = traced.action;
+            if action.at < now_millis || self.held.is_some() {
                 return Ok(());
             }
             let now = Instant::now();
-            let (new_target, new_target_duration) =
-                self.limiter
-                    .limit_command(now, action.norm_pos, action.at - now_millis);
+            let (new_target, new_target_duration) = self.limiter.limit_command(
+                now,
+                action.norm_pos,
+                self.real_duration(action.at - now_millis),
+            );
+            trace_movement(axis_id, &traced, new_target, new_target_duration);
             self.limiter
                 .notify_commanded(now, new_target, new_target_duration);
             stroker
@@ -62,6 +286,70 @@ impl AxisPlaystate {
         Ok(())
     }
 
+    /// Moves along the funscript for the next `step_millis` from `now_millis`, to where the
+    /// funscript is (interpolating linearly) at the end of the step, rather than waiting for
+    /// the next action as [`tick`](Self::tick) does. For calling at a fixed rate.
+    ///
+    /// If an action comes before the end of the step, the movement ends there instead,
+    /// so that each action's position is reached exactly at its time.
+    pub async fn interpolate(
+        &mut self,
+        now_millis: u32,
+        step_millis: u32,
+        axis_id: AxisId,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        // Keeps the place in the funscript as ticking does, for the progress and looping
+        if let Some(traced) = self.funscript.tick_traced(now_millis) {
+            self.interpolating_to = Some(traced);
+        }
+        if self.held.is_some() {
+            return Ok(());
+        }
+        let step_end = now_millis.saturating_add(step_millis);
+        let (target, target_at) = match self.funscript.segment_at(now_millis) {
+            Some((_, next)) if next.at <= step_end => (next.norm_pos, next.at),
+            Some((previous, next)) => {
+                let progress = (step_end - previous.at) as f32 / (next.at - previous.at) as f32;
+                let norm_pos = previous.norm_pos + (next.norm_pos - previous.norm_pos) * progress;
+                (norm_pos, step_end)
+            }
+            // Heading for the first action, once it's close
+            None => match self.first_action {
+                Some(first)
+                    if now_millis < first.at
+                        && first.at - now_millis <= FIRST_ACTION_LEAD_IN_MS =>
+                {
+                    (first.norm_pos, first.at)
+                }
+                _ => return Ok(()),
+            },
+        };
+
+        let now = Instant::now();
+        let (new_target, new_target_duration) =
+            self.limiter
+                .limit_command(now, target, self.real_duration(target_at - now_millis));
+        if let Some(traced) = &self.interpolating_to {
+            if traced.action.at == target_at {
+                trace_movement(axis_id, traced, new_target, new_target_duration);
+            }
+        }
+        self.limiter
+            .notify_commanded(now, new_target, new_target_duration);
+        stroker
+            .movement(
+                Movement::new(axis_id, new_target, new_target_duration).with_context(|| {
+                    format!("failed to construct interpolated movement from pos:{new_target}, {new_target_duration}ms")
+                })?,
+            )
+            .await
+            .with_context(|| {
+                format!("failed to command interpolated movement from pos:{new_target}, {new_target_duration}ms")
+            })?;
+        Ok(())
+    }
+
     pub async fn seek(
         &mut self,
         now_millis: u32,
@@ -70,17 +358,26 @@ impl AxisPlaystate {
         stroker: &mut impl Stroker,
     ) -> eyre::Result<()> {
         self.funscript.seek(now_millis);
+        if self.held.is_some() {
+            return Ok(());
+        }
 
-        if let Some(action) = self.funscript.tick(now_millis) {
+        if let Some(traced) = self.funscript.tick_traced(now_millis) {
+            let action = traced.action;
             let now = Instant::now();
 
             // if the video is paused, give a long time to gradually move to the right position
             // that way we also likely avoid being speed limited.
-            let orig_target_duration = if paused { 1000 } else { action.at - now_millis };
+            let orig_target_duration = if paused {
+                1000
+            } else {
+                self.real_duration(action.at - now_millis)
+            };
 
             let (new_target, new_target_duration) =
                 self.limiter
                     .limit_command(now, action.norm_pos, orig_target_duration);
+            trace_movement(axis_id, &traced, new_target, new_target_duration);
             self.limiter
                 .notify_commanded(now, new_target, new_target_duration);
             stroker
@@ -98,10 +395,62 @@ impl AxisPlaystate {
 
         Ok(())
     }
+
+    /// Seeks to the given time and moves gently into place, as for a seek whilst paused,
+    /// whatever the video is doing. For a funscript that's just been loaded mid-video
+    /// (e.g. one that started part-way through), as the axis could be anywhere.
+    pub async fn move_into_place(
+        &mut self,
+        now_millis: u32,
+        axis_id: AxisId,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        self.seek(now_millis, true, axis_id, stroker).await
+    }
+
+    /// Moves slowly to `norm_pos` (within the limits) over `over_millis`, e.g. out of the way
+    /// whilst paused. The limiter is told, so that resuming moves back from there.
+    pub async fn park(
+        &mut self,
+        norm_pos: f32,
+        over_millis: u32,
+        axis_id: AxisId,
+        stroker: &mut impl Stroker,
+    ) -> eyre::Result<()> {
+        let now = Instant::now();
+        let (new_target, new_target_duration) =
+            self.limiter.limit_command(now, norm_pos, over_millis);
+        self.limiter
+            .notify_commanded(now, new_target, new_target_duration);
+        stroker
+            .movement(
+                Movement::new(axis_id, new_target, new_target_duration).with_context(|| {
+                    format!("failed to construct parking movement from pos:{new_target}, {new_target_duration}ms")
+                })?,
+            )
+            .await
+            .with_context(|| {
+                format!("failed to command parking movement from pos:{new_target}, {new_target_duration}ms")
+            })?;
+        Ok(())
+    }
+}
+
+/// Logs a movement alongside the action in the funscript file it came from, if traced.
+fn trace_movement(axis_id: AxisId, traced: &TracedAction, target: f32, duration_millis: u32) {
+    let Some(raw) = &traced.raw else {
+        return;
+    };
+    debug!(
+        "{axis_id:?}: file pos {} at {} ms, normalised {:.3}, sent {target:.3} over {duration_millis} ms",
+        raw.pos, raw.at, traced.action.norm_pos
+    );
 }
 
 /// Tracks current position and limits speed.
-/// TODO should this move to `strokers` crate?
+///
+/// The limiting itself is [`limit_target`], which is shared with
+/// [`apply_limits`](strokers_funscript::processing::apply_limits).
 pub(crate) struct AxisLimiter {
     /// Maximum number of full-scale movements per second
     pub speed_limit: f32,
@@ -117,6 +466,10 @@ pub(crate) struct AxisLimiter {
     pub min: f32,
     /// The top of the axis
     pub max: f32,
+    /// Whether the motion is turned upside down, before it's fitted between `min` and `max`
+    pub inverted: bool,
+    /// How the stroker eases each movement, to estimate where it is part-way through one
+    pub easing: Easing,
 }
 
 impl AxisLimiter {
@@ -128,7 +481,8 @@ impl AxisLimiter {
             let proportion_complete = (now - self.last_command_start_time).as_secs_f64()
                 / (self.last_command_target_time - self.last_command_start_time).as_secs_f64();
             self.last_command_start
-                + (self.last_command_target - self.last_command_start) * proportion_complete as f32
+                + (self.last_command_target - self.last_command_start)
+                    * self.easing.apply(proportion_complete as f32)
         } else {
             self.last_command_start
         }
@@ -138,20 +492,57 @@ impl AxisLimiter {
     /// and limits it according to the configured bottom, top and speed limits.
     pub fn limit_command(&self, now: Instant, target: f32, duration_millis: u32) -> (f32, u32) {
         let cur_pos = self.estimate_current_position(now);
+        let target = if self.inverted { 1.0 - target } else { target };
+        let target = limit_target(
+            cur_pos,
+            target,
+            duration_millis,
+            self.speed_limit,
+            self.min,
+            self.max,
+        );
+        (target, duration_millis)
+    }
 
-        // Apply top and bottom limits
-        let target = self.min + (self.max - self.min) * target;
-
-        let delta = target - cur_pos;
+    /// Switches to the limits of a preset, keeping any it leaves out.
+    ///
+    /// Like any change of limits, this applies from the next action, so the axis doesn't jump.
+    /// A preset that would put the minimum above the maximum keeps the axis's current bottom
+    /// and top limits (with a warning), as inverting is for `invert`.
+    pub fn apply_preset(&mut self, preset: &PresetLimitsConfig) {
+        self.speed_limit = preset.speed.unwrap_or(self.speed_limit);
+        self.inverted = preset.invert.unwrap_or(self.inverted);
+        let min = preset.min.unwrap_or(self.min);
+        let max = preset.max.unwrap_or(self.max);
+        if min > max {
+            warn!(
+                "ignoring minimum limit {min} above the maximum {max}; use `invert` to invert the axis"
+            );
+            return;
+        }
+        self.min = min;
+        self.max = max;
+    }
 
-        let speed_abs = delta.abs() / (duration_millis.max(1) as f32 * 0.001);
+    /// Takes the rest of the axis's configuration, besides the limits given to [`AxisLimiter::new`].
+    pub fn apply_config(&mut self, limits: &LimitsConfig) {
+        self.easing = limits.device_easing;
+        self.inverted |= limits.invert;
+    }
 
-        if speed_abs < self.speed_limit {
-            (target, duration_millis)
-        } else {
-            let proposed_delta = delta * (self.speed_limit / speed_abs);
-            (cur_pos + proposed_delta, duration_millis)
+    /// A minimum above the maximum in the configuration used to be the way to invert an axis,
+    /// so still does that (with a warning): the limits are swapped around and the axis is
+    /// inverted instead.
+    fn fix_reversed_limits(&mut self) {
+        if self.min <= self.max {
+            return;
         }
+        warn!(
+            "minimum limit {} is above the maximum {}, so inverting the axis; use `invert` for that instead",
+            self.min, self.max
+        );
+        std::mem::swap(&mut self.min, &mut self.max);
+        self.inverted = true;
     }
 
     /// Updates the tracked state to reflect that we just commanded a move.
@@ -166,7 +557,7 @@ impl AxisLimiter {
 
     pub fn new(speed_limit: f32, min: f32, max: f32) -> AxisLimiter {
         let now = Instant::now();
-        AxisLimiter {
+        let mut limiter = AxisLimiter {
             speed_limit,
             last_command_start_time: now,
             last_command_start: 0.5,
@@ -174,6 +565,604 @@ impl AxisLimiter {
             last_command_target: 0.5,
             min,
             max,
+            inverted: false,
+            easing: Easing::Linear,
+        };
+        limiter.fix_reversed_limits();
+        limiter
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+    use strokers::{
+        config::{LimitsConfig, PresetLimitsConfig},
+        core::{AxisDescriptor, AxisId, AxisKind, Movement, Stroker},
+        devices::{
+            debug::testing::{ChannelStroker, StrokerCommand},
+            DryRunStroker,
+        },
+    };
+    use strokers_funscript::processing::{Easing, NormalisedAction};
+    use tokio::time::Instant;
+
+    use super::{
+        AxisLimiter, AxisPlaystate, Playstate, StepThrottle, HOLD_MOVE_MS, REST_MOVE_MS,
+        STEP_INTERVAL,
+    };
+
+    fn action(at: u32, norm_pos: f32) -> NormalisedAction {
+        NormalisedAction { at, norm_pos }
+    }
+
+    /// The (target, ramp time) of each command, or None for stops.
+    fn commands(rx: &flume::Receiver<StrokerCommand>) -> Vec<Option<(f32, u32)>> {
+        rx.drain()
+            .map(|command| match command {
+                StrokerCommand::Stop { .. } => None,
+                StrokerCommand::Movement { movement, .. } => {
+                    Some((movement.target(), movement.ramp_time_milliseconds()))
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_tick_and_seek() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let actions = vec![action(0, 0.0), action(500, 1.0), action(1000, 0.0)];
+        // Generous speed limit so that nothing gets limited
+        let mut playstate = AxisPlaystate::new(Arc::new(actions), 1000.0, 0.0, 1.0);
+
+        for now_millis in [0, 100, 200, 500] {
+            playstate
+                .tick(now_millis, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            commands(&rx),
+            vec![Some((0.0, 0)), Some((1.0, 400)), Some((0.0, 500))]
+        );
+
+        // Whilst paused, seeking moves slowly to the next action
+        playstate
+            .seek(250, true, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((1.0, 1000))]);
+    }
+
+    #[tokio::test]
+    async fn test_playback_speed() {
+        let axis_id = AxisId(1);
+        let actions = Arc::new(vec![action(0, 0.0), action(500, 1.0), action(1000, 0.0)]);
+
+        // (speed, ramp times for the ticks and then a seek)
+        for (speed, expected) in [(2.0, [0, 200, 250, 125]), (0.5, [0, 800, 1000, 500])] {
+            let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+            // Generous speed limit so that nothing gets limited
+            let mut playstate = AxisPlaystate::new(actions.clone(), 1000.0, 0.0, 1.0);
+            playstate.set_speed(speed);
+
+            for now_millis in [0, 100, 500] {
+                playstate
+                    .tick(now_millis, axis_id, &mut stroker)
+                    .await
+                    .unwrap();
+            }
+            playstate
+                .seek(750, false, axis_id, &mut stroker)
+                .await
+                .unwrap();
+            let ramp_times: Vec<u32> = commands(&rx)
+                .into_iter()
+                .map(|command| command.unwrap().1)
+                .collect();
+            assert_eq!(ramp_times, expected, "speed {speed}");
         }
+
+        // A change of speed applies from the next action
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let mut playstate = AxisPlaystate::new(actions, 1000.0, 0.0, 1.0);
+        for (now_millis, speed) in [(0, 1.0), (0, 1.0), (500, 2.0)] {
+            playstate.set_speed(speed);
+            playstate
+                .tick(now_millis, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            commands(&rx),
+            vec![Some((0.0, 0)), Some((1.0, 500)), Some((0.0, 250))]
+        );
+
+        // Nonsense is ignored
+        playstate.set_speed(0.0);
+        assert_eq!(playstate.real_duration(500), 500);
+    }
+
+    #[tokio::test]
+    async fn test_no_movement_long_before_first_action() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let actions = vec![action(60_000, 1.0), action(60_500, 0.0)];
+        let mut playstate = AxisPlaystate::new(Arc::new(actions), 1000.0, 0.0, 1.0);
+
+        for now_millis in [0, 100, 30_000, 58_999] {
+            playstate
+                .tick(now_millis, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(commands(&rx), vec![]);
+
+        // Only a short ramp up to the first action, then on as usual
+        for now_millis in [59_200, 60_000] {
+            playstate
+                .tick(now_millis, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(commands(&rx), vec![Some((1.0, 800)), Some((0.0, 500))]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_pause() {
+        let (stroke, twist) = (AxisId(1), AxisId(2));
+        let (mut stroker, rx) =
+            ChannelStroker::with_axes(vec![(stroke, AxisKind::Stroke), (twist, AxisKind::Twist)]);
+        let actions = Arc::new(vec![action(0, 0.0), action(500, 1.0), action(5000, 0.0)]);
+        let mut playstate = Playstate::default();
+        for axis_id in [stroke, twist] {
+            playstate.by_axis.insert(
+                axis_id,
+                AxisPlaystate::new(actions.clone(), 1000.0, 0.0, 1.0),
+            );
+        }
+
+        for now_millis in [0, 500] {
+            for (&axis_id, axis_playstate) in playstate.by_axis.iter_mut() {
+                axis_playstate
+                    .tick(now_millis, axis_id, &mut stroker)
+                    .await
+                    .unwrap();
+            }
+        }
+        assert_eq!(commands(&rx).len(), 4);
+
+        // Paused at 1000 (and the stroker stopped) whilst heading for the action at 5000:
+        // resuming heads for it again rather than waiting for it to pass
+        playstate.seek(1000, false, &mut stroker).await.unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.0, 4000)), Some((0.0, 4000))]);
+        // Once per axis however often it happens
+        playstate.seek(1000, false, &mut stroker).await.unwrap();
+        assert_eq!(commands(&rx).len(), 2);
+        for (&axis_id, axis_playstate) in playstate.by_axis.iter_mut() {
+            axis_playstate
+                .tick(1100, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(commands(&rx), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_park() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let actions = Arc::new(vec![action(0, 1.0), action(5000, 0.0)]);
+        let mut axis_playstate = AxisPlaystate::new(actions, 1000.0, 0.2, 0.8);
+        axis_playstate.tick(0, axis_id, &mut stroker).await.unwrap();
+        assert_eq!(commands(&rx).len(), 1);
+
+        // Within the limits, and the limiter knows where the axis is going
+        axis_playstate
+            .park(0.5, 1500, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.5, 1500))]);
+        assert_eq!(axis_playstate.limiter.last_command_target, 0.5);
+        axis_playstate
+            .park(0.0, 1500, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.2, 1500))]);
+
+        // Resuming heads back for the funscript from there
+        axis_playstate
+            .seek(2500, false, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.2, 2500))]);
+    }
+
+    #[tokio::test]
+    async fn test_hold() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Twist)]);
+        let actions = Arc::new(vec![action(0, 0.0), action(1000, 1.0), action(2000, 0.0)]);
+        let mut axis_playstate = AxisPlaystate::new(actions, 1000.0, 0.0, 1.0);
+        axis_playstate.tick(0, axis_id, &mut stroker).await.unwrap();
+        assert_eq!(commands(&rx).len(), 1);
+
+        axis_playstate.set_held(Some(0.5));
+        axis_playstate
+            .park(0.5, HOLD_MOVE_MS, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.5, HOLD_MOVE_MS))]);
+
+        // Held still, whatever the funscript does
+        for now_millis in [500, 1000, 1500] {
+            axis_playstate
+                .tick(now_millis, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        axis_playstate
+            .interpolate(1600, 50, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        axis_playstate
+            .seek(1700, false, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![]);
+        assert_eq!(axis_playstate.status().index, 2);
+
+        // Released, it heads back into the funscript
+        axis_playstate.set_held(None);
+        axis_playstate
+            .seek(1700, false, axis_id, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.0, 300))]);
+    }
+
+    #[tokio::test]
+    async fn test_looped_video_plays_again() {
+        let axis_id = AxisId(1);
+        // A 10-second script, moving every second
+        let actions: Vec<NormalisedAction> = (0..10)
+            .map(|idx| action(idx * 1000, (idx % 2) as f32))
+            .collect();
+        let actions = Arc::new(actions);
+
+        // Whether or not we know that the video loops: the player might not say,
+        // e.g. when it loops a playlist of one video or between A-B loop points
+        for known_to_loop in [true, false] {
+            let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+            let mut playstate = AxisPlaystate::new(actions.clone(), 1000.0, 0.0, 1.0);
+            playstate.set_loop(known_to_loop, Some(10_000));
+
+            let mut iterations = Vec::new();
+            for _ in 0..2 {
+                // The time goes back to the start without a seek
+                for now_millis in (0..10_000).step_by(100) {
+                    playstate
+                        .tick(now_millis, axis_id, &mut stroker)
+                        .await
+                        .unwrap();
+                }
+                iterations.push(commands(&rx));
+            }
+            assert_eq!(iterations[0][..2], [Some((0.0, 0)), Some((1.0, 900))]);
+            // Every action after the start plays again, and in loop mode the last one
+            // also heads for the start of the next loop, as in the first iteration
+            let count = if known_to_loop { 10 } else { 9 };
+            let expected: Vec<Option<(f32, u32)>> = (0..count)
+                .map(|idx| Some((((idx + 1) % 2) as f32, 1000)))
+                .collect();
+            assert_eq!(iterations[1], expected, "known to loop: {known_to_loop}");
+            assert_eq!(
+                iterations[0].last(),
+                iterations[1].last(),
+                "known to loop: {known_to_loop}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ab_loop_repeats_consistently() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let actions: Vec<NormalisedAction> = (0..20)
+            .map(|idx| action(idx * 500, (idx % 2) as f32))
+            .collect();
+        let mut playstate = Playstate::default();
+        playstate.by_axis.insert(
+            axis_id,
+            AxisPlaystate::new(Arc::new(actions), 1000.0, 0.0, 1.0),
+        );
+
+        // A 3-second A-B loop: each time playback reaches B, it jumps back to A,
+        // which the playtask treats as a seek
+        let mut loops = Vec::new();
+        let mut end_positions = Vec::new();
+        for _ in 0..5 {
+            playstate.seek(2000, false, &mut stroker).await.unwrap();
+            for now_millis in (2000..5000).step_by(100) {
+                playstate
+                    .by_axis
+                    .get_mut(&axis_id)
+                    .unwrap()
+                    .tick(now_millis, axis_id, &mut stroker)
+                    .await
+                    .unwrap();
+            }
+            loops.push(commands(&rx));
+            let limiter = &playstate.by_axis[&axis_id].limiter;
+            end_positions.push(limiter.estimate_current_position(
+                limiter.last_command_target_time + Duration::from_millis(1),
+            ));
+        }
+
+        // Every loop moves the same way, ending up in the same place
+        assert_eq!(
+            loops[0],
+            vec![
+                // Heading from A for the next action
+                Some((1.0, 500)),
+                Some((0.0, 500)),
+                Some((1.0, 500)),
+                Some((0.0, 500)),
+                Some((1.0, 500)),
+                Some((0.0, 500)),
+            ]
+        );
+        assert!(
+            loops.iter().all(|commands| *commands == loops[0]),
+            "{loops:?}"
+        );
+        assert!(
+            end_positions.iter().all(|&pos| pos == 0.0),
+            "{end_positions:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interpolate() {
+        let axis_id = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(axis_id, AxisKind::Stroke)]);
+        let actions = vec![action(2000, 0.0), action(3000, 1.0), action(3500, 0.5)];
+        let mut playstate = AxisPlaystate::new(Arc::new(actions), 1000.0, 0.0, 1.0);
+
+        // Nothing until the lead-in to the first action, then straight to it
+        for now_millis in [0, 600, 1200, 1800] {
+            playstate
+                .interpolate(now_millis, 300, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        assert_eq!(commands(&rx), vec![Some((0.0, 800)), Some((0.0, 200))]);
+        // Keeping its place in the funscript all the while
+        assert_eq!(playstate.status().index, 1);
+
+        // Along the funscript a step at a time, ending exactly on each action
+        for now_millis in [2000, 2300, 2600, 2900, 3200, 3500] {
+            playstate
+                .interpolate(now_millis, 300, axis_id, &mut stroker)
+                .await
+                .unwrap();
+        }
+        let commands: Vec<(f32, u32)> = commands(&rx).into_iter().flatten().collect();
+        let expected = [(0.3, 300), (0.6, 300), (0.9, 300), (1.0, 100), (0.5, 300)];
+        assert_eq!(commands.len(), expected.len(), "{commands:?}");
+        for ((target, duration), (expected_target, expected_duration)) in
+            commands.into_iter().zip(expected)
+        {
+            assert!((target - expected_target).abs() < 1e-5, "{target}");
+            assert_eq!(duration, expected_duration);
+        }
+        let status = playstate.status();
+        assert_eq!((status.index, status.finished), (3, true));
+    }
+
+    #[tokio::test]
+    async fn test_loaded_mid_video() {
+        let stroke = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(stroke, AxisKind::Stroke)]);
+        let mut actions = Vec::new();
+        for idx in 0..1000 {
+            actions.push(action(idx * 1000, if idx % 2 == 0 { 0.0 } else { 1.0 }));
+        }
+        actions[600] = action(600_000, 0.2);
+        actions[601] = action(601_000, 0.7);
+        let mut axis_playstate = AxisPlaystate::new(Arc::new(actions), 10.0, 0.0, 1.0);
+
+        // e.g. with `--start=00:10:00`, the video's time is known before the funscript loads
+        axis_playstate
+            .move_into_place(600_300, stroke, &mut stroker)
+            .await
+            .unwrap();
+        // Gently to where the funscript is heading at that time, not from its start
+        assert_eq!(commands(&rx), vec![Some((0.7, 1000))]);
+
+        // And on from there as the video plays
+        axis_playstate
+            .tick(601_000, stroke, &mut stroker)
+            .await
+            .unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.0, 1000))]);
+    }
+
+    #[tokio::test]
+    async fn test_rest() {
+        let stroke = AxisId(1);
+        let (mut stroker, rx) = ChannelStroker::with_axes(vec![(stroke, AxisKind::Stroke)]);
+        let actions = Arc::new(vec![action(0, 0.0), action(500, 1.0), action(600_000, 0.0)]);
+        let mut axis_playstate = AxisPlaystate::new(actions, 1000.0, 0.2, 0.8);
+        axis_playstate.tick(0, stroke, &mut stroker).await.unwrap();
+        axis_playstate
+            .tick(500, stroke, &mut stroker)
+            .await
+            .unwrap();
+        rx.drain().for_each(drop);
+        // Off unless configured
+        assert_eq!(axis_playstate.rest_due(), None);
+
+        axis_playstate.set_idle_timeout(Some(30_000));
+        let last_sent_at = axis_playstate.limiter.last_command_start_time;
+        assert_eq!(
+            axis_playstate.rest_due(),
+            Some(last_sent_at + Duration::from_secs(30))
+        );
+
+        // Slowly to the middle of the limits, once
+        axis_playstate.rest(stroke, &mut stroker).await.unwrap();
+        assert_eq!(commands(&rx), vec![Some((0.5, REST_MOVE_MS))]);
+        assert_eq!(axis_playstate.rest_due(), None);
+
+        // Until the funscript carries on
+        axis_playstate
+            .tick(600_000, stroke, &mut stroker)
+            .await
+            .unwrap();
+        assert!(axis_playstate.rest_due().is_some());
+
+        // Nor whilst held
+        axis_playstate.set_held(Some(0.3));
+        assert_eq!(axis_playstate.rest_due(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_step_throttle() {
+        let mut throttle = StepThrottle::default();
+        // Nothing held back, nothing due
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), throttle.due())
+                .await
+                .is_err()
+        );
+
+        // A single step is followed straight away
+        assert_eq!(throttle.step(1000), Some(1000));
+
+        // Holding down the key: the rest are held back, with only the latest followed
+        let started = tokio::time::Instant::now();
+        for idx in 1..10 {
+            assert_eq!(throttle.step(1000 + idx * 33), None);
+        }
+        throttle.due().await;
+        // To within the timer's millisecond resolution
+        let elapsed = started.elapsed();
+        assert!(
+            (STEP_INTERVAL..=STEP_INTERVAL + Duration::from_millis(1)).contains(&elapsed),
+            "{elapsed:?}"
+        );
+        assert_eq!(throttle.take(), Some(1297));
+        assert_eq!(throttle.take(), None);
+
+        // Forgotten as the video resumes
+        assert_eq!(throttle.step(1330), None);
+        throttle.clear();
+        assert_eq!(throttle.take(), None);
+    }
+
+    /// The limiter's estimate follows a stroker easing its movements as it's told the stroker
+    /// does, even when a movement is cut short by the next.
+    #[tokio::test(start_paused = true)]
+    async fn test_estimate_with_easing() {
+        let axis_id = AxisId(1);
+        for device_easing in [Easing::Linear, Easing::Cosine] {
+            for assumed_easing in [Easing::Linear, Easing::Cosine] {
+                let limits = LimitsConfig {
+                    speed: 1000.0,
+                    default_min: 0.0,
+                    default_max: 1.0,
+                    invert: false,
+                    device_easing,
+                    idle_timeout_ms: None,
+                };
+                let mut stroker = DryRunStroker::new(
+                    vec![AxisDescriptor {
+                        axis_id,
+                        axis_kind: AxisKind::Stroke,
+                    }],
+                    BTreeMap::from([(AxisKind::Stroke, limits)]),
+                );
+                let mut limiter = AxisLimiter::new(1000.0, 0.0, 1.0);
+                limiter.easing = assumed_easing;
+
+                let mut worst_error: f32 = 0.0;
+                // Up from the middle, then back down before getting there
+                for (target, ramp_time_millis) in [(0.5, 0), (1.0, 1000), (0.0, 500)] {
+                    let now = Instant::now();
+                    limiter.notify_commanded(now, target, ramp_time_millis);
+                    stroker
+                        .movement(Movement::new(axis_id, target, ramp_time_millis).unwrap())
+                        .await
+                        .unwrap();
+                    for _ in 0..10 {
+                        tokio::time::advance(Duration::from_millis(60)).await;
+                        let now = Instant::now();
+                        let position = stroker.position_at(axis_id, now).unwrap();
+                        let error = (limiter.estimate_current_position(now) - position).abs();
+                        worst_error = worst_error.max(error);
+                    }
+                }
+                assert!(stroker.violations().is_empty());
+                if assumed_easing == device_easing {
+                    assert!(worst_error < 1e-3, "{device_easing:?}: {worst_error}");
+                } else {
+                    // Assuming the wrong easing drifts by a tenth of the movement
+                    assert!(
+                        worst_error > 0.04,
+                        "{device_easing:?} assumed {assumed_easing:?}: {worst_error}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_inverted() {
+        let limiter = AxisLimiter::new(1000.0, 0.2, 0.6);
+        let now = limiter.last_command_start_time;
+        assert_eq!(limiter.limit_command(now, 0.0, 1000), (0.2, 1000));
+
+        // Reversed limits, as used to be the way to invert an axis, still invert it
+        let mut limiter = AxisLimiter::new(1000.0, 0.6, 0.2);
+        assert_eq!(
+            (limiter.min, limiter.max, limiter.inverted),
+            (0.2, 0.6, true)
+        );
+        assert_eq!(limiter.limit_command(now, 0.0, 1000), (0.6, 1000));
+        assert_eq!(limiter.limit_command(now, 0.75, 1000), (0.3, 1000));
+
+        limiter.apply_preset(&PresetLimitsConfig {
+            invert: Some(false),
+            ..PresetLimitsConfig::default()
+        });
+        assert!(!limiter.inverted);
+
+        // But presets (and limits remembered for a video) can't invert that way
+        limiter.apply_preset(&PresetLimitsConfig {
+            speed: Some(2.0),
+            min: Some(0.9),
+            ..PresetLimitsConfig::default()
+        });
+        assert_eq!(
+            (
+                limiter.speed_limit,
+                limiter.min,
+                limiter.max,
+                limiter.inverted
+            ),
+            (2.0, 0.2, 0.6, false)
+        );
+        limiter.apply_preset(&PresetLimitsConfig {
+            min: Some(0.5),
+            max: Some(0.9),
+            ..PresetLimitsConfig::default()
+        });
+        assert_eq!(
+            (limiter.min, limiter.max, limiter.inverted),
+            (0.5, 0.9, false)
+        );
     }
 }
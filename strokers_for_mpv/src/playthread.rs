@@ -1,23 +1,59 @@
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    future::Future,
+    io::ErrorKind,
+    ops::{Bound, ControlFlow},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use eyre::{bail, Context, ContextCompat};
+use eyre::{bail, eyre, Context, ContextCompat};
 use flume::{Receiver, Sender};
-use mpv_client::{osd, Client};
+use mpv_client::osd;
 use strokers::{
-    config::LimitsConfig,
-    core::{AxisKind, Stroker},
+    config::{LimitsConfig, PlaybackConfig, PresetConfig, PresetLimitsConfig, RootConfig},
+    core::{AxisDescriptor, AxisKind, Stroker},
+    StrokersError,
 };
 use strokers_funscript::{
-    processing::{normalised_from_funscript, NormalisedAction},
-    schema::Funscript,
-    search_path::scan_for_funscripts,
+    archive::{is_funscript_archive, read_funscript, scan_archive, split_archive_path},
+    processing::{
+        derive_secondary, enforce_min_interval, normalised_from_funscript, resample_with_easing,
+        Easing, NormalisedAction, SecondaryParams,
+    },
+    schema::{Funscript, FunscriptAction},
+    search_path::{
+        normalise_override_name, scan_for_funscripts_in_dirs, scan_for_funscripts_in_dirs_fuzzy,
+        scan_library, FunscriptCluster, FunscriptScan, MatchKind,
+    },
+    stats::Stats,
+    FunscriptError,
 };
+use tokio::time::{Instant, Interval, MissedTickBehavior};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    keybindings::{AxisLimitChangeCommand, KeyCommand},
-    playstate::{AxisLimiter, AxisPlaystate, Playstate},
+    calibration::Calibration,
+    clock::PlaybackClock,
+    connection::{
+        is_in_use, next_retry_delay, ConnectAttempt, Device, INITIAL_RETRY_DELAY,
+        IN_USE_RETRY_DELAY,
+    },
+    fallback::{FallbackMotion, MAX_PERIOD_MS, MIN_PERIOD_MS},
+    heatmap::HeatmapOverlay,
+    is_url,
+    keybindings::{
+        AxisLimitChangeCommand, FallbackPeriodCommand, HoldAxisCommand, InvertChange, KeyCommand,
+    },
+    mpv::Mpv,
+    playstate::{AxisLimiter, AxisPlaystate, Playstate, SeekDebounce, StepThrottle, HOLD_MOVE_MS},
+    scan_problems::{self, ScanProblems},
+    script_opts::{axis_funscript_paths, ScriptOpts},
+    status::{AxisPlayingReport, AxisStatusReport, StatusBroadcast, StatusPage, StatusReport},
+    video_state::{fnv1a, unix_time, VideoState, VideoStates},
+    watcher::FunscriptWatcher,
 };
 
 #[derive(Clone, Debug)]
@@ -25,11 +61,38 @@ pub enum PlaythreadMessage {
     /// A new video was loaded
     /// - Unload all current funscripts
     /// - Search for new funscripts
-    VideoStarting { video_path: PathBuf },
+    VideoStarting {
+        video: NewVideo,
+        /// Our options from mpv's `script-opts`, for this video
+        script_opts: ScriptOpts,
+        /// mpv's working directory, which paths in the script options are relative to
+        working_directory: Option<PathBuf>,
+    },
+    /// The video finished (or was stopped), and nothing else is playing (yet)
+    /// - Stop the stroker
+    /// - Unload all current funscripts
+    PlaybackEnded,
+    /// The funscripts for the video were reloaded, at the user's request
+    FunscriptsReloaded { axes: usize },
+    /// A funscript the user asked for couldn't be loaded
+    FunscriptFailed { filename: String, error: String },
+    /// Something went wrong finding or loading the funscripts, to tell the user about
+    /// (it's already been logged)
+    ScanProblem { message: String },
+    /// Funscripts were found for the video, to switch between their clusters.
+    /// The main cluster's are on their way.
+    FunscriptsFound(FoundFunscripts),
     /// Use the given loaded funscript
     UseFunscript {
         axis_kind: AxisKind,
         normalised_actions: Vec<NormalisedAction>,
+        /// Problems found with the funscript, to tell the user about
+        problems: Vec<FunscriptError>,
+        /// The actions as they were in the file, if tracing raw positions
+        raw_actions: Option<Vec<FunscriptAction>>,
+        /// Whether it's the axis's funscript loaded again as the file changed,
+        /// in which case the axis carries on with the limits it has
+        reload: bool,
     },
     /// The video playback time has updated in a sudden way
     Seek { now_millis: u32 },
@@ -37,264 +100,3233 @@ pub enum PlaythreadMessage {
     TimeChange { now_millis: u32 },
     /// The video pause state has updated
     PauseChange { paused: bool },
+    /// Whether the video loops has changed
+    LoopChange { looping: bool },
+    /// The length of the video is now known (or no longer known)
+    DurationChange { duration_millis: Option<u32> },
+    /// The playback speed has changed, e.g. to 1.25 for 25% faster than normal
+    SpeedChange { speed: f64 },
+    /// The points of the A-B loop have changed; playback loops between them if both are set
+    AbLoopChange {
+        a_millis: Option<u32>,
+        b_millis: Option<u32>,
+    },
+    /// The funscripts found don't quite match the video's name
+    FuzzyMatch {
+        match_kind: MatchKind,
+        filenames: Vec<String>,
+    },
     /// MPV is shutting down so we should too
     Shutdown {},
     /// A key command was triggered
     KeyCommand(KeyCommand),
 }
 
-pub(crate) async fn playtask(
-    mut stroker: impl Stroker,
-    config: strokers::config::RootConfig,
-    rx: Receiver<PlaythreadMessage>,
-    tx: Sender<PlaythreadMessage>,
-    mut weak_client: Client,
-) -> eyre::Result<()> {
-    let mut paused = false;
-    let axes = stroker.axes();
-    let mut playstate = Playstate::default();
+/// What's starting to play, as mpv's `path` property.
+#[derive(Clone, Debug)]
+pub enum NewVideo {
+    /// A video file, beside which funscripts are looked for
+    File(PathBuf),
+    /// A network stream (e.g. `https://` or `ytdl://`), with nowhere to look for funscripts,
+    /// so only those given in the script options are played.
+    Stream { url: String },
+}
 
-    let mut funscript_load_ctoken: Option<CancellationToken> = None;
+/// The funscripts found for the video that's playing, and which cluster of them is in use.
+#[derive(Clone, Debug)]
+pub struct FoundFunscripts {
+    video_dir: PathBuf,
+    video_filename: String,
+    scan: FunscriptScan,
+    /// The name of the override cluster in use, or `None` for the main cluster
+    cluster: Option<String>,
+}
 
-    while let Ok(msg) = rx.recv_async().await {
-        match msg {
-            PlaythreadMessage::VideoStarting { video_path } => {
-                debug!("VideoStarting: {video_path:?}");
-                let video_dir = video_path
-                    .parent()
-                    .context("video has no parent")?
-                    .to_owned();
-                let video_filename = video_path
-                    .file_name()
-                    .context("video has no filename")?
-                    .to_str()
-                    .context("video filename is not UTF-8")?
-                    .to_owned();
-
-                if let Some(ctoken) = funscript_load_ctoken.take() {
-                    ctoken.cancel();
-                }
+impl FoundFunscripts {
+    /// The names of the clusters, starting with the main one (`None`).
+    fn cluster_names(&self) -> Vec<Option<String>> {
+        std::iter::once(None)
+            .chain(self.scan.override_names().map(|name| Some(name.to_owned())))
+            .collect()
+    }
 
-                let new_ctoken = CancellationToken::new();
-                funscript_load_ctoken = Some(new_ctoken.clone());
+    /// The cluster after the one in use, wrapping around to the main one.
+    fn next_cluster(&self) -> Option<String> {
+        let names = self.cluster_names();
+        let idx = names
+            .iter()
+            .position(|name| *name == self.cluster)
+            .unwrap_or(0);
+        names[(idx + 1) % names.len()].clone()
+    }
 
-                let tx = tx.clone();
-                tokio::task::spawn(async move {
-                    tokio::select! {
-                        res = search_for_funscripts(video_dir, video_filename, tx) => {
-                            if let Err(err) = res {
-                                error!("failed to handle VideoLoaded: {err:?}");
-                            }
-                        }
-                        _ = new_ctoken.cancelled() => {
-                            info!("search_for_funscripts cancelled");
-                        }
-                    }
-                });
-            }
-            PlaythreadMessage::UseFunscript {
-                axis_kind,
-                normalised_actions,
-            } => {
-                debug!(
-                    "UseFunscript: {axis_kind:?} ({} actions)",
-                    normalised_actions.len()
-                );
-                let Some(axis) = axes.iter().find(|axis| axis.axis_kind == axis_kind) else {
-                    warn!("can't use loaded funscript for {axis_kind:?} because the stroker doesn't have an axis for it");
-                    continue;
-                };
+    /// Whether there are no funscripts in the cluster in use.
+    fn is_empty(&self) -> bool {
+        self.scan
+            .cluster(self.cluster.as_deref())
+            .scripts
+            .is_empty()
+    }
 
-                let limits = match config.limits.get(&axis.axis_kind) {
-                    Some(limits) => limits,
-                    None => {
-                        warn!("Axis {:?} has no limits configured; using some very pessimistic/safe/boring ones!", axis.axis_kind);
-                        &LimitsConfig {
-                            speed: 0.25,
-                            default_min: 0.4,
-                            default_max: 0.6,
-                        }
-                    }
-                };
+    /// Describes the cluster in use and the alternatives, for the OSD.
+    fn describe(&self) -> String {
+        let display = |name: &Option<String>| name.clone().unwrap_or_else(|| "main".to_owned());
+        let alternatives: Vec<String> = self
+            .cluster_names()
+            .iter()
+            .filter(|name| **name != self.cluster)
+            .map(display)
+            .collect();
+        if alternatives.is_empty() {
+            return format!("Funscripts: {} (no alternatives)", display(&self.cluster));
+        }
+        format!(
+            "Funscripts: {} (alternatives: {})",
+            display(&self.cluster),
+            alternatives.join(", ")
+        )
+    }
+}
 
-                playstate.by_axis.insert(
-                    axis.axis_id,
-                    AxisPlaystate::new(
-                        Arc::new(normalised_actions),
-                        limits.speed,
-                        limits.default_min,
-                        limits.default_max,
-                    ),
-                );
+/// Plays funscripts along with the video, on the stroker that `connect` connects to.
+///
+/// Connecting happens in the background, trying again (less and less often) until it works,
+/// so that playback is kept track of even if the stroker isn't there yet.
+pub(crate) async fn playtask<
+    S: Stroker + Send + 'static,
+    F: Future<Output = eyre::Result<S>> + 'static,
+>(
+    connect: impl Fn() -> F,
+    config: RootConfig,
+    rx: Receiver<PlaythreadMessage>,
+    tx: Sender<PlaythreadMessage>,
+    weak_client: impl Mpv,
+) -> eyre::Result<()> {
+    let mut playthread = Playthread::new(connect, config, tx, weak_client).await;
+
+    loop {
+        playthread.reconnect_if_lost();
+
+        let msg = tokio::select! {
+            msg = rx.recv_async() => match msg {
+                Ok(msg) => msg,
+                Err(_) => break,
+            },
+            result = next_connect_attempt(&mut playthread.connecting) => {
+                playthread.connect_attempted(result);
+                continue;
             }
-            PlaythreadMessage::Seek { now_millis } => {
-                debug!("Seek: {now_millis}");
-                for (&axis_id, axis_playstate) in playstate.by_axis.iter_mut() {
-                    axis_playstate
-                        .seek(now_millis, paused, axis_id, &mut stroker)
-                        .await
-                        .context("failed AP tick")?;
-                }
+            // Dealt with at the top of the loop
+            () = playthread.stroker.lost() => continue,
+            () = playthread.seek_debounce.settled() => {
+                playthread.seek_settled().await?;
+                continue;
             }
-            PlaythreadMessage::TimeChange { now_millis } => {
-                if paused {
-                    continue;
-                }
-                for (&axis_id, axis_playstate) in playstate.by_axis.iter_mut() {
-                    axis_playstate
-                        .tick(now_millis, axis_id, &mut stroker)
+            () = next_rest(&playthread.playstate),
+                if !playthread.paused && !playthread.inactive && playthread.calibration.is_none() =>
+            {
+                playthread.rest_idle_axes().await?;
+                continue;
+            }
+            () = playthread.step_throttle.due() => {
+                playthread.step_due().await?;
+                continue;
+            }
+            () = next_tick(&mut playthread.interpolation_ticker),
+                if !playthread.paused
+                    && !playthread.inactive
+                    && !playthread.seek_debounce.is_settling() =>
+            {
+                playthread.interpolate().await?;
+                continue;
+            }
+            () = next_fallback_tick(&mut playthread.fallback), if !playthread.paused => {
+                if let Some(fallback) = playthread.fallback.as_mut() {
+                    fallback
+                        .tick(&mut playthread.stroker)
                         .await
-                        .context("failed AP tick")?;
+                        .context("failed to move along generated motion")?;
                 }
+                continue;
             }
-            PlaythreadMessage::PauseChange { paused: new_paused } => {
-                debug!("PauseChange: {paused}");
-                paused = new_paused;
-                if paused {
-                    stroker
-                        .stop()
+            () = next_calibration_tick(&mut playthread.calibration) => {
+                if let Some(calibration) = playthread.calibration.as_mut() {
+                    calibration
+                        .tick(&mut playthread.weak_client, &mut playthread.stroker)
                         .await
-                        .context("failed to stop stroker upon pause")?;
-                } else {
-                    // TODO
-                    debug!("unpaused but proper resume is not supported");
+                        .context("failed to move along the metronome")?;
                 }
+                continue;
             }
-            PlaythreadMessage::Shutdown {} => {
-                debug!("Shutdown");
-                stroker
-                    .stop()
-                    .await
-                    .context("failed to stop stroker upon shutdown")?;
-                break;
-            }
-            PlaythreadMessage::KeyCommand(cmd) => match cmd {
-                KeyCommand::AxisLimitChange(cmd) => {
-                    let Some(axis) = axes.iter().find(|axis| axis.axis_kind == cmd.axis) else {
-                        warn!("Can't change axis limits for {:?} as there is no corresponding stroker axis", cmd.axis);
-                        continue;
-                    };
-                    let Some(axis) = playstate.by_axis.get_mut(&axis.axis_id) else {
-                        warn!(
-                            "Can't change axis limits for {:?} as the axis is not in use.",
-                            cmd.axis
-                        );
-                        continue;
-                    };
-
-                    if let Err(err) = update_limits(&cmd, &mut axis.limiter) {
-                        error!("Error updating axis limits for {:?}: {err:?}", cmd.axis);
-                    }
-                    if let Err(err) = osd!(
-                        weak_client,
-                        Duration::from_secs(1),
-                        "Limits: {:.4} ≤ {:?} ≤ {:.4}",
-                        axis.limiter.min,
-                        cmd.axis,
-                        axis.limiter.max
-                    ) {
-                        error!("Failed to display OSD: {err:?}");
-                    }
-                }
-            },
+            () = next_video_state_save(&playthread.video_states) => {
+                save_video_states(&mut playthread.video_states).await;
+                continue;
+            }
+            (axis_kind, path) = next_funscript_change(&mut playthread.funscript_watcher) => {
+                playthread.funscript_changed(axis_kind, path);
+                continue;
+            }
+            () = playthread.scan_problems.next_show() => {
+                playthread.show_scan_problems();
+                continue;
+            }
+            () = next_status_due(&mut playthread.status_page, &mut playthread.status_broadcast) => {
+                playthread.report_status();
+                continue;
+            }
+        };
+        if playthread.handle_message(msg).await?.is_break() {
+            break;
         }
     }
     Ok(())
 }
 
-/// Updates an axis's limits.
-/// There is nothing preventing max < min although both limits are prevented from going out of range.
-/// We can cheekily call max < min a 'feature' to allow inverting the motion *cough cough*.
-fn update_limits(cmd: &AxisLimitChangeCommand, limits: &mut AxisLimiter) -> eyre::Result<()> {
-    fn update_axis(
-        name: &str,
-        by: &Option<f32>,
-        new: &Option<f32>,
-        target: &mut f32,
-    ) -> eyre::Result<()> {
-        match (by, new) {
-            (Some(_), Some(_)) => {
-                bail!("Conflicting axis_limit parameters for {name}");
+/// What [`playtask`] keeps track of as the video plays, and the stroker it plays on.
+struct Playthread<S, C, M> {
+    /// Connects to the stroker
+    connect: C,
+    config: RootConfig,
+    tx: Sender<PlaythreadMessage>,
+    weak_client: M,
+    paused: bool,
+    /// The latest playback time, to resume from after a pause
+    last_now_millis: Option<u32>,
+    /// How far ahead of the video the funscripts are played, to fix scripts that are out of sync
+    sync_offset_ms: i32,
+    speed: f64,
+    looping: bool,
+    duration_millis: Option<u32>,
+    /// The A-B loop's start and end, if playback is looping between them
+    ab_loop: Option<(u32, u32)>,
+    stroker: Device,
+    /// The stroker's, once connected to
+    axes: Vec<AxisDescriptor>,
+    min_movement_interval_ms: Option<u32>,
+    connecting: Option<ConnectAttempt<S>>,
+    /// How long to wait before trying to connect again, if this attempt fails
+    retry_delay: Duration,
+    connect_failures: u32,
+    /// Whether the last attempt to connect found another player using the stroker
+    stroker_in_use: bool,
+    playstate: Playstate,
+    /// Time of the last action of each funscript in use, to check it against the video
+    last_action_at: BTreeMap<AxisKind, u32>,
+    /// What to find funscripts for: the file they're named after, or the stream
+    video: Option<NewVideo>,
+    /// Funscripts given for single axes in the script options, used instead of any found
+    axis_funscripts: BTreeMap<AxisKind, String>,
+    /// Axes with funscripts that were ignored for the video, as they're not in `driven_axes`
+    skipped_axes: BTreeSet<AxisKind>,
+    /// The preset of limits last switched to, also for axes whose funscripts load later
+    preset: Option<String>,
+    found: Option<FoundFunscripts>,
+    /// Problems finding or loading funscripts, gathered up to show on the OSD
+    scan_problems: ScanProblems,
+    /// With `watch_funscripts`, watches the funscript files in use to reload them as they change
+    funscript_watcher: Option<FunscriptWatcher>,
+    /// With `require_funscript` (or for a stream without funscripts given for it), whether there
+    /// are no funscripts loaded for the video, so the stroker is left alone and the video's time
+    /// is only kept track of
+    inactive: bool,
+    heatmap: HeatmapOverlay,
+    /// A page showing what everything is doing, whilst the user has it shown
+    status_page: StatusPage,
+    /// The same as JSON for other scripts, when they ask (or with `status_broadcast_interval_ms`)
+    status_broadcast: StatusBroadcast,
+    /// With `[fallback]`, motion made up whilst a video without funscripts plays
+    fallback_enabled: bool,
+    fallback_period_ms: u32,
+    fallback: Option<FallbackMotion>,
+    /// With `video_state_file`, what was chosen for each video, to go back to when it's played again
+    video_states: Option<VideoStates>,
+    /// The video that state is kept for: the video itself, even if its funscripts are named differently
+    state_video_path: Option<PathBuf>,
+    /// The limits adjusted for the video, this time or when it was last played
+    video_limits: PresetConfig,
+    /// How far ahead of the video to play every funscript, for the stroker to keep up
+    latency_ms: i32,
+    /// Whilst calibrating the latency, the metronome playing on the stroker (the video is paused)
+    calibration: Option<Calibration>,
+    /// Axes held still at a position until released, even as funscripts are loaded for them
+    held_axes: BTreeMap<AxisKind, f32>,
+    /// With `interpolation_hz` (or `interpolate_below_speed` whilst playing slowly), every axis
+    /// is moved along its funscript at a fixed rate (and how often depends on the stroker,
+    /// so is worked out again once connected)
+    interpolation_period: Option<u32>,
+    interpolation_ticker: Option<Interval>,
+    /// The playback time, estimated between updates, to move along the funscripts smoothly
+    clock: PlaybackClock,
+    /// Whilst seeking, moving into place waits until the position settles
+    seek_debounce: SeekDebounce,
+    /// With `track_while_paused`, holds back following the video as it's stepped through
+    step_throttle: StepThrottle,
+    funscript_load_ctoken: Option<CancellationToken>,
+    /// Reloads of changed funscripts, one per axis so that one doesn't cancel another's
+    funscript_reload_ctokens: BTreeMap<AxisKind, CancellationToken>,
+}
+
+impl<S, F, C, M> Playthread<S, C, M>
+where
+    S: Stroker + Send + 'static,
+    F: Future<Output = eyre::Result<S>> + 'static,
+    C: Fn() -> F,
+    M: Mpv,
+{
+    /// Starts off with nothing playing, already trying to connect to the stroker.
+    async fn new(
+        connect: C,
+        config: RootConfig,
+        tx: Sender<PlaythreadMessage>,
+        weak_client: M,
+    ) -> Self {
+        let funscript_watcher = if config.playback.watch_funscripts {
+            match FunscriptWatcher::new() {
+                Ok(watcher) => Some(watcher),
+                Err(err) => {
+                    error!("not watching funscripts for changes: {err:?}");
+                    None
+                }
             }
-            (Some(by), None) => {
-                *target = (*target + by).clamp(0.0, 1.0);
+        } else {
+            None
+        };
+        let video_states = match config.playback.video_state_path() {
+            Some(path) => Some(VideoStates::load(path).await),
+            None => None,
+        };
+        let latency_ms = video_states
+            .as_ref()
+            .and_then(VideoStates::latency_ms)
+            .unwrap_or(config.playback.latency_ms);
+        let speed = 1.0;
+        let interpolation_period =
+            interpolation_period_ms(config.playback.interpolation_hz_at(speed), None);
+        let mut playthread = Playthread {
+            connect,
+            tx,
+            weak_client,
+            paused: false,
+            last_now_millis: None,
+            sync_offset_ms: 0,
+            speed,
+            looping: false,
+            duration_millis: None,
+            ab_loop: None,
+            stroker: Device::disconnected(),
+            axes: Vec::new(),
+            min_movement_interval_ms: None,
+            connecting: None,
+            retry_delay: INITIAL_RETRY_DELAY,
+            connect_failures: 0,
+            stroker_in_use: false,
+            playstate: Playstate::default(),
+            last_action_at: BTreeMap::new(),
+            video: None,
+            axis_funscripts: BTreeMap::new(),
+            skipped_axes: BTreeSet::new(),
+            preset: None,
+            found: None,
+            scan_problems: ScanProblems::new(),
+            funscript_watcher,
+            inactive: false,
+            heatmap: HeatmapOverlay::new(config.heatmap.clone()),
+            status_page: StatusPage::new(),
+            status_broadcast: StatusBroadcast::new(config.playback.status_broadcast_interval_ms),
+            fallback_enabled: config.fallback.enabled,
+            fallback_period_ms: config
+                .fallback
+                .period_ms
+                .clamp(MIN_PERIOD_MS, MAX_PERIOD_MS),
+            fallback: None,
+            video_states,
+            state_video_path: None,
+            video_limits: PresetConfig::new(),
+            latency_ms,
+            calibration: None,
+            held_axes: BTreeMap::new(),
+            interpolation_period,
+            interpolation_ticker: new_interpolation_ticker(interpolation_period),
+            clock: PlaybackClock::new(),
+            seek_debounce: SeekDebounce::default(),
+            step_throttle: StepThrottle::default(),
+            funscript_load_ctoken: None,
+            funscript_reload_ctokens: BTreeMap::new(),
+            config,
+        };
+        playthread.connecting = Some(playthread.connect_after(Duration::ZERO));
+        playthread
+    }
+
+    /// Waits for the given time, then tries to connect to the stroker.
+    fn connect_after(&self, delay: Duration) -> ConnectAttempt<S> {
+        let attempt = (self.connect)();
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            attempt.await
+        })
+    }
+
+    /// The time in the funscripts for the given playback time, played ahead of the video
+    /// by the sync offset and the latency.
+    fn script_now(&self, now_millis: u32) -> u32 {
+        script_time(
+            now_millis,
+            self.sync_offset_ms.saturating_add(self.latency_ms),
+        )
+    }
+
+    /// Starts connecting again if the connection to the stroker was lost.
+    fn reconnect_if_lost(&mut self) {
+        if !self.stroker.take_lost() {
+            return;
+        }
+        // What was playing on the stroker is loaded afresh once it's back
+        self.axes.clear();
+        self.playstate.by_axis.clear();
+        self.fallback = None;
+        self.calibration = None;
+        self.connecting = Some(self.connect_after(INITIAL_RETRY_DELAY));
+        self.retry_delay = next_retry_delay(INITIAL_RETRY_DELAY);
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(5),
+            "Lost connection to the stroker; reconnecting",
+        );
+    }
+
+    /// Takes on the stroker once connected to, or tries again later.
+    fn connect_attempted(&mut self, result: eyre::Result<S>) {
+        self.connecting = None;
+        let new_stroker = match result {
+            Ok(new_stroker) => new_stroker,
+            Err(err) if is_in_use(&err) => {
+                // Kept trying at a steady pace (and quietly), to take over the stroker
+                // soon after the other player is done with it
+                self.connect_failures += 1;
+                if !self.stroker_in_use {
+                    self.stroker_in_use = true;
+                    warn!("the stroker is in use by another player; waiting for it to be free: {err:?}");
+                    show_osd(
+                        &mut self.weak_client,
+                        Duration::from_secs(5),
+                        "Stroker in use by another player",
+                    );
+                }
+                self.connecting = Some(self.connect_after(IN_USE_RETRY_DELAY));
+                return;
             }
-            (None, Some(new)) => {
-                if *new < 0.0 || 1.0 < *new {
-                    bail!("Can't set limit to {new:?} as that's out of range!");
+            Err(err) => {
+                self.stroker_in_use = false;
+                self.connect_failures += 1;
+                warn!(
+                    "failed to connect to the stroker (attempt {}); trying again in {:?}: {err:?}",
+                    self.connect_failures, self.retry_delay
+                );
+                if self.connect_failures == 1 {
+                    show_osd(
+                        &mut self.weak_client,
+                        Duration::from_secs(5),
+                        "Couldn't connect to the stroker; trying again in the background",
+                    );
                 }
-                *target = *new;
+                self.connecting = Some(self.connect_after(self.retry_delay));
+                self.retry_delay = next_retry_delay(self.retry_delay);
+                return;
             }
-            (None, None) => {
-                // nop
+        };
+
+        self.stroker.attach(new_stroker);
+        // With `driven_axes`, the rest are left alone as if the stroker didn't have them
+        self.axes = self
+            .stroker
+            .axes()
+            .into_iter()
+            .filter(|axis| self.config.playback.drives(axis.axis_kind))
+            .collect();
+        self.min_movement_interval_ms = self.stroker.min_movement_interval_ms();
+        self.interpolation_period = interpolation_period_ms(
+            self.config.playback.interpolation_hz_at(self.speed),
+            self.min_movement_interval_ms,
+        );
+        self.interpolation_ticker = new_interpolation_ticker(self.interpolation_period);
+        self.retry_delay = INITIAL_RETRY_DELAY;
+        self.connect_failures = 0;
+        if std::mem::take(&mut self.stroker_in_use) {
+            info!("the stroker is free again");
+        }
+        let description = match self.stroker.description() {
+            Ok(Some(description)) => description,
+            _ => "stroker".to_owned(),
+        };
+        info!("connected to {description}");
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(3),
+            &format!("Connected to {description}"),
+        );
+
+        // Catch up with the video: its funscripts are loaded again for the stroker's axes
+        // and each moves into place from where playback is
+        let Some(video) = self.video.clone() else {
+            return;
+        };
+        self.playstate.by_axis.clear();
+        self.last_action_at.clear();
+        spawn_funscript_task(
+            &mut self.funscript_load_ctoken,
+            "search_for_funscripts",
+            self.tx.clone(),
+            search_for_funscripts(
+                video,
+                self.found.as_ref().and_then(|found| found.cluster.clone()),
+                self.axis_funscripts.clone(),
+                self.config.playback.clone(),
+                self.tx.clone(),
+            ),
+        );
+    }
+
+    /// Moves into place once seeking has settled down.
+    async fn seek_settled(&mut self) -> eyre::Result<()> {
+        self.seek_debounce.clear();
+        let Some(now_millis) = self.last_now_millis else {
+            return Ok(());
+        };
+        if self.inactive || self.calibration.is_some() {
+            return Ok(());
+        }
+        self.playstate
+            .seek(self.script_now(now_millis), self.paused, &mut self.stroker)
+            .await
+            .context("failed AP seek")
+    }
+
+    /// Rests the axes that have been idle for a while.
+    async fn rest_idle_axes(&mut self) -> eyre::Result<()> {
+        let now = Instant::now();
+        for (&axis_id, axis_playstate) in self.playstate.by_axis.iter_mut() {
+            if axis_playstate
+                .rest_due()
+                .is_some_and(|rest_due| rest_due <= now)
+            {
+                debug!("{axis_id:?} idle for a while, so resting");
+                axis_playstate
+                    .rest(axis_id, &mut self.stroker)
+                    .await
+                    .context("failed to rest idle axis")?;
             }
         }
         Ok(())
     }
 
-    update_axis("min", &cmd.min_by, &cmd.min_new, &mut limits.min)?;
-    update_axis("max", &cmd.max_by, &cmd.max_new, &mut limits.max)?;
-    Ok(())
-}
+    /// Moves to the frame stepped to whilst paused, once it's no longer held back.
+    async fn step_due(&mut self) -> eyre::Result<()> {
+        let Some(step_millis) = self.step_throttle.take() else {
+            return Ok(());
+        };
+        if !self.paused || self.inactive || self.calibration.is_some() {
+            return Ok(());
+        }
+        self.playstate
+            .seek(self.script_now(step_millis), self.paused, &mut self.stroker)
+            .await
+            .context("failed AP seek to a frame whilst paused")
+    }
 
-/// Given that the video has loaded, search for appropriate funscripts
-///
-/// TODO Currently this only searches for and loads 'main' cluster funscripts;
-/// we should expand this in the future somehow.
-async fn search_for_funscripts(
-    video_dir: PathBuf,
-    video_filename: String,
-    tx: Sender<PlaythreadMessage>,
-) -> eyre::Result<()> {
-    let mut read_dir = tokio::fs::read_dir(&video_dir)
-        .await
-        .context("can't read")?;
+    /// Moves every axis along its funscript, on the interpolation ticker's tick.
+    async fn interpolate(&mut self) -> eyre::Result<()> {
+        let (Some(period_ms), Some(now_millis)) =
+            (self.interpolation_period, self.clock.now_at(Instant::now()))
+        else {
+            return Ok(());
+        };
+        let now_millis = self.script_now(now_millis);
+        let step_millis = (period_ms as f64 * self.speed).round() as u32;
+        for (&axis_id, axis_playstate) in self.playstate.by_axis.iter_mut() {
+            axis_playstate
+                .interpolate(now_millis, step_millis, axis_id, &mut self.stroker)
+                .await
+                .context("failed AP interpolation")?;
+        }
+        Ok(())
+    }
 
-    let mut filenames_in_dir: Vec<String> = Vec::new();
-    while let Some(dir_entry) = read_dir
-        .next_entry()
-        .await
-        .context("failed to read next directory entry")?
-    {
-        let file_type = dir_entry
-            .file_type()
-            .await
-            .context("can't probe type of file")?;
-        if !(file_type.is_file() || file_type.is_symlink()) {
-            continue;
+    /// Reloads the funscript for the axis, as its file has changed.
+    fn funscript_changed(&mut self, axis_kind: AxisKind, path: PathBuf) {
+        info!("{path:?} changed; reloading it for {axis_kind:?}");
+        let mut reload_ctoken = self.funscript_reload_ctokens.remove(&axis_kind);
+        spawn_funscript_task(
+            &mut reload_ctoken,
+            "reload_funscript",
+            self.tx.clone(),
+            reload_funscript(
+                path,
+                axis_kind,
+                self.config.playback.clone(),
+                self.tx.clone(),
+            ),
+        );
+        if let Some(reload_ctoken) = reload_ctoken {
+            self.funscript_reload_ctokens
+                .insert(axis_kind, reload_ctoken);
         }
-        let raw_filename = dir_entry.file_name();
-        let Some(filename) = raw_filename.to_str() else {
-            warn!("skipping potential funscript file because it has a non-UTF8 filename");
-            continue;
-        };
+    }
 
-        filenames_in_dir.push(filename.to_owned());
+    /// Shows the problems gathered up finding or loading funscripts, if there are any.
+    fn show_scan_problems(&mut self) {
+        if let Some(summary) = self.scan_problems.take_summary() {
+            show_osd(
+                &mut self.weak_client,
+                scan_problems::SHOW_DURATION,
+                &summary,
+            );
+        }
     }
 
-    let scan = scan_for_funscripts(&filenames_in_dir, &video_filename)
-        .context("failed funscript scan from list of filenames")?;
+    /// Brings the status page up to date, and sends the status to other scripts that want it.
+    fn report_status(&mut self) {
+        let axes = self
+            .axes
+            .iter()
+            .map(|axis| {
+                let axis_playstate = self.playstate.by_axis.get(&axis.axis_id);
+                AxisStatusReport {
+                    axis_kind: axis.axis_kind,
+                    playing: axis_playstate.map(|axis_playstate| {
+                        let limiter = &axis_playstate.limiter;
+                        AxisPlayingReport {
+                            progress: axis_playstate.status(),
+                            min: limiter.min,
+                            max: limiter.max,
+                            speed_limit: limiter.speed_limit,
+                            inverted: limiter.inverted,
+                            last_commanded: limiter.last_command_target,
+                        }
+                    }),
+                    held: self.held_axes.get(&axis.axis_kind).copied(),
+                }
+            })
+            .collect();
+        let mode = if self.stroker_in_use {
+            Some("Inactive: stroker in use by another player".to_owned())
+        } else if let Some(calibration) = &self.calibration {
+            Some(format!(
+                "Calibrating: latency {} ms",
+                calibration.latency_ms()
+            ))
+        } else if let Some(fallback) = &self.fallback {
+            Some(fallback.describe())
+        } else if self.inactive {
+            Some("Inactive: no funscripts for this video".to_owned())
+        } else {
+            None
+        };
+        let connected = self.stroker.is_connected();
+        let description = if connected {
+            self.stroker.description().unwrap_or_else(|err| {
+                warn!("failed to get the stroker's description: {err:?}");
+                None
+            })
+        } else {
+            None
+        };
+        let report = StatusReport {
+            connected,
+            description,
+            enabled: self.stroker.is_enabled(),
+            mode,
+            preset: self.preset.clone(),
+            funscripts_found: self.found.is_some(),
+            cluster: self.found.as_ref().and_then(|found| found.cluster.clone()),
+            sync_offset_ms: self.sync_offset_ms,
+            latency_ms: self.latency_ms,
+            axes,
+        };
+        self.status_page.draw(&mut self.weak_client, &report);
+        self.status_broadcast.send(&mut self.weak_client, &report);
+    }
 
-    for (&axis_kind, funscript_filename) in &scan.main.scripts {
-        let funscript_path = video_dir.join(funscript_filename);
-        let funscript_contents = tokio::fs::read(funscript_path)
-            .await
-            .with_context(|| format!("failed to read {funscript_filename:?}"))?;
-        let mut funscript: Funscript = serde_json::from_slice(&funscript_contents)
-            .with_context(|| format!("failed to deserialise {funscript_filename:?}"))?;
-        funscript.fixup();
-        let normalised_actions = normalised_from_funscript(&funscript);
-
-        if let Err(_) = tx
-            .send_async(PlaythreadMessage::UseFunscript {
+    /// Handles a message from mpv's side (or from a task the playthread started),
+    /// breaking once it's time to shut down.
+    async fn handle_message(&mut self, msg: PlaythreadMessage) -> eyre::Result<ControlFlow<()>> {
+        match msg {
+            PlaythreadMessage::VideoStarting {
+                video,
+                script_opts,
+                working_directory,
+            } => {
+                self.video_starting(video, script_opts, working_directory)
+                    .await;
+            }
+            PlaythreadMessage::PlaybackEnded => self.playback_ended().await,
+            PlaythreadMessage::FunscriptFailed { filename, error } => {
+                error!("failed to load {filename:?}: {error}");
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(5),
+                    &format!("Failed to load {filename}: {error}"),
+                );
+            }
+            PlaythreadMessage::ScanProblem { message } => {
+                self.scan_problems.add(message);
+            }
+            PlaythreadMessage::FunscriptsReloaded { axes } => {
+                info!("reloaded funscripts for {axes} axes");
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(2),
+                    &format!("Reloaded funscripts for {axes} axes"),
+                );
+            }
+            PlaythreadMessage::FunscriptsFound(found) => {
+                debug!("FunscriptsFound: clusters {:?}", found.cluster_names());
+                if let Some(watcher) = self.funscript_watcher.as_mut() {
+                    watcher.watch(watched_funscripts(&found, &self.axis_funscripts));
+                }
+                self.found = Some(found);
+                self.make_up_motion_if_no_funscripts().await?;
+            }
+            PlaythreadMessage::UseFunscript {
                 axis_kind,
                 normalised_actions,
+                problems,
+                raw_actions,
+                reload,
+            } => {
+                self.use_funscript(axis_kind, normalised_actions, problems, raw_actions, reload)
+                    .await?;
+            }
+            PlaythreadMessage::Seek { now_millis } => self.seek(now_millis),
+            PlaythreadMessage::TimeChange { now_millis } => self.time_change(now_millis).await?,
+            PlaythreadMessage::PauseChange { paused } => self.pause_change(paused).await?,
+            PlaythreadMessage::LoopChange { looping } => {
+                debug!("LoopChange: {looping}");
+                self.looping = looping;
+                for axis_playstate in self.playstate.by_axis.values_mut() {
+                    axis_playstate.set_loop(looping, self.duration_millis);
+                }
+            }
+            PlaythreadMessage::SpeedChange { speed } => self.speed_change(speed).await?,
+            PlaythreadMessage::AbLoopChange { a_millis, b_millis } => {
+                debug!("AbLoopChange: {a_millis:?} to {b_millis:?}");
+                self.ab_loop = match (a_millis, b_millis) {
+                    (Some(a_millis), Some(b_millis)) if a_millis < b_millis => {
+                        Some((a_millis, b_millis))
+                    }
+                    _ => None,
+                };
+            }
+            PlaythreadMessage::DurationChange { duration_millis } => {
+                self.duration_change(duration_millis);
+            }
+            PlaythreadMessage::FuzzyMatch {
+                match_kind,
+                filenames,
+            } => {
+                info!("using funscripts with similar names ({match_kind:?}): {filenames:?}");
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(5),
+                    &format!(
+                        "No funscript named after the video; using {}",
+                        filenames.join(", ")
+                    ),
+                );
+            }
+            PlaythreadMessage::Shutdown {} => {
+                self.shut_down().await?;
+                return Ok(ControlFlow::Break(()));
+            }
+            PlaythreadMessage::KeyCommand(cmd) => self.key_command(cmd).await?,
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Leaves the last video behind and starts looking for the new one's funscripts.
+    async fn video_starting(
+        &mut self,
+        new_video: NewVideo,
+        script_opts: ScriptOpts,
+        working_directory: Option<PathBuf>,
+    ) {
+        debug!("VideoStarting: {new_video:?} with options {script_opts:?}");
+        // Reloads still going are of the last video's funscripts
+        for (_, ctoken) in std::mem::take(&mut self.funscript_reload_ctokens) {
+            ctoken.cancel();
+        }
+        // Funscripts given for single axes are relative to where mpv was started,
+        // as the video's own path is
+        let working_directory = working_directory.unwrap_or_default();
+        self.state_video_path = match &new_video {
+            NewVideo::File(video_path) => Some(video_path.clone()),
+            NewVideo::Stream { .. } => None,
+        };
+        self.axis_funscripts = axis_funscript_paths(&script_opts)
+            .into_iter()
+            .filter_map(|(axis_kind, path)| {
+                if is_url(&path) {
+                    return Some((axis_kind, path));
+                }
+                let path = working_directory.join(path);
+                let Some(path) = path.to_str() else {
+                    warn!("ignoring {axis_kind:?} funscript {path:?}: path is non-UTF8");
+                    return None;
+                };
+                Some((axis_kind, path.to_owned()))
             })
-            .await
+            .collect();
+        // Most streams come without funscripts, so that's not worth a word to the user
+        let nothing_to_find =
+            matches!(new_video, NewVideo::Stream { .. }) && self.axis_funscripts.is_empty();
+        self.video = Some(new_video.clone());
+
+        self.last_action_at.clear();
+        self.last_now_millis = None;
+        self.clock.clear();
+        self.seek_debounce.clear();
+        self.step_throttle.clear();
+        self.skipped_axes.clear();
+        self.found = None;
+        if let Some(watcher) = self.funscript_watcher.as_mut() {
+            watcher.clear();
+        }
+        self.heatmap.clear(&mut self.weak_client);
+        if self.fallback.take().is_some() {
+            // The new video may well have funscripts of its own
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop generated motion for the new video: {err:?}");
+            }
+        }
+        let inactive_until_loaded = self.config.playback.require_funscript || nothing_to_find;
+        if inactive_until_loaded && !self.inactive {
+            info!("inactive until funscripts load for the video");
+            self.inactive = true;
+            // Funscripts for the last video mustn't play on this one
+            self.playstate.by_axis.clear();
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop stroker on becoming inactive: {err:?}");
+            }
+        } else if !inactive_until_loaded && self.inactive {
+            // Only the stream before had nothing to play along to
+            self.inactive = false;
+        }
+        if !self.config.playback.keep_sync_offset {
+            self.sync_offset_ms = 0;
+        }
+        self.video_limits.clear();
+        let restored = self
+            .video_states
+            .as_mut()
+            .zip(self.state_video_path.as_deref())
+            .and_then(|(states, path)| {
+                let state = VideoState {
+                    last_played: unix_time(),
+                    ..states.get(path)?.clone()
+                };
+                states.set(path, state.clone());
+                Some(state)
+            });
+        let cluster = restored.and_then(|state| {
+            info!("going back to how this video was last played: {state:?}");
+            self.sync_offset_ms = state.sync_offset_ms;
+            self.video_limits = state.limits;
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                "Restored this video's settings",
+            );
+            state.cluster
+        });
+        if nothing_to_find {
+            info!("no funscripts given for the stream, so not looking for any");
+            return;
+        }
+        spawn_funscript_task(
+            &mut self.funscript_load_ctoken,
+            "search_for_funscripts",
+            self.tx.clone(),
+            search_for_funscripts(
+                new_video,
+                cluster,
+                self.axis_funscripts.clone(),
+                self.config.playback.clone(),
+                self.tx.clone(),
+            ),
+        );
+    }
+
+    /// Stops playing the video that ended, with nothing after it (yet).
+    async fn playback_ended(&mut self) {
+        debug!("PlaybackEnded");
+        // Funscripts still loading are for the video that ended
+        if let Some(ctoken) = self.funscript_load_ctoken.take() {
+            ctoken.cancel();
+        }
+        for (_, ctoken) in std::mem::take(&mut self.funscript_reload_ctokens) {
+            ctoken.cancel();
+        }
+        save_video_states(&mut self.video_states).await;
+        // Nothing more is remembered for the video that ended
+        self.state_video_path = None;
+        self.playstate.by_axis.clear();
+        self.last_action_at.clear();
+        self.last_now_millis = None;
+        self.seek_debounce.clear();
+        self.step_throttle.clear();
+        self.found = None;
+        if let Some(watcher) = self.funscript_watcher.as_mut() {
+            watcher.clear();
+        }
+        self.heatmap.clear(&mut self.weak_client);
+        let generating = self.fallback.take().is_some();
+        if self.inactive && !generating {
+            return;
+        }
+        if let Err(err) = self.stroker.stop().await {
+            error!("failed to stop stroker at the end of playback: {err:?}");
+        }
+        if self.config.playback.require_funscript {
+            info!("inactive until funscripts load for the next video");
+            self.inactive = true;
+        }
+    }
+
+    /// Plays the loaded funscript on its axis, if the stroker has it (and it's driven).
+    async fn use_funscript(
+        &mut self,
+        axis_kind: AxisKind,
+        mut normalised_actions: Vec<NormalisedAction>,
+        mut problems: Vec<FunscriptError>,
+        raw_actions: Option<Vec<FunscriptAction>>,
+        reload: bool,
+    ) -> eyre::Result<()> {
+        debug!(
+            "UseFunscript: {axis_kind:?} ({} actions)",
+            normalised_actions.len()
+        );
+        if !self.config.playback.drives(axis_kind) {
+            if self.skipped_axes.insert(axis_kind) {
+                info!("ignoring the {axis_kind:?} funscript as that axis isn't in driven_axes");
+            }
+            return Ok(());
+        }
+        if let Some(last) = normalised_actions.last() {
+            self.last_action_at.insert(axis_kind, last.at);
+            if let Some(duration_millis) = self.duration_millis {
+                problems.extend(FunscriptError::check_video_duration(
+                    last.at,
+                    duration_millis,
+                ));
+            }
+        }
+        report_funscript_problems(&mut self.weak_client, axis_kind, &problems);
+
+        let Some(axis) = self.axes.iter().find(|axis| axis.axis_kind == axis_kind) else {
+            if self.stroker.is_connected() {
+                warn!("can't use loaded funscript for {axis_kind:?} because the stroker doesn't have an axis for it");
+            } else {
+                debug!("not using {axis_kind:?} funscript until the stroker is connected");
+            }
+            return Ok(());
+        };
+
+        let limits = axis_limits(&self.config, axis.axis_kind);
+
+        if let Some(min_interval_ms) = self.min_movement_interval_ms {
+            normalised_actions = enforce_min_interval(&normalised_actions, min_interval_ms);
+            debug!(
+                "{axis_kind:?}: {} actions left for a device needing {min_interval_ms} ms between movements",
+                normalised_actions.len()
+            );
+        }
+
+        // The script's positions are scaled into the limits, and so are its speeds.
+        let stats = Stats::from_actions(&normalised_actions);
+        let peak_speed = stats.peak_speed * (limits.default_max - limits.default_min).abs();
+        info!(
+            "{axis_kind:?} funscript: {} actions, {} strokes, peak speed {peak_speed:.2}/s (limit {:.2}/s)",
+            stats.action_count, stats.stroke_count, limits.speed
+        );
+        if peak_speed > limits.speed {
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(3),
+                &format!(
+                    "{axis_kind:?}: peak speed {peak_speed:.2}/s exceeds limit {:.2}/s",
+                    limits.speed
+                ),
+            );
+        }
+
+        let normalised_actions = Arc::new(normalised_actions);
+        if axis_kind == AxisKind::Stroke || !self.heatmap.has_actions() {
+            self.heatmap.set_actions(
+                &mut self.weak_client,
+                normalised_actions.clone(),
+                self.last_now_millis,
+            );
+        }
+        let mut axis_playstate = AxisPlaystate::new(
+            normalised_actions,
+            limits.speed,
+            limits.default_min,
+            limits.default_max,
+        );
+        axis_playstate.limiter.apply_config(limits);
+        axis_playstate.set_idle_timeout(limits.idle_timeout_ms);
+        axis_playstate.set_loop(self.looping, self.duration_millis);
+        axis_playstate.set_speed(self.speed);
+        if let Some(preset_limits) = self
+            .preset
+            .as_ref()
+            .and_then(|name| self.config.presets.get(name))
+            .and_then(|preset| preset.get(&axis_kind))
         {
-            warn!("loaded funscript but failed to send to playtask");
+            axis_playstate.limiter.apply_preset(preset_limits);
         }
+        if let Some(adjusted_limits) = self.video_limits.get(&axis_kind) {
+            axis_playstate.limiter.apply_preset(adjusted_limits);
+        }
+        // Held before it had a funscript, or by its last one
+        let held = self.held_axes.get(&axis_kind).copied();
+        axis_playstate.set_held(held);
+        if let Some(raw_actions) = raw_actions {
+            axis_playstate.set_raw_actions(Arc::new(raw_actions));
+        }
+        if reload {
+            // Carrying on from where it is, within the limits it had
+            if let Some(old_playstate) = self.playstate.by_axis.remove(&axis.axis_id) {
+                axis_playstate.limiter = old_playstate.limiter;
+            }
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                &format!("Reloaded the {axis_kind:?} funscript"),
+            );
+        }
+        if let Some(position) = held.filter(|_| self.calibration.is_none()) {
+            // Not necessarily there yet, e.g. if held before anything was loaded
+            axis_playstate
+                .park(position, HOLD_MOVE_MS, axis.axis_id, &mut self.stroker)
+                .await
+                .context("failed to move held axis into place")?;
+        } else if let Some(now_millis) = self.last_now_millis.filter(|_| self.calibration.is_none())
+        {
+            // Mid-video (e.g. after switching cluster, or as a video starts part-way
+            // through): move gently into place from wherever the axis is
+            axis_playstate
+                .move_into_place(self.script_now(now_millis), axis.axis_id, &mut self.stroker)
+                .await
+                .context("failed to seek new funscript")?;
+        }
+        if self.fallback.take().is_some() {
+            info!("funscripts loaded, so no longer making up motion");
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                "Funscripts loaded: generated motion stopped",
+            );
+        }
+        self.playstate.by_axis.insert(axis.axis_id, axis_playstate);
+        if self.inactive {
+            info!("active now that funscripts have loaded");
+            self.inactive = false;
+        }
+        Ok(())
     }
 
-    Ok(())
+    /// Keeps up with the video's time jumping, moving into place once seeking settles down.
+    fn seek(&mut self, now_millis: u32) {
+        debug!("Seek: {now_millis}");
+        self.last_now_millis = Some(now_millis);
+        self.clock.seek(now_millis, Instant::now());
+        self.heatmap.redraw(&mut self.weak_client, Some(now_millis));
+        if self.inactive || self.calibration.is_some() {
+            return;
+        }
+        // Moved into place once seeking settles down
+        self.seek_debounce.seek(now_millis);
+        self.step_throttle.clear();
+    }
+
+    /// Moves along the funscripts as the video's time goes by.
+    async fn time_change(&mut self, now_millis: u32) -> eyre::Result<()> {
+        let previous_millis = self.last_now_millis.replace(now_millis);
+        self.clock.update(now_millis, Instant::now());
+        self.heatmap.tick(&mut self.weak_client, now_millis);
+        self.seek_debounce.time_change(now_millis);
+        if self.seek_debounce.is_settling() {
+            // Moved along from wherever seeking settles down
+            return Ok(());
+        }
+        if jumped_back_into_ab_loop(previous_millis, now_millis, self.ab_loop) {
+            // mpv doesn't always tell us of a seek as it goes from B back to A,
+            // so move into place gently as for one
+            debug!("A-B loop back to {now_millis}");
+            self.heatmap.redraw(&mut self.weak_client, Some(now_millis));
+            if self.paused || self.inactive {
+                return Ok(());
+            }
+            return self
+                .playstate
+                .seek(self.script_now(now_millis), self.paused, &mut self.stroker)
+                .await
+                .context("failed AP seek at the A-B loop");
+        }
+        if self.paused
+            && self.config.pause.track_while_paused
+            && previous_millis != Some(now_millis)
+            && !self.inactive
+            && self.calibration.is_none()
+        {
+            // Stepped through frame by frame, so moved gently into place as for a seek
+            if let Some(step_millis) = self.step_throttle.step(now_millis) {
+                self.playstate
+                    .seek(self.script_now(step_millis), self.paused, &mut self.stroker)
+                    .await
+                    .context("failed AP seek to a frame whilst paused")?;
+            }
+            return Ok(());
+        }
+        if self.paused || self.inactive || self.interpolation_ticker.is_some() {
+            // (When interpolating, the ticker moves the axes instead)
+            return Ok(());
+        }
+        // Timed by the clock rather than the update itself, which may have come late
+        let now_millis = self.clock.now_at(Instant::now()).unwrap_or(now_millis);
+        let now_millis = self.script_now(now_millis);
+        // If the time went back to the start, e.g. as the video looped without a seek,
+        // each axis seeks there itself and plays on from there
+        for (&axis_id, axis_playstate) in self.playstate.by_axis.iter_mut() {
+            axis_playstate
+                .tick(now_millis, axis_id, &mut self.stroker)
+                .await
+                .context("failed AP tick")?;
+        }
+        Ok(())
+    }
+
+    /// Stops (and parks, with `[pause] park`) on pausing, and heads on again on resuming.
+    async fn pause_change(&mut self, new_paused: bool) -> eyre::Result<()> {
+        debug!("PauseChange: {new_paused}");
+        if !new_paused && self.calibration.take().is_some() {
+            // Carrying on with the video is as good as cancelling
+            info!("calibration cancelled as the video resumed");
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop the metronome: {err:?}");
+            }
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                "Calibration cancelled",
+            );
+        }
+        let was_paused = self.paused;
+        self.paused = new_paused;
+        // The time stands still whilst paused
+        self.clock.set_paused(self.paused, Instant::now());
+        if !self.paused {
+            // Playing on from there, so any step held back is out of date
+            self.step_throttle.clear();
+        }
+        if let Some(fallback) = self.fallback.as_mut() {
+            if self.paused {
+                fallback.pause();
+            } else if was_paused {
+                fallback
+                    .resume(&mut self.stroker)
+                    .await
+                    .context("failed to resume generated motion")?;
+            }
+        }
+        if self.inactive && self.fallback.is_none() {
+            return Ok(());
+        }
+        if self.paused {
+            self.stroker
+                .stop()
+                .await
+                .context("failed to stop stroker upon pause")?;
+            // With `[pause] park`, out of the way for the rest of the pause
+            let Some((park_pos, over_ms)) = self
+                .config
+                .pause
+                .park
+                .target()
+                .filter(|_| self.calibration.is_none())
+            else {
+                return Ok(());
+            };
+            for axis in &self.axes {
+                let Some(axis_playstate) =
+                    self.playstate
+                        .by_axis
+                        .get_mut(&axis.axis_id)
+                        .filter(|axis_playstate| {
+                            self.config.pause.axes.contains(&axis.axis_kind)
+                                && axis_playstate.held().is_none()
+                        })
+                else {
+                    continue;
+                };
+                axis_playstate
+                    .park(park_pos, over_ms, axis.axis_id, &mut self.stroker)
+                    .await
+                    .context("failed to park upon pause")?;
+            }
+            if let Some(fallback) = self
+                .fallback
+                .as_mut()
+                .filter(|_| self.config.pause.axes.contains(&AxisKind::Stroke))
+            {
+                fallback
+                    .park(park_pos, over_ms, &mut self.stroker)
+                    .await
+                    .context("failed to park generated motion upon pause")?;
+            }
+        } else if let Some(now_millis) = self.last_now_millis.filter(|_| was_paused) {
+            // The stroker stopped short of the action it was heading for, which
+            // the playstates have already moved past, so head for it again
+            self.playstate
+                .seek(self.script_now(now_millis), self.paused, &mut self.stroker)
+                .await
+                .context("failed to resume after pause")?;
+        }
+        Ok(())
+    }
+
+    /// Keeps up with the playback speed, interpolating or not as it calls for.
+    async fn speed_change(&mut self, speed: f64) -> eyre::Result<()> {
+        debug!("SpeedChange: {speed}");
+        self.speed = speed;
+        self.clock.set_speed(speed, Instant::now());
+        // Takes effect from the next action, which corrects any movement under way
+        for axis_playstate in self.playstate.by_axis.values_mut() {
+            axis_playstate.set_speed(speed);
+        }
+
+        // With `interpolate_below_speed`, interpolating starts or stops at some speeds
+        let interpolation_period = interpolation_period_ms(
+            self.config.playback.interpolation_hz_at(speed),
+            self.min_movement_interval_ms,
+        );
+        if interpolation_period == self.interpolation_period {
+            return Ok(());
+        }
+        let was_interpolating = self.interpolation_period.is_some();
+        self.interpolation_period = interpolation_period;
+        self.interpolation_ticker = new_interpolation_ticker(interpolation_period);
+        // Starting to interpolate moves on from wherever the axes are straight away,
+        // but stopping would leave them still until the next action, so they head
+        // for it now instead
+        if was_interpolating && interpolation_period.is_none() {
+            info!("moving once per action again at {speed}x speed");
+            if let Some(now_millis) = self
+                .last_now_millis
+                .filter(|_| !self.paused && !self.inactive)
+            {
+                self.playstate
+                    .seek(self.script_now(now_millis), false, &mut self.stroker)
+                    .await
+                    .context("failed AP seek after interpolating")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps up with the length of the video, checking the funscripts against it once known.
+    fn duration_change(&mut self, duration_millis: Option<u32>) {
+        debug!("DurationChange: {duration_millis:?}");
+        if let (None, Some(duration_millis)) = (self.duration_millis, duration_millis) {
+            // Funscripts often load before the video's length is known
+            for (&axis_kind, &last_at) in &self.last_action_at {
+                let problems: Vec<FunscriptError> =
+                    FunscriptError::check_video_duration(last_at, duration_millis)
+                        .into_iter()
+                        .collect();
+                report_funscript_problems(&mut self.weak_client, axis_kind, &problems);
+            }
+        }
+        self.duration_millis = duration_millis;
+        self.heatmap.set_duration(duration_millis);
+        for axis_playstate in self.playstate.by_axis.values_mut() {
+            axis_playstate.set_loop(self.looping, duration_millis);
+        }
+    }
+
+    /// Saves what there is to save and leaves the stroker stopped, as mpv is shutting down.
+    async fn shut_down(&mut self) -> eyre::Result<()> {
+        debug!("Shutdown");
+        save_video_states(&mut self.video_states).await;
+        self.stroker
+            .stop()
+            .await
+            .context("failed to stop stroker upon shutdown")?;
+        self.stroker.flush().await;
+        Ok(())
+    }
+
+    /// Does what the user asked for with a key binding (or a script message).
+    async fn key_command(&mut self, cmd: KeyCommand) -> eyre::Result<()> {
+        match cmd {
+            KeyCommand::AxisLimitChange(cmd) => self.change_axis_limits(&cmd),
+            KeyCommand::Status => {
+                let shown = self.status_page.toggle(&mut self.weak_client);
+                debug!("status page shown: {shown}");
+            }
+            KeyCommand::GetStatus(cmd) => {
+                debug!("status asked for by {:?}", cmd.reply_to);
+                self.status_broadcast.request(cmd.reply_to);
+            }
+            KeyCommand::ToggleHeatmap => {
+                let shown = self
+                    .heatmap
+                    .toggle(&mut self.weak_client, self.last_now_millis);
+                debug!("heatmap shown: {shown}");
+                if shown && !self.heatmap.has_actions() {
+                    show_osd(
+                        &mut self.weak_client,
+                        Duration::from_secs(2),
+                        "No funscripts in use",
+                    );
+                }
+            }
+            KeyCommand::SyncOffset(cmd) if self.calibration.is_some() => {
+                // Whilst calibrating, the offset matches the metronome up instead
+                let Some(calibration) = self.calibration.as_mut() else {
+                    return Ok(());
+                };
+                let mut new_latency_ms = cmd.new.unwrap_or(calibration.latency_ms());
+                if let Some(by) = cmd.by {
+                    new_latency_ms = new_latency_ms.saturating_add(by);
+                }
+                calibration
+                    .set_latency_ms(new_latency_ms, &mut self.stroker)
+                    .await
+                    .context("failed to move the metronome to the new latency")?;
+                debug!("calibrating with latency {new_latency_ms} ms");
+            }
+            KeyCommand::SyncOffset(cmd) => {
+                if let Some(new) = cmd.new {
+                    self.sync_offset_ms = new;
+                }
+                if let Some(by) = cmd.by {
+                    self.sync_offset_ms = self.sync_offset_ms.saturating_add(by);
+                }
+                info!("sync offset now {} ms", self.sync_offset_ms);
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(1),
+                    &format!("Sync offset: {:+} ms", self.sync_offset_ms),
+                );
+                // Jump to the new place in the funscripts straight away
+                if let Some(now_millis) = self.last_now_millis {
+                    self.playstate
+                        .seek(self.script_now(now_millis), self.paused, &mut self.stroker)
+                        .await
+                        .context("failed to seek after changing the sync offset")?;
+                }
+                self.remember_for_video();
+            }
+            KeyCommand::CyclePreset => {
+                let name = next_preset(&self.config.presets, self.preset.as_deref());
+                self.switch_preset(name);
+            }
+            KeyCommand::SetPreset(cmd) => self.switch_preset(Some(cmd.name)),
+            KeyCommand::ReloadFunscripts => self.reload_funscripts(),
+            KeyCommand::CycleCluster => self.switch_cluster(None),
+            KeyCommand::SetCluster(cmd) => self.switch_cluster(Some(&cmd.name)),
+            KeyCommand::ToggleEnabled => {
+                let enabled = !self.stroker.is_enabled();
+                self.set_enabled(enabled).await?;
+            }
+            KeyCommand::SetEnabled(cmd) => self.set_enabled(cmd.enabled).await?,
+            KeyCommand::Reconnect => self.reconnect().await,
+            KeyCommand::Calibrate if self.calibration.is_some() => {
+                self.finish_calibration().await;
+            }
+            KeyCommand::Calibrate => self.start_calibration(),
+            KeyCommand::CancelCalibration => self.cancel_calibration().await,
+            KeyCommand::SaveLimits => self.save_limits().await,
+            KeyCommand::HoldAxis(cmd) => self.hold_axis(&cmd).await?,
+            KeyCommand::ToggleFallback => self.toggle_fallback().await?,
+            KeyCommand::FallbackPeriod(cmd) => self.change_fallback_period(&cmd).await?,
+        }
+        Ok(())
+    }
+
+    /// Adjusts the limits of an axis in use, as `axis_limit` asks.
+    fn change_axis_limits(&mut self, cmd: &AxisLimitChangeCommand) {
+        let Some(axis) = self.axes.iter().find(|axis| axis.axis_kind == cmd.axis) else {
+            warn!(
+                "Can't change axis limits for {:?} as there is no corresponding stroker axis",
+                cmd.axis
+            );
+            return;
+        };
+        let Some(axis) = self.playstate.by_axis.get_mut(&axis.axis_id) else {
+            warn!(
+                "Can't change axis limits for {:?} as the axis is not in use.",
+                cmd.axis
+            );
+            return;
+        };
+
+        if let Err(err) = update_limits(cmd, &mut axis.limiter) {
+            error!("Error updating axis limits for {:?}: {err:?}", cmd.axis);
+        }
+        let inverted = if axis.limiter.inverted {
+            " (inverted)"
+        } else {
+            ""
+        };
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(1),
+            &format!(
+                "Limits: {:.4} ≤ {:?} ≤ {:.4}{inverted}",
+                axis.limiter.min, cmd.axis, axis.limiter.max
+            ),
+        );
+        self.remember_for_video();
+    }
+
+    /// Switches to the named preset of limits, for every axis in use.
+    fn switch_preset(&mut self, name: Option<String>) {
+        let Some((name, preset_axes)) =
+            name.and_then(|name| self.config.presets.get_key_value(&name))
+        else {
+            warn!(
+                "no such preset; presets are {:?}",
+                self.config.presets.keys()
+            );
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                "No such preset",
+            );
+            return;
+        };
+
+        for axis in &self.axes {
+            if let (Some(preset_limits), Some(axis_playstate)) = (
+                preset_axes.get(&axis.axis_kind),
+                self.playstate.by_axis.get_mut(&axis.axis_id),
+            ) {
+                axis_playstate.limiter.apply_preset(preset_limits);
+            }
+        }
+        self.preset = Some(name.clone());
+        info!("switched to preset {name:?}");
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            &format!("Preset: {name}"),
+        );
+    }
+
+    /// Loads the video's funscripts again, e.g. after editing them.
+    fn reload_funscripts(&mut self) {
+        let Some(video) = self.video.clone() else {
+            warn!("no video to reload funscripts for");
+            return;
+        };
+        // Stay with the same cluster, if it's still there
+        let cluster = self.found.as_ref().and_then(|found| found.cluster.clone());
+
+        // The reloaded funscripts take over each axis from where playback is
+        self.playstate.by_axis.clear();
+        self.last_action_at.clear();
+        let axis_funscripts = self.axis_funscripts.clone();
+        let playback = self.config.playback.clone();
+        let tx = self.tx.clone();
+        spawn_funscript_task(
+            &mut self.funscript_load_ctoken,
+            "reload_funscripts",
+            tx.clone(),
+            async move {
+                let axes =
+                    search_for_funscripts(video, cluster, axis_funscripts, playback, tx.clone())
+                        .await?;
+                if tx
+                    .send_async(PlaythreadMessage::FunscriptsReloaded { axes })
+                    .await
+                    .is_err()
+                {
+                    warn!("reloaded funscripts but failed to tell the playtask");
+                }
+                Ok(())
+            },
+        );
+    }
+
+    /// Switches to the named cluster of funscripts, or the next one if there's no name.
+    fn switch_cluster(&mut self, name: Option<&str>) {
+        let Some(found) = self.found.as_mut() else {
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                "No funscripts in use",
+            );
+            return;
+        };
+        let cluster = match name.map(normalise_override_name) {
+            Some(name) if name == "main" => None,
+            Some(name) if found.scan.overrides.contains_key(&name) => Some(name),
+            Some(name) => {
+                warn!("no funscripts for {name:?}");
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(3),
+                    &format!("No {name:?} funscripts\n{}", found.describe()),
+                );
+                return;
+            }
+            None => found.next_cluster(),
+        };
+        found.cluster = cluster;
+        if let Some(watcher) = self.funscript_watcher.as_mut() {
+            watcher.watch(watched_funscripts(found, &self.axis_funscripts));
+        }
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(3),
+            &found.describe(),
+        );
+
+        // The new funscripts take over each axis from where playback is
+        self.playstate.by_axis.clear();
+        self.last_action_at.clear();
+        spawn_funscript_task(
+            &mut self.funscript_load_ctoken,
+            "load_cluster",
+            self.tx.clone(),
+            load_cluster(
+                found.video_dir.clone(),
+                found.video_filename.clone(),
+                found.scan.cluster(found.cluster.as_deref()),
+                self.axis_funscripts.clone(),
+                self.config.playback.clone(),
+                self.tx.clone(),
+            ),
+        );
+        self.remember_for_video();
+    }
+
+    /// Enables or disables the stroker, catching up with playback on enabling it.
+    async fn set_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        self.stroker.set_enabled(enabled);
+        info!("stroker enabled: {enabled}");
+        if !enabled {
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop stroker on disabling it: {err:?}");
+            }
+        } else if !self.paused {
+            // Catch up with where playback got to whilst disabled
+            if let Some(now_millis) = self.last_now_millis {
+                self.playstate
+                    .seek(self.script_now(now_millis), self.paused, &mut self.stroker)
+                    .await
+                    .context("failed to seek on enabling the stroker")?;
+            }
+            if let Some(fallback) = self.fallback.as_mut() {
+                fallback
+                    .resume(&mut self.stroker)
+                    .await
+                    .context("failed to resume generated motion")?;
+            }
+        }
+        let state = if enabled { "enabled" } else { "disabled" };
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            &format!("Stroker {state}"),
+        );
+        Ok(())
+    }
+
+    /// Lets go of the stroker (once stopped) and connects to it afresh.
+    async fn reconnect(&mut self) {
+        if self.stroker.is_connected() {
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop stroker before reconnecting: {err:?}");
+            }
+            // Let go of it only once stopped, and closed so as to connect to it afresh
+            self.stroker.release().await;
+            self.axes.clear();
+            self.playstate.by_axis.clear();
+            self.fallback = None;
+        }
+        info!("connecting to the stroker again");
+        self.connecting = Some(self.connect_after(Duration::ZERO));
+        self.retry_delay = INITIAL_RETRY_DELAY;
+        self.connect_failures = 0;
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            "Connecting to the stroker",
+        );
+    }
+
+    /// Pauses the video and starts the metronome, to calibrate the latency against.
+    fn start_calibration(&mut self) {
+        let stroke_axis = self
+            .axes
+            .iter()
+            .find(|axis| axis.axis_kind == AxisKind::Stroke)
+            .filter(|_| self.stroker.is_enabled());
+        let Some(stroke_axis) = stroke_axis else {
+            warn!("can't calibrate without an enabled stroker with a stroke axis");
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(3),
+                "Can't calibrate: the stroker isn't connected, is disabled or has no stroke axis",
+            );
+            return;
+        };
+        info!("calibrating, starting from latency {} ms", self.latency_ms);
+        // The video is paused so that the metronome has the stroker to itself
+        if !self.paused {
+            set_paused(&mut self.weak_client, true);
+        }
+        self.calibration = Some(Calibration::new(
+            stroke_axis.axis_id,
+            axis_limits(&self.config, AxisKind::Stroke),
+            self.latency_ms,
+            !self.paused,
+        ));
+    }
+
+    /// Takes on the latency the metronome was matched up with, saving it if there's somewhere to.
+    async fn finish_calibration(&mut self) {
+        let Some(finished) = self.calibration.take() else {
+            return;
+        };
+        let latency_ms = finished.latency_ms();
+        self.latency_ms = latency_ms;
+        info!("calibrated: latency {latency_ms} ms");
+        if let Err(err) = self.stroker.stop().await {
+            error!("failed to stop the metronome: {err:?}");
+        }
+        match self.video_states.as_mut() {
+            Some(video_states) => {
+                video_states.set_latency_ms(latency_ms);
+                show_osd(
+                    &mut self.weak_client,
+                    Duration::from_secs(3),
+                    &format!("Latency: {latency_ms} ms (saved)"),
+                );
+            }
+            None => show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(8),
+                &format!("Latency: {latency_ms} ms\nTo keep it, set latency_ms = {latency_ms} in [playback]"),
+            ),
+        }
+        if finished.was_playing {
+            set_paused(&mut self.weak_client, false);
+        }
+    }
+
+    /// Stops the metronome, leaving the latency as it was.
+    async fn cancel_calibration(&mut self) {
+        let Some(cancelled) = self.calibration.take() else {
+            return;
+        };
+        info!("calibration cancelled");
+        if let Err(err) = self.stroker.stop().await {
+            error!("failed to stop the metronome: {err:?}");
+        }
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            "Calibration cancelled",
+        );
+        if cancelled.was_playing {
+            set_paused(&mut self.weak_client, false);
+        }
+    }
+
+    /// Saves the limits of the axes in use into the configuration file.
+    async fn save_limits(&mut self) {
+        // Only the axes in use, as only their limits can have been adjusted
+        let mut saved_limits = BTreeMap::new();
+        for axis in &self.axes {
+            let Some(axis_playstate) = self.playstate.by_axis.get(&axis.axis_id) else {
+                continue;
+            };
+            let limiter = &axis_playstate.limiter;
+            let limits = LimitsConfig {
+                speed: limiter.speed_limit,
+                default_min: limiter.min,
+                default_max: limiter.max,
+                invert: limiter.inverted,
+                ..axis_limits(&self.config, axis.axis_kind).clone()
+            };
+            saved_limits.insert(axis.axis_kind, limits);
+        }
+        let message = if saved_limits.is_empty() {
+            "No axes in use, so no limits to save".to_owned()
+        } else {
+            match save_limits(&saved_limits).await {
+                Ok(path) => {
+                    info!("saved limits to {path:?}");
+                    // Videos played from now on start from them too
+                    self.config.limits.extend(saved_limits);
+                    format!("Limits saved to {}", path.display())
+                }
+                Err(err) => {
+                    error!("failed to save limits: {err:?}");
+                    format!("Couldn't save limits: {err}")
+                }
+            }
+        };
+        show_osd(&mut self.weak_client, Duration::from_secs(3), &message);
+    }
+
+    /// Holds an axis still at a position, or releases it to carry on with its funscript.
+    async fn hold_axis(&mut self, cmd: &HoldAxisCommand) -> eyre::Result<()> {
+        if !self.config.playback.drives(cmd.axis) {
+            show_osd(
+                &mut self.weak_client,
+                Duration::from_secs(2),
+                &format!("Can't hold {:?}: it isn't in driven_axes", cmd.axis),
+            );
+            return Ok(());
+        }
+        let Some(axis) = self.axes.iter().find(|axis| axis.axis_kind == cmd.axis) else {
+            warn!(
+                "Can't hold {:?} as there is no corresponding stroker axis",
+                cmd.axis
+            );
+            return Ok(());
+        };
+        let release = cmd.toggle && self.held_axes.contains_key(&cmd.axis);
+        let position = cmd.position.unwrap_or(0.5).clamp(0.0, 1.0);
+        if release {
+            self.held_axes.remove(&cmd.axis);
+        } else {
+            self.held_axes.insert(cmd.axis, position);
+        }
+        info!("held axes now {:?}", self.held_axes);
+
+        // Where to carry on from, on releasing it
+        let script_millis = self
+            .last_now_millis
+            .filter(|_| !self.inactive && self.calibration.is_none())
+            .map(|now_millis| self.script_now(now_millis));
+        // An axis without a funscript yet is held once it has one
+        if let Some(axis_playstate) = self.playstate.by_axis.get_mut(&axis.axis_id) {
+            if release {
+                axis_playstate.set_held(None);
+                if let Some(script_millis) = script_millis {
+                    axis_playstate
+                        .seek(script_millis, self.paused, axis.axis_id, &mut self.stroker)
+                        .await
+                        .context("failed to seek on releasing held axis")?;
+                }
+            } else {
+                axis_playstate.set_held(Some(position));
+                axis_playstate
+                    .park(position, HOLD_MOVE_MS, axis.axis_id, &mut self.stroker)
+                    .await
+                    .context("failed to move held axis into place")?;
+            }
+        }
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            &describe_held_axes(&self.held_axes),
+        );
+        Ok(())
+    }
+
+    /// Turns making up motion for videos without funscripts on or off.
+    async fn toggle_fallback(&mut self) -> eyre::Result<()> {
+        self.fallback_enabled = !self.fallback_enabled;
+        info!(
+            "making up motion for videos without funscripts: {}",
+            self.fallback_enabled
+        );
+        if !self.fallback_enabled && self.fallback.take().is_some() {
+            if let Err(err) = self.stroker.stop().await {
+                error!("failed to stop generated motion: {err:?}");
+            }
+        }
+        let state = if self.fallback_enabled { "on" } else { "off" };
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(2),
+            &format!("Generated motion for videos without funscripts: {state}"),
+        );
+        self.make_up_motion_if_no_funscripts().await
+    }
+
+    /// Changes how long each cycle of made-up motion takes.
+    async fn change_fallback_period(&mut self, cmd: &FallbackPeriodCommand) -> eyre::Result<()> {
+        if let Some(new) = cmd.new {
+            self.fallback_period_ms = new;
+        }
+        if let Some(by) = cmd.by {
+            self.fallback_period_ms = self.fallback_period_ms.saturating_add_signed(by);
+        }
+        self.fallback_period_ms = self.fallback_period_ms.clamp(MIN_PERIOD_MS, MAX_PERIOD_MS);
+        info!("generated motion period now {} ms", self.fallback_period_ms);
+        if let Some(fallback) = self.fallback.as_mut() {
+            fallback
+                .set_period(self.fallback_period_ms, &mut self.stroker)
+                .await
+                .context("failed to change the period of generated motion")?;
+        }
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(1),
+            &format!("Generated motion: {} ms per cycle", self.fallback_period_ms),
+        );
+        Ok(())
+    }
+
+    /// Remembers what the user adjusted for the video, to go back to when it's played again.
+    fn remember_for_video(&mut self) {
+        let (Some(states), Some(path)) = (self.video_states.as_mut(), &self.state_video_path)
+        else {
+            return;
+        };
+        for axis in &self.axes {
+            if let Some(axis_playstate) = self.playstate.by_axis.get(&axis.axis_id) {
+                let limiter = &axis_playstate.limiter;
+                self.video_limits.insert(
+                    axis.axis_kind,
+                    PresetLimitsConfig {
+                        speed: Some(limiter.speed_limit),
+                        min: Some(limiter.min),
+                        max: Some(limiter.max),
+                        invert: Some(limiter.inverted),
+                    },
+                );
+            }
+        }
+        let state = VideoState {
+            limits: self.video_limits.clone(),
+            sync_offset_ms: self.sync_offset_ms,
+            cluster: self.found.as_ref().and_then(|found| found.cluster.clone()),
+            last_played: unix_time(),
+        };
+        debug!("remembering for the video: {state:?}");
+        states.set(path, state);
+    }
+
+    /// With `[fallback]`, starts making up motion if it's clear that the video has no funscripts.
+    async fn make_up_motion_if_no_funscripts(&mut self) -> eyre::Result<()> {
+        if !self.fallback_enabled || self.fallback.is_some() {
+            return Ok(());
+        }
+        let Some(found) = self
+            .found
+            .as_ref()
+            .filter(|found| found.is_empty() && self.axis_funscripts.is_empty())
+        else {
+            return Ok(());
+        };
+        let Some(stroke_axis) = self
+            .axes
+            .iter()
+            .find(|axis| axis.axis_kind == AxisKind::Stroke)
+        else {
+            warn!("can't make up motion because the stroker doesn't have a stroke axis");
+            return Ok(());
+        };
+        let mut motion = FallbackMotion::new(
+            self.config.fallback.clone(),
+            self.fallback_period_ms,
+            stroke_axis.axis_id,
+            axis_limits(&self.config, AxisKind::Stroke),
+            self.min_movement_interval_ms,
+            video_seed(&found.video_filename),
+        );
+        if let Some(preset_limits) = self
+            .preset
+            .as_ref()
+            .and_then(|name| self.config.presets.get(name))
+            .and_then(|preset| preset.get(&AxisKind::Stroke))
+        {
+            motion.limiter_mut().apply_preset(preset_limits);
+        }
+        if let Some(adjusted_limits) = self.video_limits.get(&AxisKind::Stroke) {
+            motion.limiter_mut().apply_preset(adjusted_limits);
+        }
+        // Funscripts for the last video mustn't fight the made-up motion
+        self.playstate.by_axis.clear();
+        if !self.paused {
+            motion
+                .resume(&mut self.stroker)
+                .await
+                .context("failed to start generated motion")?;
+        }
+        info!("no funscripts for the video, so making up motion");
+        show_osd(
+            &mut self.weak_client,
+            Duration::from_secs(5),
+            &format!("No funscripts: {}", motion.describe()),
+        );
+        self.fallback = Some(motion);
+        Ok(())
+    }
+}
+
+/// The configured limits of an axis, or some very pessimistic/safe/boring ones if there are none.
+fn axis_limits(config: &RootConfig, axis_kind: AxisKind) -> &LimitsConfig {
+    config.limits.get(&axis_kind).unwrap_or_else(|| {
+        warn!("Axis {axis_kind:?} has no limits configured; using some very pessimistic/safe/boring ones!");
+        &LimitsConfig {
+            speed: 0.25,
+            default_min: 0.4,
+            default_max: 0.6,
+            invert: false,
+            device_easing: Easing::Linear,
+            idle_timeout_ms: None,
+        }
+    })
+}
+
+/// Saves the limits into the configuration file (wherever it was loaded from), giving its path.
+async fn save_limits(limits: &BTreeMap<AxisKind, LimitsConfig>) -> Result<PathBuf, StrokersError> {
+    let path = strokers::config_path()?;
+    strokers::config_edit::save_limits(&path, limits).await?;
+    Ok(path)
+}
+
+/// A number that's always the same for the same video, e.g. to seed made-up motion with.
+fn video_seed(video_filename: &str) -> u64 {
+    fnv1a(video_filename.as_bytes())
+}
+
+/// The time in the funscripts for the given playback time, with the sync offset applied:
+/// a positive offset plays the funscripts ahead of the video, for scripts that run late.
+///
+/// Never before the start (or after the end) of time, however large the offset.
+fn script_time(now_millis: u32, sync_offset_ms: i32) -> u32 {
+    now_millis.saturating_add_signed(sync_offset_ms)
+}
+
+/// Lists the axes that are held and where, for the OSD.
+fn describe_held_axes(held_axes: &BTreeMap<AxisKind, f32>) -> String {
+    if held_axes.is_empty() {
+        return "No axes held".to_owned();
+    }
+    let held: Vec<String> = held_axes
+        .iter()
+        .map(|(axis_kind, position)| format!("{axis_kind:?} at {position:.2}"))
+        .collect();
+    format!("Held: {}", held.join(", "))
+}
+
+/// How close to B (or past it) playback has to have been to have looped back to A from there.
+const AB_LOOP_NEAR_B_MS: u32 = 250;
+
+/// How far back within the A-B loop playback has to go, from anywhere, to have jumped,
+/// rather than the time reported having just wobbled.
+const AB_LOOP_MIN_JUMP_MS: u32 = 1000;
+
+/// Whether playback went back to within the A-B loop (as it does on reaching B),
+/// going by the playback time before and after.
+fn jumped_back_into_ab_loop(
+    previous_millis: Option<u32>,
+    now_millis: u32,
+    ab_loop: Option<(u32, u32)>,
+) -> bool {
+    let (Some(previous_millis), Some((a_millis, b_millis))) = (previous_millis, ab_loop) else {
+        return false;
+    };
+    let near_b = previous_millis.saturating_add(AB_LOOP_NEAR_B_MS) >= b_millis;
+    now_millis < previous_millis
+        && (a_millis..b_millis).contains(&now_millis)
+        && (near_b || previous_millis - now_millis >= AB_LOOP_MIN_JUMP_MS)
+}
+
+/// How often to move along the funscripts with `interpolation_hz`, in milliseconds,
+/// but no more often than the stroker can take commands.
+/// `None` if not interpolating (or the rate is nonsense).
+fn interpolation_period_ms(
+    interpolation_hz: Option<f32>,
+    min_movement_interval_ms: Option<u32>,
+) -> Option<u32> {
+    let hz = interpolation_hz.filter(|hz| hz.is_finite() && *hz > 0.0)?;
+    let period_ms = (1000.0 / hz).round().max(1.0) as u32;
+    match min_movement_interval_ms {
+        Some(min_interval_ms) if period_ms < min_interval_ms => {
+            warn!("interpolating at {hz} Hz is too often for the stroker; every {min_interval_ms} ms instead");
+            Some(min_interval_ms)
+        }
+        _ => Some(period_ms),
+    }
+}
+
+/// The ticker to interpolate with, every `period_ms` as from [`interpolation_period_ms`].
+fn new_interpolation_ticker(period_ms: Option<u32>) -> Option<Interval> {
+    period_ms.map(|period_ms| {
+        info!("moving along the funscripts every {period_ms} ms");
+        let mut ticker = tokio::time::interval(Duration::from_millis(period_ms.into()));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        ticker
+    })
+}
+
+/// Waits for the attempt to connect to the stroker, or forever if not connecting.
+async fn next_connect_attempt<S>(connecting: &mut Option<ConnectAttempt<S>>) -> eyre::Result<S> {
+    match connecting {
+        Some(attempt) => attempt.await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until the status page is due to be brought up to date, or the status to be sent
+/// to other scripts, or forever if neither.
+async fn next_status_due(status_page: &mut StatusPage, status_broadcast: &mut StatusBroadcast) {
+    tokio::select! {
+        () = status_page.next_refresh() => {}
+        () = status_broadcast.next_due() => {}
+    }
+}
+
+/// Waits for the next tick of the interpolation ticker, or forever if not interpolating.
+async fn next_tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until made-up motion should move along, or forever if there's none.
+async fn next_fallback_tick(fallback: &mut Option<FallbackMotion>) {
+    match fallback {
+        Some(fallback) => fallback.next_tick().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until an axis has been idle long enough to rest, or forever if none will be.
+async fn next_rest(playstate: &Playstate) {
+    match playstate
+        .by_axis
+        .values()
+        .filter_map(AxisPlaystate::rest_due)
+        .min()
+    {
+        Some(rest_due) => tokio::time::sleep_until(rest_due).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until a funscript in use has changed, or forever if they're not being watched.
+async fn next_funscript_change(watcher: &mut Option<FunscriptWatcher>) -> (AxisKind, PathBuf) {
+    match watcher {
+        Some(watcher) => watcher.next_change().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until the metronome should move along, or forever if not calibrating.
+async fn next_calibration_tick(calibration: &mut Option<Calibration>) {
+    match calibration {
+        Some(calibration) => calibration.next_tick().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Shows the text on the OSD for the given time. Failing to is only logged,
+/// as playback can carry on regardless.
+fn show_osd(weak_client: &mut impl Mpv, duration: Duration, text: &str) {
+    if let Err(err) = osd!(weak_client, duration, "{text}") {
+        error!("Failed to display OSD: {err:?}");
+    }
+}
+
+/// Pauses or unpauses the video.
+fn set_paused(weak_client: &mut impl Mpv, paused: bool) {
+    if let Err(err) = weak_client.set_pause(paused) {
+        error!("Failed to set pause to {paused}: {err:?}");
+    }
+}
+
+/// Waits until the changes to the state of the videos should be saved,
+/// or forever if there are none (or no file to save them in).
+async fn next_video_state_save(video_states: &Option<VideoStates>) {
+    match video_states {
+        Some(video_states) => video_states.next_save().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Saves any changes to the state of the videos. Failing to is only logged,
+/// as playback can carry on regardless.
+async fn save_video_states(video_states: &mut Option<VideoStates>) {
+    let Some(video_states) = video_states.as_mut() else {
+        return;
+    };
+    if let Err(err) = video_states.save_now().await {
+        error!("failed to save the state of the videos: {err:?}");
+    }
+}
+
+/// The preset after the given one (in alphabetical order), wrapping around to the first,
+/// or the first if there's none yet. `None` if there are no presets.
+fn next_preset(presets: &BTreeMap<String, PresetConfig>, current: Option<&str>) -> Option<String> {
+    let after = current.and_then(|current| {
+        presets
+            .range::<str, _>((Bound::Excluded(current), Bound::Unbounded))
+            .next()
+    });
+    after
+        .or_else(|| presets.iter().next())
+        .map(|(name, _)| name.clone())
+}
+
+/// The directory of a video and its file name.
+fn split_video_path(video_path: &Path) -> eyre::Result<(PathBuf, String)> {
+    let video_dir = video_path
+        .parent()
+        .context("video has no parent")?
+        .to_owned();
+    let video_filename = video_path
+        .file_name()
+        .context("video has no filename")?
+        .to_str()
+        .context("video filename is not UTF-8")?
+        .to_owned();
+    Ok((video_dir, video_filename))
+}
+
+/// Reads a funscript beside the video (or in an archive there), as [`read_funscript`],
+/// or downloads it if given as a URL, falling back to lenient parsing if it isn't valid JSON.
+async fn load_funscript(video_dir: &Path, funscript_filename: &str) -> eyre::Result<Funscript> {
+    let contents = if is_url(funscript_filename) {
+        fetch_funscript(funscript_filename).await
+    } else {
+        read_funscript(video_dir, funscript_filename).await
+    }
+    .with_context(|| format!("failed to load {funscript_filename:?}"))?;
+    match Funscript::from_slice(&contents) {
+        Ok(funscript) => Ok(funscript),
+        Err(err) => {
+            warn!("{funscript_filename:?} isn't valid JSON ({err:#}); trying to load it leniently");
+            Funscript::from_slice_lenient(&contents)
+                .with_context(|| format!("failed to load {funscript_filename:?}"))
+        }
+    }
+}
+
+/// Downloads a funscript given as a URL, e.g. for a stream.
+#[cfg(feature = "http")]
+async fn fetch_funscript(url: &str) -> eyre::Result<Vec<u8>> {
+    let response = reqwest::get(url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("failed to download {url}"))?;
+    let contents = response
+        .bytes()
+        .await
+        .with_context(|| format!("failed to download {url}"))?;
+    Ok(contents.to_vec())
+}
+
+/// Downloads a funscript given as a URL, which this build can't.
+#[cfg(not(feature = "http"))]
+async fn fetch_funscript(url: &str) -> eyre::Result<Vec<u8>> {
+    bail!("can't download {url}: built without the `http` feature")
+}
+
+/// Runs a task that finds or loads funscripts in the background,
+/// cancelling the last such task (e.g. for the previous video) and making the token
+/// for cancelling this one. If it fails, the playtask is told, to show on the OSD.
+fn spawn_funscript_task<T: Send + 'static>(
+    ctoken: &mut Option<CancellationToken>,
+    name: &'static str,
+    tx: Sender<PlaythreadMessage>,
+    task: impl Future<Output = eyre::Result<T>> + Send + 'static,
+) {
+    if let Some(ctoken) = ctoken.take() {
+        ctoken.cancel();
+    }
+    let new_ctoken = CancellationToken::new();
+    *ctoken = Some(new_ctoken.clone());
+
+    tokio::task::spawn(async move {
+        tokio::select! {
+            res = task => {
+                if let Err(err) = res {
+                    error!("{name} failed: {err:?}");
+                    let problem = PlaythreadMessage::ScanProblem {
+                        message: format!("{err:#}"),
+                    };
+                    if tx.send_async(problem).await.is_err() {
+                        warn!("{name} failed and failed to tell the playtask");
+                    }
+                }
+            }
+            _ = new_ctoken.cancelled() => {
+                info!("{name} cancelled");
+            }
+        }
+    });
+}
+
+/// Tells the user about problems with a funscript, in the log and on the OSD.
+fn report_funscript_problems(
+    weak_client: &mut impl Mpv,
+    axis_kind: AxisKind,
+    problems: &[FunscriptError],
+) {
+    if problems.is_empty() {
+        return;
+    }
+    for problem in problems {
+        warn!("{axis_kind:?}: {problem}");
+    }
+    let lines: Vec<String> = problems
+        .iter()
+        .map(|problem| format!("{axis_kind:?}: {problem}"))
+        .collect();
+    show_osd(weak_client, Duration::from_secs(5), &lines.join("\n"));
+}
+
+/// Updates an axis's limits, and whether it's inverted.
+/// Both limits are prevented from going out of range, and the minimum from going above the maximum:
+/// nudged past each other, they stop where they meet, and set past each other, nothing changes.
+fn update_limits(cmd: &AxisLimitChangeCommand, limits: &mut AxisLimiter) -> eyre::Result<()> {
+    fn update_axis(
+        name: &str,
+        by: &Option<f32>,
+        new: &Option<f32>,
+        target: &mut f32,
+    ) -> eyre::Result<()> {
+        match (by, new) {
+            (Some(_), Some(_)) => {
+                bail!("Conflicting axis_limit parameters for {name}");
+            }
+            (Some(by), None) => {
+                *target = (*target + by).clamp(0.0, 1.0);
+            }
+            (None, Some(new)) => {
+                if *new < 0.0 || 1.0 < *new {
+                    bail!("Can't set limit to {new:?} as that's out of range!");
+                }
+                *target = *new;
+            }
+            (None, None) => {
+                // nop
+            }
+        }
+        Ok(())
+    }
+
+    let (mut min, mut max) = (limits.min, limits.max);
+    update_axis("min", &cmd.min_by, &cmd.min_new, &mut min)?;
+    update_axis("max", &cmd.max_by, &cmd.max_new, &mut max)?;
+    if min > max {
+        if cmd.min_new.is_some() || cmd.max_new.is_some() {
+            bail!("Can't set the minimum limit ({min}) above the maximum ({max}); use invert=toggle to invert the motion");
+        }
+        if cmd.min_by.is_some() {
+            min = max;
+        } else {
+            max = min;
+        }
+    }
+    limits.min = min;
+    limits.max = max;
+    limits.inverted = match cmd.invert {
+        Some(InvertChange::Toggle) => !limits.inverted,
+        Some(InvertChange::On) => true,
+        Some(InvertChange::Off) => false,
+        None => limits.inverted,
+    };
+    Ok(())
+}
+
+/// Logs a problem finding or loading funscripts in full, and tells the playtask,
+/// to show on the OSD, rather than leaving the user none the wiser.
+async fn report_scan_problem(tx: &Sender<PlaythreadMessage>, problem: eyre::Report) {
+    warn!("{problem:?}");
+    let message = PlaythreadMessage::ScanProblem {
+        message: format!("{problem:#}"),
+    };
+    if tx.send_async(message).await.is_err() {
+        warn!("failed to tell the playtask about a problem finding funscripts");
+    }
+}
+
+/// Lists the names of the files (or symlinks) in a directory.
+/// Files with non-UTF8 names are skipped, as reported to the playtask.
+async fn list_files(dir: &Path, tx: &Sender<PlaythreadMessage>) -> eyre::Result<Vec<String>> {
+    let mut read_dir = tokio::fs::read_dir(dir).await.context("can't read")?;
+
+    let mut filenames_in_dir: Vec<String> = Vec::new();
+    while let Some(dir_entry) = read_dir
+        .next_entry()
+        .await
+        .context("failed to read next directory entry")?
+    {
+        let file_type = dir_entry
+            .file_type()
+            .await
+            .context("can't probe type of file")?;
+        if !(file_type.is_file() || file_type.is_symlink()) {
+            continue;
+        }
+        let raw_filename = dir_entry.file_name();
+        let Some(filename) = raw_filename.to_str() else {
+            let problem = eyre!("skipped {raw_filename:?} in {dir:?}: its name isn't UTF-8");
+            report_scan_problem(tx, problem).await;
+            continue;
+        };
+
+        filenames_in_dir.push(filename.to_owned());
+    }
+    Ok(filenames_in_dir)
+}
+
+/// How many levels of subdirectories of each script library to look in for funscripts.
+const LIBRARY_MAX_DEPTH: usize = 4;
+
+/// How many files (and directories) across the script libraries to look at at most,
+/// so that a huge library can't hold up finding the funscripts for long.
+const LIBRARY_MAX_ENTRIES: usize = 50_000;
+
+/// Lists the files in the script libraries and their subdirectories
+/// (down to [`LIBRARY_MAX_DEPTH`]), as for [`scan_library`]: the path of each directory
+/// with the names of the files in it. Shallower directories are listed first.
+///
+/// Directories that can't be read are skipped, as reported to the playtask.
+/// Symlinks to directories aren't followed, so that loops can't be.
+async fn list_libraries(
+    roots: Vec<PathBuf>,
+    tx: &Sender<PlaythreadMessage>,
+) -> Vec<(String, Vec<String>)> {
+    let mut listings = Vec::new();
+    let mut entries_seen = 0;
+    let mut queue: VecDeque<(PathBuf, usize)> = roots.into_iter().map(|root| (root, 0)).collect();
+
+    while let Some((dir, depth)) = queue.pop_front() {
+        let Some(dir_name) = dir.to_str().map(str::to_owned) else {
+            let problem = eyre!("skipped script library directory {dir:?}: its path isn't UTF-8");
+            report_scan_problem(tx, problem).await;
+            continue;
+        };
+        let mut read_dir = match tokio::fs::read_dir(&dir).await {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("not looking for funscripts in {dir:?}: {err}");
+                continue;
+            }
+            Err(err) => {
+                let problem = eyre!(err).wrap_err(format!("can't read script library {dir:?}"));
+                report_scan_problem(tx, problem).await;
+                continue;
+            }
+        };
+
+        let mut filenames = Vec::new();
+        loop {
+            let dir_entry = match read_dir.next_entry().await {
+                Ok(Some(dir_entry)) => dir_entry,
+                Ok(None) => break,
+                Err(err) => {
+                    let problem = eyre!(err)
+                        .wrap_err(format!("failed to read all of script library {dir:?}"));
+                    report_scan_problem(tx, problem).await;
+                    break;
+                }
+            };
+            entries_seen += 1;
+            if entries_seen > LIBRARY_MAX_ENTRIES {
+                let problem = eyre!(
+                    "stopped looking through the script libraries after {LIBRARY_MAX_ENTRIES} files"
+                );
+                report_scan_problem(tx, problem).await;
+                listings.push((dir_name, filenames));
+                return listings;
+            }
+
+            let Ok(file_type) = dir_entry.file_type().await else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if depth < LIBRARY_MAX_DEPTH {
+                    queue.push_back((dir_entry.path(), depth + 1));
+                }
+            } else if let Some(filename) = dir_entry.file_name().to_str() {
+                filenames.push(filename.to_owned());
+            }
+        }
+        if !filenames.is_empty() {
+            listings.push((dir_name, filenames));
+        }
+    }
+    listings
+}
+
+/// Given that the video has loaded, search for appropriate funscripts,
+/// telling the playtask what was found and loading the named cluster
+/// (or the 'main' cluster, for `None` or if there's no such cluster).
+/// The funscripts in `axis_funscripts` are used for their axes instead of any found.
+/// For a stream there's nowhere to search, so only those are loaded.
+///
+/// Returns how many axes funscripts were loaded (or made up) for.
+async fn search_for_funscripts(
+    video: NewVideo,
+    cluster: Option<String>,
+    axis_funscripts: BTreeMap<AxisKind, String>,
+    playback: PlaybackConfig,
+    tx: Sender<PlaythreadMessage>,
+) -> eyre::Result<usize> {
+    let (video_dir, video_filename) = match video {
+        NewVideo::File(video_path) => split_video_path(&video_path)?,
+        NewVideo::Stream { url, .. } => {
+            // Their paths are already whole, and the URL makes up motion as a name would
+            return load_cluster(
+                PathBuf::new(),
+                url,
+                FunscriptCluster::default(),
+                axis_funscripts,
+                playback,
+                tx,
+            )
+            .await;
+        }
+    };
+    let filenames = list_files(&video_dir, &tx)
+        .await
+        .with_context(|| format!("failed to list the files in {video_dir:?}"))?;
+    let mut listings = vec![(String::new(), filenames)];
+    for subdirectory in &playback.funscript_subdirectories {
+        match list_files(&video_dir.join(subdirectory), &tx).await {
+            Ok(filenames) => listings.push((subdirectory.clone(), filenames)),
+            Err(err)
+                if err
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|err| err.kind() == ErrorKind::NotFound) =>
+            {
+                debug!("not looking for funscripts in {subdirectory:?}: {err:?}");
+            }
+            Err(err) => {
+                let problem = err.wrap_err(format!("failed to list the files in {subdirectory:?}"));
+                report_scan_problem(&tx, problem).await;
+            }
+        }
+    }
+
+    let mut scan = scan_for_funscripts_in_dirs(&listings, &video_filename)
+        .context("failed funscript scan from list of filenames")?;
+
+    // Loose funscripts take precedence over those in archives
+    for archive_name in &listings[0].1 {
+        if is_funscript_archive(archive_name, &video_filename) {
+            scan.merge_missing(scan_archive(&video_dir, archive_name, &video_filename).await);
+        }
+    }
+
+    if playback.fuzzy_funscript_matching && scan.main.scripts.is_empty() {
+        let mut fuzzy_scan = scan_for_funscripts_in_dirs_fuzzy(&listings, &video_filename)
+            .context("failed fuzzy funscript scan from list of filenames")?;
+        // Keep any override clusters that did match exactly
+        fuzzy_scan.merge_missing(scan);
+        scan = fuzzy_scan;
+    }
+
+    if scan.main.scripts.is_empty() && !playback.script_library_dirs.is_empty() {
+        // This runs on the search task, so a new video cancels it like the rest of the search
+        let library_listings = list_libraries(playback.script_library_paths(), &tx).await;
+        let mut library_scan = scan_library(
+            &library_listings,
+            &video_filename,
+            playback.fuzzy_funscript_matching,
+        )
+        .context("failed funscript scan of the script libraries")?;
+        if !library_scan.main.scripts.is_empty() {
+            info!(
+                "found funscripts in the script libraries: {:?}",
+                library_scan.main.scripts
+            );
+        }
+        library_scan.merge_missing(scan);
+        scan = library_scan;
+    }
+
+    if scan.match_kind != MatchKind::Exact
+        && tx
+            .send_async(PlaythreadMessage::FuzzyMatch {
+                match_kind: scan.match_kind,
+                filenames: scan.main.scripts.values().cloned().collect(),
+            })
+            .await
+            .is_err()
+    {
+        warn!("found funscripts but failed to tell the playtask");
+    }
+
+    let cluster = cluster.filter(|name| scan.overrides.contains_key(name));
+    let to_load = scan.cluster(cluster.as_deref());
+    let found = FoundFunscripts {
+        video_dir: video_dir.clone(),
+        video_filename: video_filename.clone(),
+        scan,
+        cluster,
+    };
+    if tx
+        .send_async(PlaythreadMessage::FunscriptsFound(found))
+        .await
+        .is_err()
+    {
+        warn!("found funscripts but failed to tell the playtask");
+    }
+
+    load_cluster(
+        video_dir,
+        video_filename,
+        to_load,
+        axis_funscripts,
+        playback,
+        tx,
+    )
+    .await
+}
+
+/// Gets a loaded funscript ready to play on an axis, as a [`PlaythreadMessage::UseFunscript`].
+/// Also gives its actions as they are in the file, before resampling, to make up motion from.
+fn prepare_funscript(
+    axis_kind: AxisKind,
+    mut funscript: Funscript,
+    playback: &PlaybackConfig,
+    reload: bool,
+) -> (Vec<NormalisedAction>, PlaythreadMessage) {
+    funscript.fixup();
+    let problems = funscript.sanity_check(None);
+    let file_actions = normalised_from_funscript(&funscript);
+    let raw_actions = playback
+        .trace_raw_positions
+        .then(|| funscript.actions.clone());
+    let normalised_actions = match playback.resample_interval_ms {
+        Some(interval_ms) => {
+            resample_with_easing(&file_actions, interval_ms, playback.resample_easing)
+        }
+        None => file_actions.clone(),
+    };
+    let message = PlaythreadMessage::UseFunscript {
+        axis_kind,
+        normalised_actions,
+        problems,
+        raw_actions,
+        reload,
+    };
+    (file_actions, message)
+}
+
+/// Loads a funscript again as its file changed, and sends it to the playtask for its axis.
+async fn reload_funscript(
+    path: PathBuf,
+    axis_kind: AxisKind,
+    playback: PlaybackConfig,
+    tx: Sender<PlaythreadMessage>,
+) -> eyre::Result<()> {
+    let (Some(dir), Some(filename)) = (
+        path.parent(),
+        path.file_name().and_then(|name| name.to_str()),
+    ) else {
+        bail!("can't reload {path:?}: not a path to a file");
+    };
+    let funscript = load_funscript(dir, filename).await?;
+    let (_, message) = prepare_funscript(axis_kind, funscript, &playback, true);
+    if tx.send_async(message).await.is_err() {
+        warn!("reloaded funscript but failed to send to playtask");
+    }
+    Ok(())
+}
+
+/// The funscript files in use (of the cluster in use, or given for single axes),
+/// to watch for changes. Funscripts in archives or given as URLs aren't watched.
+fn watched_funscripts(
+    found: &FoundFunscripts,
+    axis_funscripts: &BTreeMap<AxisKind, String>,
+) -> Vec<(AxisKind, PathBuf)> {
+    let mut scripts = found.scan.cluster(found.cluster.as_deref()).scripts;
+    scripts.extend(axis_funscripts.clone());
+    scripts
+        .into_iter()
+        .filter(|(_, filename)| {
+            split_archive_path(&found.video_dir, filename).is_none() && !is_url(filename)
+        })
+        .map(|(axis_kind, filename)| (axis_kind, found.video_dir.join(filename)))
+        .collect()
+}
+
+/// Loads the funscripts of a cluster and sends them to the playtask, along with motion
+/// made up for any of `playback.synthesize_axes` that the cluster has no funscript for.
+///
+/// The funscripts in `axis_funscripts` take the place of the cluster's for their axes.
+/// As the user asked for them, if they can't be loaded the playtask is told, to show on the OSD,
+/// and their axes are left without a funscript. The same goes for the cluster's own funscripts,
+/// though their problems are gathered up with others finding the funscripts.
+///
+/// Returns how many axes funscripts were sent for.
+async fn load_cluster(
+    video_dir: PathBuf,
+    video_filename: String,
+    mut cluster: FunscriptCluster,
+    axis_funscripts: BTreeMap<AxisKind, String>,
+    playback: PlaybackConfig,
+    tx: Sender<PlaythreadMessage>,
+) -> eyre::Result<usize> {
+    cluster.scripts.extend(axis_funscripts.clone());
+    let mut axes = cluster.scripts.len();
+    let mut stroke_actions: Option<Vec<NormalisedAction>> = None;
+    for (&axis_kind, funscript_filename) in &cluster.scripts {
+        let funscript = match load_funscript(&video_dir, funscript_filename).await {
+            Ok(funscript) => funscript,
+            Err(err) if axis_funscripts.contains_key(&axis_kind) => {
+                axes -= 1;
+                let failed = PlaythreadMessage::FunscriptFailed {
+                    filename: funscript_filename.clone(),
+                    error: format!("{err:#}"),
+                };
+                if tx.send_async(failed).await.is_err() {
+                    warn!("failed to load funscript and failed to tell the playtask");
+                }
+                continue;
+            }
+            Err(err) => {
+                axes -= 1;
+                report_scan_problem(&tx, err).await;
+                continue;
+            }
+        };
+        let (file_actions, message) = prepare_funscript(axis_kind, funscript, &playback, false);
+        if axis_kind == AxisKind::Stroke {
+            stroke_actions = Some(file_actions);
+        }
+
+        if let Err(_) = tx.send_async(message).await {
+            warn!("loaded funscript but failed to send to playtask");
+        }
+    }
+
+    if let Some(stroke_actions) = stroke_actions {
+        // The same video always gets the same made-up motion
+        let params = SecondaryParams {
+            seed: video_seed(&video_filename),
+            ..SecondaryParams::default()
+        };
+        for &axis_kind in &playback.synthesize_axes {
+            if cluster.scripts.contains_key(&axis_kind) {
+                continue;
+            }
+            let normalised_actions = derive_secondary(&stroke_actions, axis_kind, &params);
+            axes += 1;
+            info!(
+                "synthesised {} actions for {axis_kind:?} from the stroke funscript",
+                normalised_actions.len()
+            );
+            if tx
+                .send_async(PlaythreadMessage::UseFunscript {
+                    axis_kind,
+                    normalised_actions,
+                    problems: Vec::new(),
+                    raw_actions: None,
+                    reload: false,
+                })
+                .await
+                .is_err()
+            {
+                warn!("synthesised funscript but failed to send to playtask");
+            }
+        }
+    }
+
+    Ok(axes)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        future::{ready, Ready},
+        path::PathBuf,
+        sync::Mutex,
+        time::Duration,
+    };
+
+    use eyre::eyre;
+    use serde_json::json;
+    use strokers::{
+        config::{PresetConfig, RootConfig},
+        core::{AxisId, AxisKind},
+        devices::debug::testing::{ChannelStroker, StrokerCommand},
+    };
+    use strokers_funscript::{processing::NormalisedAction, search_path::scan_for_funscripts};
+    use tokio::time::Instant;
+
+    use super::{
+        describe_held_axes, interpolation_period_ms, jumped_back_into_ab_loop, next_preset,
+        playtask, script_time, update_limits, watched_funscripts, FoundFunscripts, NewVideo,
+        PlaythreadMessage,
+    };
+    use crate::{
+        keybindings::{parse_action, KeyCommand},
+        mpv::testing::RecordingMpv,
+        playstate::{AxisLimiter, HOLD_MOVE_MS, SEEK_SETTLE},
+        script_opts::ScriptOpts,
+        writer::test::{SlowStroker, SLOW_MOVEMENT},
+    };
+
+    #[test]
+    fn test_cycle_clusters() {
+        let listing = [
+            "Video.funscript",
+            "Video.soft.funscript",
+            "Video (Hard).funscript",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+        let mut found = FoundFunscripts {
+            video_dir: PathBuf::from("/videos"),
+            video_filename: "Video.mp4".to_owned(),
+            scan: scan_for_funscripts(&listing, "Video.mp4").unwrap(),
+            cluster: None,
+        };
+        assert_eq!(
+            found.describe(),
+            "Funscripts: main (alternatives: hard, soft)"
+        );
+
+        let mut cycled = Vec::new();
+        for _ in 0..3 {
+            found.cluster = found.next_cluster();
+            cycled.push(found.cluster.clone());
+        }
+        assert_eq!(
+            cycled,
+            vec![Some("hard".to_owned()), Some("soft".to_owned()), None]
+        );
+
+        found.cluster = Some("soft".to_owned());
+        assert_eq!(
+            found.describe(),
+            "Funscripts: soft (alternatives: main, hard)"
+        );
+
+        // Without alternatives, cycling stays put
+        found.scan.overrides.clear();
+        found.cluster = None;
+        assert_eq!(found.next_cluster(), None);
+        assert_eq!(found.describe(), "Funscripts: main (no alternatives)");
+    }
+
+    #[test]
+    fn test_watched_funscripts() {
+        let listing = [
+            "Video.funscript",
+            "Video.twist.funscript",
+            "Video.soft.funscript",
+            "Video.funscript.zip/Video.roll.funscript",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+        let mut found = FoundFunscripts {
+            video_dir: PathBuf::from("/videos"),
+            video_filename: "Video.mp4".to_owned(),
+            scan: scan_for_funscripts(&listing, "Video.mp4").unwrap(),
+            cluster: None,
+        };
+        let axis_funscripts = BTreeMap::from([
+            (AxisKind::Twist, "/scripts/Twist.funscript".to_owned()),
+            (
+                AxisKind::Roll,
+                "https://example.com/Roll.funscript".to_owned(),
+            ),
+        ]);
+
+        // Those given for single axes are used instead,
+        // and those in archives or given as URLs aren't watched
+        assert_eq!(
+            watched_funscripts(&found, &axis_funscripts),
+            vec![
+                (AxisKind::Stroke, PathBuf::from("/videos/Video.funscript")),
+                (AxisKind::Twist, PathBuf::from("/scripts/Twist.funscript")),
+            ]
+        );
+
+        found.cluster = Some("soft".to_owned());
+        assert_eq!(
+            watched_funscripts(&found, &BTreeMap::new())[0],
+            (
+                AxisKind::Stroke,
+                PathBuf::from("/videos/Video.soft.funscript")
+            )
+        );
+    }
+
+    #[test]
+    fn test_script_time() {
+        assert_eq!(script_time(1000, 0), 1000);
+        assert_eq!(script_time(1000, 250), 1250);
+        assert_eq!(script_time(1000, -250), 750);
+        // Near the start, a negative offset holds the funscripts at their start
+        assert_eq!(script_time(100, -250), 0);
+        assert_eq!(script_time(u32::MAX - 10, 250), u32::MAX);
+    }
+
+    #[test]
+    fn test_jumped_back_into_ab_loop() {
+        let ab_loop = Some((2000, 5000));
+        // From B back to A
+        assert!(jumped_back_into_ab_loop(Some(5010), 2000, ab_loop));
+        // Playing on, or with no loop
+        assert!(!jumped_back_into_ab_loop(Some(2000), 2050, ab_loop));
+        assert!(!jumped_back_into_ab_loop(Some(5010), 2000, None));
+        assert!(!jumped_back_into_ab_loop(None, 2000, ab_loop));
+        // Seeking back to before the loop
+        assert!(!jumped_back_into_ab_loop(Some(4000), 1000, ab_loop));
+        // From just before B, as mpv's last report before looping can be
+        assert!(jumped_back_into_ab_loop(Some(4900), 2000, ab_loop));
+        // Further back than the time could wobble, from anywhere
+        assert!(jumped_back_into_ab_loop(Some(3500), 2000, ab_loop));
+        // But not a wobble in the middle of the loop
+        assert!(!jumped_back_into_ab_loop(Some(3000), 2990, ab_loop));
+    }
+
+    #[test]
+    fn test_describe_held_axes() {
+        let mut held_axes = BTreeMap::new();
+        assert_eq!(describe_held_axes(&held_axes), "No axes held");
+        held_axes.insert(AxisKind::Twist, 0.5);
+        held_axes.insert(AxisKind::Stroke, 0.25);
+        assert_eq!(
+            describe_held_axes(&held_axes),
+            "Held: Stroke at 0.25, Twist at 0.50"
+        );
+    }
+
+    #[test]
+    fn test_update_limits() {
+        let update = |limiter: &mut AxisLimiter, action: &str| {
+            let Ok(KeyCommand::AxisLimitChange(cmd)) = parse_action(action) else {
+                panic!("{action}");
+            };
+            update_limits(&cmd, limiter)
+        };
+        let mut limiter = AxisLimiter::new(1.0, 0.2, 0.8);
+
+        update(
+            &mut limiter,
+            "axis_limit axis=stroke&min_by=-0.1&max_by=0.3",
+        )
+        .unwrap();
+        assert!((limiter.min - 0.1).abs() < 1e-6 && limiter.max == 1.0);
+
+        // Nudged past each other, they meet
+        update(&mut limiter, "axis_limit axis=stroke&max_new=0.5").unwrap();
+        update(&mut limiter, "axis_limit axis=stroke&min_by=0.6").unwrap();
+        assert_eq!((limiter.min, limiter.max), (0.5, 0.5));
+
+        // Set past each other, nothing changes
+        assert!(update(
+            &mut limiter,
+            "axis_limit axis=stroke&min_new=0.9&max_new=0.1"
+        )
+        .is_err());
+        assert!(update(&mut limiter, "axis_limit axis=stroke&max_new=1.5").is_err());
+        assert_eq!((limiter.min, limiter.max), (0.5, 0.5));
+
+        assert!(!limiter.inverted);
+        update(&mut limiter, "axis_limit axis=stroke&invert=toggle").unwrap();
+        assert!(limiter.inverted);
+        update(
+            &mut limiter,
+            "axis_limit axis=stroke&invert=toggle&min_new=0.3",
+        )
+        .unwrap();
+        assert!(!limiter.inverted);
+        assert_eq!((limiter.min, limiter.max), (0.3, 0.5));
+        update(&mut limiter, "axis_limit axis=stroke&invert=on").unwrap();
+        update(&mut limiter, "axis_limit axis=stroke&invert=on").unwrap();
+        assert!(limiter.inverted);
+        assert!(parse_action("axis_limit axis=stroke&invert=maybe").is_err());
+    }
+
+    #[test]
+    fn test_next_preset() {
+        let presets: BTreeMap<String, PresetConfig> = ["normal", "full", "gentle"]
+            .into_iter()
+            .map(|name| (name.to_owned(), PresetConfig::new()))
+            .collect();
+        let next = |current| next_preset(&presets, current);
+        assert_eq!(next(None).as_deref(), Some("full"));
+        assert_eq!(next(Some("full")).as_deref(), Some("gentle"));
+        assert_eq!(next(Some("gentle")).as_deref(), Some("normal"));
+        assert_eq!(next(Some("normal")).as_deref(), Some("full"));
+        // e.g. a preset that's since been removed from the configuration
+        assert_eq!(next(Some("medium")).as_deref(), Some("normal"));
+        assert_eq!(next_preset(&BTreeMap::new(), Some("full")), None);
+    }
+
+    #[test]
+    fn test_interpolation_period() {
+        assert_eq!(interpolation_period_ms(None, None), None);
+        assert_eq!(interpolation_period_ms(Some(20.0), None), Some(50));
+        assert_eq!(interpolation_period_ms(Some(20.0), Some(30)), Some(50));
+        // Not more often than the stroker can take
+        assert_eq!(interpolation_period_ms(Some(100.0), Some(30)), Some(30));
+        assert_eq!(interpolation_period_ms(Some(0.0), None), None);
+        assert_eq!(interpolation_period_ms(Some(f32::NAN), None), None);
+    }
+
+    /// Connects to the given stroker, the first time only.
+    fn connect_once<S>(stroker: S) -> impl Fn() -> Ready<eyre::Result<S>> {
+        let stroker = Mutex::new(Some(stroker));
+        move || {
+            ready(
+                stroker
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .ok_or_else(|| eyre!("already connected")),
+            )
+        }
+    }
+
+    fn stroke_config() -> RootConfig {
+        serde_json::from_value(json!({
+            "stroker": {"type": "debug"},
+            "limits": {"stroke": {"speed": 10.0, "default_min": 0.0, "default_max": 1.0}},
+        }))
+        .unwrap()
+    }
+
+    /// A stream starting to play, with a funscript for the stroke axis (in the script options).
+    fn stream_starting(positions: &[(u32, f32)]) -> [PlaythreadMessage; 2] {
+        [
+            PlaythreadMessage::VideoStarting {
+                video: NewVideo::Stream {
+                    url: "https://example.com/video.mp4".to_owned(),
+                },
+                script_opts: ScriptOpts::default(),
+                working_directory: None,
+            },
+            PlaythreadMessage::UseFunscript {
+                axis_kind: AxisKind::Stroke,
+                normalised_actions: positions
+                    .iter()
+                    .map(|&(at, norm_pos)| NormalisedAction { at, norm_pos })
+                    .collect(),
+                problems: Vec::new(),
+                raw_actions: None,
+                reload: false,
+            },
+        ]
+    }
+
+    /// Plays a funscript through the whole playthread, as mpv's events come in.
+    #[tokio::test(start_paused = true)]
+    async fn test_playtask() {
+        let (stroker, commands) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let mpv = RecordingMpv::default();
+        let (tx, rx) = flume::unbounded();
+
+        let play = playtask(
+            connect_once(stroker),
+            stroke_config(),
+            rx,
+            tx.clone(),
+            mpv.clone(),
+        );
+        let events = async {
+            let send = |msg| tx.send(msg).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stream_starting(&[(0, 0.0), (1000, 1.0), (2000, 0.0)])
+                .into_iter()
+                .for_each(send);
+            for now_millis in [0, 500, 1000, 1500] {
+                send(PlaythreadMessage::TimeChange { now_millis });
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            send(PlaythreadMessage::PauseChange { paused: true });
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            send(PlaythreadMessage::Shutdown {});
+        };
+        let (result, ()) = tokio::join!(play, events);
+        result.unwrap();
+
+        assert!(mpv.take_commands().contains(
+            &["show-text", "Connected to ChannelStroker", "3000"]
+                .map(str::to_owned)
+                .to_vec()
+        ));
+
+        // Each action is headed for as the one before it is reached, arriving on time
+        let commands: Vec<_> = commands.drain().collect();
+        let started_at = match commands[0] {
+            StrokerCommand::Stop { at } | StrokerCommand::Movement { at, .. } => at,
+        };
+        let commands: Vec<(u128, Option<(f32, u32)>)> = commands
+            .into_iter()
+            .map(|command| match command {
+                StrokerCommand::Stop { at } => ((at - started_at).as_millis(), None),
+                StrokerCommand::Movement { at, movement } => (
+                    (at - started_at).as_millis(),
+                    Some((movement.target(), movement.ramp_time_milliseconds())),
+                ),
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                // Stopped until the stream's funscript loaded
+                (0, None),
+                // To the first action, as fast as the speed limit allows
+                (0, Some((0.49, 0))),
+                (500, Some((1.0, 500))),
+                (1000, Some((0.0, 1000))),
+                // Paused, then shut down
+                (2000, None),
+                (2010, None),
+            ]
+        );
+    }
+
+    /// An axis held before its funscript loads is moved to where it's held, and stays there.
+    #[tokio::test(start_paused = true)]
+    async fn test_hold_before_loading() {
+        let (stroker, commands) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let (tx, rx) = flume::unbounded();
+
+        let play = playtask(
+            connect_once(stroker),
+            stroke_config(),
+            rx,
+            tx.clone(),
+            RecordingMpv::default(),
+        );
+        let events = async {
+            let send = |msg| tx.send(msg).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let hold = parse_action("hold_axis axis=stroke&position=0.25").unwrap();
+            send(PlaythreadMessage::KeyCommand(hold));
+            let [video_starting, use_funscript] =
+                stream_starting(&[(0, 0.0), (1000, 1.0), (2000, 0.0)]);
+            send(video_starting);
+            send(PlaythreadMessage::TimeChange { now_millis: 0 });
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            commands.drain().for_each(drop);
+            send(use_funscript);
+            for now_millis in [10, 500, 1000, 1500] {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                send(PlaythreadMessage::TimeChange { now_millis });
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            send(PlaythreadMessage::Shutdown {});
+        };
+        let ((), result) = tokio::join!(events, play);
+        result.unwrap();
+
+        let movements: Vec<(f32, u32)> = commands
+            .drain()
+            .filter_map(StrokerCommand::into_movement)
+            .map(|(_, movement)| (movement.target(), movement.ramp_time_milliseconds()))
+            .collect();
+        assert_eq!(movements, vec![(0.25, HOLD_MOVE_MS)]);
+    }
+
+    /// Slowing down past `interpolate_below_speed` starts interpolating straight away,
+    /// and speeding up again heads straight for the next action.
+    #[tokio::test(start_paused = true)]
+    async fn test_interpolate_below_speed() {
+        let (stroker, commands) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let (tx, rx) = flume::unbounded();
+        let mut config = stroke_config();
+        config.playback.interpolate_below_speed = Some(0.75);
+
+        let play = playtask(
+            connect_once(stroker),
+            config,
+            rx,
+            tx.clone(),
+            RecordingMpv::default(),
+        );
+        let events = async {
+            let send = |msg| tx.send(msg).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stream_starting(&[(0, 0.0), (2000, 1.0), (4000, 0.0)])
+                .into_iter()
+                .for_each(send);
+            send(PlaythreadMessage::TimeChange { now_millis: 0 });
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            send(PlaythreadMessage::TimeChange { now_millis: 500 });
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            commands.drain().for_each(drop);
+
+            let slowed_at = Instant::now();
+            send(PlaythreadMessage::SpeedChange { speed: 0.5 });
+            tokio::time::sleep(Duration::from_millis(225)).await;
+            let slowed: Vec<StrokerCommand> = commands.drain().collect();
+
+            // Between interpolation steps, as mpv says where the video is
+            let sped_up_at = Instant::now();
+            send(PlaythreadMessage::TimeChange { now_millis: 620 });
+            send(PlaythreadMessage::SpeedChange { speed: 1.0 });
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let sped_up: Vec<StrokerCommand> = commands.drain().collect();
+            send(PlaythreadMessage::Shutdown {});
+            (slowed_at, slowed, sped_up_at, sped_up)
+        };
+        let (result, (slowed_at, slowed, sped_up_at, sped_up)) = tokio::join!(play, events);
+        result.unwrap();
+        // Moving on from where playback is straight away, 25 ms of the video every 50 ms
+        assert_eq!(slowed.len(), 5, "{slowed:?}");
+        assert!(
+            matches!(
+                &slowed[0],
+                StrokerCommand::Movement { at, movement }
+                    if *at == slowed_at
+                        && movement.target() == 0.2675
+                        && movement.ramp_time_milliseconds() == 50
+            ),
+            "{slowed:?}"
+        );
+        // Then straight for the next action, arriving on time
+        assert!(
+            matches!(
+                &sped_up[..],
+                [StrokerCommand::Movement { at, movement }]
+                    if *at == sped_up_at
+                        && movement.target() == 1.0
+                        && movement.ramp_time_milliseconds() == 1380
+            ),
+            "{sped_up:?}"
+        );
+    }
+
+    /// Pausing stops the stroker straight away, even as it struggles to keep up with movements.
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_stops_slow_stroker() {
+        let (stroker, commands) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let (tx, rx) = flume::unbounded();
+
+        let play = playtask(
+            connect_once(SlowStroker(stroker)),
+            stroke_config(),
+            rx,
+            tx.clone(),
+            RecordingMpv::default(),
+        );
+        let events = async {
+            let send = |msg| tx.send(msg).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            // Movements come twice as fast as the stroker takes them
+            let positions: Vec<(u32, f32)> =
+                (0..40).map(|idx| (idx * 50, (idx % 2) as f32)).collect();
+            stream_starting(&positions).into_iter().for_each(send);
+            for now_millis in (0..1000).step_by(50) {
+                send(PlaythreadMessage::TimeChange { now_millis });
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            let paused_at = Instant::now();
+            send(PlaythreadMessage::PauseChange { paused: true });
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            send(PlaythreadMessage::Shutdown {});
+            paused_at
+        };
+        let (result, paused_at) = tokio::join!(play, events);
+        result.unwrap();
+
+        // No later than the movement being written as the video paused,
+        // with those still waiting to be written dropped
+        let commands: Vec<StrokerCommand> = commands.drain().collect();
+        let stopped_at = commands
+            .iter()
+            .find_map(|command| match command {
+                StrokerCommand::Stop { at } if *at >= paused_at => Some(*at),
+                _ => None,
+            })
+            .expect("should have stopped");
+        assert!(stopped_at - paused_at <= SLOW_MOVEMENT, "{commands:?}");
+        let written_after_pausing = commands
+            .iter()
+            .filter(|command| {
+                matches!(command, StrokerCommand::Movement { at, .. } if *at >= paused_at)
+            })
+            .count();
+        assert!(written_after_pausing <= 1, "{commands:?}");
+    }
+
+    /// Dragging the seek bar moves each axis into place once, where the dragging stops.
+    #[tokio::test(start_paused = true)]
+    async fn test_seek_debounce() {
+        let (stroker, commands) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let (tx, rx) = flume::unbounded();
+
+        let play = playtask(
+            connect_once(stroker),
+            stroke_config(),
+            rx,
+            tx.clone(),
+            RecordingMpv::default(),
+        );
+        let events = async {
+            let send = |msg| tx.send(msg).unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            stream_starting(&[(0, 0.0), (500, 1.0), (5000, 0.0)])
+                .into_iter()
+                .for_each(send);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            commands.drain().for_each(drop);
+
+            // A burst of seeks, each followed by mpv reporting the time seeked to
+            let mut last_seek_at = Instant::now();
+            for idx in 0..30 {
+                last_seek_at = Instant::now();
+                send(PlaythreadMessage::Seek {
+                    now_millis: idx * 100,
+                });
+                send(PlaythreadMessage::TimeChange {
+                    now_millis: idx * 100,
+                });
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            tokio::time::sleep(SEEK_SETTLE * 2).await;
+            let dragged: Vec<StrokerCommand> = commands.drain().collect();
+
+            // Playing on from the time seeked to settles straight away
+            let seeked_at = Instant::now();
+            send(PlaythreadMessage::Seek { now_millis: 1000 });
+            send(PlaythreadMessage::TimeChange { now_millis: 1020 });
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            let played_on: Vec<StrokerCommand> = commands.drain().collect();
+            send(PlaythreadMessage::Shutdown {});
+            (last_seek_at, dragged, seeked_at, played_on)
+        };
+        let (result, (last_seek_at, dragged, seeked_at, played_on)) = tokio::join!(play, events);
+        result.unwrap();
+
+        // Into place once, at the last time seeked to, once the seeking settled
+        assert!(
+            matches!(
+                &dragged[..],
+                [StrokerCommand::Movement { at, movement }]
+                    if *at == last_seek_at + SEEK_SETTLE
+                        && movement.target() == 0.0
+                        && movement.ramp_time_milliseconds() == 2100
+            ),
+            "{dragged:?}"
+        );
+        assert!(
+            matches!(
+                &played_on[..],
+                [StrokerCommand::Movement { at, .. }] if *at == seeked_at
+            ),
+            "{played_on:?}"
+        );
+    }
 }
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// How long to gather problems for before showing them, so that a burst of them
+/// (e.g. a directory full of broken funscripts) is shown as one summary.
+const GATHER_DURATION: Duration = Duration::from_millis(500);
+
+/// How long problems are shown on the OSD for. Any more wait until they've been shown.
+pub const SHOW_DURATION: Duration = Duration::from_secs(5);
+
+/// How many problems to list on the OSD at most; the rest are only counted.
+const MAX_LISTED: usize = 3;
+
+/// Problems finding or loading funscripts, gathered up to show on the OSD now and then
+/// (they're logged in full as they happen).
+pub(crate) struct ScanProblems {
+    pending: Vec<String>,
+    /// When to show the pending problems, if there are any
+    show_at: Option<Instant>,
+    /// Until when the problems last shown are on the OSD
+    shown_until: Instant,
+}
+
+impl ScanProblems {
+    pub fn new() -> ScanProblems {
+        ScanProblems {
+            pending: Vec::new(),
+            show_at: None,
+            shown_until: Instant::now(),
+        }
+    }
+
+    /// Adds a problem, to show shortly along with any others that come with it.
+    pub fn add(&mut self, message: String) {
+        self.pending.push(message);
+        if self.show_at.is_none() {
+            self.show_at = Some((Instant::now() + GATHER_DURATION).max(self.shown_until));
+        }
+    }
+
+    /// Waits until it's time to show the problems, or forever if there are none.
+    pub async fn next_show(&self) {
+        match self.show_at {
+            Some(show_at) => tokio::time::sleep_until(show_at).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// The problems gathered, summed up to show on the OSD (for [`SHOW_DURATION`]).
+    pub fn take_summary(&mut self) -> Option<String> {
+        self.show_at = None;
+        if self.pending.is_empty() {
+            return None;
+        }
+        self.shown_until = Instant::now() + SHOW_DURATION;
+        let problems = std::mem::take(&mut self.pending);
+        if let [problem] = &problems[..] {
+            return Some(format!("Problem finding funscripts: {problem}"));
+        }
+        let mut summary = format!("{} problems finding funscripts:", problems.len());
+        for problem in problems.iter().take(MAX_LISTED) {
+            summary.push('\n');
+            summary.push_str(problem);
+        }
+        if problems.len() > MAX_LISTED {
+            summary.push_str(&format!(
+                "\n…and {} more (see the log)",
+                problems.len() - MAX_LISTED
+            ));
+        }
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::time::Instant;
+
+    use super::{ScanProblems, GATHER_DURATION, SHOW_DURATION};
+
+    #[tokio::test]
+    async fn test_summary() {
+        let mut problems = ScanProblems::new();
+        assert_eq!(problems.take_summary(), None);
+        problems.add("failed to load Video.funscript: bad JSON".to_owned());
+        assert_eq!(
+            problems.take_summary().as_deref(),
+            Some("Problem finding funscripts: failed to load Video.funscript: bad JSON")
+        );
+
+        // A burst is gathered into one summary
+        let mut problems = ScanProblems::new();
+        let started = Instant::now();
+        for idx in 0..50 {
+            problems.add(format!("failed to load {idx}.funscript"));
+        }
+        problems.next_show().await;
+        assert!(started.elapsed() >= GATHER_DURATION);
+        assert_eq!(
+            problems.take_summary().as_deref(),
+            Some(
+                "50 problems finding funscripts:\nfailed to load 0.funscript\n\
+                 failed to load 1.funscript\nfailed to load 2.funscript\n…and 47 more (see the log)"
+            )
+        );
+        assert_eq!(problems.take_summary(), None);
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let mut problems = ScanProblems::new();
+        problems.add("first".to_owned());
+        problems.take_summary().unwrap();
+
+        // More whilst that's still on the OSD wait until it's gone
+        let shown_at = Instant::now();
+        problems.add("second".to_owned());
+        assert!(problems.show_at.unwrap() >= shown_at + SHOW_DURATION - GATHER_DURATION);
+    }
+}
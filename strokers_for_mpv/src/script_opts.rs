@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+use mpv_client::Node;
+use serde::{de::IntoDeserializer, Deserialize};
+use strokers::core::AxisKind;
+use tracing::warn;
+
+/// The prefix of our options in mpv's `script-opts`, e.g. `strokers-funscript_path_twist`.
+pub const OPTION_PREFIX: &str = "strokers-";
+
+/// Prefix of the options giving the funscript for a single axis,
+/// e.g. `funscript_path_twist`, relative to the video's directory.
+pub const OPT_AXIS_FUNSCRIPT_PATH_PREFIX: &str = "funscript_path_";
+
+/// Our options from mpv's `script-opts`
+/// (e.g. `--script-opts=strokers-funscript_path_twist=x.funscript`), without their prefix.
+pub type ScriptOpts = BTreeMap<String, String>;
+
+/// Picks our options out of the `script-opts` property, read as the map that mpv keeps it as
+/// (rather than as text, where values with commas in can't be told apart from the next option).
+///
+/// Options without our prefix belong to other scripts and are left out.
+pub fn script_opts_from_node(node: Node) -> ScriptOpts {
+    let Node::Map(entries) = node else {
+        warn!("ignoring script options that aren't a map: {node:?}");
+        return ScriptOpts::new();
+    };
+    entries
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let key = key.strip_prefix(OPTION_PREFIX)?.to_owned();
+            match value {
+                Node::String(value) => Some((key, value)),
+                other => {
+                    warn!("ignoring script option {OPTION_PREFIX}{key}: not text but {other:?}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// The funscript given for each axis with `funscript_path_<axis>` options,
+/// where `<axis>` is as in the configuration, e.g. `twist`.
+///
+/// Options for axes that don't exist are warned about and left out.
+pub fn axis_funscript_paths(opts: &ScriptOpts) -> BTreeMap<AxisKind, String> {
+    opts.iter()
+        .filter_map(|(key, path)| {
+            let axis_name = key.strip_prefix(OPT_AXIS_FUNSCRIPT_PATH_PREFIX)?;
+            let parsed: Result<AxisKind, serde::de::value::Error> =
+                AxisKind::deserialize(axis_name.into_deserializer());
+            match parsed {
+                Ok(axis_kind) => Some((axis_kind, path.clone())),
+                Err(err) => {
+                    warn!("ignoring script option {OPTION_PREFIX}{key}: {err}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use mpv_client::Node;
+    use strokers::core::AxisKind;
+
+    use super::{axis_funscript_paths, script_opts_from_node};
+
+    fn node_map(entries: &[(&str, &str)]) -> Node {
+        Node::Map(
+            entries
+                .iter()
+                .map(|&(key, value)| (key.to_owned(), Node::String(value.to_owned())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_script_opts_from_node() {
+        let opts = script_opts_from_node(node_map(&[
+            ("osc-layout", "bottombar"),
+            ("strokers-funscript_path_twist", "/scripts/a=b,c.funscript"),
+            ("strokers-flag", ""),
+            ("other-strokers-thing", "1"),
+        ]));
+        assert_eq!(
+            opts.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("flag".to_owned(), "".to_owned()),
+                (
+                    "funscript_path_twist".to_owned(),
+                    "/scripts/a=b,c.funscript".to_owned()
+                ),
+            ]
+        );
+        assert!(script_opts_from_node(node_map(&[])).is_empty());
+        assert!(script_opts_from_node(Node::String("strokers-flag=".to_owned())).is_empty());
+    }
+
+    #[test]
+    fn test_axis_funscript_paths() {
+        let opts = script_opts_from_node(node_map(&[
+            ("strokers-funscript_path_twist", "b.funscript"),
+            ("strokers-funscript_path_roll", "/c.funscript"),
+            ("strokers-funscript_path_wobble", "d.funscript"),
+        ]));
+        assert_eq!(
+            axis_funscript_paths(&opts).into_iter().collect::<Vec<_>>(),
+            vec![
+                (AxisKind::Twist, "b.funscript".to_owned()),
+                (AxisKind::Roll, "/c.funscript".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use std::{fmt::Write, time::Duration};
+
+use serde::Serialize;
+use strokers::core::AxisKind;
+use strokers_funscript::playstate::PlaystateStatus;
+use tokio::time::{Interval, MissedTickBehavior};
+use tracing::error;
+
+use crate::mpv::Mpv;
+
+/// Our ID for the overlay, as given to mpv's `osd-overlay` command
+/// (the heatmap's is `1`).
+const OVERLAY_ID: &str = "2";
+
+/// Size of the canvas that the overlay is drawn on, which mpv scales to fit the window.
+const CANVAS_WIDTH: u32 = 1280;
+const CANVAS_HEIGHT: u32 = 720;
+
+/// How often the page is brought up to date whilst it's shown.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the report is broadcast to other scripts as, with `status_broadcast_interval_ms`.
+const BROADCAST_MESSAGE: &str = "strokers-status";
+
+/// A snapshot of everything going on, for the status page,
+/// and as JSON for other scripts (see [`StatusBroadcast`]).
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct StatusReport {
+    pub connected: bool,
+    /// What the stroker says it is, if it's connected and says
+    pub description: Option<String>,
+    /// Whether the user has the stroker enabled
+    pub enabled: bool,
+    /// What's going on instead of following the funscripts, if anything,
+    /// e.g. calibrating the latency
+    pub mode: Option<String>,
+    /// The preset of limits switched to, if any
+    pub preset: Option<String>,
+    /// Whether funscripts were found for the video
+    pub funscripts_found: bool,
+    /// The cluster of funscripts in use, if they were found, with `None` for the main one
+    pub cluster: Option<String>,
+    pub sync_offset_ms: i32,
+    pub latency_ms: i32,
+    pub axes: Vec<AxisStatusReport>,
+}
+
+/// A snapshot of one of the stroker's axes, for the status page.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct AxisStatusReport {
+    pub axis_kind: AxisKind,
+    /// Progress through the axis's funscript and what it's been doing, if one is loaded
+    pub playing: Option<AxisPlayingReport>,
+    /// Where the axis is held still at the user's request, if it is
+    pub held: Option<f32>,
+}
+
+/// What an axis with a funscript is doing, for the status page.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct AxisPlayingReport {
+    pub progress: PlaystateStatus,
+    pub min: f32,
+    pub max: f32,
+    pub speed_limit: f32,
+    pub inverted: bool,
+    /// Where the axis was last told to go
+    pub last_commanded: f32,
+}
+
+impl StatusReport {
+    /// The report as lines of text.
+    pub fn render(&self) -> String {
+        let mut text = match (self.connected, &self.description) {
+            (true, Some(description)) => format!("Stroker: {description}"),
+            (true, None) => "Stroker: connected".to_owned(),
+            (false, _) => "Stroker: not connected (yet)".to_owned(),
+        };
+        if !self.enabled {
+            text.push_str(" (disabled)");
+        }
+        if let Some(mode) = &self.mode {
+            let _ = write!(text, "\n{mode}");
+        }
+
+        let cluster = match (self.funscripts_found, &self.cluster) {
+            (true, Some(name)) => name.as_str(),
+            (true, None) => "main",
+            (false, _) => "none",
+        };
+        let _ = write!(
+            text,
+            "\nFunscripts: {cluster}, preset: {}, sync offset: {:+} ms, latency: {} ms",
+            self.preset.as_deref().unwrap_or("none"),
+            self.sync_offset_ms,
+            self.latency_ms
+        );
+
+        for axis in &self.axes {
+            let _ = write!(text, "\n{:?}: ", axis.axis_kind);
+            match &axis.playing {
+                Some(playing) => {
+                    let progress = &playing.progress;
+                    let _ = write!(
+                        text,
+                        "{}/{} actions{}, limits {:.2}–{:.2} at {:.2}/s{}, last sent {:.2}",
+                        progress.index,
+                        progress.action_count,
+                        if progress.finished { " (finished)" } else { "" },
+                        playing.min,
+                        playing.max,
+                        playing.speed_limit,
+                        if playing.inverted { " (inverted)" } else { "" },
+                        playing.last_commanded
+                    );
+                }
+                None => text.push_str("no funscript"),
+            }
+            if let Some(position) = axis.held {
+                let _ = write!(text, ", held at {position:.2}");
+            }
+        }
+        text
+    }
+}
+
+/// The status page: the [`StatusReport`] drawn in a corner of the OSD, kept up to date
+/// whilst it's shown.
+pub(crate) struct StatusPage {
+    /// When to bring the page up to date, whilst it's shown
+    refresh: Option<Interval>,
+}
+
+impl StatusPage {
+    pub fn new() -> StatusPage {
+        StatusPage { refresh: None }
+    }
+
+    /// Shows the page if it's hidden (drawn at the next refresh, straight away),
+    /// or hides it if it's shown. Returns whether it's now shown.
+    pub fn toggle(&mut self, client: &mut impl Mpv) -> bool {
+        if self.refresh.take().is_some() {
+            if let Err(err) = client.command(["osd-overlay", OVERLAY_ID, "none", ""]) {
+                error!("Failed to remove status page: {err:?}");
+            }
+            return false;
+        }
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+        refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self.refresh = Some(refresh);
+        true
+    }
+
+    /// Waits until the page is due to be brought up to date, or forever if it's hidden.
+    pub async fn next_refresh(&mut self) {
+        match self.refresh.as_mut() {
+            Some(refresh) => {
+                refresh.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Draws the page with the given report.
+    pub fn draw(&mut self, client: &mut impl Mpv, report: &StatusReport) {
+        if self.refresh.is_none() {
+            return;
+        }
+        let events = format!(
+            "{{\\an7\\pos(24,24)\\fs18\\bord1.5}}{}",
+            escape_ass(&report.render())
+        );
+        if let Err(err) = client.command([
+            "osd-overlay".to_owned(),
+            OVERLAY_ID.to_owned(),
+            "ass-events".to_owned(),
+            events,
+            CANVAS_WIDTH.to_string(),
+            CANVAS_HEIGHT.to_string(),
+        ]) {
+            error!("Failed to draw status page: {err:?}");
+        }
+    }
+}
+
+/// Sends the [`StatusReport`] as JSON to other scripts in `script-message`s: to those that ask
+/// for it with `get-status`, and to any listening every `status_broadcast_interval_ms`, if set.
+pub(crate) struct StatusBroadcast {
+    /// When to broadcast the report, if it's broadcast regularly
+    interval: Option<Interval>,
+    /// Whether it's time to broadcast the report
+    due: bool,
+    /// What to reply to requests for the report with, once it's next made
+    reply_to: Vec<String>,
+}
+
+impl StatusBroadcast {
+    pub fn new(interval_ms: Option<u32>) -> StatusBroadcast {
+        let interval = interval_ms.filter(|&ms| ms > 0).map(|ms| {
+            let mut interval = tokio::time::interval(Duration::from_millis(ms.into()));
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        StatusBroadcast {
+            interval,
+            due: false,
+            reply_to: Vec::new(),
+        }
+    }
+
+    /// Another script asked for the report, to be sent as `script-message <reply_to> <json>`.
+    pub fn request(&mut self, reply_to: String) {
+        self.reply_to.push(reply_to);
+    }
+
+    /// Waits until the report is due to be sent: straight away if it's been asked for,
+    /// or when it's next to be broadcast, or forever if neither.
+    pub async fn next_due(&mut self) {
+        if !self.reply_to.is_empty() {
+            return;
+        }
+        match self.interval.as_mut() {
+            Some(interval) => {
+                interval.tick().await;
+                self.due = true;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Sends the report to whoever asked for it, and broadcasts it if that's due.
+    pub fn send(&mut self, client: &mut impl Mpv, report: &StatusReport) {
+        let recipients: Vec<String> = std::mem::take(&mut self.reply_to)
+            .into_iter()
+            .chain(std::mem::take(&mut self.due).then(|| BROADCAST_MESSAGE.to_owned()))
+            .collect();
+        if recipients.is_empty() {
+            return;
+        }
+        let json = match serde_json::to_string(report) {
+            Ok(json) => json,
+            Err(err) => {
+                error!("Failed to serialise status report: {err:?}");
+                return;
+            }
+        };
+        for recipient in recipients {
+            if let Err(err) = client.command(["script-message", &recipient, &json]) {
+                error!("Failed to send status report to {recipient:?}: {err:?}");
+            }
+        }
+    }
+}
+
+/// Text to show as it is in ASS, which would otherwise take `{...}` and `\` as formatting.
+fn escape_ass(text: &str) -> String {
+    text.replace('\\', "\\\u{feff}")
+        .replace('{', "\\{")
+        .replace('\n', "\\N")
+}
+
+#[cfg(test)]
+mod test {
+    use strokers::core::AxisKind;
+    use strokers_funscript::playstate::PlaystateStatus;
+
+    use super::{escape_ass, AxisPlayingReport, AxisStatusReport, StatusReport};
+
+    fn example_report() -> StatusReport {
+        StatusReport {
+            connected: true,
+            description: Some("T-Code device on /dev/ttyUSB0".to_owned()),
+            enabled: true,
+            mode: None,
+            preset: Some("gentle".to_owned()),
+            funscripts_found: true,
+            cluster: Some("soft".to_owned()),
+            sync_offset_ms: 50,
+            latency_ms: 0,
+            axes: vec![
+                AxisStatusReport {
+                    axis_kind: AxisKind::Stroke,
+                    playing: Some(AxisPlayingReport {
+                        progress: PlaystateStatus {
+                            index: 120,
+                            action_count: 500,
+                            fraction_complete: 0.24,
+                            previous_at: Some(60_000),
+                            next_at: Some(60_500),
+                            finished: false,
+                        },
+                        min: 0.1,
+                        max: 0.9,
+                        speed_limit: 2.5,
+                        inverted: true,
+                        last_commanded: 0.42,
+                    }),
+                    held: None,
+                },
+                AxisStatusReport {
+                    axis_kind: AxisKind::Twist,
+                    playing: None,
+                    held: Some(0.5),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            example_report().render(),
+            "Stroker: T-Code device on /dev/ttyUSB0\n\
+             Funscripts: soft, preset: gentle, sync offset: +50 ms, latency: 0 ms\n\
+             Stroke: 120/500 actions, limits 0.10–0.90 at 2.50/s (inverted), last sent 0.42\n\
+             Twist: no funscript, held at 0.50"
+        );
+
+        let report = StatusReport {
+            mode: Some("Inactive: no funscripts for this video".to_owned()),
+            ..StatusReport::default()
+        };
+        assert_eq!(
+            report.render(),
+            "Stroker: not connected (yet) (disabled)\n\
+             Inactive: no funscripts for this video\n\
+             Funscripts: none, preset: none, sync offset: +0 ms, latency: 0 ms"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json: serde_json::Value = serde_json::to_value(example_report()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "connected": true,
+                "description": "T-Code device on /dev/ttyUSB0",
+                "enabled": true,
+                "mode": null,
+                "preset": "gentle",
+                "funscripts_found": true,
+                "cluster": "soft",
+                "sync_offset_ms": 50,
+                "latency_ms": 0,
+                "axes": [
+                    {
+                        "axis_kind": "stroke",
+                        "playing": {
+                            "progress": {
+                                "index": 120,
+                                "action_count": 500,
+                                "fraction_complete": 0.24f32,
+                                "previous_at": 60_000,
+                                "next_at": 60_500,
+                                "finished": false,
+                            },
+                            "min": 0.1f32,
+                            "max": 0.9f32,
+                            "speed_limit": 2.5,
+                            "inverted": true,
+                            "last_commanded": 0.42f32,
+                        },
+                        "held": null,
+                    },
+                    {
+                        "axis_kind": "twist",
+                        "playing": null,
+                        "held": 0.5,
+                    },
+                ],
+            })
+        );
+
+        // Nothing going on yet
+        let json = serde_json::to_string(&StatusReport::default()).unwrap();
+        assert_eq!(
+            json,
+            "{\"connected\":false,\"description\":null,\"enabled\":false,\"mode\":null,\
+             \"preset\":null,\"funscripts_found\":false,\"cluster\":null,\
+             \"sync_offset_ms\":0,\"latency_ms\":0,\"axes\":[]}"
+        );
+    }
+
+    #[test]
+    fn test_escape_ass() {
+        assert_eq!(
+            escape_ass("Stroker: {weird} \\name\nnext"),
+            "Stroker: \\{weird} \\\u{feff}name\\Nnext"
+        );
+    }
+}
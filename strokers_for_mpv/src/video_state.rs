@@ -0,0 +1,354 @@
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use strokers::config::PresetConfig;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// How long after the last adjustment to wait before saving, so that holding down a key
+/// doesn't write the file over and over.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Videos not played for this long are forgotten.
+const MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// How many videos to remember at most; those played longest ago are forgotten first.
+const MAX_VIDEOS: usize = 1000;
+
+/// What the user chose whilst playing a video, to go back to when it's played again.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoState {
+    /// The limits of each axis, as adjusted for the video
+    #[serde(default)]
+    pub limits: PresetConfig,
+
+    /// How far ahead of the video the funscripts were played
+    #[serde(default)]
+    pub sync_offset_ms: i32,
+
+    /// The override cluster in use, or `None` for the main cluster
+    #[serde(default)]
+    pub cluster: Option<String>,
+
+    /// When the video was last played, in seconds since the Unix epoch
+    #[serde(default)]
+    pub last_played: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct VideoStateFile {
+    /// By [`video_key`]
+    #[serde(default)]
+    videos: BTreeMap<String, VideoState>,
+
+    /// The stroker's latency, as found by calibrating, which goes for every video
+    #[serde(default)]
+    latency_ms: Option<i32>,
+}
+
+/// The state of each video played, kept in a file, with changes saved shortly after they're made.
+pub(crate) struct VideoStates {
+    path: PathBuf,
+    file: VideoStateFile,
+    /// When to save the changes made since the last save, if there are any
+    save_at: Option<Instant>,
+}
+
+impl VideoStates {
+    /// Loads the state of the videos from the file, if there is one yet.
+    ///
+    /// If the file can't be read or makes no sense, the videos' state is started afresh
+    /// rather than getting in the way of playback.
+    pub async fn load(path: PathBuf) -> VideoStates {
+        let file = match tokio::fs::read(&path).await {
+            Ok(contents) => parse_file(&path, &contents),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("no video state file at {path:?} yet");
+                VideoStateFile::default()
+            }
+            Err(err) => {
+                warn!("failed to read video state file {path:?}, so starting afresh: {err}");
+                VideoStateFile::default()
+            }
+        };
+        info!(
+            "remembering the state of videos in {path:?} ({} so far)",
+            file.videos.len()
+        );
+        VideoStates {
+            path,
+            file,
+            save_at: None,
+        }
+    }
+
+    /// The state the video was left in, if it's been played before.
+    pub fn get(&self, video_path: &Path) -> Option<&VideoState> {
+        self.file.videos.get(&video_key(video_path))
+    }
+
+    /// Remembers the state of the video, saving it shortly.
+    pub fn set(&mut self, video_path: &Path, state: VideoState) {
+        self.file.videos.insert(video_key(video_path), state);
+        self.save_at = Some(Instant::now() + SAVE_DELAY);
+    }
+
+    /// The stroker's latency, if it's been calibrated.
+    pub fn latency_ms(&self) -> Option<i32> {
+        self.file.latency_ms
+    }
+
+    /// Remembers the stroker's latency, as found by calibrating, saving it shortly.
+    pub fn set_latency_ms(&mut self, latency_ms: i32) {
+        self.file.latency_ms = Some(latency_ms);
+        self.save_at = Some(Instant::now() + SAVE_DELAY);
+    }
+
+    /// Waits until it's time to save the changes, or forever if there are none.
+    pub async fn next_save(&self) {
+        match self.save_at {
+            Some(save_at) => tokio::time::sleep_until(save_at).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Saves any changes now, e.g. as the video ends, forgetting videos not played for a long time.
+    pub async fn save_now(&mut self) -> eyre::Result<()> {
+        if self.save_at.take().is_none() {
+            return Ok(());
+        }
+        self.file.prune(unix_time());
+        let json = serde_json::to_vec_pretty(&self.file).context("failed to serialise")?;
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("failed to create {dir:?}"))?;
+        }
+        // Written in full alongside first, so that a crash can't leave half a file
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        tokio::fs::write(&temp_path, json)
+            .await
+            .with_context(|| format!("failed to write {temp_path:?}"))?;
+        tokio::fs::rename(&temp_path, &self.path)
+            .await
+            .with_context(|| format!("failed to replace {:?}", self.path))?;
+        debug!(
+            "saved the state of {} videos to {:?}",
+            self.file.videos.len(),
+            self.path
+        );
+        Ok(())
+    }
+}
+
+impl VideoStateFile {
+    /// Forgets videos not played in [`MAX_AGE_SECS`] and, past [`MAX_VIDEOS`],
+    /// those played longest ago.
+    fn prune(&mut self, now: u64) {
+        self.videos
+            .retain(|_, state| now.saturating_sub(state.last_played) <= MAX_AGE_SECS);
+        if self.videos.len() <= MAX_VIDEOS {
+            return;
+        }
+        let mut last_played: Vec<u64> = self
+            .videos
+            .values()
+            .map(|state| state.last_played)
+            .collect();
+        last_played.sort_unstable_by(|a, b| b.cmp(a));
+        let oldest_kept = last_played[MAX_VIDEOS - 1];
+        self.videos
+            .retain(|_, state| state.last_played >= oldest_kept);
+        // Videos played at the same moment as the oldest kept may still be too many
+        while self.videos.len() > MAX_VIDEOS {
+            let key = self
+                .videos
+                .iter()
+                .find(|(_, state)| state.last_played == oldest_kept)
+                .map(|(key, _)| key.clone())
+                .expect("too many videos, so some were played at the same moment");
+            self.videos.remove(&key);
+        }
+    }
+}
+
+/// Reads the contents of the video state file, starting afresh if they're no good.
+fn parse_file(path: &Path, contents: &[u8]) -> VideoStateFile {
+    match serde_json::from_slice(contents) {
+        Ok(file) => file,
+        Err(err) => {
+            warn!("video state file {path:?} is corrupt, so starting afresh: {err}");
+            VideoStateFile::default()
+        }
+    }
+}
+
+/// The key a video's state is kept under: a hash of its path, so that the file doesn't
+/// list what's been watched. Always the same for the same path, unlike [`std::hash::Hash`].
+fn video_key(video_path: &Path) -> String {
+    format!("{:016x}", fnv1a(video_path.as_os_str().as_encoded_bytes()))
+}
+
+/// The 64-bit FNV-1a hash of the bytes, which (unlike [`std::hash::DefaultHasher`])
+/// is the same across runs and versions of Rust.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Seconds since the Unix epoch, as [`VideoState::last_played`] is kept in.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use strokers::{config::PresetLimitsConfig, core::AxisKind};
+
+    use super::{parse_file, video_key, VideoState, VideoStateFile, VideoStates, MAX_AGE_SECS};
+
+    fn played_at(last_played: u64) -> VideoState {
+        VideoState {
+            last_played,
+            ..VideoState::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut file = VideoStateFile::default();
+        file.videos.insert(
+            video_key(Path::new("/videos/Video.mp4")),
+            VideoState {
+                limits: [(
+                    AxisKind::Stroke,
+                    PresetLimitsConfig {
+                        speed: Some(2.5),
+                        min: Some(0.1),
+                        max: Some(0.75),
+                        invert: Some(true),
+                    },
+                )]
+                .into(),
+                sync_offset_ms: -150,
+                cluster: Some("soft".to_owned()),
+                last_played: 1_700_000_000,
+            },
+        );
+        file.latency_ms = Some(80);
+        let json = serde_json::to_vec(&file).unwrap();
+        assert_eq!(parse_file(Path::new("test.json"), &json), file);
+
+        // Anything left out takes its default
+        let file = parse_file(
+            Path::new("test.json"),
+            br#"{"videos": {"0123": {"sync_offset_ms": 20}}}"#,
+        );
+        assert_eq!(
+            file.videos["0123"],
+            VideoState {
+                sync_offset_ms: 20,
+                ..VideoState::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_corrupt_file() {
+        for contents in [&b"{\"videos\": {"[..], b"", b"[1, 2, 3]", b"\xff\xfe"] {
+            assert_eq!(
+                parse_file(Path::new("test.json"), contents),
+                VideoStateFile::default()
+            );
+        }
+    }
+
+    #[test]
+    fn test_video_key() {
+        let key = video_key(Path::new("/videos/Video.mp4"));
+        assert_eq!(key.len(), 16);
+        assert_eq!(key, video_key(Path::new("/videos/Video.mp4")));
+        assert_ne!(key, video_key(Path::new("/videos/Video 2.mp4")));
+        // Empty input gives the FNV offset basis
+        assert_eq!(video_key(Path::new("")), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn test_prune() {
+        let now = 2 * MAX_AGE_SECS;
+        let mut file = VideoStateFile::default();
+        file.videos.insert("old".to_owned(), played_at(1));
+        file.videos.insert("recent".to_owned(), played_at(now - 60));
+        file.prune(now);
+        assert_eq!(file.videos.keys().collect::<Vec<_>>(), vec!["recent"]);
+
+        // Too many: those played longest ago go first
+        let mut file = VideoStateFile::default();
+        for idx in 0..1010 {
+            file.videos
+                .insert(format!("{idx:04}"), played_at(now - 1010 + idx));
+        }
+        file.prune(now);
+        assert_eq!(file.videos.len(), 1000);
+        assert!(!file.videos.contains_key("0009"));
+        assert!(file.videos.contains_key("0010"));
+
+        // Even if many were played at the same moment
+        let mut file = VideoStateFile::default();
+        for idx in 0..1010 {
+            file.videos.insert(format!("{idx:04}"), played_at(now));
+        }
+        file.prune(now);
+        assert_eq!(file.videos.len(), 1000);
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "strokers_video_state_{}/video_state.json",
+            std::process::id()
+        ));
+        let video = Path::new("/videos/Video.mp4");
+        let state = VideoState {
+            sync_offset_ms: 100,
+            last_played: super::unix_time(),
+            ..VideoState::default()
+        };
+
+        let mut states = VideoStates::load(path.clone()).await;
+        assert!(states.get(video).is_none());
+        states.set(video, state.clone());
+        states.save_now().await.unwrap();
+        assert_eq!(
+            VideoStates::load(path.clone()).await.get(video),
+            Some(&state)
+        );
+
+        // A corrupt file is started afresh, and replaced on the next save
+        tokio::fs::write(&path, "not json").await.unwrap();
+        let mut states = VideoStates::load(path.clone()).await;
+        assert!(states.get(video).is_none());
+        states.set(video, state.clone());
+        states.save_now().await.unwrap();
+        assert_eq!(
+            VideoStates::load(path.clone()).await.get(video),
+            Some(&state)
+        );
+
+        tokio::fs::remove_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+use eyre::Context;
+use flume::Receiver;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use strokers::core::AxisKind;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// How long to wait after a funscript last changed before reloading it,
+/// as editors often save in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches the funscript files in use, to reload them as they're saved, e.g. from an editor.
+///
+/// The directories they're in are watched rather than the files themselves, and changes matched
+/// to the files by path, as many editors save by writing a new file and renaming it over the old
+/// one, which a watch on the old file would miss.
+pub(crate) struct FunscriptWatcher {
+    watcher: RecommendedWatcher,
+    changed: Receiver<PathBuf>,
+    dirs: BTreeSet<PathBuf>,
+    /// The axis of each file being watched, by path
+    files: BTreeMap<PathBuf, AxisKind>,
+    /// Files that changed, and when to reload them if they don't change again before then
+    pending: BTreeMap<PathBuf, Instant>,
+}
+
+impl FunscriptWatcher {
+    pub fn new() -> eyre::Result<FunscriptWatcher> {
+        let (tx, rx) = flume::unbounded();
+        let watcher =
+            notify::recommended_watcher(
+                move |result: notify::Result<notify::Event>| match result {
+                    Ok(event) => {
+                        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                            for path in event.paths {
+                                let _ = tx.send(path);
+                            }
+                        }
+                    }
+                    Err(err) => warn!("failed to watch funscripts: {err}"),
+                },
+            )
+            .context("failed to start watching files")?;
+        Ok(FunscriptWatcher {
+            watcher,
+            changed: rx,
+            dirs: BTreeSet::new(),
+            files: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        })
+    }
+
+    /// Watches these files (and no longer any others), each for the given axis.
+    pub fn watch(&mut self, files: Vec<(AxisKind, PathBuf)>) {
+        self.clear();
+        for (axis_kind, path) in files {
+            let Some(dir) = path.parent() else {
+                continue;
+            };
+            if !self.dirs.contains(dir) {
+                if let Err(err) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                    warn!("can't watch {dir:?} for changes to funscripts: {err}");
+                    continue;
+                }
+                self.dirs.insert(dir.to_owned());
+            }
+            debug!("watching {path:?} for changes");
+            self.files.insert(path, axis_kind);
+        }
+    }
+
+    /// Stops watching, e.g. as the video changes.
+    pub fn clear(&mut self) {
+        for dir in std::mem::take(&mut self.dirs) {
+            if let Err(err) = self.watcher.unwatch(&dir) {
+                debug!("failed to stop watching {dir:?}: {err}");
+            }
+        }
+        self.files.clear();
+        self.pending.clear();
+    }
+
+    /// Waits until a file being watched has changed (and then not changed again for a moment),
+    /// giving its axis and path.
+    pub async fn next_change(&mut self) -> (AxisKind, PathBuf) {
+        loop {
+            let reload_at = self.pending.values().min().copied();
+            let reload_due = async {
+                match reload_at {
+                    Some(reload_at) => tokio::time::sleep_until(reload_at).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                changed = self.changed.recv_async() => {
+                    let Ok(path) = changed else {
+                        // Can't happen: the watcher, which sends the changes, lives as long as this
+                        continue;
+                    };
+                    if self.files.contains_key(&path) {
+                        self.pending.insert(path, Instant::now() + DEBOUNCE);
+                    }
+                }
+                () = reload_due => {
+                    let now = Instant::now();
+                    let Some(path) = self
+                        .pending
+                        .iter()
+                        .find(|(_, &reload_at)| reload_at <= now)
+                        .map(|(path, _)| path.clone())
+                    else {
+                        continue;
+                    };
+                    self.pending.remove(&path);
+                    if let Some(&axis_kind) = self.files.get(&path) {
+                        return (axis_kind, path);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use strokers::core::AxisKind;
+
+    use super::FunscriptWatcher;
+
+    #[tokio::test]
+    async fn test_saved_via_rename() {
+        let dir = std::env::temp_dir().join(format!("strokers_watcher_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stroke = dir.join("Video.funscript");
+        let twist = dir.join("Video.twist.funscript");
+        std::fs::write(&stroke, "{}").unwrap();
+        std::fs::write(&twist, "{}").unwrap();
+
+        let mut watcher = FunscriptWatcher::new().unwrap();
+        watcher.watch(vec![
+            (AxisKind::Stroke, stroke.clone()),
+            (AxisKind::Twist, twist.clone()),
+        ]);
+
+        // Saved as an editor would: in several steps, via a temporary file
+        let temp = dir.join("Video.funscript.tmp");
+        std::fs::write(&temp, "{\"actions\": []}").unwrap();
+        std::fs::rename(&temp, &stroke).unwrap();
+        std::fs::write(dir.join("Unrelated.txt"), "").unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(5), watcher.next_change())
+            .await
+            .unwrap();
+        assert_eq!(changed, (AxisKind::Stroke, stroke.clone()));
+        // Only once, however many steps it took
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), watcher.next_change())
+                .await
+                .is_err()
+        );
+
+        // Nothing once no longer watching
+        watcher.clear();
+        std::fs::write(&twist, "{\"actions\": []}").unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(500), watcher.next_change())
+                .await
+                .is_err()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
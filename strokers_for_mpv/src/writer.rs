@@ -0,0 +1,342 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use flume::{Receiver, Sender};
+use strokers::core::{AxisId, Disconnected, Movement, Stroker};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+/// Commands waiting to be written to the stroker.
+#[derive(Default)]
+struct Queue {
+    /// Whether to stop, which is written before any movements
+    stop: bool,
+    /// The latest movement for each axis, replacing any that weren't written in time
+    movements: BTreeMap<AxisId, Movement>,
+    /// Those waiting for everything queued to be written
+    flushes: Vec<Sender<()>>,
+    /// Whether the stroker was found to be disconnected, in which case nothing more is written
+    lost: bool,
+}
+
+enum Command {
+    Stop,
+    Movement(Movement),
+}
+
+impl Queue {
+    /// The next command to write, the stop first.
+    fn next_command(&mut self) -> Option<Command> {
+        if std::mem::take(&mut self.stop) {
+            return Some(Command::Stop);
+        }
+        self.movements
+            .pop_first()
+            .map(|(_, movement)| Command::Movement(movement))
+    }
+}
+
+/// Writes commands to the stroker on a task of its own, so that a slow write
+/// (e.g. as a serial port hiccups) doesn't hold up following the video.
+///
+/// Commands are queued rather than waited for. Stopping takes priority over movements
+/// (and drops any not yet written); if movements queue up faster than they can be written,
+/// only the latest for each axis is written.
+pub(crate) struct Writer {
+    queue: Arc<Mutex<Queue>>,
+    /// Wakes the task when there's something to write; the task finishes once this is dropped
+    wake: Sender<()>,
+    /// Told when the stroker is found to be disconnected
+    lost: Receiver<()>,
+    /// The task, which drops the stroker as it finishes
+    task: JoinHandle<()>,
+}
+
+impl Writer {
+    /// Starts writing commands to the stroker.
+    pub fn spawn<S: Stroker + Send + 'static>(stroker: S) -> Writer {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        let (wake_tx, wake_rx) = flume::bounded(1);
+        let (lost_tx, lost_rx) = flume::bounded(1);
+        let task = tokio::task::spawn(write_commands(stroker, queue.clone(), wake_rx, lost_tx));
+        Writer {
+            queue,
+            wake: wake_tx,
+            lost: lost_rx,
+            task,
+        }
+    }
+
+    fn queue(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wake(&self) {
+        // If full, the task is already due to look at the queue
+        let _ = self.wake.try_send(());
+    }
+
+    /// Stops the stroker as soon as what's being written now has been,
+    /// dropping any movements not yet written.
+    pub fn stop(&self) {
+        let mut queue = self.queue();
+        queue.stop = true;
+        queue.movements.clear();
+        drop(queue);
+        self.wake();
+    }
+
+    /// Queues a movement, replacing any for the same axis not yet written.
+    pub fn movement(&self, movement: Movement) {
+        self.queue().movements.insert(movement.axis(), movement);
+        self.wake();
+    }
+
+    /// Waits until everything queued has been written, e.g. to stop before shutting down.
+    pub async fn flush(&self) {
+        let (tx, rx) = flume::bounded(1);
+        {
+            let mut queue = self.queue();
+            if queue.lost {
+                // Nothing more will be written
+                return;
+            }
+            queue.flushes.push(tx);
+        }
+        self.wake();
+        let _ = rx.recv_async().await;
+    }
+
+    /// Whether the stroker was found to be disconnected.
+    pub fn is_lost(&self) -> bool {
+        self.queue().lost
+    }
+
+    /// Waits until the stroker is found to be disconnected, or forever if it isn't.
+    pub async fn lost(&self) {
+        if self.lost.recv_async().await.is_err() {
+            std::future::pending().await
+        }
+    }
+
+    /// Writes what's left, then waits until the stroker has been dropped,
+    /// e.g. so that its serial port can be opened again.
+    pub async fn close(self) {
+        let Writer { wake, task, .. } = self;
+        drop(wake);
+        if let Err(err) = task.await {
+            error!("writing to the stroker failed: {err:?}");
+        }
+    }
+}
+
+/// Writes the queued commands as they come, until the [`Writer`] is dropped
+/// (having written what's left) or the stroker is disconnected.
+async fn write_commands<S: Stroker + Send>(
+    mut stroker: S,
+    queue: Arc<Mutex<Queue>>,
+    wake: Receiver<()>,
+    lost: Sender<()>,
+) {
+    loop {
+        let command = {
+            let mut queue = queue
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let command = queue.next_command();
+            if command.is_none() {
+                for flush in queue.flushes.drain(..) {
+                    let _ = flush.try_send(());
+                }
+            }
+            command
+        };
+        let result = match command {
+            Some(Command::Stop) => stroker.stop().await,
+            Some(Command::Movement(movement)) => stroker.movement(movement).await,
+            None => {
+                if wake.recv_async().await.is_err() {
+                    debug!("finished writing to the stroker");
+                    return;
+                }
+                continue;
+            }
+        };
+        match result {
+            Ok(()) => {}
+            Err(err) if err.downcast_ref::<Disconnected>().is_some() => {
+                error!("lost connection to the stroker: {err:?}");
+                let mut queue = queue
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                queue.lost = true;
+                queue.flushes.clear();
+                let _ = lost.try_send(());
+                return;
+            }
+            Err(err) => error!("failed to write to the stroker: {err:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use strokers::{
+        core::{AxisDescriptor, AxisId, AxisKind, Disconnected, Movement, Stroker},
+        devices::debug::testing::{ChannelStroker, StrokerCommand},
+    };
+    use tokio::time::Instant;
+
+    use super::Writer;
+
+    /// Takes a while over each movement, like a struggling serial port.
+    pub(crate) struct SlowStroker(pub ChannelStroker);
+
+    /// How long [`SlowStroker`] takes over each movement.
+    pub(crate) const SLOW_MOVEMENT: Duration = Duration::from_millis(100);
+
+    #[async_trait]
+    impl Stroker for SlowStroker {
+        fn axes(&mut self) -> Vec<AxisDescriptor> {
+            self.0.axes()
+        }
+
+        async fn stop(&mut self) -> eyre::Result<()> {
+            self.0.stop().await
+        }
+
+        async fn movement(&mut self, movement: Movement) -> eyre::Result<()> {
+            tokio::time::sleep(SLOW_MOVEMENT).await;
+            self.0.movement(movement).await
+        }
+
+        fn description(&mut self) -> eyre::Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    /// Says it's been disconnected, whatever it's asked to do.
+    pub(crate) struct UnpluggedStroker;
+
+    #[async_trait]
+    impl Stroker for UnpluggedStroker {
+        fn axes(&mut self) -> Vec<AxisDescriptor> {
+            Vec::new()
+        }
+
+        async fn stop(&mut self) -> eyre::Result<()> {
+            Err(Disconnected.into())
+        }
+
+        async fn movement(&mut self, _movement: Movement) -> eyre::Result<()> {
+            Err(Disconnected.into())
+        }
+
+        fn description(&mut self) -> eyre::Result<Option<String>> {
+            Ok(None)
+        }
+    }
+
+    fn movement(axis_id: AxisId, target: f32) -> Movement {
+        Movement::new(axis_id, target, 100).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stop_overtakes_slow_movements() {
+        let (stroke, twist) = (AxisId(1), AxisId(2));
+        let (stroker, rx) =
+            ChannelStroker::with_axes(vec![(stroke, AxisKind::Stroke), (twist, AxisKind::Twist)]);
+        let writer = Writer::spawn(SlowStroker(stroker));
+
+        // Queueing doesn't wait for the stroker
+        let started = Instant::now();
+        for idx in 0..10 {
+            writer.movement(movement(stroke, idx as f32 / 10.0));
+            writer.movement(movement(twist, idx as f32 / 10.0));
+            tokio::task::yield_now().await;
+        }
+        // e.g. the video pausing
+        writer.stop();
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        writer.flush().await;
+        let commands: Vec<StrokerCommand> = rx.drain().collect();
+        // At most the movement already being written, then the stop
+        assert!(
+            matches!(
+                &commands[..],
+                [StrokerCommand::Stop { .. }]
+                    | [StrokerCommand::Movement { .. }, StrokerCommand::Stop { .. }]
+            ),
+            "{commands:?}"
+        );
+        assert!(started.elapsed() <= SLOW_MOVEMENT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_coalesces_movements() {
+        let (stroke, twist) = (AxisId(1), AxisId(2));
+        let (stroker, rx) =
+            ChannelStroker::with_axes(vec![(stroke, AxisKind::Stroke), (twist, AxisKind::Twist)]);
+        let writer = Writer::spawn(SlowStroker(stroker));
+
+        writer.movement(movement(stroke, 0.1));
+        tokio::task::yield_now().await;
+        // Whilst that's being written, only the latest for each axis is kept
+        for target in [0.2, 0.3, 0.4] {
+            writer.movement(movement(stroke, target));
+        }
+        writer.movement(movement(twist, 0.9));
+        writer.flush().await;
+
+        let targets: Vec<(AxisId, f32)> = rx
+            .drain()
+            .filter_map(|command| match command {
+                StrokerCommand::Movement { movement, .. } => {
+                    Some((movement.axis(), movement.target()))
+                }
+                StrokerCommand::Stop { .. } => None,
+            })
+            .collect();
+        assert_eq!(targets, vec![(stroke, 0.1), (stroke, 0.4), (twist, 0.9)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_close() {
+        let (stroker, rx) = ChannelStroker::with_axes(vec![(AxisId(1), AxisKind::Stroke)]);
+        let writer = Writer::spawn(SlowStroker(stroker));
+        writer.movement(movement(AxisId(1), 0.5));
+        writer.stop();
+        writer.movement(movement(AxisId(1), 0.25));
+
+        // What was queued is written, and then the stroker is let go of
+        writer.close().await;
+        assert!(rx.is_disconnected());
+        assert!(matches!(
+            &rx.drain().collect::<Vec<_>>()[..],
+            [
+                StrokerCommand::Stop { .. },
+                StrokerCommand::Movement { movement, .. }
+            ] if movement.target() == 0.25
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lost() {
+        let writer = Writer::spawn(UnpluggedStroker);
+        assert!(!writer.is_lost());
+        writer.stop();
+        writer.lost().await;
+        assert!(writer.is_lost());
+        // Nothing more is written, and nothing waits for it to be
+        writer.movement(movement(AxisId(1), 0.5));
+        writer.flush().await;
+    }
+}
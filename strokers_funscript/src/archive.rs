@@ -0,0 +1,310 @@
+//! Funscripts in zip archives, as script packs are often distributed.
+//!
+//! An archive next to the video (e.g. `MyVideo.funscript.zip`) is searched like a directory.
+//! Funscripts in it are named in a [`FunscriptScan`] by the archive's path followed by the
+//! entry's name, e.g. `MyVideo.funscript.zip/MyVideo.twist.funscript`;
+//! [`load_funscript`] understands these names.
+
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use eyre::{bail, Context};
+use tracing::warn;
+use zip::ZipArchive;
+
+use crate::{
+    schema::Funscript,
+    search_path::{scan_for_funscripts, FunscriptScan},
+};
+
+/// The most that's read of an entry in an archive, as it's told how big its entries are by the
+/// archive itself. Funscripts are rarely more than a few megabytes.
+const MAX_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Whether `filename` looks like an archive of funscripts for the given video,
+/// i.e. its name starts with the video's name (without extension) and ends with `.zip`.
+pub fn is_funscript_archive(filename: &str, video_name: &str) -> bool {
+    let video_without_extension = video_name
+        .rsplit_once('.')
+        .map(|(a, _)| a)
+        .unwrap_or(video_name);
+    filename.starts_with(video_without_extension)
+        && filename.len() >= 4
+        && filename.is_char_boundary(filename.len() - 4)
+        && filename[filename.len() - 4..].eq_ignore_ascii_case(".zip")
+}
+
+/// Splits a name from a [`FunscriptScan`] (relative to `video_dir`) into the path of an archive
+/// and the name of the entry within it, if it refers to a funscript in an archive.
+///
+/// Only a `.zip` that's a file counts as an archive; a folder named like one is just a folder.
+pub fn split_archive_path<'a>(video_dir: &Path, relative: &'a str) -> Option<(&'a str, &'a str)> {
+    let mut offset = 0;
+    for segment in relative.split('/') {
+        offset += segment.len();
+        if segment.len() >= 4
+            && segment.is_char_boundary(segment.len() - 4)
+            && segment[segment.len() - 4..].eq_ignore_ascii_case(".zip")
+            && video_dir.join(&relative[..offset]).is_file()
+        {
+            let entry = relative
+                .get(offset + 1..)
+                .filter(|entry| !entry.is_empty())?;
+            return Some((&relative[..offset], entry));
+        }
+        offset += 1;
+    }
+    None
+}
+
+/// A zip archive containing funscripts.
+pub struct FunscriptArchive<R> {
+    archive: ZipArchive<R>,
+}
+
+impl FunscriptArchive<File> {
+    /// Opens the archive at the given path.
+    pub fn open(path: &Path) -> eyre::Result<FunscriptArchive<File>> {
+        let file = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+        FunscriptArchive::new(file).with_context(|| format!("failed to read archive {path:?}"))
+    }
+}
+
+impl<R: Read + Seek> FunscriptArchive<R> {
+    pub fn new(reader: R) -> eyre::Result<FunscriptArchive<R>> {
+        let archive = ZipArchive::new(reader).context("not a readable zip archive")?;
+        Ok(FunscriptArchive { archive })
+    }
+
+    /// Searches the archive for funscripts that likely match the video,
+    /// matching entries by their file names (ignoring any folders in the archive).
+    ///
+    /// Entries are named `{archive_name}/{entry}` in the scan.
+    /// Entries that can't be read (e.g. because they need a password) are skipped with a warning.
+    pub fn scan(&mut self, video_name: &str, archive_name: &str) -> eyre::Result<FunscriptScan> {
+        let entries: Vec<String> = self.archive.file_names().map(str::to_owned).collect();
+        let basenames: Vec<String> = entries
+            .iter()
+            .map(|entry| entry.rsplit('/').next().unwrap_or(entry).to_owned())
+            .collect();
+
+        let mut scan = scan_for_funscripts(&basenames, video_name)?;
+        let clusters = std::iter::once(&mut scan.main).chain(scan.overrides.values_mut());
+        for cluster in clusters {
+            cluster.scripts.retain(|_, filename| {
+                let Some(idx) = basenames.iter().position(|basename| basename == filename) else {
+                    return false;
+                };
+                let entry = &entries[idx];
+                if let Err(err) = self.archive.by_name(entry) {
+                    warn!("skipping {entry:?} in {archive_name:?}: {err}");
+                    return false;
+                }
+                *filename = format!("{archive_name}/{entry}");
+                true
+            });
+        }
+        scan.overrides
+            .retain(|_, cluster| !cluster.scripts.is_empty());
+        Ok(scan)
+    }
+
+    /// Reads and deserialises the funscript in the given entry, without extracting it to disk.
+    ///
+    /// This doesn't call [`Funscript::fixup`].
+    pub fn load(&mut self, entry: &str) -> eyre::Result<Funscript> {
+        let contents = self.read(entry)?;
+        Funscript::from_slice(&contents).with_context(|| format!("failed to load {entry:?}"))
+    }
+
+    /// Reads the contents of the given entry, without extracting it to disk.
+    ///
+    /// Fails if the entry is bigger than [`MAX_ENTRY_BYTES`].
+    pub fn read(&mut self, entry: &str) -> eyre::Result<Vec<u8>> {
+        let file = self
+            .archive
+            .by_name(entry)
+            .with_context(|| format!("failed to open {entry:?} in archive"))?;
+        if file.size() > MAX_ENTRY_BYTES {
+            bail!("{entry:?} in archive is too big to be a funscript");
+        }
+        let mut contents = Vec::with_capacity(file.size() as usize);
+        // The size given by the archive may not be the truth
+        file.take(MAX_ENTRY_BYTES + 1)
+            .read_to_end(&mut contents)
+            .with_context(|| format!("failed to read {entry:?} from archive"))?;
+        if contents.len() as u64 > MAX_ENTRY_BYTES {
+            bail!("{entry:?} in archive is too big to be a funscript");
+        }
+        Ok(contents)
+    }
+}
+
+/// Loads a funscript named by a [`FunscriptScan`] (relative to the video's directory),
+/// whether it's a file or in an archive.
+///
+/// This doesn't call [`Funscript::fixup`].
+pub async fn load_funscript(video_dir: &Path, relative: &str) -> eyre::Result<Funscript> {
+    let contents = read_funscript(video_dir, relative).await?;
+    Funscript::from_slice(&contents).with_context(|| format!("failed to load {relative:?}"))
+}
+
+/// Reads the contents of a funscript named by a [`FunscriptScan`], as [`load_funscript`] does,
+/// leaving the caller to deserialise it (e.g. with [`Funscript::from_slice_lenient`]).
+pub async fn read_funscript(video_dir: &Path, relative: &str) -> eyre::Result<Vec<u8>> {
+    let Some((archive_path, entry)) = split_archive_path(video_dir, relative) else {
+        let path = video_dir.join(relative);
+        return tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("failed to read {path:?}"));
+    };
+
+    let archive_path = video_dir.join(archive_path);
+    let entry = entry.to_owned();
+    tokio::task::spawn_blocking(move || FunscriptArchive::open(&archive_path)?.read(&entry))
+        .await
+        .context("archive reading task failed")?
+}
+
+/// Searches an archive file for funscripts, as [`FunscriptArchive::scan`].
+///
+/// Archives that can't be read (e.g. corrupt ones) are skipped with a warning,
+/// giving an empty scan.
+pub async fn scan_archive(video_dir: &Path, archive_name: &str, video_name: &str) -> FunscriptScan {
+    let archive_path = video_dir.join(archive_name);
+    let archive_name = archive_name.to_owned();
+    let video_name = video_name.to_owned();
+    let result = tokio::task::spawn_blocking(move || {
+        FunscriptArchive::open(&archive_path)?.scan(&video_name, &archive_name)
+    })
+    .await
+    .context("archive scanning task failed")
+    .and_then(|result| result);
+
+    match result {
+        Ok(scan) => scan,
+        Err(err) => {
+            warn!("skipping funscript archive: {err:?}");
+            FunscriptScan::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use strokers_core::AxisKind;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::{is_funscript_archive, split_archive_path, FunscriptArchive, MAX_ENTRY_BYTES};
+
+    const STROKE: &str = r#"{"actions":[{"at":0,"pos":0},{"at":500,"pos":100}]}"#;
+    const TWIST: &str = r#"{"actions":[{"at":0,"pos":50},{"at":500,"pos":0}]}"#;
+
+    /// Builds a zip archive with the given entries.
+    fn build_zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Marks every entry in the archive's central directory as encrypted.
+    fn mark_encrypted(bytes: &mut [u8]) {
+        const CENTRAL_HEADER: [u8; 4] = [0x50, 0x4b, 0x01, 0x02];
+        let mut idx = 0;
+        while idx + 10 <= bytes.len() {
+            if bytes[idx..idx + 4] == CENTRAL_HEADER {
+                // General purpose flags; bit 0 means encrypted
+                bytes[idx + 8] |= 1;
+            }
+            idx += 1;
+        }
+    }
+
+    #[test]
+    fn test_scan_and_load() {
+        let bytes = build_zip(&[
+            ("pack/MyVideo.funscript", STROKE),
+            ("pack/MyVideo.twist.funscript", TWIST),
+            ("pack/readme.txt", "enjoy"),
+            ("Other.funscript", STROKE),
+        ]);
+        let mut archive = FunscriptArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.archive.len(), 4);
+
+        let scan = archive
+            .scan("MyVideo.mp4", "MyVideo.funscript.zip")
+            .unwrap();
+        assert!(scan.overrides.is_empty());
+        assert_eq!(scan.main.scripts.len(), 2);
+        let twist = &scan.main.scripts[&AxisKind::Twist];
+        assert_eq!(twist, "MyVideo.funscript.zip/pack/MyVideo.twist.funscript");
+
+        let funscript = archive.load("pack/MyVideo.twist.funscript").unwrap();
+        assert_eq!(funscript.actions[0].pos, 50);
+    }
+
+    #[test]
+    fn test_password_protected_skipped() {
+        let mut bytes = build_zip(&[("MyVideo.funscript", STROKE)]);
+        mark_encrypted(&mut bytes);
+        let mut archive = FunscriptArchive::new(Cursor::new(bytes)).unwrap();
+        let scan = archive.scan("MyVideo.mp4", "MyVideo.zip").unwrap();
+        assert!(scan.main.scripts.is_empty());
+    }
+
+    #[test]
+    fn test_corrupt_archive() {
+        let mut bytes = build_zip(&[("MyVideo.funscript", STROKE)]);
+        bytes.truncate(bytes.len() / 2);
+        assert!(FunscriptArchive::new(Cursor::new(bytes)).is_err());
+    }
+
+    #[test]
+    fn test_archive_names() {
+        assert!(is_funscript_archive("MyVideo.funscript.zip", "MyVideo.mp4"));
+        assert!(is_funscript_archive("MyVideo.ZIP", "MyVideo.mp4"));
+        assert!(!is_funscript_archive("Other.zip", "MyVideo.mp4"));
+        assert!(!is_funscript_archive("MyVideo.funscript", "MyVideo.mp4"));
+    }
+
+    #[test]
+    fn test_split_archive_path() {
+        let video_dir =
+            std::env::temp_dir().join(format!("strokers_archive_{}", std::process::id()));
+        std::fs::create_dir_all(video_dir.join("scripts")).unwrap();
+        std::fs::create_dir_all(video_dir.join("unpacked.zip")).unwrap();
+        std::fs::write(video_dir.join("scripts/pack.zip"), build_zip(&[])).unwrap();
+        let split = |relative| split_archive_path(&video_dir, relative);
+
+        assert_eq!(
+            split("scripts/pack.zip/a/b.funscript"),
+            Some(("scripts/pack.zip", "a/b.funscript"))
+        );
+        assert_eq!(split("funscripts/MyVideo.funscript"), None);
+        assert_eq!(split("scripts/pack.zip"), None);
+        // A folder named like an archive is just a folder
+        assert_eq!(split("unpacked.zip/MyVideo.funscript"), None);
+        // As is anything that isn't there
+        assert_eq!(split("missing.zip/MyVideo.funscript"), None);
+
+        std::fs::remove_dir_all(&video_dir).unwrap();
+    }
+
+    #[test]
+    fn test_oversized_entry() {
+        let big = " ".repeat(MAX_ENTRY_BYTES as usize + 1);
+        let bytes = build_zip(&[("MyVideo.funscript", &big)]);
+        let mut archive = FunscriptArchive::new(Cursor::new(bytes)).unwrap();
+        assert!(archive.read("MyVideo.funscript").is_err());
+    }
+}
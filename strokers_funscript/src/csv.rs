@@ -0,0 +1,249 @@
+//! Reading and writing actions as CSV, as used by scripting tools and spreadsheets.
+//!
+//! Two layouts are understood, told apart by the header:
+//!
+//! ```text
+//! at,pos
+//! 0,10
+//! 1500,90
+//! ```
+//!
+//! ```text
+//! time_seconds,position_percent
+//! 0.0,10
+//! 1.5,90
+//! ```
+//!
+//! A file without a header is read as `at,pos`. Other columns are ignored.
+
+use std::io::{BufRead, Write};
+
+use eyre::{bail, Context, ContextCompat};
+
+use crate::schema::FunscriptAction;
+
+/// Names accepted for a time column in milliseconds.
+const MILLISECOND_COLUMNS: &[&str] = &["at", "time_ms", "ms", "milliseconds"];
+
+/// Names accepted for a time column in (fractional) seconds.
+const SECOND_COLUMNS: &[&str] = &["time_seconds", "time", "seconds", "t"];
+
+/// Names accepted for a position column.
+const POSITION_COLUMNS: &[&str] = &["pos", "position", "position_percent", "percent"];
+
+/// How times are written in a CSV file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CsvTimeFormat {
+    /// `at,pos`, with times in milliseconds.
+    #[default]
+    Milliseconds,
+    /// `time_seconds,position_percent`, with times in fractional seconds.
+    Seconds,
+}
+
+/// Where the interesting columns are.
+struct Layout {
+    time_column: usize,
+    position_column: usize,
+    time_format: CsvTimeFormat,
+}
+
+impl Layout {
+    /// Works out the layout from a header line, if it is one.
+    fn from_header(line: &str) -> eyre::Result<Option<Layout>> {
+        let names: Vec<String> = line
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .collect();
+        // A header has no numbers in it
+        if names.iter().all(|name| name.parse::<f64>().is_err()) {
+            let find = |candidates: &[&str]| {
+                names
+                    .iter()
+                    .position(|name| candidates.contains(&name.as_str()))
+            };
+            let (time_column, time_format) = match (find(MILLISECOND_COLUMNS), find(SECOND_COLUMNS))
+            {
+                (Some(column), _) => (column, CsvTimeFormat::Milliseconds),
+                (None, Some(column)) => (column, CsvTimeFormat::Seconds),
+                (None, None) => bail!("no time column in header {line:?}"),
+            };
+            let position_column = find(POSITION_COLUMNS)
+                .with_context(|| format!("no position column in header {line:?}"))?;
+            return Ok(Some(Layout {
+                time_column,
+                position_column,
+                time_format,
+            }));
+        }
+        Ok(None)
+    }
+
+    fn parse_row(&self, line: &str) -> eyre::Result<FunscriptAction> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |column: usize, what: &str| {
+            fields
+                .get(column)
+                .copied()
+                .with_context(|| format!("missing {what}"))
+        };
+
+        let time = field(self.time_column, "time")?;
+        let at = match self.time_format {
+            CsvTimeFormat::Milliseconds => time
+                .parse::<u32>()
+                .with_context(|| format!("bad time {time:?}"))?,
+            CsvTimeFormat::Seconds => {
+                let seconds: f64 = time.parse().with_context(|| format!("bad time {time:?}"))?;
+                if !(0.0..=u32::MAX as f64 / 1000.0).contains(&seconds) {
+                    bail!("time {time:?} out of range");
+                }
+                (seconds * 1000.0).round() as u32
+            }
+        };
+
+        let position = field(self.position_column, "position")?;
+        let pos: f64 = position
+            .parse()
+            .with_context(|| format!("bad position {position:?}"))?;
+        if !(pos.is_finite() && pos >= 0.0) {
+            bail!("position {position:?} out of range");
+        }
+
+        Ok(FunscriptAction {
+            at,
+            pos: pos.round() as u32,
+        })
+    }
+}
+
+/// Reads actions from CSV. Blank lines are skipped.
+///
+/// Errors name the line they were found on.
+pub fn from_csv_reader(reader: impl BufRead) -> eyre::Result<Vec<FunscriptAction>> {
+    let mut layout: Option<Layout> = None;
+    let mut actions = Vec::new();
+
+    for (idx, line) in reader.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = line.with_context(|| format!("failed to read line {line_number}"))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let layout = match &layout {
+            Some(layout) => layout,
+            None => {
+                let header = Layout::from_header(line)
+                    .with_context(|| format!("bad header on line {line_number}"))?;
+                let is_header = header.is_some();
+                let layout = layout.insert(header.unwrap_or(Layout {
+                    time_column: 0,
+                    position_column: 1,
+                    time_format: CsvTimeFormat::Milliseconds,
+                }));
+                if is_header {
+                    continue;
+                }
+                layout
+            }
+        };
+
+        let action = layout
+            .parse_row(line)
+            .with_context(|| format!("bad row on line {line_number}"))?;
+        actions.push(action);
+    }
+
+    Ok(actions)
+}
+
+/// Writes actions as CSV, with a header.
+pub fn to_csv_writer(
+    mut writer: impl Write,
+    actions: &[FunscriptAction],
+    time_format: CsvTimeFormat,
+) -> eyre::Result<()> {
+    match time_format {
+        CsvTimeFormat::Milliseconds => {
+            writeln!(writer, "at,pos")?;
+            for action in actions {
+                writeln!(writer, "{},{}", action.at, action.pos)?;
+            }
+        }
+        CsvTimeFormat::Seconds => {
+            writeln!(writer, "time_seconds,position_percent")?;
+            for action in actions {
+                writeln!(writer, "{:.3},{}", action.at as f64 / 1000.0, action.pos)?;
+            }
+        }
+    }
+    writer.flush().context("failed to flush CSV")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::{Funscript, FunscriptAction};
+
+    use super::{from_csv_reader, to_csv_writer, CsvTimeFormat};
+
+    fn action(at: u32, pos: u32) -> FunscriptAction {
+        FunscriptAction { at, pos }
+    }
+
+    #[test]
+    fn test_header_variants() {
+        let expected = vec![action(0, 10), action(1500, 90)];
+        for text in [
+            "at,pos\n0,10\n1500,90\n",
+            "0,10\n1500,90\n",
+            "time_seconds,position_percent\n0.0,10\n1.5,90\n",
+            " Position , Time \r\n\n10, 0\n90.2, 1.5\n",
+            "id,ms,pos,note\n1,0,10,start\n2,1500,90,\n",
+        ] {
+            assert_eq!(
+                from_csv_reader(text.as_bytes()).unwrap(),
+                expected,
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        for (text, expected_line) in [
+            ("at,pos\n0,10\n100\n", "line 3"),
+            ("at,pos\n0,10\n\nabc,20\n", "line 4"),
+            ("at,pos\n0,-5\n", "line 2"),
+            ("time,pos\n-1.0,5\n", "line 2"),
+            ("when,where\n0,10\n", "line 1"),
+        ] {
+            let err = from_csv_reader(text.as_bytes()).unwrap_err();
+            assert!(
+                format!("{err:#}").contains(expected_line),
+                "{text:?}: {err:#}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let actions = vec![action(0, 0), action(333, 100), action(1001, 42)];
+        for time_format in [CsvTimeFormat::Milliseconds, CsvTimeFormat::Seconds] {
+            let mut out = Vec::new();
+            to_csv_writer(&mut out, &actions, time_format).unwrap();
+            assert_eq!(from_csv_reader(out.as_slice()).unwrap(), actions);
+        }
+    }
+
+    #[test]
+    fn test_funscript_from_csv() {
+        let funscript = Funscript::from_csv("at,pos\n0,10\n500,90\n".as_bytes()).unwrap();
+        assert_eq!(funscript.actions.len(), 2);
+        assert_eq!(funscript.range, 100);
+        let saved: serde_json::Value =
+            serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(saved["range"], 100);
+    }
+}
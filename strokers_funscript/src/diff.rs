@@ -0,0 +1,198 @@
+//! Comparing two versions of a funscript, e.g. to see what an edit or a processing step changed.
+
+use serde::Serialize;
+
+use crate::{
+    processing::{normalised_from_funscript, NormalisedAction},
+    schema::Funscript,
+    slice::position_at,
+};
+
+/// How two funscripts differ, from [`compare`].
+///
+/// Positions are normalised (0.0 to 1.0), so scripts with different ranges compare fairly.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DiffReport {
+    /// Times of actions only in the second script.
+    pub added: Vec<u32>,
+    /// Times of actions only in the first script.
+    pub removed: Vec<u32>,
+    /// Times of actions in both scripts, but at different positions.
+    pub moved: Vec<u32>,
+    /// How many actions are the same in both scripts.
+    pub unchanged: usize,
+    /// The largest difference in position between the scripts at any time.
+    pub max_deviation: f32,
+    /// When the largest difference in position is, if the scripts differ at all.
+    pub max_deviation_at_ms: Option<u32>,
+    /// How much longer (or, if negative, shorter) the second script is,
+    /// going by the time of the last action.
+    pub duration_change_ms: i64,
+}
+
+impl DiffReport {
+    /// Whether the scripts have the same actions.
+    pub fn is_identical(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Compares the main actions of two funscripts.
+///
+/// Actions are matched up by time. The deviation treats each script as a position that moves
+/// in straight lines between its actions (held before the first and after the last), and is
+/// sampled every `sample_interval_ms` as well as at every action of either script,
+/// which is exact for such lines; an interval of zero samples at the actions alone.
+pub fn compare(a: &Funscript, b: &Funscript, sample_interval_ms: u32) -> DiffReport {
+    compare_actions(
+        &normalised_from_funscript(a),
+        &normalised_from_funscript(b),
+        sample_interval_ms,
+    )
+}
+
+/// Compares two lists of normalised actions, as [`compare`] does, e.g. to check how far
+/// [`simplify`](crate::processing::simplify) strays from the original.
+///
+/// Both lists must be sorted by time.
+pub fn compare_actions(
+    a: &[NormalisedAction],
+    b: &[NormalisedAction],
+    sample_interval_ms: u32,
+) -> DiffReport {
+    let mut report = DiffReport::default();
+
+    let (mut a_idx, mut b_idx) = (0, 0);
+    while a_idx < a.len() || b_idx < b.len() {
+        let a_at = a.get(a_idx).map(|action| action.at);
+        let b_at = b.get(b_idx).map(|action| action.at);
+        match (a_at, b_at) {
+            (Some(a_at), Some(b_at)) if a_at == b_at => {
+                if a[a_idx].norm_pos == b[b_idx].norm_pos {
+                    report.unchanged += 1;
+                } else {
+                    report.moved.push(a_at);
+                }
+                a_idx += 1;
+                b_idx += 1;
+            }
+            (Some(a_at), Some(b_at)) if a_at < b_at => {
+                report.removed.push(a_at);
+                a_idx += 1;
+            }
+            (Some(a_at), None) => {
+                report.removed.push(a_at);
+                a_idx += 1;
+            }
+            (_, Some(b_at)) => {
+                report.added.push(b_at);
+                b_idx += 1;
+            }
+            (None, None) => unreachable!("loop condition"),
+        }
+    }
+
+    let last_at = |actions: &[NormalisedAction]| actions.last().map_or(0, |action| action.at);
+    report.duration_change_ms = last_at(b) as i64 - last_at(a) as i64;
+
+    let (Some(start), Some(end)) = (
+        a.iter().chain(b).map(|action| action.at).min(),
+        a.iter().chain(b).map(|action| action.at).max(),
+    ) else {
+        return report;
+    };
+    let interval_samples = (sample_interval_ms > 0)
+        .then(|| (start..=end).step_by(sample_interval_ms as usize))
+        .into_iter()
+        .flatten();
+    let action_samples = a.iter().chain(b).map(|action| action.at);
+    for at in interval_samples.chain(action_samples) {
+        let (Some(a_pos), Some(b_pos)) = (
+            position_at(a, at, normalised_point),
+            position_at(b, at, normalised_point),
+        ) else {
+            // One of the scripts has no actions, so there's nothing to compare against
+            continue;
+        };
+        let deviation = (a_pos - b_pos).abs() as f32;
+        if deviation > report.max_deviation {
+            report.max_deviation = deviation;
+            report.max_deviation_at_ms = Some(at);
+        }
+    }
+
+    report
+}
+
+/// The time and position of a normalised action, for [`position_at`].
+fn normalised_point(action: &NormalisedAction) -> (u32, f64) {
+    (action.at, action.norm_pos as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        processing::{simplify, NormalisedAction},
+        schema::Funscript,
+    };
+
+    use super::{compare, compare_actions};
+
+    fn funscript(actions: &[(u32, u32)]) -> Funscript {
+        Funscript::builder()
+            .actions(actions.iter().copied())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = funscript(&[(0, 0), (1000, 100), (2000, 0)]);
+        let b = funscript(&[(0, 0), (500, 50), (1000, 60), (2500, 0)]);
+        let report = compare(&a, &b, 100);
+
+        assert_eq!(report.added, vec![500, 2500]);
+        assert_eq!(report.removed, vec![2000]);
+        assert_eq!(report.moved, vec![1000]);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.duration_change_ms, 500);
+        assert!((report.max_deviation - 0.4).abs() < 1e-6, "{report:?}");
+        assert_eq!(report.max_deviation_at_ms, Some(1000));
+        assert!(!report.is_identical());
+
+        let same = compare(&a, &a.clone(), 100);
+        assert!(same.is_identical());
+        assert_eq!((same.max_deviation, same.max_deviation_at_ms), (0.0, None));
+
+        // Serialisable, e.g. for tooling
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["added"], serde_json::json!([500, 2500]));
+    }
+
+    #[test]
+    fn test_compare_empty() {
+        let report = compare(&funscript(&[]), &funscript(&[(0, 0), (1000, 100)]), 100);
+        assert_eq!(report.added, vec![0, 1000]);
+        assert_eq!(report.duration_change_ms, 1000);
+        assert_eq!(report.max_deviation_at_ms, None);
+    }
+
+    #[test]
+    fn test_simplify_stays_within_epsilon() {
+        let actions: Vec<NormalisedAction> = (0..500)
+            .map(|idx| NormalisedAction {
+                at: idx * 20,
+                norm_pos: 0.5 + 0.5 * (idx as f32 * 0.13).sin(),
+            })
+            .collect();
+        for epsilon in [0.01, 0.05, 0.2] {
+            let simplified = simplify(&actions, epsilon);
+            let report = compare_actions(&actions, &simplified, 5);
+            assert!(
+                report.max_deviation <= epsilon + 1e-6,
+                "epsilon {epsilon}: {report:?}"
+            );
+            assert!(report.added.is_empty() && report.moved.is_empty());
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! A faster way to deserialise funscripts, for scripts of hundreds of thousands of actions.
+//!
+//! The usual path reads each action's numbers into a [`Value`] (to cope with bad exporters)
+//! and, to collect the unknown keys, buffers the whole document before deserialising it.
+//! This reads the top-level object (and each axis) key by key instead and the actions
+//! straight into [`FunscriptAction`]s. It only handles well-formed funscripts; anything that would need
+//! repairing whilst reading (numbers written as strings, negative positions, …) or positions
+//! written as fractions (see [`PositionConvention`]) is an error,
+//! so that the caller can fall back to the usual path.
+//!
+//! See [`Funscript::from_slice`](crate::schema::Funscript::from_slice).
+
+use std::fmt;
+
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Value};
+
+use crate::schema::{FunscriptAction, FunscriptAxis, FunscriptMetadata, PositionConvention};
+
+/// The parts of a funscript as read by [`parse`].
+pub(crate) struct FastFunscript {
+    pub actions: Vec<FunscriptAction>,
+    pub inverted: Option<bool>,
+    pub range: Option<u32>,
+    pub metadata: Option<FunscriptMetadata>,
+    pub axes: Option<Vec<FunscriptAxis>>,
+    pub unknown: Map<String, Value>,
+}
+
+/// Deserialises a well-formed funscript, failing on anything that would need repairing.
+pub(crate) fn parse(contents: &[u8]) -> serde_json::Result<FastFunscript> {
+    let mut deserializer = serde_json::Deserializer::from_slice(contents);
+    let funscript = deserializer.deserialize_map(FunscriptVisitor)?;
+    deserializer.end()?;
+    Ok(funscript)
+}
+
+struct FunscriptVisitor;
+
+impl<'de> Visitor<'de> for FunscriptVisitor {
+    type Value = FastFunscript;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a funscript object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FastFunscript, A::Error> {
+        let mut actions: Option<Vec<FastAction>> = None;
+        let mut inverted = None;
+        let mut range = None;
+        let mut metadata = None;
+        let mut axes: Option<Vec<FastAxis>> = None;
+        let mut unknown = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "actions" if actions.is_none() => actions = Some(map.next_value()?),
+                "inverted" => inverted = map.next_value()?,
+                "range" => range = map.next_value()?,
+                "metadata" => metadata = map.next_value()?,
+                "axes" => axes = map.next_value()?,
+                "actions" => return Err(de::Error::duplicate_field("actions")),
+                _ => {
+                    unknown.insert(key, map.next_value()?);
+                }
+            }
+        }
+
+        let actions = actions.ok_or_else(|| de::Error::missing_field("actions"))?;
+        // Positions written as fractions would have been rounded away
+        let normalised = |version| {
+            PositionConvention::from_version(version) == Some(PositionConvention::Normalised)
+        };
+        if normalised(unknown.get("version"))
+            || axes
+                .iter()
+                .flatten()
+                .any(|axis| normalised(axis.0.unknown.get("version")))
+        {
+            return Err(de::Error::custom("positions are fractions"));
+        }
+        Ok(FastFunscript {
+            actions: actions.into_iter().map(|action| action.0).collect(),
+            inverted,
+            range,
+            metadata,
+            axes: axes.map(|axes| axes.into_iter().map(|axis| axis.0).collect()),
+            unknown,
+        })
+    }
+}
+
+/// One of the further axes of a multiscript, read as [`FunscriptVisitor`] reads the funscript.
+struct FastAxis(FunscriptAxis);
+
+impl<'de> Deserialize<'de> for FastAxis {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FastAxis, D::Error> {
+        deserializer.deserialize_map(AxisVisitor)
+    }
+}
+
+struct AxisVisitor;
+
+impl<'de> Visitor<'de> for AxisVisitor {
+    type Value = FastAxis;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a multiscript axis object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<FastAxis, A::Error> {
+        let mut id: Option<String> = None;
+        let mut actions: Option<Vec<FastAction>> = None;
+        let mut inverted = None;
+        let mut range = None;
+        let mut unknown = Map::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "id" if id.is_none() => id = Some(map.next_value()?),
+                "actions" if actions.is_none() => actions = Some(map.next_value()?),
+                "inverted" => inverted = map.next_value()?,
+                "range" => range = map.next_value()?,
+                "id" | "actions" => {
+                    return Err(de::Error::custom(format_args!("duplicate field `{key}`")))
+                }
+                _ => {
+                    unknown.insert(key, map.next_value()?);
+                }
+            }
+        }
+
+        Ok(FastAxis(FunscriptAxis {
+            id: id.ok_or_else(|| de::Error::missing_field("id"))?,
+            actions: actions
+                .ok_or_else(|| de::Error::missing_field("actions"))?
+                .into_iter()
+                .map(|action| action.0)
+                .collect(),
+            inverted,
+            range,
+            unknown,
+        }))
+    }
+}
+
+/// An action whose time and position are plain numbers within range.
+struct FastAction(FunscriptAction);
+
+impl<'de> Deserialize<'de> for FastAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<FastAction, D::Error> {
+        #[derive(Deserialize)]
+        struct Numbers {
+            at: f64,
+            pos: f64,
+        }
+
+        let Numbers { at, pos } = Numbers::deserialize(deserializer)?;
+        if !(0.0..=u32::MAX as f64).contains(&at) {
+            return Err(de::Error::custom(format_args!("time {at} out of range")));
+        }
+        if !(0.0..=u32::MAX as f64).contains(&pos) {
+            return Err(de::Error::custom(format_args!(
+                "position {pos} out of range"
+            )));
+        }
+        // Rounded as in `crate::repair::actions_from_raw`
+        Ok(FastAction(FunscriptAction {
+            at: at.round() as u32,
+            pos: pos.round() as u32,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{processing::NormalisedAction, schema::Funscript};
+
+    use super::parse;
+
+    const FIXTURES: [&str; 8] = [
+        include_str!("../fixtures/with_metadata.funscript"),
+        include_str!("../fixtures/millisecond_metadata.funscript"),
+        include_str!("../fixtures/per_axis_inverted.funscript"),
+        include_str!("../fixtures/cluster/video.funscript"),
+        include_str!("../fixtures/cluster/video.roll.funscript"),
+        include_str!("../fixtures/cluster/video.twist.funscript"),
+        include_str!("../fixtures/malformed/unsorted.funscript"),
+        include_str!("../fixtures/malformed/duplicates.funscript"),
+    ];
+
+    /// Deserialises without the fast path.
+    fn slow(text: &str) -> Funscript {
+        serde_json::from_str(text).unwrap()
+    }
+
+    fn normalised(mut funscript: Funscript) -> Vec<Vec<(u32, f32)>> {
+        funscript.fixup();
+        funscript
+            .get_axes_funscripts()
+            .values()
+            .map(|axis| {
+                axis.normalised()
+                    .map(|NormalisedAction { at, norm_pos }| (at, norm_pos))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fast_matches_slow() {
+        for text in FIXTURES {
+            let fast: Funscript = parse(text.as_bytes()).unwrap().into();
+            assert_eq!(
+                fast.to_json_string().unwrap(),
+                slow(text).to_json_string().unwrap()
+            );
+            assert_eq!(normalised(fast), normalised(slow(text)));
+        }
+    }
+
+    #[test]
+    fn test_fast_rejects_what_needs_repairing() {
+        for text in [
+            include_str!("../fixtures/malformed/garbage.funscript"),
+            include_str!("../fixtures/malformed/out_of_range.funscript"),
+            r#"{"actions": [{"at": 0, "pos": 0}], "actions": []}"#,
+            r#"{"range": 100}"#,
+        ] {
+            assert!(parse(text.as_bytes()).is_err(), "{text}");
+            // but the usual path still copes (or fails) on its own
+            let _ = Funscript::from_slice(text.as_bytes());
+        }
+    }
+}
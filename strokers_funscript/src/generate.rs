@@ -0,0 +1,290 @@
+//! Generates standard movement patterns, e.g. for testing devices when there is no funscript.
+
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::processing::NormalisedAction;
+
+/// One of the patterns, e.g. as chosen in a configuration file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    /// See [`sine`].
+    #[default]
+    Sine,
+    /// See [`triangle`].
+    Triangle,
+    /// See [`sawtooth`].
+    Sawtooth,
+    /// See [`random_walk`].
+    RandomWalk,
+}
+
+impl Pattern {
+    /// Generates the pattern. The seed is only used by [`Pattern::RandomWalk`].
+    pub fn generate(self, params: &PatternParams, seed: u64) -> Vec<NormalisedAction> {
+        match self {
+            Pattern::Sine => sine(params),
+            Pattern::Triangle => triangle(params),
+            Pattern::Sawtooth => sawtooth(params),
+            Pattern::RandomWalk => random_walk(params, seed),
+        }
+    }
+}
+
+/// Parameters shared by all patterns.
+#[derive(Clone, Debug)]
+pub struct PatternParams {
+    /// How long the pattern lasts, in milliseconds.
+    pub duration_ms: u32,
+    /// How long one cycle of the pattern takes, in milliseconds.
+    pub period_ms: u32,
+    /// Lowest position (0.0 to 1.0).
+    pub min: f32,
+    /// Highest position (0.0 to 1.0).
+    pub max: f32,
+    /// Time between generated actions, in milliseconds.
+    pub sample_interval_ms: u32,
+}
+
+impl PatternParams {
+    /// Times to sample at: every sample interval, plus the end.
+    fn sample_times(&self) -> impl Iterator<Item = u32> + '_ {
+        let interval = self.sample_interval_ms.max(1);
+        (0..=self.duration_ms / interval)
+            .map(move |idx| idx * interval)
+            .chain(std::iter::once(self.duration_ms))
+    }
+
+    /// Progress through the current cycle at the given time, from 0.0 to 1.0.
+    fn phase(&self, at: u32) -> f32 {
+        let period = self.period_ms.max(1);
+        (at % period) as f32 / period as f32
+    }
+
+    /// Maps 0.0 to 1.0 into the range from `min` to `max`.
+    fn position(&self, fraction: f32) -> f32 {
+        (self.min + (self.max - self.min) * fraction).clamp(0.0, 1.0)
+    }
+}
+
+/// Sorts the actions and removes any at the same time as a later one.
+fn sorted_and_deduplicated(mut actions: Vec<NormalisedAction>) -> Vec<NormalisedAction> {
+    actions.sort_by_key(|action| action.at);
+    let mut out: Vec<NormalisedAction> = Vec::with_capacity(actions.len());
+    for action in actions {
+        match out.last_mut() {
+            Some(previous) if previous.at == action.at => *previous = action,
+            _ => out.push(action),
+        }
+    }
+    out
+}
+
+/// A sine wave, starting at the lowest position.
+pub fn sine(params: &PatternParams) -> Vec<NormalisedAction> {
+    let actions = params
+        .sample_times()
+        .map(|at| NormalisedAction {
+            at,
+            norm_pos: params.position((1.0 - (params.phase(at) * 2.0 * PI).cos()) / 2.0),
+        })
+        .collect();
+    sorted_and_deduplicated(actions)
+}
+
+/// Moves at constant speed from the lowest position to the highest and back again.
+///
+/// The turning points are always included, whatever the sample interval.
+pub fn triangle(params: &PatternParams) -> Vec<NormalisedAction> {
+    let half_period = (params.period_ms / 2).max(1);
+    let corners = (0..=params.duration_ms / half_period).map(|idx| idx * half_period);
+    let actions = params
+        .sample_times()
+        .chain(corners)
+        .map(|at| {
+            let phase = params.phase(at);
+            let fraction = if phase < 0.5 {
+                phase * 2.0
+            } else {
+                2.0 - phase * 2.0
+            };
+            NormalisedAction {
+                at,
+                norm_pos: params.position(fraction),
+            }
+        })
+        .collect();
+    sorted_and_deduplicated(actions)
+}
+
+/// Moves at constant speed from the lowest position to the highest,
+/// then jumps back to the lowest at the start of the next cycle.
+pub fn sawtooth(params: &PatternParams) -> Vec<NormalisedAction> {
+    let actions = params
+        .sample_times()
+        .map(|at| NormalisedAction {
+            at,
+            norm_pos: params.position(params.phase(at)),
+        })
+        .collect();
+    sorted_and_deduplicated(actions)
+}
+
+/// Wanders randomly between the lowest and highest positions, starting in the middle.
+///
+/// Each step moves at most as far as a triangle wave with the same period would,
+/// bouncing off the limits. The same seed always gives the same pattern.
+pub fn random_walk(params: &PatternParams, seed: u64) -> Vec<NormalisedAction> {
+    let mut rng = SplitMix64(seed);
+    let mut fraction = 0.5f32;
+    let mut previous_at = 0;
+
+    let actions = params
+        .sample_times()
+        .map(|at| {
+            let max_step = 2.0 * (at - previous_at) as f32 / params.period_ms.max(1) as f32;
+            previous_at = at;
+            fraction += (rng.next_f32() * 2.0 - 1.0) * max_step;
+            // Bounce off the limits
+            fraction = fraction.rem_euclid(2.0);
+            if fraction > 1.0 {
+                fraction = 2.0 - fraction;
+            }
+            NormalisedAction {
+                at,
+                norm_pos: params.position(fraction),
+            }
+        })
+        .collect();
+    sorted_and_deduplicated(actions)
+}
+
+/// A small, fast, deterministic random number generator. Not for anything secret.
+pub(crate) struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random number from 0.0 (inclusive) to 1.0 (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::processing::NormalisedAction;
+
+    use super::{random_walk, sawtooth, sine, triangle, Pattern, PatternParams};
+
+    fn params(sample_interval_ms: u32) -> PatternParams {
+        PatternParams {
+            duration_ms: 2000,
+            period_ms: 1000,
+            min: 0.2,
+            max: 0.8,
+            sample_interval_ms,
+        }
+    }
+
+    fn assert_well_formed(actions: &[NormalisedAction]) {
+        assert!(actions.windows(2).all(|pair| pair[0].at < pair[1].at));
+        assert!(actions
+            .iter()
+            .all(|action| (0.2 - 1e-6..=0.8 + 1e-6).contains(&action.norm_pos)));
+        assert_eq!(actions.first().unwrap().at, 0);
+        assert_eq!(actions.last().unwrap().at, 2000);
+    }
+
+    fn assert_positions(actions: &[NormalisedAction], expected: &[(u32, f32)]) {
+        for &(at, norm_pos) in expected {
+            let action = actions.iter().find(|action| action.at == at).unwrap();
+            assert!(
+                (action.norm_pos - norm_pos).abs() < 1e-5,
+                "at {at}: {} != {norm_pos}",
+                action.norm_pos
+            );
+        }
+    }
+
+    #[test]
+    fn test_sine() {
+        let actions = sine(&params(250));
+        assert_well_formed(&actions);
+        assert_eq!(actions.len(), 9);
+        assert_positions(&actions, &[(0, 0.2), (250, 0.5), (500, 0.8), (1000, 0.2)]);
+    }
+
+    #[test]
+    fn test_triangle() {
+        // Sampling doesn't line up with the turning points, but they're still there
+        let actions = triangle(&params(300));
+        assert_well_formed(&actions);
+        assert_positions(
+            &actions,
+            &[(0, 0.2), (300, 0.56), (500, 0.8), (1000, 0.2), (1500, 0.8)],
+        );
+    }
+
+    #[test]
+    fn test_sawtooth() {
+        let actions = sawtooth(&params(250));
+        assert_well_formed(&actions);
+        assert_positions(&actions, &[(0, 0.2), (750, 0.65), (1000, 0.2), (2000, 0.2)]);
+    }
+
+    #[test]
+    fn test_pattern() {
+        let same = |a: &[NormalisedAction], b: &[NormalisedAction]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.at == b.at && a.norm_pos == b.norm_pos)
+        };
+        let params = params(100);
+        assert!(same(&Pattern::Sine.generate(&params, 1), &sine(&params)));
+        assert!(same(
+            &Pattern::Triangle.generate(&params, 1),
+            &triangle(&params)
+        ));
+        assert!(same(
+            &Pattern::RandomWalk.generate(&params, 7),
+            &random_walk(&params, 7)
+        ));
+
+        let pattern: Pattern = serde_json::from_str("\"random_walk\"").unwrap();
+        assert_eq!(pattern, Pattern::RandomWalk);
+    }
+
+    #[test]
+    fn test_random_walk() {
+        let actions = random_walk(&params(50), 42);
+        assert_well_formed(&actions);
+        assert_eq!(actions.len(), 41);
+        // No step is faster than the equivalent triangle wave
+        for pair in actions.windows(2) {
+            let step = (pair[1].norm_pos - pair[0].norm_pos).abs();
+            assert!(step <= 0.6 * 2.0 * 50.0 / 1000.0 + 1e-6);
+        }
+
+        // Deterministic for a seed
+        let again = random_walk(&params(50), 42);
+        assert!(actions
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.at == b.at && a.norm_pos == b.norm_pos));
+        let other = random_walk(&params(50), 43);
+        assert!(actions
+            .iter()
+            .zip(&other)
+            .any(|(a, b)| a.norm_pos != b.norm_pos));
+    }
+}
@@ -0,0 +1,171 @@
+//! Cleaning up text that is almost, but not quite, JSON, as some funscript editors write.
+//!
+//! See [`Funscript::from_slice_lenient`](crate::schema::Funscript::from_slice_lenient).
+
+use crate::repair::Repair;
+
+const BYTE_ORDER_MARK: &[u8] = b"\xEF\xBB\xBF";
+
+/// Non-finite numbers as JavaScript writes them, which JSON has no way to express.
+const NON_FINITE: [&[u8]; 4] = [b"NaN", b"Infinity", b"-Infinity", b"+Infinity"];
+
+/// Turns almost-JSON into JSON, returning it along with the repairs that were needed:
+/// - a byte order mark at the start is stripped
+/// - `//` and `/* */` comments are removed
+/// - trailing commas in objects and arrays are removed
+/// - `NaN` and `Infinity` are replaced with `null`
+///
+/// Strings are left alone. Anything else that isn't JSON is left for the parser to reject.
+pub(crate) fn clean_json(text: &[u8]) -> (Vec<u8>, Vec<Repair>) {
+    let mut repairs = Vec::new();
+    let text = match text.strip_prefix(BYTE_ORDER_MARK) {
+        Some(rest) => {
+            repairs.push(Repair::StrippedByteOrderMark);
+            rest
+        }
+        None => text,
+    };
+
+    let mut out = Vec::with_capacity(text.len());
+    let mut comments = 0;
+    let mut trailing_commas = 0;
+    let mut non_finite = 0;
+    // Where in `out` the last comma is, if only whitespace and comments have come since
+    let mut pending_comma: Option<usize> = None;
+
+    let mut idx = 0;
+    while idx < text.len() {
+        let rest = &text[idx..];
+        match rest[0] {
+            b'"' => {
+                let len = string_len(rest);
+                out.extend_from_slice(&rest[..len]);
+                pending_comma = None;
+                idx += len;
+            }
+            b'/' if rest.starts_with(b"//") => {
+                let len = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                comments += 1;
+                idx += len;
+            }
+            b'/' if rest.starts_with(b"/*") => {
+                let len = rest[2..]
+                    .windows(2)
+                    .position(|pair| pair == b"*/")
+                    .map_or(rest.len(), |end| end + 4);
+                // Keep tokens either side of the comment apart
+                out.push(b' ');
+                comments += 1;
+                idx += len;
+            }
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out.remove(comma);
+                    trailing_commas += 1;
+                }
+                out.push(rest[0]);
+                idx += 1;
+            }
+            b',' => {
+                pending_comma = Some(out.len());
+                out.push(b',');
+                idx += 1;
+            }
+            byte if byte.is_ascii_whitespace() => {
+                out.push(byte);
+                idx += 1;
+            }
+            _ => {
+                pending_comma = None;
+                if let Some(token) = NON_FINITE
+                    .iter()
+                    .find(|token| starts_with_word(rest, token))
+                {
+                    out.extend_from_slice(b"null");
+                    non_finite += 1;
+                    idx += token.len();
+                } else {
+                    out.push(rest[0]);
+                    idx += 1;
+                }
+            }
+        }
+    }
+
+    if comments > 0 {
+        repairs.push(Repair::RemovedComments { count: comments });
+    }
+    if trailing_commas > 0 {
+        repairs.push(Repair::RemovedTrailingCommas {
+            count: trailing_commas,
+        });
+    }
+    if non_finite > 0 {
+        repairs.push(Repair::ReplacedNonFiniteNumbers { count: non_finite });
+    }
+    (out, repairs)
+}
+
+/// The length of the string literal at the start of `text`, including its quotes.
+/// An unterminated string runs to the end.
+fn string_len(text: &[u8]) -> usize {
+    let mut escaped = false;
+    for (idx, &byte) in text.iter().enumerate().skip(1) {
+        match byte {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return idx + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Whether `text` starts with `word`, not followed by anything that would make it a longer word.
+fn starts_with_word(text: &[u8], word: &[u8]) -> bool {
+    text.starts_with(word)
+        && !text
+            .get(word.len())
+            .is_some_and(|&next| next.is_ascii_alphanumeric() || next == b'_')
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repair::Repair;
+
+    use super::clean_json;
+
+    fn cleaned(text: &str) -> (String, Vec<Repair>) {
+        let (out, repairs) = clean_json(text.as_bytes());
+        (String::from_utf8(out).unwrap(), repairs)
+    }
+
+    #[test]
+    fn test_clean_json() {
+        let (out, repairs) = cleaned(
+            "\u{feff}{\"a\": [1, 2,], // two\n \"b\": NaN, /* gone */ \"c\": -Infinity,\n}",
+        );
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"a": [1, 2], "b": null, "c": null})
+        );
+        assert_eq!(
+            repairs,
+            vec![
+                Repair::StrippedByteOrderMark,
+                Repair::RemovedComments { count: 2 },
+                Repair::RemovedTrailingCommas { count: 2 },
+                Repair::ReplacedNonFiniteNumbers { count: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clean_json_leaves_strings_alone() {
+        let text = r#"{"title": "NaN, // not a comment, ]", "note": "say \"/*\"", "NaNa": 1}"#;
+        let (out, repairs) = cleaned(text);
+        assert_eq!(out, text);
+        assert!(repairs.is_empty());
+    }
+}
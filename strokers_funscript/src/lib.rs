@@ -1,4 +1,53 @@
+pub mod archive;
+pub mod csv;
+pub mod diff;
+#[cfg(feature = "fast_parse")]
+mod fast_parse;
+pub mod generate;
+mod lenient;
+pub mod limits;
 pub mod playstate;
 pub mod processing;
+pub mod repair;
 pub mod schema;
 pub mod search_path;
+mod slice;
+pub mod stats;
+
+use thiserror::Error;
+
+/// How far past the end of the video a funscript can go before it's worth a warning.
+/// Scripts often run on a little after the video, but hours of extra actions suggest that
+/// the script is for a different video (or a different cut of it).
+const BEYOND_VIDEO_TOLERANCE_MS: u32 = 60_000;
+
+/// Something wrong with a funscript that makes it unlikely to do what the user wants,
+/// from [`Funscript::sanity_check`](schema::Funscript::sanity_check).
+///
+/// These aren't fatal: the script can still be played, but the user should be told.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum FunscriptError {
+    #[error("funscript has no actions")]
+    Empty,
+
+    #[error("funscript has only one action, so never moves")]
+    SingleAction,
+
+    #[error("funscript never moves from position {pos}")]
+    NoMovement { pos: u32 },
+
+    #[error("funscript goes on for {} s beyond the end of the video", .by_ms / 1000)]
+    ExtendsBeyondVideo { by_ms: u32 },
+}
+
+impl FunscriptError {
+    /// Checks that a funscript whose last action is at `last_action_at` fits the video,
+    /// allowing it to go on a little beyond the end.
+    pub fn check_video_duration(
+        last_action_at: u32,
+        video_duration_ms: u32,
+    ) -> Option<FunscriptError> {
+        let by_ms = last_action_at.saturating_sub(video_duration_ms);
+        (by_ms > BEYOND_VIDEO_TOLERANCE_MS).then_some(FunscriptError::ExtendsBeyondVideo { by_ms })
+    }
+}
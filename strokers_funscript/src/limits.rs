@@ -0,0 +1,31 @@
+//! The maths of limiting an axis's range and speed, shared by the limiter used during playback
+//! and [`apply_limits`](crate::processing::apply_limits), which bakes limits into a script.
+
+/// Where to command an axis to go to, given that it's at `current` (on the device's scale)
+/// and the script wants it at `target` (on the script's scale) in `duration_millis` ms.
+///
+/// `target` is first scaled into the band from `min` to `max`. If getting there in time would
+/// take more than `speed_limit` full-scale movements per second, the axis is sent only as far
+/// towards it as it can go at that speed, so the duration of the movement is left as it is.
+pub fn limit_target(
+    current: f32,
+    target: f32,
+    duration_millis: u32,
+    speed_limit: f32,
+    min: f32,
+    max: f32,
+) -> f32 {
+    // Apply top and bottom limits
+    let target = min + (max - min) * target;
+
+    let delta = target - current;
+
+    let speed_abs = delta.abs() / (duration_millis.max(1) as f32 * 0.001);
+
+    if speed_abs < speed_limit {
+        target
+    } else {
+        let proposed_delta = delta * (speed_limit / speed_abs);
+        current + proposed_delta
+    }
+}
@@ -1,10 +1,53 @@
 use std::sync::Arc;
 
+use serde::Serialize;
 use tracing::debug;
 
-use crate::processing::NormalisedAction;
+use crate::{
+    processing::{is_sorted, NormalisedAction},
+    schema::FunscriptAction,
+};
+
+/// A jump backwards in time by more than this between ticks is treated as a seek,
+/// e.g. when the player restarts the video (in loop mode) or seeks without saying so.
+/// Smaller ones, as players report around A/V resyncs and frame stepping, are carried on from.
+const BACKWARDS_JUMP_MS: u32 = 100;
+
+/// How long before the first action it is returned by [`FunscriptPlaystate::tick`],
+/// so that there is time to move to its position. Until then, there is nothing to do.
+pub const FIRST_ACTION_LEAD_IN_MS: u32 = 1000;
+
+/// Progress of playback through a funscript, from [`FunscriptPlaystate::status`].
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct PlaystateStatus {
+    /// How many actions have been passed (in the current loop, in loop mode).
+    pub index: usize,
+    /// How many actions the funscript has.
+    pub action_count: usize,
+    /// `index` as a fraction of `action_count`, from 0.0 to 1.0.
+    pub fraction_complete: f32,
+    /// Time of the last action passed, as from [`FunscriptPlaystate::peek_previous`].
+    pub previous_at: Option<u32>,
+    /// Time of the next action, as from [`FunscriptPlaystate::peek_next`].
+    pub next_at: Option<u32>,
+    /// Whether playback has run past the last action. Never true in loop mode.
+    pub finished: bool,
+}
+
+/// An action from [`FunscriptPlaystate::tick_traced`], along with where it came from,
+/// e.g. to log when motion doesn't seem to match the script.
+#[derive(Clone, Debug)]
+pub struct TracedAction {
+    pub action: NormalisedAction,
+    /// The action in the funscript at the same time, as it was in the file (before normalising).
+    /// `None` if processing made the action up (e.g. resampling), or without raw actions.
+    pub raw: Option<FunscriptAction>,
+}
 
 /// Tracker for playback of a funscript.
+///
+/// In loop mode, the script repeats forever; times are taken modulo the length of the loop
+/// and the actions returned are shifted into the loop that is playing.
 pub struct FunscriptPlaystate {
     /// The normalised actions from the funscript
     normalised_actions: Arc<Vec<NormalisedAction>>,
@@ -14,59 +57,912 @@ pub struct FunscriptPlaystate {
 
     /// Time at which the next tick is due
     next_tick_at: Option<u32>,
+
+    /// Whether playback wraps around to the start after the last action
+    looping: bool,
+
+    /// Length of a loop in milliseconds, if longer than the time of the last action
+    loop_duration: Option<u32>,
+
+    /// How many times playback has wrapped around; the loop `next_index` is in
+    cycle: u32,
+
+    /// Time of the last tick or seek, to detect jumps backwards
+    last_tick_at: Option<u32>,
+
+    /// The actions as they were in the funscript, for [`Self::tick_traced`]
+    raw_actions: Option<Arc<Vec<FunscriptAction>>>,
 }
 
 impl FunscriptPlaystate {
+    /// The actions must be sorted by time, as they are from
+    /// [`normalised_from_funscript`](crate::processing::normalised_from_funscript).
     pub fn new(normalised_actions: Arc<Vec<NormalisedAction>>) -> FunscriptPlaystate {
+        debug_assert!(
+            is_sorted(&normalised_actions),
+            "playstate actions must be sorted by time"
+        );
         FunscriptPlaystate {
+            next_tick_at: lead_in_tick_at(&normalised_actions, 0),
             normalised_actions,
             next_index: 0,
-            next_tick_at: Some(0),
+            looping: false,
+            loop_duration: None,
+            cycle: 0,
+            last_tick_at: None,
+            raw_actions: None,
+        }
+    }
+
+    /// Gives the actions as they were in the funscript, sorted by time, so that
+    /// [`Self::tick_traced`] can say what each action was before normalising.
+    ///
+    /// This is only for debugging, so costs nothing unless given.
+    pub fn set_raw_actions(&mut self, raw_actions: Arc<Vec<FunscriptAction>>) {
+        self.raw_actions = Some(raw_actions);
+    }
+
+    /// Enables or disables loop mode. Takes effect from the next seek or tick.
+    pub fn set_loop(&mut self, enabled: bool) {
+        self.looping = enabled;
+        if !enabled {
+            self.cycle = 0;
+        }
+    }
+
+    /// Sets the length of a loop in milliseconds, e.g. to the length of the video.
+    ///
+    /// The loop is never shorter than the time of the last action, which is the default.
+    pub fn set_loop_duration(&mut self, duration_milliseconds: Option<u32>) {
+        self.loop_duration = duration_milliseconds;
+    }
+
+    /// The length of a loop, or `None` if not in loop mode (or the loop would be empty).
+    fn loop_length(&self) -> Option<u32> {
+        if !self.looping {
+            return None;
+        }
+        let last_at = self.normalised_actions.last()?.at;
+        let length = self.loop_duration.unwrap_or(0).max(last_at);
+        (length > 0).then_some(length)
+    }
+
+    /// Shifts an action from the script into the given loop.
+    fn in_cycle(action: NormalisedAction, cycle: u32, loop_length: u32) -> NormalisedAction {
+        NormalisedAction {
+            at: action.at.saturating_add(cycle.saturating_mul(loop_length)),
+            ..action
         }
     }
 
     /// Seek in the stream to a given time in milliseconds.
+    ///
+    /// The next tick returns the action after that time straight away, so that the position
+    /// is updated when there's the chance, unless the time is before the first action
+    /// (of the first loop), when it waits until [`FIRST_ACTION_LEAD_IN_MS`] before it.
     pub fn seek(&mut self, time_milliseconds: u32) {
         let idx_old = self.next_index;
-        // always tick immediately so that we update our position when we get the chance
         self.next_tick_at = Some(time_milliseconds);
+        self.last_tick_at = Some(time_milliseconds);
 
-        self.next_index = match self
-            .normalised_actions
-            .binary_search_by_key(&(time_milliseconds + 1), |action| action.at)
-        {
-            Ok(idx) => idx,
-            Err(idx) => idx,
+        let script_time = match self.loop_length() {
+            Some(loop_length) => {
+                self.cycle = time_milliseconds / loop_length;
+                time_milliseconds % loop_length
+            }
+            None => time_milliseconds,
         };
 
+        // The first action after the time.
+        // (Not a search for `script_time + 1`, which would overflow at `u32::MAX`.)
+        self.next_index = self
+            .normalised_actions
+            .partition_point(|action| action.at <= script_time);
+
+        if self.next_index == 0 && self.cycle == 0 {
+            self.next_tick_at = lead_in_tick_at(&self.normalised_actions, time_milliseconds);
+        }
+
         let idx_new = self.next_index;
-        let idx_1 = idx_new - 1;
-        let ele_1 = self.normalised_actions.get(idx_1);
+        let ele_1 = idx_new
+            .checked_sub(1)
+            .and_then(|idx_1| self.normalised_actions.get(idx_1));
         let ele_2 = self.normalised_actions.get(idx_new);
         let idx_3 = idx_new + 1;
         let ele_3 = self.normalised_actions.get(idx_3);
-        debug!("sought from idx{idx_old} to idx{idx_new} ({idx_1}={ele_1:?}, {idx_new}={ele_2:?}, {idx_3}={ele_3:?})");
+        debug!("sought from idx{idx_old} to idx{idx_new} (previous={ele_1:?}, {idx_new}={ele_2:?}, {idx_3}={ele_3:?})");
     }
 
     /// Inform the playstate about the current time and see if there is an action to be performed
+    ///
+    /// Each action is returned once the previous one is reached, to move towards it in the time
+    /// until it is due. The first action is returned [`FIRST_ACTION_LEAD_IN_MS`] before it
+    /// (or straight away, if it is sooner than that), rather than at the start of playback.
+    ///
+    /// If the time goes backwards by more than a little since the last tick, this seeks to it;
+    /// if only a little, playback carries on as though it hadn't.
     pub fn tick(&mut self, time_milliseconds: u32) -> Option<NormalisedAction> {
-        let Some(next_tick_at) = self.next_tick_at else {
-            return None;
-        };
-
-        if time_milliseconds < next_tick_at {
-            return None;
+        let jumped_back = self
+            .last_tick_at
+            .is_some_and(|last| time_milliseconds.saturating_add(BACKWARDS_JUMP_MS) < last);
+        if jumped_back {
+            debug!("time jumped back to {time_milliseconds}; seeking");
+            self.seek(time_milliseconds);
+        } else if let Some(loop_length) = self.loop_length() {
+            // e.g. because the loop is shorter than the time between ticks
+            let fallen_behind = self
+                .peek_next()
+                .is_some_and(|next| next.at.saturating_add(loop_length) <= time_milliseconds);
+            if fallen_behind {
+                self.seek(time_milliseconds);
+            } else if self.next_index >= self.normalised_actions.len() {
+                self.next_index = 0;
+                self.cycle += 1;
+            }
         }
+        self.last_tick_at = Some(time_milliseconds);
 
-        if self.next_index >= self.normalised_actions.len() {
+        let next_tick_at = self.next_tick_at?;
+
+        if time_milliseconds < next_tick_at {
             return None;
         }
 
-        let next_action = self.normalised_actions[self.next_index];
-        self.next_index += 1;
+        let next_action = self.peek_next()?;
+        self.next_index = self.last_at_same_time(self.next_index) + 1;
 
         self.next_tick_at = Some(next_action.at);
 
         Some(next_action)
     }
+
+    /// Like [`tick`](Self::tick), but along with the action in the funscript that the action
+    /// came from, if raw actions were given with [`Self::set_raw_actions`].
+    pub fn tick_traced(&mut self, time_milliseconds: u32) -> Option<TracedAction> {
+        let action = self.tick(time_milliseconds)?;
+        let raw = self.raw_actions.as_ref().and_then(|raw_actions| {
+            // The action just ticked past, or at the end of a loop, the first of the next one
+            let idx = self
+                .next_index
+                .checked_sub(1)
+                .filter(|&idx| idx < self.normalised_actions.len())
+                .unwrap_or(0);
+            let script_at = self.normalised_actions[idx].at;
+            // The last of any at that time, as only the last is played
+            let raw_idx = raw_actions
+                .partition_point(|raw| raw.at <= script_at)
+                .checked_sub(1)?;
+            raw_actions
+                .get(raw_idx)
+                .filter(|raw| raw.at == script_at)
+                .cloned()
+        });
+        Some(TracedAction { action, raw })
+    }
+
+    /// The index of the last action at the same time as the action at `idx`
+    /// (`idx` itself unless there are several), or `idx` if it's beyond the actions.
+    ///
+    /// Actions at the same time would ask for different positions at once, so only the last
+    /// of them is played, as [`Funscript::fixup`](crate::schema::Funscript::fixup) would keep.
+    fn last_at_same_time(&self, idx: usize) -> usize {
+        let actions = &self.normalised_actions;
+        let Some(action) = actions.get(idx) else {
+            return idx;
+        };
+        idx + actions[idx + 1..].partition_point(|later| later.at == action.at)
+    }
+
+    /// The action that the next [`tick`](Self::tick) will return, without consuming it.
+    /// `None` once playback is finished.
+    pub fn peek_next(&self) -> Option<NormalisedAction> {
+        let next = self
+            .normalised_actions
+            .get(self.last_at_same_time(self.next_index));
+        let Some(loop_length) = self.loop_length() else {
+            return next.copied();
+        };
+        match next {
+            Some(&action) => Some(Self::in_cycle(action, self.cycle, loop_length)),
+            None => Some(Self::in_cycle(
+                *self.normalised_actions.get(self.last_at_same_time(0))?,
+                self.cycle + 1,
+                loop_length,
+            )),
+        }
+    }
+
+    /// The action most recently returned by [`tick`](Self::tick),
+    /// or after a [`seek`](Self::seek), the last action at or before the time sought to.
+    /// `None` before the first action.
+    pub fn peek_previous(&self) -> Option<NormalisedAction> {
+        let Some(loop_length) = self.loop_length() else {
+            let idx = self.next_index.checked_sub(1)?;
+            return self.normalised_actions.get(idx).copied();
+        };
+        match self.next_index.checked_sub(1) {
+            Some(idx) => Some(Self::in_cycle(
+                *self.normalised_actions.get(idx)?,
+                self.cycle,
+                loop_length,
+            )),
+            None => Some(Self::in_cycle(
+                *self.normalised_actions.last()?,
+                self.cycle.checked_sub(1)?,
+                loop_length,
+            )),
+        }
+    }
+
+    /// Progress of playback, e.g. for display. Cheap enough to call every tick.
+    pub fn status(&self) -> PlaystateStatus {
+        let action_count = self.normalised_actions.len();
+        let index = self.next_index.min(action_count);
+        PlaystateStatus {
+            index,
+            action_count,
+            fraction_complete: if action_count == 0 {
+                1.0
+            } else {
+                index as f32 / action_count as f32
+            },
+            previous_at: self.peek_previous().map(|action| action.at),
+            next_at: self.peek_next().map(|action| action.at),
+            finished: self.loop_length().is_none() && index >= action_count,
+        }
+    }
+
+    /// The pair of actions either side of the given time: the last action at or before it
+    /// and the first action after it.
+    ///
+    /// `None` if the time is before the first action or at or after the last one,
+    /// unless in loop mode, where the pair can span the end of one loop and the start of the next.
+    /// This doesn't affect playback.
+    pub fn segment_at(
+        &self,
+        time_milliseconds: u32,
+    ) -> Option<(NormalisedAction, NormalisedAction)> {
+        let actions = &self.normalised_actions;
+        let Some(loop_length) = self.loop_length() else {
+            let idx = actions.partition_point(|action| action.at <= time_milliseconds);
+            let previous = *actions.get(idx.checked_sub(1)?)?;
+            let next = *actions.get(self.last_at_same_time(idx))?;
+            return Some((previous, next));
+        };
+
+        let cycle = time_milliseconds / loop_length;
+        let script_time = time_milliseconds % loop_length;
+        let idx = actions.partition_point(|action| action.at <= script_time);
+        let previous = match idx.checked_sub(1) {
+            Some(idx) => Self::in_cycle(actions[idx], cycle, loop_length),
+            None => Self::in_cycle(*actions.last()?, cycle.checked_sub(1)?, loop_length),
+        };
+        let next = match actions.get(self.last_at_same_time(idx)) {
+            Some(&action) => Self::in_cycle(action, cycle, loop_length),
+            None => Self::in_cycle(actions[self.last_at_same_time(0)], cycle + 1, loop_length),
+        };
+        Some((previous, next))
+    }
+}
+
+/// When to tick for the first action, if playback is at `time_milliseconds` and hasn't
+/// reached it yet: not before the lead-in, but straight away if that's already passed.
+fn lead_in_tick_at(actions: &[NormalisedAction], time_milliseconds: u32) -> Option<u32> {
+    let first = actions.first()?;
+    Some(
+        first
+            .at
+            .saturating_sub(FIRST_ACTION_LEAD_IN_MS)
+            .max(time_milliseconds),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        processing::{normalised_from_funscript, NormalisedAction},
+        schema::{Funscript, FunscriptAction},
+    };
+
+    use super::{FunscriptPlaystate, PlaystateStatus, TracedAction};
+
+    fn playstate() -> FunscriptPlaystate {
+        FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 100,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 200,
+                norm_pos: 1.0,
+            },
+            NormalisedAction {
+                at: 300,
+                norm_pos: 0.5,
+            },
+        ]))
+    }
+
+    fn at(action: Option<NormalisedAction>) -> Option<u32> {
+        action.map(|action| action.at)
+    }
+
+    #[test]
+    fn test_peek_follows_tick() {
+        let mut playstate = playstate();
+        assert_eq!(at(playstate.peek_previous()), None);
+        assert_eq!(at(playstate.peek_next()), Some(100));
+        // Peeking doesn't consume
+        assert_eq!(at(playstate.peek_next()), Some(100));
+
+        assert_eq!(at(playstate.tick(0)), Some(100));
+        assert_eq!(at(playstate.peek_previous()), Some(100));
+        assert_eq!(at(playstate.peek_next()), Some(200));
+
+        assert_eq!(at(playstate.tick(50)), None);
+        assert_eq!(at(playstate.peek_next()), Some(200));
+        assert_eq!(at(playstate.tick(100)), Some(200));
+        assert_eq!(at(playstate.tick(200)), Some(300));
+
+        // After the last action
+        assert_eq!(at(playstate.peek_previous()), Some(300));
+        assert_eq!(at(playstate.peek_next()), None);
+        assert_eq!(at(playstate.tick(1000)), None);
+    }
+
+    #[test]
+    fn test_peek_follows_seek() {
+        let mut playstate = playstate();
+
+        playstate.seek(0);
+        assert_eq!(at(playstate.peek_previous()), None);
+        assert_eq!(at(playstate.peek_next()), Some(100));
+
+        playstate.seek(200);
+        assert_eq!(at(playstate.peek_previous()), Some(200));
+        assert_eq!(at(playstate.peek_next()), Some(300));
+        assert_eq!(at(playstate.tick(200)), Some(300));
+
+        playstate.seek(5000);
+        assert_eq!(at(playstate.peek_previous()), Some(300));
+        assert_eq!(at(playstate.peek_next()), None);
+    }
+
+    #[test]
+    fn test_segment_at() {
+        let playstate = playstate();
+        let segment = |time| {
+            playstate
+                .segment_at(time)
+                .map(|(previous, next)| (previous.at, next.at))
+        };
+        assert_eq!(segment(0), None);
+        assert_eq!(segment(99), None);
+        assert_eq!(segment(100), Some((100, 200)));
+        assert_eq!(segment(150), Some((100, 200)));
+        assert_eq!(segment(200), Some((200, 300)));
+        assert_eq!(segment(299), Some((200, 300)));
+        assert_eq!(segment(300), None);
+        assert_eq!(segment(u32::MAX), None);
+        // Doesn't affect playback
+        assert_eq!(at(playstate.peek_next()), Some(100));
+    }
+
+    fn looping_playstate() -> FunscriptPlaystate {
+        let mut playstate = FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 0,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 500,
+                norm_pos: 1.0,
+            },
+            NormalisedAction {
+                at: 1000,
+                norm_pos: 0.0,
+            },
+        ]));
+        playstate.set_loop(true);
+        playstate
+    }
+
+    fn raw(pairs: &[(u32, u32)]) -> Arc<Vec<FunscriptAction>> {
+        Arc::new(
+            pairs
+                .iter()
+                .map(|&(at, pos)| FunscriptAction { at, pos })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_tick_traced() {
+        let mut playstate = playstate();
+        // Without raw actions, there's nothing to trace back to
+        let traced = playstate.tick_traced(0).unwrap();
+        assert_eq!((traced.action.at, traced.raw), (100, None));
+
+        // As though the action at 300 was made up by resampling
+        let mut playstate = self::playstate();
+        playstate.set_raw_actions(raw(&[(100, 0), (200, 100)]));
+        let raw_at = |traced: Option<TracedAction>| traced.unwrap().raw.map(|raw| raw.at);
+        assert_eq!(raw_at(playstate.tick_traced(0)), Some(100));
+        let traced = playstate.tick_traced(100).unwrap();
+        assert_eq!(traced.raw, Some(FunscriptAction { at: 200, pos: 100 }));
+        assert_eq!(traced.action.norm_pos, 1.0);
+        assert_eq!(raw_at(playstate.tick_traced(200)), None);
+        assert!(playstate.tick_traced(1000).is_none());
+
+        // Later loops trace back to the same raw actions
+        let mut playstate = looping_playstate();
+        playstate.set_raw_actions(raw(&[(0, 0), (500, 100), (1000, 0)]));
+        let traced: Vec<(u32, Option<u32>)> = [0, 100, 500, 1000, 1010]
+            .into_iter()
+            .map(|time| {
+                let traced = playstate.tick_traced(time).unwrap();
+                (traced.action.at, traced.raw.map(|raw| raw.at))
+            })
+            .collect();
+        assert_eq!(
+            traced,
+            vec![
+                (0, Some(0)),
+                (500, Some(500)),
+                (1000, Some(1000)),
+                (1000, Some(0)),
+                (1500, Some(500))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_loop_wraps_around() {
+        let mut playstate = looping_playstate();
+        let ticks: Vec<Option<u32>> = [0, 100, 500, 990, 1000, 1010, 1500, 2000]
+            .into_iter()
+            .map(|time| at(playstate.tick(time)))
+            .collect();
+        assert_eq!(
+            ticks,
+            vec![
+                Some(0),
+                Some(500),
+                Some(1000),
+                None,
+                // The start of the second loop
+                Some(1000),
+                Some(1500),
+                Some(2000),
+                Some(2000)
+            ]
+        );
+        assert_eq!(at(playstate.peek_previous()), Some(2000));
+        assert_eq!(at(playstate.peek_next()), Some(2500));
+
+        // Restarting the video counts as a seek
+        assert_eq!(at(playstate.tick(0)), Some(500));
+        assert_eq!(at(playstate.peek_next()), Some(1000));
+
+        // Seeking into a later loop
+        playstate.seek(3700);
+        assert_eq!(at(playstate.peek_previous()), Some(3500));
+        assert_eq!(at(playstate.tick(3700)), Some(4000));
+    }
+
+    #[test]
+    fn test_loop_without_looping() {
+        let mut playstate = looping_playstate();
+        playstate.set_loop(false);
+        for time in [0, 100, 500] {
+            playstate.tick(time);
+        }
+        assert_eq!(at(playstate.tick(1000)), None);
+        assert_eq!(at(playstate.peek_next()), None);
+        assert!(playstate.segment_at(1000).is_none());
+    }
+
+    #[test]
+    fn test_loop_duration() {
+        let mut playstate = looping_playstate();
+        // The video is longer than the script
+        playstate.set_loop_duration(Some(1200));
+        playstate.seek(1100);
+        assert_eq!(at(playstate.peek_previous()), Some(1000));
+        assert_eq!(at(playstate.tick(1100)), Some(1200));
+        assert_eq!(at(playstate.tick(1200)), Some(1700));
+
+        // Shorter than the script: ignored
+        playstate.set_loop_duration(Some(300));
+        playstate.seek(1100);
+        assert_eq!(at(playstate.peek_next()), Some(1500));
+    }
+
+    #[test]
+    fn test_loop_shorter_than_tick_interval() {
+        let mut playstate = FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 0,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 50,
+                norm_pos: 1.0,
+            },
+            NormalisedAction {
+                at: 100,
+                norm_pos: 0.0,
+            },
+        ]));
+        playstate.set_loop(true);
+
+        // Every tick gives an action that is still to come, rather than one from a past loop
+        for time in (0..2000).step_by(250) {
+            let action = playstate.tick(time).unwrap();
+            assert!(
+                (time..time + 100).contains(&action.at),
+                "at {time}: {action:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_loop_segment_at() {
+        let playstate = looping_playstate();
+        let segment = |time| {
+            playstate
+                .segment_at(time)
+                .map(|(previous, next)| (previous.at, next.at))
+        };
+        assert_eq!(segment(0), Some((0, 500)));
+        assert_eq!(segment(999), Some((500, 1000)));
+        assert_eq!(segment(1000), Some((1000, 1500)));
+        assert_eq!(segment(2250), Some((2000, 2500)));
+    }
+
+    #[test]
+    fn test_status() {
+        let mut playstate = playstate();
+        let status = playstate.status();
+        assert_eq!(
+            status,
+            PlaystateStatus {
+                index: 0,
+                action_count: 3,
+                fraction_complete: 0.0,
+                previous_at: None,
+                next_at: Some(100),
+                finished: false,
+            }
+        );
+
+        playstate.tick(0);
+        playstate.tick(100);
+        let status = playstate.status();
+        assert_eq!((status.index, status.previous_at), (2, Some(200)));
+        assert!((status.fraction_complete - 2.0 / 3.0).abs() < 1e-6);
+
+        // Past the end
+        playstate.seek(5000);
+        let status = playstate.status();
+        assert_eq!((status.index, status.fraction_complete), (3, 1.0));
+        assert_eq!((status.previous_at, status.next_at), (Some(300), None));
+        assert!(status.finished);
+
+        // Back to the start
+        playstate.seek(0);
+        assert_eq!(
+            playstate.status(),
+            PlaystateStatus {
+                index: 0,
+                action_count: 3,
+                fraction_complete: 0.0,
+                previous_at: None,
+                next_at: Some(100),
+                finished: false,
+            }
+        );
+
+        // Never finished in loop mode
+        playstate.set_loop(true);
+        playstate.seek(5050);
+        let status = playstate.status();
+        assert_eq!(
+            (status.index, status.previous_at, status.next_at),
+            (2, Some(5000), Some(5100))
+        );
+        assert!(!status.finished);
+
+        let empty = FunscriptPlaystate::new(Arc::new(Vec::new())).status();
+        assert_eq!((empty.action_count, empty.fraction_complete), (0, 1.0));
+        assert!(empty.finished);
+    }
+
+    #[test]
+    fn test_seek_before_first_action() {
+        let mut playstate = FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 5000,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 6000,
+                norm_pos: 1.0,
+            },
+        ]));
+        playstate.seek(0);
+        assert_eq!(at(playstate.peek_previous()), None);
+        // Nothing to do until the lead-in before the first action
+        assert_eq!(at(playstate.tick(0)), None);
+        assert_eq!(at(playstate.peek_next()), Some(5000));
+        assert_eq!(at(playstate.tick(3999)), None);
+        assert_eq!(at(playstate.tick(4000)), Some(5000));
+
+        // Seeking into the lead-in, or past the first action, ticks straight away
+        playstate.seek(4500);
+        assert_eq!(at(playstate.tick(4500)), Some(5000));
+        playstate.seek(5500);
+        assert_eq!(at(playstate.tick(5500)), Some(6000));
+    }
+
+    #[test]
+    fn test_first_tick_waits_for_lead_in() {
+        let actions = |first_at| {
+            Arc::new(vec![
+                NormalisedAction {
+                    at: first_at,
+                    norm_pos: 0.0,
+                },
+                NormalisedAction {
+                    at: first_at + 1000,
+                    norm_pos: 1.0,
+                },
+            ])
+        };
+
+        // Minutes of nothing before the script starts
+        let mut playstate = FunscriptPlaystate::new(actions(120_000));
+        assert_eq!(at(playstate.tick(0)), None);
+        assert_eq!(at(playstate.tick(60_000)), None);
+        assert_eq!(at(playstate.tick(118_999)), None);
+        assert_eq!(at(playstate.tick(119_000)), Some(120_000));
+        assert_eq!(at(playstate.tick(119_500)), None);
+        assert_eq!(at(playstate.tick(120_000)), Some(121_000));
+
+        // Playback starting late still gets the first action, even if it's overdue
+        let mut playstate = FunscriptPlaystate::new(actions(120_000));
+        assert_eq!(at(playstate.tick(120_100)), Some(120_000));
+        assert_eq!(at(playstate.tick(120_100)), Some(121_000));
+
+        // Scripts starting within the lead-in (or at zero) tick straight away, as before
+        for first_at in [0, 500] {
+            let mut playstate = FunscriptPlaystate::new(actions(first_at));
+            assert_eq!(at(playstate.tick(0)), Some(first_at));
+        }
+
+        // and without any actions, nothing ever ticks
+        assert!(FunscriptPlaystate::new(Arc::new(Vec::new()))
+            .tick(0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_loop_lead_in_only_for_first_loop() {
+        let mut playstate = FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 3000,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 4000,
+                norm_pos: 1.0,
+            },
+        ]));
+        playstate.set_loop(true);
+        playstate.seek(0);
+        assert_eq!(at(playstate.tick(0)), None);
+        assert_eq!(at(playstate.tick(2000)), Some(3000));
+
+        // In later loops, the gap before the first action follows on from the last action
+        playstate.seek(4500);
+        assert_eq!(at(playstate.tick(4500)), Some(7000));
+    }
+
+    #[test]
+    fn test_seek_to_end_of_time() {
+        let mut playstate = FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 0,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: u32::MAX,
+                norm_pos: 1.0,
+            },
+        ]));
+        playstate.seek(u32::MAX);
+        assert_eq!(at(playstate.peek_previous()), Some(u32::MAX));
+        assert_eq!(at(playstate.tick(u32::MAX)), None);
+        assert!(playstate.status().finished);
+
+        playstate.seek(u32::MAX - 1);
+        assert_eq!(at(playstate.tick(u32::MAX - 1)), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_seek_out_of_order_script() {
+        let funscript: Funscript =
+            serde_json::from_str(include_str!("../fixtures/malformed/unsorted.funscript")).unwrap();
+        let mut playstate =
+            FunscriptPlaystate::new(Arc::new(normalised_from_funscript(&funscript)));
+
+        playstate.seek(700);
+        assert_eq!(at(playstate.peek_previous()), Some(500));
+        assert_eq!(playstate.status().index, 2);
+        assert_eq!(at(playstate.tick(700)), Some(1000));
+        assert_eq!(at(playstate.tick(1000)), Some(1500));
+    }
+
+    #[test]
+    fn test_small_backwards_jump_carries_on() {
+        let ticks = |times: &[u32]| {
+            let mut playstate = playstate();
+            times
+                .iter()
+                .map(|&time| at(playstate.tick(time)))
+                .collect::<Vec<_>>()
+        };
+
+        // 50 ms back just after an action, and just before one
+        assert_eq!(
+            ticks(&[0, 100, 120, 70, 110, 190, 140, 200, 300]),
+            vec![
+                Some(100),
+                Some(200),
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(300),
+                None
+            ]
+        );
+        // Nothing skipped even when the jump back lands before the action just returned
+        assert_eq!(
+            ticks(&[0, 50, 100, 200, 150, 199, 250, 300]),
+            vec![
+                Some(100),
+                None,
+                Some(200),
+                Some(300),
+                None,
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_large_backwards_jump_seeks() {
+        let mut playstate = playstate();
+        assert_eq!(at(playstate.tick(0)), Some(100));
+        assert_eq!(at(playstate.tick(100)), Some(200));
+        assert_eq!(at(playstate.tick(200)), Some(300));
+        assert_eq!(at(playstate.tick(300)), None);
+
+        // As though the video was seeked back without telling the playstate
+        assert_eq!(at(playstate.tick(150)), Some(200));
+        assert_eq!(at(playstate.tick(200)), Some(300));
+    }
+
+    /// A playstate for actions that weren't merged by `fixup` or normalisation.
+    fn unmerged_playstate(actions: &[(u32, f32)]) -> FunscriptPlaystate {
+        FunscriptPlaystate::new(Arc::new(
+            actions
+                .iter()
+                .map(|&(at, norm_pos)| NormalisedAction { at, norm_pos })
+                .collect(),
+        ))
+    }
+
+    fn at_pos(action: Option<NormalisedAction>) -> Option<(u32, f32)> {
+        action.map(|action| (action.at, action.norm_pos))
+    }
+
+    #[test]
+    fn test_same_time_actions_play_last() {
+        // A double at the start and end, a triple in the middle
+        let doubles = [
+            (0, 0.1),
+            (0, 0.2),
+            (500, 1.0),
+            (1000, 0.3),
+            (1000, 0.4),
+            (1000, 0.5),
+            (1500, 0.0),
+            (1500, 0.6),
+        ];
+        // A triple at the start and end, a double in the middle
+        let triples = [
+            (0, 0.1),
+            (0, 0.2),
+            (0, 0.3),
+            (500, 1.0),
+            (1000, 0.4),
+            (1000, 0.5),
+            (1500, 0.0),
+            (1500, 0.7),
+            (1500, 0.8),
+        ];
+        for (actions, expected) in [
+            (
+                &doubles[..],
+                [(0, 0.2), (500, 1.0), (1000, 0.5), (1500, 0.6)],
+            ),
+            (
+                &triples[..],
+                [(0, 0.3), (500, 1.0), (1000, 0.5), (1500, 0.8)],
+            ),
+        ] {
+            let mut playstate = unmerged_playstate(actions);
+            let ticks: Vec<Option<(u32, f32)>> = [0, 0, 500, 1000, 1500]
+                .into_iter()
+                .map(|time| at_pos(playstate.tick(time)))
+                .collect();
+            assert_eq!(
+                ticks,
+                expected
+                    .into_iter()
+                    .map(Some)
+                    .chain([None])
+                    .collect::<Vec<_>>()
+            );
+            assert!(playstate.status().finished);
+
+            // Seeking lands the same way, whichever of the actions it is near
+            for (time, previous, next) in [
+                (0, (0, expected[0].1), expected[1]),
+                (999, expected[1], expected[2]),
+                (1000, expected[2], expected[3]),
+            ] {
+                playstate.seek(time);
+                assert_eq!(at_pos(playstate.peek_previous()), Some(previous), "{time}");
+                assert_eq!(at_pos(playstate.peek_next()), Some(next), "{time}");
+                assert_eq!(
+                    playstate
+                        .segment_at(time)
+                        .map(|(previous, next)| (previous.norm_pos, next.norm_pos)),
+                    Some((previous.1, next.1))
+                );
+                assert_eq!(at_pos(playstate.tick(time)), Some(next), "{time}");
+            }
+
+            // And in loop mode, at the start of the next loop
+            playstate.set_loop(true);
+            playstate.seek(1400);
+            assert_eq!(at_pos(playstate.tick(1400)), Some(expected[3]));
+            assert_eq!(at_pos(playstate.tick(1500)), Some((1500, expected[0].1)));
+            assert_eq!(at_pos(playstate.tick(1500)), Some((2000, 1.0)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "sorted")]
+    #[cfg(debug_assertions)]
+    fn test_unsorted_actions_rejected() {
+        FunscriptPlaystate::new(Arc::new(vec![
+            NormalisedAction {
+                at: 200,
+                norm_pos: 0.0,
+            },
+            NormalisedAction {
+                at: 100,
+                norm_pos: 1.0,
+            },
+        ]));
+    }
 }
@@ -1,4 +1,15 @@
-use crate::schema::{Funscript, FunscriptAction};
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use strokers_core::AxisKind;
+
+use crate::{
+    generate::SplitMix64,
+    limits::limit_target,
+    schema::{Funscript, FunscriptAction},
+};
 
 /// A data point of where an axis should be at a given time, but normalised.
 #[derive(Copy, Clone, Debug)]
@@ -12,23 +23,1464 @@ pub struct NormalisedAction {
 /// Extract a list of normalised actions from a funscript.
 ///
 /// These always go from 0.0 to 1.0 and don't have any 'inverted' flags to follow.
+///
+/// `pos` is interpreted relative to the funscript's `range`, so that a script authored
+/// with a reduced range still uses the full scale (e.g. with range 90, pos 90 is 1.0).
+/// Positions beyond the range (e.g. from bad casts of negative numbers) are clamped into
+/// 0.0 to 1.0 after any inversion, with one warning saying how many there were,
+/// although after [`Funscript::fixup`] there are none.
+///
+/// The actions are always returned sorted by time, as playback needs them to be;
+/// if the funscript's weren't (it hasn't been fixed up), they are sorted with a warning.
 pub fn normalised_from_funscript(funscript: &Funscript) -> Vec<NormalisedAction> {
     let mut out = Vec::with_capacity(funscript.actions.len());
-
-    let max_f64 = funscript.range as f64;
-    let inverted = funscript.inverted;
+    let mut clamped = 0;
 
     for action in &funscript.actions {
-        let FunscriptAction { at, pos } = *action;
+        let (normalised, was_clamped) = normalise(action, funscript.range, funscript.inverted);
+        if was_clamped {
+            clamped += 1;
+        }
+        out.push(normalised);
+    }
+
+    if clamped > 0 {
+        warn!(
+            "clamped {clamped} of {} actions with positions beyond the range of {}",
+            out.len(),
+            effective_range(funscript.range)
+        );
+    }
+
+    if !is_sorted(&out) {
+        warn!("funscript actions are out of order; sorting them by time");
+        // Stable, so actions at the same time stay in the order the script has them.
+        out.sort_by_key(|action| action.at);
+    }
+
+    let merged = merge_same_time(&mut out);
+    if merged > 0 {
+        warn!("dropped {merged} actions at the same time as a later action; keeping the last at each time");
+    }
+
+    out
+}
+
+/// Keeps only the last of any actions at the same time, as [`Funscript::fixup`] does,
+/// returning how many were dropped.
+///
+/// Such actions are often left by tools that merge scripts, and would otherwise ask for
+/// two different positions at once. The actions must be sorted by time.
+///
+/// [`Funscript::fixup`]: crate::schema::Funscript::fixup
+fn merge_same_time(actions: &mut Vec<NormalisedAction>) -> usize {
+    let before = actions.len();
+    actions.dedup_by(|later, kept| {
+        let same_time = later.at == kept.at;
+        if same_time {
+            *kept = *later;
+        }
+        same_time
+    });
+    before - actions.len()
+}
+
+/// Whether the actions are in order of time, as [`FunscriptPlaystate`] needs them to be.
+///
+/// [`FunscriptPlaystate`]: crate::playstate::FunscriptPlaystate
+pub fn is_sorted(actions: &[NormalisedAction]) -> bool {
+    actions.windows(2).all(|pair| pair[0].at <= pair[1].at)
+}
+
+/// Normalises actions lazily, as [`normalised_from_funscript`] does but without another copy
+/// of them, which matters for scripts of hundreds of thousands of actions.
+///
+/// Clamped positions aren't logged, and unlike [`normalised_from_funscript`],
+/// out-of-order actions aren't sorted.
+pub fn normalised_actions(
+    actions: &[FunscriptAction],
+    range: u32,
+    inverted: bool,
+) -> impl Iterator<Item = NormalisedAction> + '_ {
+    actions
+        .iter()
+        .map(move |action| normalise(action, range, inverted).0)
+}
+
+/// A range of 0 means `fixup` wasn't called; assume the standard range.
+fn effective_range(range: u32) -> f64 {
+    match range {
+        0 => 100.0,
+        range => range as f64,
+    }
+}
+
+/// Normalises one action, also saying whether its position had to be clamped.
+fn normalise(action: &FunscriptAction, range: u32, inverted: bool) -> (NormalisedAction, bool) {
+    let FunscriptAction { at, pos } = *action;
+
+    let norm_pos = pos as f64 / effective_range(range);
+    let norm_pos = if inverted { 1.0 - norm_pos } else { norm_pos };
+    let clamped = !(0.0..=1.0).contains(&norm_pos);
+    let norm_pos = norm_pos.clamp(0.0, 1.0) as f32;
+
+    (NormalisedAction { at, norm_pos }, clamped)
+}
+
+/// The inverse of [`normalise`]: the position out of `range`, rounded.
+/// A range of 0 is taken as the standard range, as when normalising.
+pub(crate) fn denormalise(
+    action: &NormalisedAction,
+    range: u32,
+    inverted: bool,
+) -> FunscriptAction {
+    let norm_pos = (action.norm_pos as f64).clamp(0.0, 1.0);
+    let norm_pos = if inverted { 1.0 - norm_pos } else { norm_pos };
+    FunscriptAction {
+        at: action.at,
+        pos: (norm_pos * effective_range(range)).round() as u32,
+    }
+}
+
+/// How to interpolate between two positions, e.g. between two actions when resampling.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// Constant speed between actions.
+    #[default]
+    Linear,
+    /// Slow down towards each action and speed up away from it.
+    Cosine,
+}
+
+impl Easing {
+    /// Maps progress through a segment (0.0 to 1.0) to progress through the movement.
+    pub fn apply(self, progress: f32) -> f32 {
+        match self {
+            Easing::Linear => progress,
+            Easing::Cosine => (1.0 - (progress * PI).cos()) / 2.0,
+        }
+    }
+}
+
+/// Resamples actions at a fixed cadence, interpolating linearly between them.
+///
+/// See [`resample_with_easing`].
+pub fn resample(actions: &[NormalisedAction], interval_ms: u32) -> Vec<NormalisedAction> {
+    resample_with_easing(actions, interval_ms, Easing::Linear)
+}
+
+/// Resamples actions at a fixed cadence: an action is added at every multiple of `interval_ms`
+/// that falls between two existing actions, with its position interpolated between them.
+///
+/// The original actions are all kept, with their exact times and positions.
+/// Nothing is added before the first action or after the last, as there is nothing to
+/// interpolate towards. An `interval_ms` of 0 leaves the actions unchanged.
+pub fn resample_with_easing(
+    actions: &[NormalisedAction],
+    interval_ms: u32,
+    easing: Easing,
+) -> Vec<NormalisedAction> {
+    if interval_ms == 0 {
+        return actions.to_vec();
+    }
+
+    let mut out = Vec::with_capacity(actions.len());
+    for pair in actions.windows(2) {
+        let [from, to] = [pair[0], pair[1]];
+        out.push(from);
+
+        let span = to.at.saturating_sub(from.at);
+        // The first multiple of the interval after `from`
+        let mut at = (from.at / interval_ms + 1).saturating_mul(interval_ms);
+        while at < to.at {
+            let progress = easing.apply((at - from.at) as f32 / span as f32);
+            out.push(NormalisedAction {
+                at,
+                norm_pos: from.norm_pos + (to.norm_pos - from.norm_pos) * progress,
+            });
+            at = at.saturating_add(interval_ms);
+        }
+    }
+    if let Some(last) = actions.last() {
+        out.push(*last);
+    }
+
+    out
+}
+
+/// Shifts actions by `offset_ms` milliseconds, e.g. to sync a script with a different cut of
+/// the video.
+///
+/// Times are clamped at zero and at `u32::MAX`. Of the actions that end up at zero, only the
+/// latest is kept (the others would all happen at once), so the script still starts from the
+/// position it would have been at.
+pub fn shift_ms(actions: &mut Vec<NormalisedAction>, offset_ms: i32) {
+    shift_times(actions, offset_ms, |action| &mut action.at);
+}
+
+/// Shifts the times of anything with a time, as described in [`shift_ms`].
+/// `items` must be sorted by time.
+pub(crate) fn shift_times<T>(items: &mut Vec<T>, offset_ms: i32, at: impl Fn(&mut T) -> &mut u32) {
+    if offset_ms == 0 {
+        return;
+    }
+
+    let mut latest_at_zero = None;
+    for (idx, item) in items.iter_mut().enumerate() {
+        let time = at(item);
+        let shifted = *time as i64 + offset_ms as i64;
+        if shifted <= 0 {
+            latest_at_zero = Some(idx);
+        }
+        *time = shifted.clamp(0, u32::MAX as i64) as u32;
+    }
+    if let Some(idx) = latest_at_zero {
+        items.drain(..idx);
+    }
+}
+
+/// Scales the times of actions by `factor`, e.g. to follow a change in playback rate.
+///
+/// Times are rounded to the nearest millisecond (and clamped at `u32::MAX`).
+/// If several actions end up at the same millisecond, only the last of them is kept,
+/// so the order of the actions is never ambiguous.
+///
+/// `factor` must be positive and finite; otherwise the actions are returned unscaled.
+pub fn scale_time(actions: &[NormalisedAction], factor: f64) -> Vec<NormalisedAction> {
+    if !(factor.is_finite() && factor > 0.0) {
+        warn!("can't scale time by {factor}; leaving actions unscaled");
+        return actions.to_vec();
+    }
+
+    let mut out: Vec<NormalisedAction> = Vec::with_capacity(actions.len());
+    for action in actions {
+        let at = (action.at as f64 * factor).round().min(u32::MAX as f64) as u32;
+        let scaled = NormalisedAction {
+            at,
+            norm_pos: action.norm_pos,
+        };
+        match out.last_mut() {
+            Some(previous) if previous.at == at => *previous = scaled,
+            _ => out.push(scaled),
+        }
+    }
+    out
+}
+
+/// Maps a time in the (scaled) video into the time in the original script,
+/// i.e. the inverse of the scaling done by [`scale_time`] with the same `factor`.
+///
+/// This avoids making a scaled copy of the actions when only a lookup is needed.
+pub fn script_time(video_time_ms: u32, factor: f64) -> u32 {
+    if !(factor.is_finite() && factor > 0.0) {
+        return video_time_ms;
+    }
+    (video_time_ms as f64 / factor).round().min(u32::MAX as f64) as u32
+}
+
+/// Simplifies actions with the Ramer–Douglas–Peucker algorithm, dropping actions that are
+/// within `epsilon_pos` (in position, full scale being 1.0) of the line between their neighbours.
+///
+/// The first and last actions are always kept, as are all local extrema (including both ends
+/// of a plateau), so that strokes never lose their peaks.
+pub fn simplify(actions: &[NormalisedAction], epsilon_pos: f32) -> Vec<NormalisedAction> {
+    if actions.len() <= 2 {
+        return actions.to_vec();
+    }
+
+    let mut keep = vec![false; actions.len()];
+    keep[0] = true;
+    keep[actions.len() - 1] = true;
+    for idx in 1..actions.len() - 1 {
+        let before = direction(actions[idx - 1].norm_pos, actions[idx].norm_pos);
+        let after = direction(actions[idx].norm_pos, actions[idx + 1].norm_pos);
+        if before != after {
+            keep[idx] = true;
+        }
+    }
+
+    // Run RDP between each pair of kept actions, which are all monotonic in between.
+    let anchors: Vec<usize> = (0..actions.len()).filter(|&idx| keep[idx]).collect();
+    let mut stack: Vec<(usize, usize)> = anchors.windows(2).map(|w| (w[0], w[1])).collect();
+    while let Some((first, last)) = stack.pop() {
+        if last - first < 2 {
+            continue;
+        }
+        let (furthest, distance) = (first + 1..last)
+            .map(|idx| {
+                (
+                    idx,
+                    position_distance(&actions[first], &actions[last], &actions[idx]),
+                )
+            })
+            .fold((first, 0.0f32), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        if distance > epsilon_pos {
+            keep[furthest] = true;
+            stack.push((first, furthest));
+            stack.push((furthest, last));
+        }
+    }
+
+    let out: Vec<NormalisedAction> = actions
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(action, _)| *action)
+        .collect();
+    debug!("simplified {} actions to {}", actions.len(), out.len());
+    out
+}
+
+/// Thins out actions so that none are closer together than `min_interval_ms`,
+/// for devices that can't take commands as often as scripts have actions.
+///
+/// Unlike [`simplify`], this goes by time rather than shape. Points partway along a movement
+/// in one direction are the first to go, being merged into the next action; local extrema
+/// (peaks and troughs, including both ends of a plateau) and the last action are only dropped
+/// if they come too soon after another extremum, as when strokes are faster than the device
+/// could follow anyway. The first action is always kept.
+pub fn enforce_min_interval(
+    actions: &[NormalisedAction],
+    min_interval_ms: u32,
+) -> Vec<NormalisedAction> {
+    if actions.len() <= 1 || min_interval_ms == 0 {
+        return actions.to_vec();
+    }
+
+    let last_idx = actions.len() - 1;
+    let is_extremum = |idx: usize| {
+        idx == 0
+            || idx == last_idx
+            || direction(actions[idx - 1].norm_pos, actions[idx].norm_pos)
+                != direction(actions[idx].norm_pos, actions[idx + 1].norm_pos)
+    };
+
+    // (action, whether it's an extremum)
+    let mut kept: Vec<(NormalisedAction, bool)> = vec![(actions[0], true)];
+    for (idx, &action) in actions.iter().enumerate().skip(1) {
+        let extremum = is_extremum(idx);
+        let &(previous, previous_extremum) = kept.last().expect("first action is always kept");
+        if action.at.saturating_sub(previous.at) >= min_interval_ms {
+            kept.push((action, extremum));
+        } else if !previous_extremum {
+            // Merge the in-between point into this one; it's no closer to the one before.
+            *kept.last_mut().expect("just looked at it") = (action, extremum);
+        }
+        // Otherwise this comes too soon after an extremum, so is dropped.
+    }
+
+    let out: Vec<NormalisedAction> = kept.into_iter().map(|(action, _)| action).collect();
+    debug!(
+        "thinned {} actions to {} at least {min_interval_ms} ms apart",
+        actions.len(),
+        out.len()
+    );
+    out
+}
+
+/// Smooths out jitter in positions (e.g. from motion tracking) with a centred moving average
+/// over `window_ms`, weighted by time: each action's new position is the average position
+/// of the script over the window around it, interpolating linearly between actions.
+///
+/// Times are left alone, as are the first and last actions. With `min_prominence`, peaks and
+/// troughs that rise or fall at least that far (in position, full scale being 1.0) from the
+/// extrema either side of them keep their positions too, so that real strokes aren't flattened.
+pub fn smooth(
+    actions: &[NormalisedAction],
+    window_ms: u32,
+    min_prominence: Option<f32>,
+) -> Vec<NormalisedAction> {
+    if actions.len() <= 2 || window_ms == 0 {
+        return actions.to_vec();
+    }
+
+    let mut keep = vec![false; actions.len()];
+    keep[0] = true;
+    keep[actions.len() - 1] = true;
+    if let Some(min_prominence) = min_prominence {
+        for idx in prominent_extrema(actions, min_prominence) {
+            keep[idx] = true;
+        }
+    }
+
+    let half_window = window_ms as f64 / 2.0;
+    let out: Vec<NormalisedAction> = actions
+        .iter()
+        .zip(&keep)
+        .map(|(&action, &keep)| {
+            if keep {
+                return action;
+            }
+            let centre = action.at as f64;
+            NormalisedAction {
+                at: action.at,
+                norm_pos: average_position(actions, centre - half_window, centre + half_window),
+            }
+        })
+        .collect();
+    debug!(
+        "smoothed {} actions over {window_ms} ms, keeping {} exact",
+        actions.len(),
+        keep.iter().filter(|&&keep| keep).count()
+    );
+    out
+}
+
+/// Indices of the local extrema (every action of a plateau, if the extremum is one) that are
+/// at least `min_prominence` in position from the nearest extrema either side of them
+/// (or the first or last action, if there are none).
+fn prominent_extrema(actions: &[NormalisedAction], min_prominence: f32) -> Vec<usize> {
+    // The direction of the last movement before each action and the first one after it,
+    // ignoring plateaus.
+    let mut before = vec![0i8; actions.len()];
+    for idx in 1..actions.len() {
+        before[idx] = match direction(actions[idx - 1].norm_pos, actions[idx].norm_pos) {
+            0 => before[idx - 1],
+            dir => dir,
+        };
+    }
+    let mut after = vec![0i8; actions.len()];
+    for idx in (0..actions.len() - 1).rev() {
+        after[idx] = match direction(actions[idx].norm_pos, actions[idx + 1].norm_pos) {
+            0 => after[idx + 1],
+            dir => dir,
+        };
+    }
+    let extrema: Vec<usize> = (1..actions.len() - 1)
+        .filter(|&idx| before[idx] != 0 && after[idx] != 0 && before[idx] != after[idx])
+        .collect();
+
+    let pos = |idx: usize| actions[idx].norm_pos;
+    extrema
+        .iter()
+        .enumerate()
+        .filter(|&(nth, &idx)| {
+            let previous = extrema[..nth]
+                .iter()
+                .rev()
+                .copied()
+                .find(|&other| pos(other) != pos(idx))
+                .unwrap_or(0);
+            let next = extrema[nth + 1..]
+                .iter()
+                .copied()
+                .find(|&other| pos(other) != pos(idx))
+                .unwrap_or(actions.len() - 1);
+            let prominence = (pos(idx) - pos(previous))
+                .abs()
+                .min((pos(idx) - pos(next)).abs());
+            prominence >= min_prominence
+        })
+        .map(|(_, &idx)| idx)
+        .collect()
+}
+
+/// The average position over the time from `start` to `end`, interpolating linearly between
+/// actions and holding the first and last positions outside of them.
+fn average_position(actions: &[NormalisedAction], start: f64, end: f64) -> f32 {
+    let first = actions[0];
+    let last = actions[actions.len() - 1];
+    let mut area = 0.0;
+
+    // Before the first action and after the last
+    if start < first.at as f64 {
+        area += ((first.at as f64).min(end) - start) * first.norm_pos as f64;
+    }
+    if end > last.at as f64 {
+        area += (end - (last.at as f64).max(start)) * last.norm_pos as f64;
+    }
+
+    let from_idx = actions
+        .partition_point(|action| (action.at as f64) <= start)
+        .saturating_sub(1);
+    for pair in actions[from_idx..].windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let (a_at, b_at) = (a.at as f64, b.at as f64);
+        if a_at >= end {
+            break;
+        }
+        let seg_start = a_at.max(start);
+        let seg_end = b_at.min(end);
+        if seg_end <= seg_start {
+            continue;
+        }
+        let pos_at = |t: f64| {
+            a.norm_pos as f64 + (b.norm_pos as f64 - a.norm_pos as f64) * (t - a_at) / (b_at - a_at)
+        };
+        area += (seg_end - seg_start) * (pos_at(seg_start) + pos_at(seg_end)) / 2.0;
+    }
+
+    (area / (end - start)) as f32
+}
+
+/// Which way the position goes between two actions: -1, 0 or 1.
+fn direction(from: f32, to: f32) -> i8 {
+    match to.partial_cmp(&from) {
+        Some(std::cmp::Ordering::Greater) => 1,
+        Some(std::cmp::Ordering::Less) => -1,
+        _ => 0,
+    }
+}
+
+/// How far `action` is in position from the line between `first` and `last`, at the same time.
+fn position_distance(
+    first: &NormalisedAction,
+    last: &NormalisedAction,
+    action: &NormalisedAction,
+) -> f32 {
+    let span = last.at.saturating_sub(first.at);
+    let on_line = if span == 0 {
+        first.norm_pos
+    } else {
+        let progress = action.at.saturating_sub(first.at) as f32 / span as f32;
+        first.norm_pos + (last.norm_pos - first.norm_pos) * progress
+    };
+    (action.norm_pos - on_line).abs()
+}
+
+/// Inserts a gentle move to the `neutral` position during each gap between actions that is
+/// longer than `gap_threshold_ms`, so that the axis isn't left parked at an extreme
+/// through long stretches without action.
+///
+/// The move to neutral takes `return_over_ms` from the start of the gap, and the axis stays
+/// there until `return_over_ms` before the end of the gap, leaving that long to reach the
+/// next action. Gaps too short for both moves are left alone, as is everything else:
+/// existing actions keep their times and positions.
+pub fn fill_gaps(
+    actions: &[NormalisedAction],
+    gap_threshold_ms: u32,
+    neutral: f32,
+    return_over_ms: u32,
+) -> Vec<NormalisedAction> {
+    let neutral = neutral.clamp(0.0, 1.0);
+    let mut out = Vec::with_capacity(actions.len());
+    for (idx, &action) in actions.iter().enumerate() {
+        out.push(action);
+        let Some(next) = actions.get(idx + 1) else {
+            continue;
+        };
+        let gap = next.at.saturating_sub(action.at);
+        if gap <= gap_threshold_ms || gap <= return_over_ms.saturating_mul(2) {
+            continue;
+        }
+        out.push(NormalisedAction {
+            at: action.at + return_over_ms,
+            norm_pos: neutral,
+        });
+        out.push(NormalisedAction {
+            at: next.at - return_over_ms,
+            norm_pos: neutral,
+        });
+    }
+    debug!(
+        "filled {} gaps with moves to neutral",
+        (out.len() - actions.len()) / 2
+    );
+    out
+}
 
-        let norm_pos = if inverted {
-            max_f64 * (1.0 - (pos as f64 / max_f64))
-        } else {
-            pos as f64 / max_f64
-        } as f32;
+/// Applies range and speed limits to the whole of a script ahead of time, as the limiter
+/// would during playback, for devices or players that can't be limited as they play.
+///
+/// Positions are scaled into the band from `min` to `max`, and any movement faster than
+/// `speed_limit_fs_per_s` full-scale movements per second only goes as far as it can in time
+/// (see [`limit_target`]), with the next movement starting from wherever that got to.
+/// The device is assumed to reach the first action in time. Times are left alone.
+///
+/// The result is on the device's scale, so shouldn't be limited again when played.
+pub fn apply_limits(
+    actions: &[NormalisedAction],
+    speed_limit_fs_per_s: f32,
+    min: f32,
+    max: f32,
+) -> Vec<NormalisedAction> {
+    let mut out: Vec<NormalisedAction> = Vec::with_capacity(actions.len());
+    for &action in actions {
+        let norm_pos = match out.last() {
+            Some(previous) => limit_target(
+                previous.norm_pos,
+                action.norm_pos,
+                action.at.saturating_sub(previous.at),
+                speed_limit_fs_per_s,
+                min,
+                max,
+            ),
+            None => min + (max - min) * action.norm_pos,
+        };
+        out.push(NormalisedAction {
+            at: action.at,
+            norm_pos,
+        });
+    }
+    out
+}
+
+/// How [`derive_secondary`] makes up motion for another axis.
+#[derive(Clone, Debug)]
+pub struct SecondaryParams {
+    /// How far the axis moves either side of the middle, at most (0.0 to 0.5).
+    pub amplitude: f32,
+    /// How far behind the stroke a copied axis (e.g. roll) lags, in milliseconds.
+    pub lag_ms: u32,
+    /// How long one slow sweep of a generated axis (e.g. twist) takes, in milliseconds.
+    pub period_ms: u32,
+    /// Time between generated actions, in milliseconds.
+    pub sample_interval_ms: u32,
+    /// Stroke speed, in full-scale movements per second, at which a generated axis
+    /// reaches its full amplitude.
+    pub full_intensity_speed: f32,
+    /// The same seed always gives the same motion.
+    pub seed: u64,
+}
 
-        out.push(NormalisedAction { at, norm_pos });
+impl Default for SecondaryParams {
+    fn default() -> Self {
+        SecondaryParams {
+            amplitude: 0.15,
+            lag_ms: 150,
+            period_ms: 4000,
+            sample_interval_ms: 100,
+            full_intensity_speed: 3.0,
+            seed: 0,
+        }
     }
+}
+
+/// Makes up plausible motion for another axis from the (normalised) actions of a stroke
+/// script, so that multi-axis devices have something to do when a video only has a stroke
+/// script.
+///
+/// - Roll, pitch, surge and sway follow the stroke, lagging behind it by `lag_ms` and scaled
+///   down to `amplitude` either side of the middle.
+/// - Twist sweeps slowly from side to side, further when the stroke is moving faster.
+/// - Vibration follows how fast the stroke is moving.
+///
+/// Other axes get no actions. The result is sorted by time with positions from 0.0 to 1.0,
+/// and is the same every time for the same seed.
+pub fn derive_secondary(
+    actions: &[NormalisedAction],
+    kind: AxisKind,
+    params: &SecondaryParams,
+) -> Vec<NormalisedAction> {
+    let (Some(first), Some(last)) = (actions.first(), actions.last()) else {
+        return Vec::new();
+    };
+    let amplitude = params.amplitude.clamp(0.0, 0.5);
+    let interval = params.sample_interval_ms.max(1);
+    let sample_times = (first.at..=last.at)
+        .step_by(interval as usize)
+        .chain(std::iter::once(last.at));
+    // Speed of the stroke around the time, from 0.0 to 1.0
+    let intensity = |at: u32| {
+        let window = params.period_ms.max(interval) / 4;
+        let speed = movement_between(
+            actions,
+            at.saturating_sub(window / 2),
+            at.saturating_add(window / 2),
+        ) / (window as f32 / 1000.0);
+        (speed / params.full_intensity_speed.max(f32::EPSILON)).min(1.0)
+    };
 
+    let derived: Vec<NormalisedAction> = match kind {
+        AxisKind::Roll | AxisKind::Pitch | AxisKind::Surge | AxisKind::Sway => actions
+            .iter()
+            .map(|action| NormalisedAction {
+                at: action.at.saturating_add(params.lag_ms),
+                norm_pos: 0.5 + (action.norm_pos - 0.5) * amplitude * 2.0,
+            })
+            .collect(),
+        AxisKind::Twist => {
+            let mut rng = SplitMix64(params.seed);
+            let phase = rng.next_f32() * 2.0 * PI;
+            let period = params.period_ms.max(1) as f32;
+            sample_times
+                .map(|at| {
+                    let sweep = (at as f32 / period * 2.0 * PI + phase).sin();
+                    NormalisedAction {
+                        at,
+                        norm_pos: 0.5 + sweep * amplitude * intensity(at),
+                    }
+                })
+                .collect()
+        }
+        AxisKind::Vibration => sample_times
+            .map(|at| NormalisedAction {
+                at,
+                norm_pos: intensity(at),
+            })
+            .collect(),
+        _ => {
+            warn!("can't derive motion for {kind:?} from a stroke script");
+            Vec::new()
+        }
+    };
+
+    let mut out: Vec<NormalisedAction> = Vec::with_capacity(derived.len());
+    for action in derived {
+        let action = NormalisedAction {
+            at: action.at,
+            norm_pos: action.norm_pos.clamp(0.0, 1.0),
+        };
+        match out.last_mut() {
+            Some(previous) if previous.at == action.at => *previous = action,
+            _ => out.push(action),
+        }
+    }
     out
 }
+
+/// How far, in total, the position moves between `start` and `end`.
+fn movement_between(actions: &[NormalisedAction], start: u32, end: u32) -> f32 {
+    // Only the actions from the last at or before `start` to the first at or after `end`
+    let first = actions
+        .partition_point(|action| action.at <= start)
+        .saturating_sub(1);
+    let last = actions.partition_point(|action| action.at < end);
+    actions[first..(last + 1).min(actions.len())]
+        .windows(2)
+        .map(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+            let span = b.at.saturating_sub(a.at);
+            let overlap = b.at.min(end).saturating_sub(a.at.max(start));
+            if span == 0 || overlap == 0 {
+                return 0.0;
+            }
+            (b.norm_pos - a.norm_pos).abs() * overlap as f32 / span as f32
+        })
+        .sum()
+}
+
+/// Mirrors actions top to bottom, so that 0.0 becomes 1.0 and vice versa.
+pub fn invert(actions: &[NormalisedAction]) -> Vec<NormalisedAction> {
+    actions
+        .iter()
+        .map(|action| NormalisedAction {
+            at: action.at,
+            norm_pos: 1.0 - action.norm_pos,
+        })
+        .collect()
+}
+
+/// Stretches (or squashes) the positions of actions so that the lowest and highest positions
+/// the script reaches become `new_min` and `new_max`, e.g. to give full travel to a script
+/// that only uses the middle of the range.
+///
+/// A script that never moves is put in the middle of the new band.
+/// `new_min` may be more than `new_max`, which also inverts the script.
+pub fn rescale(actions: &[NormalisedAction], new_min: f32, new_max: f32) -> Vec<NormalisedAction> {
+    let (observed_min, observed_max) = actions
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), action| {
+            (min.min(action.norm_pos), max.max(action.norm_pos))
+        });
+    let observed_span = observed_max - observed_min;
+
+    actions
+        .iter()
+        .map(|action| {
+            let progress = if observed_span > 0.0 {
+                (action.norm_pos - observed_min) / observed_span
+            } else {
+                0.5
+            };
+            NormalisedAction {
+                at: action.at,
+                norm_pos: new_min + (new_max - new_min) * progress,
+            }
+        })
+        .collect()
+}
+
+/// Clamps the positions of actions into the band from `min` to `max`,
+/// leaving positions already inside it alone.
+pub fn clamp_band(actions: &[NormalisedAction], min: f32, max: f32) -> Vec<NormalisedAction> {
+    let (min, max) = if min <= max { (min, max) } else { (max, min) };
+    actions
+        .iter()
+        .map(|action| NormalisedAction {
+            at: action.at,
+            norm_pos: action.norm_pos.clamp(min, max),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use strokers_core::AxisKind;
+
+    use crate::{generate::SplitMix64, schema::Funscript};
+
+    use super::{
+        apply_limits, clamp_band, derive_secondary, enforce_min_interval, fill_gaps, invert,
+        is_sorted, movement_between, normalised_from_funscript, resample, resample_with_easing,
+        rescale, scale_time, script_time, shift_ms, simplify, smooth, Easing, NormalisedAction,
+        SecondaryParams,
+    };
+
+    #[test]
+    fn test_normalisation() {
+        // (range, inverted, pos, expected norm_pos)
+        // `pos` is relative to `range`: a range-90 script reaching 90 is at full scale,
+        // and anything beyond the range is clamped.
+        let table = [
+            (100, false, 0, 0.0),
+            (100, false, 50, 0.5),
+            (100, false, 100, 1.0),
+            (100, true, 0, 1.0),
+            (100, true, 25, 0.75),
+            (100, true, 100, 0.0),
+            (90, false, 45, 0.5),
+            (90, false, 90, 1.0),
+            (90, false, 100, 1.0),
+            (90, true, 90, 0.0),
+            (90, true, 100, 0.0),
+            (200, false, 100, 0.5),
+            (100, false, 105, 1.0),
+            (100, true, 105, 0.0),
+            // -5 after a bad cast
+            (100, false, -5i32 as u32, 1.0),
+            (100, true, -5i32 as u32, 0.0),
+            // Not fixed up: assume the standard range
+            (0, false, 50, 0.5),
+            (0, true, 150, 0.0),
+        ];
+
+        for (range, inverted, pos, expected) in table {
+            let funscript = Funscript::builder()
+                .action(0, pos)
+                .range(range)
+                .inverted(inverted)
+                .build_without_fixup()
+                .unwrap();
+            let norm_pos = normalised_from_funscript(&funscript)[0].norm_pos;
+            assert!(
+                (norm_pos - expected).abs() < 1e-6,
+                "range {range}, inverted {inverted}, pos {pos}: got {norm_pos}, expected {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normalisation_sorts() {
+        let funscript: Funscript =
+            serde_json::from_str(include_str!("../fixtures/malformed/unsorted.funscript")).unwrap();
+        let normalised = normalised_from_funscript(&funscript);
+        assert!(is_sorted(&normalised));
+        assert_actions(
+            &normalised,
+            &[(0, 0.0), (500, 1.0), (1000, 0.0), (1500, 1.0)],
+        );
+    }
+
+    #[test]
+    fn test_normalisation_keeps_last_at_same_time() {
+        let normalised = |actions: &[(u32, u32)]| {
+            let funscript = Funscript::builder()
+                .actions(actions.iter().copied())
+                .build_without_fixup()
+                .unwrap();
+            normalised_from_funscript(&funscript)
+        };
+
+        // Doubles and triples at the start, middle and end
+        assert_actions(
+            &normalised(&[(0, 10), (0, 20), (500, 100), (1000, 0), (1000, 30)]),
+            &[(0, 0.2), (500, 1.0), (1000, 0.3)],
+        );
+        assert_actions(
+            &normalised(&[
+                (0, 10),
+                (0, 20),
+                (0, 30),
+                (500, 100),
+                (500, 90),
+                (500, 80),
+                (1000, 0),
+                (1000, 40),
+                (1000, 50),
+            ]),
+            &[(0, 0.3), (500, 0.8), (1000, 0.5)],
+        );
+
+        // Out of order too: still the last in the script, not the last by position
+        let funscript: Funscript =
+            serde_json::from_str(include_str!("../fixtures/malformed/duplicates.funscript"))
+                .unwrap();
+        assert_actions(
+            &normalised_from_funscript(&funscript),
+            &[(0, 0.0), (500, 0.8), (1000, 0.1)],
+        );
+    }
+
+    fn action(at: u32, norm_pos: f32) -> NormalisedAction {
+        NormalisedAction { at, norm_pos }
+    }
+
+    fn assert_actions(actual: &[NormalisedAction], expected: &[(u32, f32)]) {
+        let actual: Vec<(u32, f32)> = actual.iter().map(|a| (a.at, a.norm_pos)).collect();
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                a.0 == e.0 && (a.1 - e.1).abs() < 1e-5,
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_resample_linear() {
+        // Keyframes off the cadence are kept exactly; nothing before the first or after the last.
+        let actions = [action(150, 0.0), action(550, 1.0), action(600, 0.5)];
+        assert_actions(
+            &resample(&actions, 100),
+            &[
+                (150, 0.0),
+                (200, 0.125),
+                (300, 0.375),
+                (400, 0.625),
+                (500, 0.875),
+                (550, 1.0),
+                (600, 0.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_resample_cosine() {
+        let actions = [action(0, 0.0), action(400, 1.0)];
+        assert_actions(
+            &resample_with_easing(&actions, 100, Easing::Cosine),
+            &[
+                (0, 0.0),
+                (100, 0.146_446_6),
+                (200, 0.5),
+                (300, 0.853_553_4),
+                (400, 1.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_resample_edge_cases() {
+        assert!(resample(&[], 100).is_empty());
+        assert_actions(&resample(&[action(250, 0.3)], 100), &[(250, 0.3)]);
+        // Keyframes on the cadence aren't duplicated
+        assert_actions(
+            &resample(&[action(0, 0.0), action(200, 1.0)], 100),
+            &[(0, 0.0), (100, 0.5), (200, 1.0)],
+        );
+        // Two keyframes at the same time
+        assert_actions(
+            &resample(&[action(100, 0.0), action(100, 1.0)], 50),
+            &[(100, 0.0), (100, 1.0)],
+        );
+        assert_actions(
+            &resample(&[action(0, 0.0), action(200, 1.0)], 0),
+            &[(0, 0.0), (200, 1.0)],
+        );
+    }
+
+    #[test]
+    fn test_simplify_dense_sine() {
+        // A 1 Hz sine sampled every 10 ms for 3 seconds, like a motion-capture script.
+        let actions: Vec<NormalisedAction> = (0..=300)
+            .map(|idx| {
+                let at = idx * 10;
+                let phase = at as f32 / 1000.0 * 2.0 * std::f32::consts::PI;
+                action(at, 0.5 + 0.5 * phase.sin())
+            })
+            .collect();
+        let simplified = simplify(&actions, 0.02);
+
+        assert!(
+            simplified.len() < actions.len() / 5,
+            "{} actions simplified to {}",
+            actions.len(),
+            simplified.len()
+        );
+        // Ends are kept
+        assert_eq!(simplified.first().unwrap().at, 0);
+        assert_eq!(simplified.last().unwrap().at, 3000);
+        // Every peak and trough is kept
+        for extreme_at in [250, 750, 1250, 1750, 2250, 2750] {
+            assert!(
+                simplified.iter().any(|a| a.at == extreme_at),
+                "lost the extreme at {extreme_at}"
+            );
+        }
+        // Nothing dropped is further than epsilon from the simplified line
+        for original in &actions {
+            let idx = simplified.partition_point(|a| a.at <= original.at);
+            if idx == simplified.len() {
+                continue;
+            }
+            let (from, to) = (simplified[idx - 1], simplified[idx]);
+            let progress = (original.at - from.at) as f32 / (to.at - from.at) as f32;
+            let on_line = from.norm_pos + (to.norm_pos - from.norm_pos) * progress;
+            assert!((original.norm_pos - on_line).abs() <= 0.02 + 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_simplify_keeps_small_extrema() {
+        // The wiggle at 200 ms is within epsilon but is still a peak.
+        let actions = [
+            action(0, 0.0),
+            action(100, 0.25),
+            action(200, 0.51),
+            action(300, 0.5),
+            action(400, 1.0),
+            action(500, 1.0),
+            action(600, 0.0),
+        ];
+        let simplified = simplify(&actions, 0.1);
+        let times: Vec<u32> = simplified.iter().map(|a| a.at).collect();
+        assert_eq!(times, vec![0, 200, 300, 400, 500, 600]);
+
+        assert!(simplify(&[], 0.1).is_empty());
+        assert_eq!(simplify(&actions[..2], 0.1).len(), 2);
+    }
+
+    #[test]
+    fn test_shift_ms() {
+        let times = |actions: &[NormalisedAction]| -> Vec<(u32, f32)> {
+            actions.iter().map(|a| (a.at, a.norm_pos)).collect()
+        };
+        let original = vec![
+            action(0, 0.0),
+            action(100, 0.2),
+            action(200, 0.4),
+            action(300, 0.6),
+        ];
+
+        let mut actions = original.clone();
+        shift_ms(&mut actions, 50);
+        assert_eq!(
+            times(&actions),
+            vec![(50, 0.0), (150, 0.2), (250, 0.4), (350, 0.6)]
+        );
+
+        // The latest action at or before zero is pinned there; earlier ones are dropped.
+        let mut actions = original.clone();
+        shift_ms(&mut actions, -150);
+        assert_eq!(times(&actions), vec![(0, 0.2), (50, 0.4), (150, 0.6)]);
+        let mut actions = original.clone();
+        shift_ms(&mut actions, -200);
+        assert_eq!(times(&actions), vec![(0, 0.4), (100, 0.6)]);
+        let mut actions = original.clone();
+        shift_ms(&mut actions, i32::MIN);
+        assert_eq!(times(&actions), vec![(0, 0.6)]);
+
+        // Clamped rather than overflowing
+        let mut actions = vec![action(u32::MAX - 10, 0.0), action(u32::MAX, 1.0)];
+        shift_ms(&mut actions, 100);
+        assert_eq!(times(&actions), vec![(u32::MAX, 0.0), (u32::MAX, 1.0)]);
+
+        let mut actions = Vec::new();
+        shift_ms(&mut actions, -100);
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn test_scale_time() {
+        let actions = [
+            action(0, 0.0),
+            action(1, 0.1),
+            action(2, 0.2),
+            action(1000, 1.0),
+            action(1001, 0.5),
+        ];
+
+        let slower = scale_time(&actions, 1.5);
+        assert_actions(
+            &slower,
+            &[(0, 0.0), (2, 0.1), (3, 0.2), (1500, 1.0), (1502, 0.5)],
+        );
+
+        // Actions that collapse onto the same millisecond keep the later position
+        let faster = scale_time(&actions, 0.4);
+        assert_actions(&faster, &[(0, 0.1), (1, 0.2), (400, 0.5)]);
+
+        // Unusable factors leave the actions alone
+        assert_eq!(scale_time(&actions, 0.0).len(), actions.len());
+        assert_eq!(scale_time(&actions, f64::NAN).len(), actions.len());
+
+        assert_actions(
+            &scale_time(&[action(u32::MAX / 2, 0.0), action(u32::MAX, 1.0)], 4.0),
+            &[(u32::MAX, 1.0)],
+        );
+    }
+
+    #[test]
+    fn test_script_time() {
+        let actions = [action(0, 0.0), action(333, 0.5), action(1000, 1.0)];
+        for factor in [0.5, 1.0, 1.5, 2.0] {
+            for (original, scaled) in actions.iter().zip(scale_time(&actions, factor)) {
+                let back = script_time(scaled.at, factor);
+                assert!(
+                    back.abs_diff(original.at) <= 1,
+                    "{} -> {} -> {back} at {factor}",
+                    original.at,
+                    scaled.at
+                );
+            }
+        }
+        assert_eq!(script_time(1500, 1.5), 1000);
+        assert_eq!(script_time(1500, -1.0), 1500);
+    }
+
+    #[test]
+    fn test_fill_gaps() {
+        let actions = [
+            // Normal strokes
+            action(0, 0.0),
+            action(400, 1.0),
+            action(800, 0.0),
+            // Parked at the top for just under the threshold
+            action(1200, 1.0),
+            action(11_199, 0.0),
+            // Parked at the top for just over the threshold
+            action(11_600, 1.0),
+            action(21_601, 0.0),
+            action(22_000, 1.0),
+        ];
+        let filled = fill_gaps(&actions, 10_000, 0.5, 1000);
+        assert_actions(
+            &filled,
+            &[
+                (0, 0.0),
+                (400, 1.0),
+                (800, 0.0),
+                (1200, 1.0),
+                (11_199, 0.0),
+                (11_600, 1.0),
+                (12_600, 0.5),
+                (20_601, 0.5),
+                (21_601, 0.0),
+                (22_000, 1.0),
+            ],
+        );
+
+        // No room for a move there and back
+        let actions = [action(0, 1.0), action(1500, 0.0)];
+        assert_actions(
+            &fill_gaps(&actions, 1000, 0.5, 1000),
+            &[(0, 1.0), (1500, 0.0)],
+        );
+
+        assert!(fill_gaps(&[], 1000, 0.5, 100).is_empty());
+    }
+
+    #[test]
+    fn test_enforce_min_interval() {
+        // Strokes every 500 ms, sampled every 20 ms like a motion-capture script
+        let actions: Vec<NormalisedAction> = (0..=100)
+            .map(|idx| {
+                let at = idx * 20;
+                let phase = (at % 1000) as f32 / 500.0;
+                let norm_pos = if phase <= 1.0 { phase } else { 2.0 - phase };
+                action(at, norm_pos)
+            })
+            .collect();
+        let thinned = enforce_min_interval(&actions, 100);
+
+        assert!(thinned
+            .windows(2)
+            .all(|pair| pair[1].at - pair[0].at >= 100));
+        // Every peak and trough survives
+        for extreme_at in [0, 500, 1000, 1500, 2000] {
+            assert!(
+                thinned.iter().any(|a| a.at == extreme_at),
+                "lost the extreme at {extreme_at}: {thinned:?}"
+            );
+        }
+        // What's left is still on the original line
+        for thinned_action in &thinned {
+            let original = actions.iter().find(|a| a.at == thinned_action.at).unwrap();
+            assert_eq!(original.norm_pos, thinned_action.norm_pos);
+        }
+        assert!(thinned.len() <= 21, "{thinned:?}");
+    }
+
+    #[test]
+    fn test_enforce_min_interval_fast_strokes() {
+        // Strokes faster than the interval: extrema too close to the last one kept are dropped
+        let actions = [
+            action(0, 0.0),
+            action(40, 1.0),
+            action(80, 0.0),
+            action(120, 1.0),
+            action(160, 0.0),
+            action(400, 1.0),
+        ];
+        assert_actions(
+            &enforce_min_interval(&actions, 100),
+            &[(0, 0.0), (120, 1.0), (400, 1.0)],
+        );
+
+        // Nothing to do
+        assert_actions(
+            &enforce_min_interval(&actions, 0),
+            &[
+                (0, 0.0),
+                (40, 1.0),
+                (80, 0.0),
+                (120, 1.0),
+                (160, 0.0),
+                (400, 1.0),
+            ],
+        );
+        assert!(enforce_min_interval(&[], 100).is_empty());
+    }
+
+    #[test]
+    fn test_invert() {
+        let actions = [action(0, 0.0), action(100, 0.25), action(200, 1.0)];
+        assert_actions(&invert(&actions), &[(0, 1.0), (100, 0.75), (200, 0.0)]);
+        assert_actions(
+            &invert(&invert(&actions)),
+            &[(0, 0.0), (100, 0.25), (200, 1.0)],
+        );
+    }
+
+    #[test]
+    fn test_rescale() {
+        // A timid script using 40–60% of the range
+        let actions = [action(0, 0.4), action(100, 0.6), action(200, 0.5)];
+        assert_actions(
+            &rescale(&actions, 0.0, 1.0),
+            &[(0, 0.0), (100, 1.0), (200, 0.5)],
+        );
+        assert_actions(
+            &rescale(&actions, 0.2, 0.4),
+            &[(0, 0.2), (100, 0.4), (200, 0.3)],
+        );
+        // Reversed band inverts
+        assert_actions(
+            &rescale(&actions, 1.0, 0.0),
+            &[(0, 1.0), (100, 0.0), (200, 0.5)],
+        );
+
+        // A constant script goes to the middle of the band
+        let constant = [action(0, 0.7), action(100, 0.7)];
+        assert_actions(&rescale(&constant, 0.2, 0.4), &[(0, 0.3), (100, 0.3)]);
+        assert!(rescale(&[], 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_clamp_band() {
+        let actions = [action(0, 0.0), action(100, 0.5), action(200, 1.0)];
+        assert_actions(
+            &clamp_band(&actions, 0.2, 0.8),
+            &[(0, 0.2), (100, 0.5), (200, 0.8)],
+        );
+        // Either order of bounds
+        assert_actions(
+            &clamp_band(&actions, 0.8, 0.2),
+            &[(0, 0.2), (100, 0.5), (200, 0.8)],
+        );
+    }
+
+    /// A 1 Hz sine sampled every 20 ms, with deterministic jitter of up to ±0.03.
+    fn noisy_sine() -> (Vec<NormalisedAction>, Vec<f32>) {
+        let clean: Vec<f32> = (0..200)
+            .map(|idx| 0.5 + 0.4 * (idx as f32 * 20.0 / 1000.0 * std::f32::consts::TAU).sin())
+            .collect();
+        let noisy = clean
+            .iter()
+            .enumerate()
+            .map(|(idx, &pos)| {
+                let jitter = ((idx * 7919) % 13) as f32 / 12.0 * 0.06 - 0.03;
+                action(idx as u32 * 20, pos + jitter)
+            })
+            .collect();
+        (noisy, clean)
+    }
+
+    fn mean_error(actions: &[NormalisedAction], clean: &[f32]) -> f32 {
+        actions
+            .iter()
+            .zip(clean)
+            .map(|(action, clean)| (action.norm_pos - clean).abs())
+            .sum::<f32>()
+            / clean.len() as f32
+    }
+
+    #[test]
+    fn test_smooth_reduces_noise() {
+        let (noisy, clean) = noisy_sine();
+        let smoothed = smooth(&noisy, 100, None);
+
+        let before = mean_error(&noisy, &clean);
+        let after = mean_error(&smoothed, &clean);
+        assert!(after < before * 0.6, "error {before} -> {after}");
+
+        // Times untouched, ends exact
+        assert!(noisy.iter().zip(&smoothed).all(|(a, b)| a.at == b.at));
+        assert_eq!(smoothed[0].norm_pos, noisy[0].norm_pos);
+        assert_eq!(
+            smoothed.last().unwrap().norm_pos,
+            noisy.last().unwrap().norm_pos
+        );
+        // Nothing to do
+        assert_actions(
+            &smooth(&noisy[..2], 100, None),
+            &[(0, noisy[0].norm_pos), (20, noisy[1].norm_pos)],
+        );
+        assert_eq!(smooth(&noisy, 0, None).len(), noisy.len());
+    }
+
+    #[test]
+    fn test_smooth_keeps_prominent_strokes() {
+        // Jittering around the middle, with one sharp stroke to the top
+        let mut actions: Vec<NormalisedAction> = (0..50)
+            .map(|idx| action(idx * 20, if idx % 2 == 0 { 0.5 } else { 0.52 }))
+            .collect();
+        actions[25].norm_pos = 1.0;
+
+        let flattened = smooth(&actions, 100, None);
+        assert!(flattened[25].norm_pos < 0.8, "{:?}", flattened[25]);
+
+        let kept = smooth(&actions, 100, Some(0.2));
+        assert_eq!(kept[25].norm_pos, 1.0);
+        // The jitter is still smoothed
+        assert!((kept[10].norm_pos - 0.51).abs() < 0.005, "{:?}", kept[10]);
+    }
+
+    #[test]
+    fn test_apply_limits() {
+        // Full strokes every 100 ms: 10 full-scale movements per second
+        let actions: Vec<NormalisedAction> = (0..50)
+            .map(|idx| action(idx * 100, (idx % 2) as f32))
+            .collect();
+
+        for (speed_limit, min, max) in [(2.0, 0.0, 1.0), (5.0, 0.2, 0.8), (50.0, 0.1, 0.9)] {
+            let baked = apply_limits(&actions, speed_limit, min, max);
+            assert_eq!(baked.len(), actions.len());
+            for pair in baked.windows(2) {
+                let speed = (pair[1].norm_pos - pair[0].norm_pos).abs()
+                    / (pair[1].at - pair[0].at) as f32
+                    * 1000.0;
+                assert!(
+                    speed <= speed_limit * (1.0 + 1e-4),
+                    "{speed} > {speed_limit} between {pair:?}"
+                );
+            }
+            assert!(baked
+                .iter()
+                .all(|a| (min - 1e-6..=max + 1e-6).contains(&a.norm_pos)));
+            assert_eq!(baked[0].norm_pos, min);
+        }
+
+        // Slow enough to follow exactly: only the band applies
+        assert_actions(
+            &apply_limits(&actions[..3], 50.0, 0.2, 0.8),
+            &[(0, 0.2), (100, 0.8), (200, 0.2)],
+        );
+        // Too fast: each stroke goes as far as it can
+        assert_actions(
+            &apply_limits(&actions[..3], 2.0, 0.0, 1.0),
+            &[(0, 0.0), (100, 0.2), (200, 0.0)],
+        );
+    }
+
+    #[test]
+    fn test_derive_secondary_well_formed() {
+        // Property test over scripts of random strokes, some of them very fast
+        for seed in 0..50u64 {
+            let mut rng = SplitMix64(seed);
+            let mut at = 0;
+            let stroke: Vec<NormalisedAction> = (0..200)
+                .map(|_| {
+                    at += 1 + (rng.next_f32() * 600.0) as u32;
+                    action(at, rng.next_f32())
+                })
+                .collect();
+            let params = SecondaryParams {
+                amplitude: 0.4,
+                seed,
+                ..SecondaryParams::default()
+            };
+            for kind in [
+                AxisKind::Roll,
+                AxisKind::Pitch,
+                AxisKind::Twist,
+                AxisKind::Vibration,
+            ] {
+                let derived = derive_secondary(&stroke, kind, &params);
+                assert!(!derived.is_empty(), "{kind:?}");
+                assert!(
+                    derived.windows(2).all(|pair| pair[0].at < pair[1].at),
+                    "{kind:?} not sorted"
+                );
+                assert!(
+                    derived
+                        .iter()
+                        .all(|action| (0.0..=1.0).contains(&action.norm_pos)),
+                    "{kind:?} out of range"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_derive_secondary() {
+        let stroke = [action(0, 0.0), action(500, 1.0), action(1000, 0.0)];
+        let params = SecondaryParams::default();
+
+        // Roll lags and is scaled down
+        assert_actions(
+            &derive_secondary(&stroke, AxisKind::Roll, &params),
+            &[(150, 0.35), (650, 0.65), (1150, 0.35)],
+        );
+
+        // Twist stays still when the stroke does, and is deterministic
+        let still = [action(0, 0.5), action(5000, 0.5)];
+        let twist = derive_secondary(&still, AxisKind::Twist, &params);
+        assert!(twist.iter().all(|action| action.norm_pos == 0.5));
+        let moving: Vec<NormalisedAction> = (0..50)
+            .map(|idx| action(idx * 250, (idx % 2) as f32))
+            .collect();
+        let twist = derive_secondary(&moving, AxisKind::Twist, &params);
+        let again = derive_secondary(&moving, AxisKind::Twist, &params);
+        assert!(twist.iter().any(|action| action.norm_pos != 0.5));
+        assert!(twist
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.at == b.at && a.norm_pos == b.norm_pos));
+        let other_seed = SecondaryParams {
+            seed: 1,
+            ..params.clone()
+        };
+        let other = derive_secondary(&moving, AxisKind::Twist, &other_seed);
+        assert!(twist
+            .iter()
+            .zip(&other)
+            .any(|(a, b)| a.norm_pos != b.norm_pos));
+
+        assert!(derive_secondary(&stroke, AxisKind::Lubricant, &params).is_empty());
+        assert!(derive_secondary(&[], AxisKind::Roll, &params).is_empty());
+
+        // Right up to the end of time
+        let late = [action(u32::MAX - 1000, 0.0), action(u32::MAX, 1.0)];
+        let vibration = derive_secondary(&late, AxisKind::Vibration, &params);
+        assert_eq!(vibration.last().map(|action| action.at), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_movement_between() {
+        let stroke = [action(0, 0.0), action(1000, 1.0), action(2000, 0.0)];
+        assert_eq!(movement_between(&stroke, 0, 2000), 2.0);
+        assert_eq!(movement_between(&stroke, 500, 1500), 1.0);
+        assert_eq!(movement_between(&stroke, 250, 500), 0.25);
+        assert_eq!(movement_between(&stroke, 2500, 3000), 0.0);
+        assert_eq!(movement_between(&[], 0, 1000), 0.0);
+    }
+}
@@ -0,0 +1,200 @@
+//! Repairs for the defects found in real-world funscripts.
+//!
+//! See [`crate::schema::Funscript::validate_and_repair`].
+
+use std::fmt::{self, Display};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::schema::FunscriptAction;
+
+/// A repair made to a funscript.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixupReport {
+    /// The T-Code name of the multiscript axis that was repaired,
+    /// or `None` for the main actions.
+    pub axis: Option<String>,
+    pub repair: Repair,
+}
+
+/// What was wrong and what was done about it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Repair {
+    /// An action whose time or position wasn't a usable number was dropped.
+    DroppedInvalidAction { index: usize, reason: String },
+    /// The actions weren't in time order, so were sorted.
+    Sorted,
+    /// Several actions had the same time; only the last was kept.
+    MergedDuplicates { at: u32, count: usize },
+    /// A position was outside `0..=range`, so was clamped.
+    ClampedPosition { at: u32, pos: f64, clamped_to: u32 },
+    /// The file started with a byte order mark, which was ignored (lenient parsing only).
+    StrippedByteOrderMark,
+    /// Comments were removed (lenient parsing only).
+    RemovedComments { count: usize },
+    /// Commas before the end of an object or array were removed (lenient parsing only).
+    RemovedTrailingCommas { count: usize },
+    /// `NaN` or `Infinity` was replaced with `null` (lenient parsing only),
+    /// so any actions with them were then dropped.
+    ReplacedNonFiniteNumbers { count: usize },
+}
+
+impl Display for FixupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(axis) = &self.axis {
+            write!(f, "{axis}: ")?;
+        }
+        match &self.repair {
+            Repair::DroppedInvalidAction { index, reason } => {
+                write!(f, "dropped action #{index}: {reason}")
+            }
+            Repair::Sorted => write!(f, "sorted actions into time order"),
+            Repair::MergedDuplicates { at, count } => {
+                write!(f, "merged {count} actions at {at} ms, keeping the last")
+            }
+            Repair::ClampedPosition {
+                at,
+                pos,
+                clamped_to,
+            } => write!(f, "clamped position {pos} at {at} ms to {clamped_to}"),
+            Repair::StrippedByteOrderMark => write!(f, "ignored byte order mark"),
+            Repair::RemovedComments { count } => write!(f, "removed {count} comments"),
+            Repair::RemovedTrailingCommas { count } => {
+                write!(f, "removed {count} trailing commas")
+            }
+            Repair::ReplacedNonFiniteNumbers { count } => {
+                write!(f, "replaced {count} NaN or Infinity values with null")
+            }
+        }
+    }
+}
+
+/// An action as it appears in the file, before we know whether it makes sense.
+#[derive(Deserialize)]
+pub(crate) struct RawAction {
+    #[serde(default)]
+    at: Value,
+    #[serde(default)]
+    pos: Value,
+}
+
+/// Reads a number, which buggy exporters sometimes write as a string.
+fn number(value: &Value) -> Option<f64> {
+    let number = match value {
+        Value::Number(number) => number.as_f64()?,
+        Value::String(text) => text.trim().parse().ok()?,
+        _ => return None,
+    };
+    number.is_finite().then_some(number)
+}
+
+/// Converts actions as they appear in the file into actions,
+/// dropping those without a usable time or position and clamping negative positions.
+///
+/// Positions are multiplied by `pos_scale` before rounding, for files whose positions are
+/// fractions (see [`PositionConvention`](crate::schema::PositionConvention)).
+pub(crate) fn actions_from_raw(
+    raw: Vec<RawAction>,
+    pos_scale: f64,
+) -> (Vec<FunscriptAction>, Vec<Repair>) {
+    let mut actions = Vec::with_capacity(raw.len());
+    let mut repairs = Vec::new();
+
+    for (index, raw_action) in raw.into_iter().enumerate() {
+        let Some(at) = number(&raw_action.at) else {
+            repairs.push(Repair::DroppedInvalidAction {
+                index,
+                reason: format!("bad time {}", raw_action.at),
+            });
+            continue;
+        };
+        if !(0.0..=u32::MAX as f64).contains(&at) {
+            repairs.push(Repair::DroppedInvalidAction {
+                index,
+                reason: format!("time {at} out of range"),
+            });
+            continue;
+        }
+        let at = at.round() as u32;
+
+        let Some(pos) = number(&raw_action.pos).map(|pos| pos * pos_scale) else {
+            repairs.push(Repair::DroppedInvalidAction {
+                index,
+                reason: format!("bad position {}", raw_action.pos),
+            });
+            continue;
+        };
+        if pos < 0.0 {
+            repairs.push(Repair::ClampedPosition {
+                at,
+                pos,
+                clamped_to: 0,
+            });
+        }
+
+        actions.push(FunscriptAction {
+            at,
+            pos: pos.round().clamp(0.0, u32::MAX as f64) as u32,
+        });
+    }
+
+    (actions, repairs)
+}
+
+/// Sorts actions stably by time, merges actions at the same time (keeping the last)
+/// and clamps positions into `0..=range`.
+pub(crate) fn repair_actions(actions: &mut Vec<FunscriptAction>, range: u32) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+
+    if !actions.windows(2).all(|pair| pair[0].at <= pair[1].at) {
+        actions.sort_by_key(|action| action.at);
+        repairs.push(Repair::Sorted);
+    }
+
+    let mut merged: Vec<FunscriptAction> = Vec::with_capacity(actions.len());
+    let mut duplicates = 1;
+    for action in actions.drain(..) {
+        match merged.last_mut() {
+            Some(previous) if previous.at == action.at => {
+                *previous = action;
+                duplicates += 1;
+            }
+            _ => {
+                if duplicates > 1 {
+                    repairs.push(Repair::MergedDuplicates {
+                        at: merged
+                            .last()
+                            .map(|previous| previous.at)
+                            .unwrap_or_default(),
+                        count: duplicates,
+                    });
+                }
+                duplicates = 1;
+                merged.push(action);
+            }
+        }
+    }
+    if duplicates > 1 {
+        if let Some(last) = merged.last() {
+            repairs.push(Repair::MergedDuplicates {
+                at: last.at,
+                count: duplicates,
+            });
+        }
+    }
+    *actions = merged;
+
+    for action in actions.iter_mut() {
+        if action.pos > range {
+            repairs.push(Repair::ClampedPosition {
+                at: action.at,
+                pos: action.pos as f64,
+                clamped_to: range,
+            });
+            action.pos = range;
+        }
+    }
+
+    repairs
+}
@@ -1,11 +1,28 @@
-use std::cmp::max;
+use std::{cmp::max, collections::BTreeMap, fmt, ops::Range, path::Path};
 
-use serde::{Deserialize, Serialize};
+use eyre::{bail, Context};
+use serde::{
+    de::{self, Unexpected, Visitor},
+    ser::Error as _,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::{Map, Value};
+use strokers_core::{time::parse_time_text, AxisKind};
+use tracing::warn;
+
+use crate::{
+    lenient::clean_json,
+    processing::{denormalise, normalised_actions, shift_times, NormalisedAction},
+    repair::{actions_from_raw, repair_actions, FixupReport, RawAction, Repair},
+    slice::{exclude_range, slice_actions},
+    FunscriptError,
+};
 
 /// A funscript is a JSON-encoded document that describes how one axis moves throughout time.
 ///
 /// You should call [`Self::fixup`] on this afterwards if you want to interpret it.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "RawFunscript")]
 pub struct Funscript {
     /// List of actions **sorted by timestamp order**
     /// (or at least I am claiming this needs to be sorted until proven otherwise)
@@ -21,18 +38,461 @@ pub struct Funscript {
     #[serde(default)]
     pub range: u32,
 
+    /// Metadata, as written by OpenFunscripter.
+    pub metadata: Option<FunscriptMetadata>,
+
+    /// Further axes of a multi-axis script ('multiscript'), beyond the main one in `actions`.
+    pub axes: Option<Vec<FunscriptAxis>>,
+
     /// Keys that we don't know about or don't care to implement right now.
     /// This just ensures they get preserved if we re-emit the file.
-    #[serde(flatten)]
     pub unknown: serde_json::Value,
+
+    /// Whether `inverted` was present in the file, so that we can leave it out when saving if not.
+    inverted_present: bool,
+
+    /// Whether `range` was present in the file, so that we can leave it out when saving if not.
+    range_present: bool,
+
+    /// Whether `range` was computed by [`Self::fixup`] rather than coming from the file.
+    range_computed: bool,
+
+    /// Repairs made whilst reading the file, to be reported by [`Self::validate_and_repair`].
+    parse_repairs: Vec<FixupReport>,
+}
+
+/// How the positions of actions are written in a file, going by its `version`.
+///
+/// Whichever convention a file uses, positions are whole numbers in memory: fractions are
+/// scaled up to a range of [`NORMALISED_RANGE`], so that everything else (normalising,
+/// processing, …) works the same for both. Saving converts them back.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PositionConvention {
+    /// Whole numbers from 0 to `range` (usually 100), as written by most tools.
+    #[default]
+    Integer,
+    /// Fractions from 0.0 to 1.0, as written by newer tools with a `version` of 2 or above.
+    /// There is no `range`.
+    Normalised,
+}
+
+/// The range that positions written as fractions are scaled up to in memory.
+pub const NORMALISED_RANGE: u32 = 10_000;
+
+impl PositionConvention {
+    /// The convention for a `version` field, which may be a string (`"2.0"`) or a number.
+    ///
+    /// `None` if there is no version or it isn't recognisable as one.
+    pub fn from_version(version: Option<&Value>) -> Option<PositionConvention> {
+        let major = match version? {
+            Value::String(text) => text.trim().split('.').next()?.parse().ok()?,
+            Value::Number(number) => number.as_f64()?.trunc(),
+            _ => return None,
+        };
+        Some(if major >= 2.0 {
+            PositionConvention::Normalised
+        } else {
+            PositionConvention::Integer
+        })
+    }
+
+    /// What positions in the file are multiplied by to get positions in memory.
+    fn scale(self) -> f64 {
+        match self {
+            PositionConvention::Integer => 1.0,
+            PositionConvention::Normalised => NORMALISED_RANGE as f64,
+        }
+    }
+}
+
+/// The funscript as it appears in the file, so we can tell which fields were present.
+#[derive(Deserialize)]
+struct RawFunscript {
+    actions: Vec<RawAction>,
+    #[serde(default)]
+    inverted: Option<bool>,
+    #[serde(default)]
+    range: Option<u32>,
+    #[serde(default)]
+    metadata: Option<FunscriptMetadata>,
+    #[serde(default)]
+    axes: Option<Vec<RawAxis>>,
+    #[serde(flatten)]
+    unknown: Value,
+}
+
+/// A further axis as it appears in the file: its positions can't be read until we know
+/// which [`PositionConvention`] it follows, which may come from the funscript around it.
+#[derive(Deserialize)]
+struct RawAxis {
+    id: String,
+    actions: Vec<RawAction>,
+    #[serde(default)]
+    inverted: Option<bool>,
+    #[serde(default)]
+    range: Option<u32>,
+    #[serde(flatten)]
+    unknown: Map<String, Value>,
+}
+
+impl RawAxis {
+    /// Reads the axis, following its own `version` if it has one or else `parent`'s.
+    fn into_axis(self, parent: PositionConvention) -> (FunscriptAxis, Vec<Repair>) {
+        let convention =
+            PositionConvention::from_version(self.unknown.get("version")).unwrap_or(parent);
+        let (actions, repairs) = actions_from_raw(self.actions, convention.scale());
+        let range = match convention {
+            PositionConvention::Normalised => Some(NORMALISED_RANGE),
+            // The funscript's range is for fractions, so can't be inherited
+            PositionConvention::Integer
+                if parent == PositionConvention::Normalised
+                    && self.range.unwrap_or_default() == 0 =>
+            {
+                Some(max(
+                    actions.iter().map(|action| action.pos).max().unwrap_or(100),
+                    100,
+                ))
+            }
+            PositionConvention::Integer => self.range,
+        };
+        let axis = FunscriptAxis {
+            id: self.id,
+            actions,
+            inverted: self.inverted,
+            range,
+            unknown: self.unknown,
+        };
+        (axis, repairs)
+    }
+}
+
+impl From<RawFunscript> for Funscript {
+    fn from(raw: RawFunscript) -> Self {
+        let convention =
+            PositionConvention::from_version(raw.unknown.get("version")).unwrap_or_default();
+        let (actions, repairs) = actions_from_raw(raw.actions, convention.scale());
+        let mut parse_repairs: Vec<FixupReport> = repairs
+            .into_iter()
+            .map(|repair| FixupReport { axis: None, repair })
+            .collect();
+        let axes = raw.axes.map(|axes| {
+            axes.into_iter()
+                .map(|axis| {
+                    let (axis, repairs) = axis.into_axis(convention);
+                    parse_repairs.extend(repairs.into_iter().map(|repair| FixupReport {
+                        axis: Some(axis.id.clone()),
+                        repair,
+                    }));
+                    axis
+                })
+                .collect()
+        });
+
+        // A `range` means nothing alongside fractions, so it's dropped
+        let range_present = raw.range.is_some() && convention == PositionConvention::Integer;
+        let range = match convention {
+            PositionConvention::Integer => raw.range.unwrap_or_default(),
+            PositionConvention::Normalised => NORMALISED_RANGE,
+        };
+        Funscript {
+            actions,
+            inverted: raw.inverted.unwrap_or_default(),
+            range,
+            metadata: raw.metadata,
+            axes,
+            unknown: raw.unknown,
+            inverted_present: raw.inverted.is_some(),
+            range_present,
+            range_computed: false,
+            parse_repairs,
+        }
+    }
+}
+
+#[cfg(feature = "fast_parse")]
+impl From<crate::fast_parse::FastFunscript> for Funscript {
+    fn from(fast: crate::fast_parse::FastFunscript) -> Self {
+        Funscript {
+            actions: fast.actions,
+            inverted: fast.inverted.unwrap_or_default(),
+            range: fast.range.unwrap_or_default(),
+            metadata: fast.metadata,
+            axes: fast.axes,
+            unknown: Value::Object(fast.unknown),
+            inverted_present: fast.inverted.is_some(),
+            range_present: fast.range.is_some(),
+            range_computed: false,
+            parse_repairs: Vec::new(),
+        }
+    }
+}
+
+/// One of the further axes of a multiscript.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunscriptAxis {
+    /// The T-Code name of the axis, e.g. `R0`.
+    pub id: String,
+
+    /// List of actions, sorted by timestamp order like [`Funscript::actions`].
+    pub actions: Vec<FunscriptAction>,
+
+    /// Whether this axis is inverted, if different from [`Funscript::inverted`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inverted: Option<bool>,
+
+    /// The maximum value of `pos` for this axis, if different from [`Funscript::range`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<u32>,
+
+    /// Keys that we don't know about, preserved for re-emitting the file.
+    #[serde(flatten)]
+    pub unknown: Map<String, Value>,
+}
+
+/// The actions for one axis of a (multi)script, borrowed from it,
+/// with the `inverted` and `range` that apply to them.
+/// See [`Funscript::get_axes_funscripts`].
+#[derive(Copy, Clone, Debug)]
+pub struct AxisActions<'a> {
+    pub actions: &'a [FunscriptAction],
+    pub inverted: bool,
+    pub range: u32,
+}
+
+impl<'a> AxisActions<'a> {
+    /// The actions, normalised as by [`crate::processing::normalised_from_funscript`].
+    pub fn normalised(&self) -> impl Iterator<Item = NormalisedAction> + 'a {
+        normalised_actions(self.actions, self.range, self.inverted)
+    }
+}
+
+/// The `metadata` object written by OpenFunscripter.
+///
+/// All fields are optional, as other tools write some, all or none of them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FunscriptMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Duration of the video, in seconds.
+    /// Kept as a JSON number, as some tools write integers and some write fractions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<serde_json::Number>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chapters: Option<Vec<FunscriptChapter>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bookmarks: Option<Vec<FunscriptBookmark>>,
+
+    /// Metadata keys that we don't know about, preserved for re-emitting the file.
+    #[serde(flatten)]
+    pub unknown: Map<String, Value>,
+}
+
+/// A named section of the video.
+///
+/// The times are optional so that a chapter missing one doesn't stop the whole script
+/// from loading; such chapters are skipped by [`Funscript::chapters`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunscriptChapter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "startTime", default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<MetadataTime>,
+    #[serde(rename = "endTime", default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<MetadataTime>,
+}
+
+/// A named point in the video.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunscriptBookmark {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub time: MetadataTime,
+}
+
+/// A time in a funscript's metadata.
+///
+/// Whole and fractional milliseconds and `[hh:]mm:ss[.fff]` strings are all found in the wild;
+/// the original form is kept so the file can be re-emitted as it was.
+/// Anything else is kept too, rather than refusing the script, but has no time.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataTime {
+    Milliseconds(u32),
+    /// Milliseconds that aren't a whole number (or are out of range)
+    FractionalMilliseconds(serde_json::Number),
+    Text(String),
+    Other(Value),
+}
+
+impl MetadataTime {
+    /// The time in milliseconds since the start of the video.
+    pub fn milliseconds(&self) -> eyre::Result<u32> {
+        match self {
+            MetadataTime::Milliseconds(millis) => Ok(*millis),
+            MetadataTime::FractionalMilliseconds(number) => {
+                let millis = number.as_f64().unwrap_or(f64::NAN).round();
+                if !(0.0..=u32::MAX as f64).contains(&millis) {
+                    bail!("time {number} out of range");
+                }
+                Ok(millis as u32)
+            }
+            MetadataTime::Text(text) => parse_time_text(text),
+            MetadataTime::Other(other) => bail!("{other} isn't a time"),
+        }
+    }
+}
+
+/// A chapter of a funscript, with its times resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChapterSpan {
+    pub name: Option<String>,
+    /// Milliseconds since the start of the video.
+    pub range: Range<u32>,
+}
+
+/// Options for saving a [`Funscript`].
+#[derive(Clone, Debug, Default)]
+pub struct SaveOptions {
+    /// Write `range` even if it wasn't in the original file and was only computed by [`Funscript::fixup`].
+    pub write_computed_range: bool,
 }
 
 impl Funscript {
-    /// Applies fixups to the funscript
+    /// Create a funscript with the given actions and nothing else.
+    pub fn new(actions: Vec<FunscriptAction>) -> Funscript {
+        Funscript {
+            actions,
+            inverted: false,
+            range: 0,
+            metadata: None,
+            axes: None,
+            unknown: Value::Object(Map::new()),
+            inverted_present: false,
+            range_present: false,
+            range_computed: false,
+            parse_repairs: Vec::new(),
+        }
+    }
+
+    /// Starts building a funscript action by action, e.g. in tests.
+    pub fn builder() -> FunscriptBuilder {
+        FunscriptBuilder::default()
+    }
+
+    /// Loads a funscript from a file at the given path.
     ///
-    /// Current fixups:
-    /// - populate a value for `range` if it is unset (zero)
+    /// This doesn't call [`Self::fixup`].
+    pub async fn load_from_path(path: &Path) -> eyre::Result<Funscript> {
+        let contents = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("failed to read {path:?}"))?;
+        Funscript::from_slice(&contents).with_context(|| format!("failed to load {path:?}"))
+    }
+
+    /// Deserialises a funscript from the contents of a file, which must be valid JSON.
+    ///
+    /// With the `fast_parse` feature, well-formed funscripts are read more quickly,
+    /// falling back to the usual way for anything else.
+    ///
+    /// This doesn't call [`Self::fixup`].
+    pub fn from_slice(contents: &[u8]) -> eyre::Result<Funscript> {
+        #[cfg(feature = "fast_parse")]
+        match crate::fast_parse::parse(contents) {
+            Ok(fast) => return Ok(fast.into()),
+            Err(err) => tracing::debug!("fast funscript parsing failed, so going slowly: {err}"),
+        }
+        serde_json::from_slice(contents).context("failed to deserialise funscript")
+    }
+
+    /// Deserialises a funscript from the contents of a file, tolerating the ways in which
+    /// some editors write not-quite-JSON: a byte order mark at the start, comments,
+    /// trailing commas and `NaN` or `Infinity` for numbers (actions with them are dropped).
+    ///
+    /// What had to be tolerated is reported by [`Self::validate_and_repair`] along with
+    /// any other repairs. Prefer [`Self::from_slice`] and only fall back to this.
+    ///
+    /// This doesn't call [`Self::fixup`].
+    pub fn from_slice_lenient(contents: &[u8]) -> eyre::Result<Funscript> {
+        let (cleaned, repairs) = clean_json(contents);
+        let mut funscript: Funscript = serde_json::from_slice(&cleaned)
+            .context("failed to deserialise funscript, even leniently")?;
+        let mut reports: Vec<FixupReport> = repairs
+            .into_iter()
+            .map(|repair| FixupReport { axis: None, repair })
+            .collect();
+        reports.append(&mut funscript.parse_repairs);
+        funscript.parse_repairs = reports;
+        Ok(funscript)
+    }
+
+    /// Creates a funscript from actions in CSV (see [`crate::csv`]).
+    ///
+    /// The actions are sorted by time and [`Self::fixup`] is applied; the range it computes
+    /// is kept as though it came from the file, so it gets written when saving.
+    pub fn from_csv(reader: impl std::io::BufRead) -> eyre::Result<Funscript> {
+        let mut actions = crate::csv::from_csv_reader(reader)?;
+        actions.sort_by_key(|action| action.at);
+        let mut funscript = Funscript::new(actions);
+        funscript.fixup();
+        funscript.range_computed = false;
+        Ok(funscript)
+    }
+
+    /// Creates a funscript from normalised actions, e.g. from [`crate::processing`],
+    /// so that they can be saved.
+    ///
+    /// Positions are scaled up to `range` and rounded; nothing else is set.
+    /// A range of 0 is taken as 100, as when normalising.
+    pub fn from_normalised(actions: &[NormalisedAction], range: u32) -> Funscript {
+        let mut funscript = Funscript::new(
+            actions
+                .iter()
+                .map(|action| denormalise(action, range, false))
+                .collect(),
+        );
+        funscript.range = range;
+        funscript
+    }
+
+    /// Replaces the actions (of the main axis) with normalised actions, e.g. from
+    /// [`crate::processing`], keeping everything else, such as the metadata.
+    ///
+    /// Positions are scaled to this funscript's `range` and `inverted`,
+    /// so that normalising it again gives the same actions (within rounding).
+    pub fn replace_actions_from_normalised(&mut self, actions: &[NormalisedAction]) {
+        self.actions = actions
+            .iter()
+            .map(|action| denormalise(action, self.range, self.inverted))
+            .collect();
+    }
+
+    /// Applies fixups to the funscript, logging any repairs that were needed.
+    ///
+    /// See [`Self::validate_and_repair`].
     pub fn fixup(&mut self) {
+        for report in self.validate_and_repair() {
+            warn!("repaired funscript: {report}");
+        }
+    }
+
+    /// Applies fixups to the funscript and returns the repairs that were needed.
+    ///
+    /// Current fixups:
+    /// - drop actions whose time or position isn't a usable number (whilst reading the file)
+    /// - populate a value for `range` if it is unset (zero); this isn't reported
+    /// - sort actions stably by time
+    /// - merge actions with the same time, keeping the last
+    /// - clamp positions into `0..=range`
+    ///
+    /// These apply to the actions of further axes too.
+    /// Afterwards, actions are strictly increasing in time with positions within the range.
+    pub fn validate_and_repair(&mut self) -> Vec<FixupReport> {
+        let mut reports: Vec<FixupReport> = self.parse_repairs.drain(..).collect();
+
         if self.range == 0 {
             // If the range isn't set, then set it to 100 or whatever the maximum value is in the file.
             self.range = max(
@@ -43,16 +503,1276 @@ impl Funscript {
                     .unwrap_or(100),
                 100,
             );
+            self.range_computed = true;
+        }
+
+        reports.extend(
+            repair_actions(&mut self.actions, self.range)
+                .into_iter()
+                .map(|repair| FixupReport { axis: None, repair }),
+        );
+        for axis in self.axes.iter_mut().flatten() {
+            let range = axis.range.filter(|&range| range != 0).unwrap_or(self.range);
+            reports.extend(
+                repair_actions(&mut axis.actions, range)
+                    .into_iter()
+                    .map(|repair| FixupReport {
+                        axis: Some(axis.id.clone()),
+                        repair,
+                    }),
+            );
+        }
+        reports
+    }
+
+    /// Looks for signs that the funscript won't do what the user expects, such as having no
+    /// actions at all, so that they can be told rather than left wondering why nothing moves.
+    ///
+    /// The main actions are checked, unless this is a multiscript without any (only other axes).
+    /// With the video's duration, the actions of every axis are checked to fit in the video.
+    /// This is best done after [`Self::fixup`].
+    pub fn sanity_check(&self, video_duration_ms: Option<u32>) -> Vec<FunscriptError> {
+        let mut problems = Vec::new();
+
+        let other_axes_have_actions = self
+            .axes
+            .iter()
+            .flatten()
+            .any(|axis| !axis.actions.is_empty());
+        match self.actions.as_slice() {
+            [] if !other_axes_have_actions => problems.push(FunscriptError::Empty),
+            [] => {}
+            [_] => problems.push(FunscriptError::SingleAction),
+            [first, rest @ ..] => {
+                if rest.iter().all(|action| action.pos == first.pos) {
+                    problems.push(FunscriptError::NoMovement { pos: first.pos });
+                }
+            }
+        }
+
+        let last_action_at = self
+            .actions
+            .iter()
+            .chain(self.axes.iter().flatten().flat_map(|axis| &axis.actions))
+            .map(|action| action.at)
+            .max();
+        if let (Some(last_action_at), Some(video_duration_ms)) = (last_action_at, video_duration_ms)
+        {
+            problems.extend(FunscriptError::check_video_duration(
+                last_action_at,
+                video_duration_ms,
+            ));
+        }
+
+        problems
+    }
+
+    /// Adds the actions for another axis, making this a multiscript.
+    ///
+    /// Stroke actions go in [`Self::actions`]; other axes go in [`Self::axes`] under their
+    /// T-Code names. Positions should be on the same scale as this funscript's `range`.
+    ///
+    /// Fails if this funscript already has actions for that axis;
+    /// use [`Self::set_axis_actions`] to replace them instead.
+    pub fn merge_axes(
+        &mut self,
+        axis: AxisKind,
+        actions: Vec<FunscriptAction>,
+    ) -> eyre::Result<()> {
+        self.set_axis(axis, actions, false)
+    }
+
+    /// Like [`Self::merge_axes`], but replaces any existing actions for the axis.
+    ///
+    /// A replaced further axis loses any `inverted` or `range` of its own,
+    /// as the new actions are on this funscript's scale.
+    pub fn set_axis_actions(
+        &mut self,
+        axis: AxisKind,
+        actions: Vec<FunscriptAction>,
+    ) -> eyre::Result<()> {
+        self.set_axis(axis, actions, true)
+    }
+
+    fn set_axis(
+        &mut self,
+        axis: AxisKind,
+        actions: Vec<FunscriptAction>,
+        overwrite: bool,
+    ) -> eyre::Result<()> {
+        if axis == AxisKind::Stroke {
+            if !self.actions.is_empty() && !overwrite {
+                bail!("funscript already has actions for {axis:?}");
+            }
+            self.actions = actions;
+            return Ok(());
+        }
+
+        let Some(id) = axis.tcode_name() else {
+            bail!("{axis:?} has no T-Code name to use in a multiscript");
+        };
+        let axes = self.axes.get_or_insert_with(Vec::new);
+        match axes.iter_mut().find(|existing| existing.id == id) {
+            Some(_) if !overwrite => bail!("funscript already has actions for {axis:?} ({id})"),
+            Some(existing) => {
+                existing.actions = actions;
+                existing.inverted = None;
+                existing.range = None;
+            }
+            None => axes.push(FunscriptAxis {
+                id: id.to_owned(),
+                actions,
+                inverted: None,
+                range: None,
+                unknown: Map::new(),
+            }),
+        }
+        Ok(())
+    }
+
+    /// Splits a (multi)script into the actions for each kind of axis,
+    /// each with the `inverted` and `range` that apply to it.
+    ///
+    /// Further axes use their own `inverted` and `range` if they have them,
+    /// otherwise those of this funscript.
+    /// Further axes with T-Code names that we don't recognise are skipped.
+    ///
+    /// The actions are borrowed rather than copied: for a script of 500 000 actions,
+    /// a copy would be another 4 MB per axis.
+    pub fn get_axes_funscripts(&self) -> BTreeMap<AxisKind, AxisActions<'_>> {
+        let mut out = BTreeMap::new();
+        if !self.actions.is_empty() {
+            out.insert(
+                AxisKind::Stroke,
+                AxisActions {
+                    actions: &self.actions,
+                    inverted: self.inverted,
+                    range: self.range,
+                },
+            );
+        }
+        for axis in self.axes.iter().flatten() {
+            let Some(axis_kind) = AxisKind::from_tcode_name(&axis.id) else {
+                warn!(
+                    "skipping multiscript axis with unknown T-Code name {:?}",
+                    axis.id
+                );
+                continue;
+            };
+            out.insert(
+                axis_kind,
+                AxisActions {
+                    actions: &axis.actions,
+                    inverted: axis.inverted.unwrap_or(self.inverted),
+                    range: axis.range.filter(|&range| range != 0).unwrap_or(self.range),
+                },
+            );
+        }
+        out
+    }
+
+    /// Returns the main actions with `range` and `inverted` applied, so that positions
+    /// go from 0 to 100 the right way up.
+    pub fn standardised_actions(&self) -> Vec<FunscriptAction> {
+        let range = match self.range {
+            0 => 100,
+            range => range,
+        };
+        self.actions
+            .iter()
+            .map(|action| {
+                let pos = (action.pos.min(range) as f64 * 100.0 / range as f64).round() as u32;
+                FunscriptAction {
+                    at: action.at,
+                    pos: if self.inverted { 100 - pos } else { pos },
+                }
+            })
+            .collect()
+    }
+
+    /// Shifts all actions, including those of further axes, by `offset_ms` milliseconds.
+    ///
+    /// See [`crate::processing::shift_ms`] for what happens to actions that would go below zero.
+    pub fn shift_ms(&mut self, offset_ms: i32) {
+        shift_times(&mut self.actions, offset_ms, |action| &mut action.at);
+        for axis in self.axes.iter_mut().flatten() {
+            shift_times(&mut axis.actions, offset_ms, |action| &mut action.at);
+        }
+    }
+
+    /// Returns the part of the script from `start_ms` to `end_ms`, re-based to start at zero,
+    /// e.g. to play a single chapter.
+    ///
+    /// Each axis gets an action at both ends, interpolated from the actions either side
+    /// if there isn't one there already, so positions are right from the start.
+    /// Everything else is kept, except for the chapters and bookmarks in the metadata,
+    /// which would no longer line up.
+    pub fn slice_range(&self, start_ms: u32, end_ms: u32) -> Funscript {
+        let mut sliced = self.clone();
+        sliced.actions = slice_actions(&self.actions, start_ms, end_ms);
+        for axis in sliced.axes.iter_mut().flatten() {
+            axis.actions = slice_actions(&axis.actions, start_ms, end_ms);
+        }
+        if let Some(metadata) = &mut sliced.metadata {
+            metadata.chapters = None;
+            metadata.bookmarks = None;
+        }
+        sliced
+    }
+
+    /// Removes the actions within each of the given ranges, e.g. to skip an intro,
+    /// keeping the times of all other actions (so the script still lines up with the video).
+    ///
+    /// Each axis gets actions at the edges of each range with the positions that were there,
+    /// so that it glides across the range rather than jumping at either edge.
+    pub fn exclude_ranges(&mut self, ranges: &[Range<u32>]) {
+        for range in ranges {
+            exclude_range(&mut self.actions, range);
+            for axis in self.axes.iter_mut().flatten() {
+                exclude_range(&mut axis.actions, range);
+            }
+        }
+    }
+
+    /// Returns the chapters from the metadata, in the order they appear, with times in milliseconds.
+    ///
+    /// Chapters with missing or unreadable times, or that end before they start, are skipped.
+    pub fn chapters(&self) -> Vec<ChapterSpan> {
+        let Some(chapters) = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.chapters.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        let mut out = Vec::with_capacity(chapters.len());
+        for chapter in chapters {
+            let (Some(start_time), Some(end_time)) = (&chapter.start_time, &chapter.end_time)
+            else {
+                warn!(
+                    "skipping chapter {:?} without a start and end",
+                    chapter.name
+                );
+                continue;
+            };
+            let (start, end) = match (start_time.milliseconds(), end_time.milliseconds()) {
+                (Ok(start), Ok(end)) => (start, end),
+                (Err(err), _) | (_, Err(err)) => {
+                    warn!("skipping chapter {:?} with bad time: {err:?}", chapter.name);
+                    continue;
+                }
+            };
+            if end < start {
+                warn!(
+                    "skipping chapter {:?} which ends before it starts",
+                    chapter.name
+                );
+                continue;
+            }
+            out.push(ChapterSpan {
+                name: chapter.name.clone(),
+                range: start..end,
+            });
+        }
+        out
+    }
+
+    /// Converts the funscript into a JSON value, with the unknown fields alongside the known ones.
+    ///
+    /// Known fields take precedence over unknown fields with the same name.
+    pub fn to_json_value(&self, options: &SaveOptions) -> eyre::Result<Value> {
+        let mut object = match &self.unknown {
+            Value::Object(unknown) => unknown.clone(),
+            Value::Null => Map::new(),
+            other => bail!("unknown fields should be an object, not {other}"),
+        };
+
+        let convention = self.position_convention();
+        object.insert(
+            "actions".to_owned(),
+            actions_to_json(&self.actions, convention, self.range)?,
+        );
+        insert_optional(&mut object, "metadata", self.metadata.as_ref())?;
+        match &self.axes {
+            Some(axes) => {
+                let axes = axes
+                    .iter()
+                    .map(|axis| self.axis_to_json(axis))
+                    .collect::<eyre::Result<_>>()?;
+                object.insert("axes".to_owned(), Value::Array(axes));
+            }
+            None => {
+                object.remove("axes");
+            }
+        }
+        if self.inverted_present || self.inverted {
+            object.insert("inverted".to_owned(), Value::Bool(self.inverted));
+        } else {
+            object.remove("inverted");
+        }
+        let write_range = convention == PositionConvention::Integer
+            && (self.range_present
+                || (self.range != 0 && (!self.range_computed || options.write_computed_range)));
+        if write_range {
+            object.insert("range".to_owned(), Value::from(self.range));
+        } else {
+            object.remove("range");
+        }
+
+        Ok(Value::Object(object))
+    }
+
+    /// Converts a further axis into a JSON value, in whichever convention it was read in.
+    fn axis_to_json(&self, axis: &FunscriptAxis) -> eyre::Result<Value> {
+        let mut value = serde_json::to_value(axis).context("failed to serialise axis")?;
+        let convention = self.axis_position_convention(axis);
+        if convention == PositionConvention::Normalised {
+            let range = axis.range.filter(|&range| range != 0).unwrap_or(self.range);
+            value["actions"] = actions_to_json(&axis.actions, convention, range)?;
+            if let Value::Object(object) = &mut value {
+                object.remove("range");
+            }
+        }
+        Ok(value)
+    }
+
+    /// How positions are written in the file, going by its `version`.
+    ///
+    /// Files without a (recognisable) version use [`PositionConvention::Integer`].
+    pub fn position_convention(&self) -> PositionConvention {
+        PositionConvention::from_version(self.unknown.get("version")).unwrap_or_default()
+    }
+
+    /// How positions of a further axis are written, going by its own `version` if it has one
+    /// (e.g. when merged in from a file of another generation), or else the funscript's.
+    pub fn axis_position_convention(&self, axis: &FunscriptAxis) -> PositionConvention {
+        PositionConvention::from_version(axis.unknown.get("version"))
+            .unwrap_or_else(|| self.position_convention())
+    }
+
+    /// Serialises the funscript to a JSON string, using the default [`SaveOptions`].
+    pub fn to_json_string(&self) -> eyre::Result<String> {
+        self.to_json_string_with_options(&SaveOptions::default())
+    }
+
+    /// Serialises the funscript to a JSON string.
+    pub fn to_json_string_with_options(&self, options: &SaveOptions) -> eyre::Result<String> {
+        serde_json::to_string(&self.to_json_value(options)?)
+            .context("failed to serialise funscript")
+    }
+
+    /// Saves the funscript as a file at the given path.
+    pub async fn save_to_path(&self, path: &Path, options: &SaveOptions) -> eyre::Result<()> {
+        let json = self.to_json_string_with_options(options)?;
+        tokio::fs::write(path, json)
+            .await
+            .with_context(|| format!("failed to write funscript to {path:?}"))
+    }
+}
+
+/// Converts actions into a JSON value, with positions written as fractions of `range`
+/// for [`PositionConvention::Normalised`].
+fn actions_to_json(
+    actions: &[FunscriptAction],
+    convention: PositionConvention,
+    range: u32,
+) -> eyre::Result<Value> {
+    if convention == PositionConvention::Integer {
+        return serde_json::to_value(actions).context("failed to serialise actions");
+    }
+    let range = match range {
+        0 => NORMALISED_RANGE,
+        range => range,
+    };
+    Ok(actions
+        .iter()
+        .map(|action| {
+            serde_json::json!({
+                "at": action.at,
+                "pos": action.pos as f64 / range as f64,
+            })
+        })
+        .collect())
+}
+
+/// Inserts `value` under `key` if it is set, or otherwise removes `key`.
+fn insert_optional<T: Serialize>(
+    object: &mut Map<String, Value>,
+    key: &str,
+    value: Option<&T>,
+) -> eyre::Result<()> {
+    match value {
+        Some(value) => {
+            let value = serde_json::to_value(value)
+                .with_context(|| format!("failed to serialise {key}"))?;
+            object.insert(key.to_owned(), value);
+        }
+        None => {
+            object.remove(key);
+        }
+    }
+    Ok(())
+}
+
+impl Serialize for Funscript {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json_value(&SaveOptions::default())
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Builds a [`Funscript`], so that code making them (tests, generators, …) needn't change
+/// whenever the schema grows.
+///
+/// Actions are given as `(at, pos)` pairs, with positions out of the `range`
+/// (if unset, [`Funscript::fixup`] picks 100 or the highest position).
+#[derive(Clone, Debug, Default)]
+pub struct FunscriptBuilder {
+    actions: Vec<FunscriptAction>,
+    inverted: bool,
+    range: u32,
+    axes: Vec<(AxisKind, Vec<FunscriptAction>)>,
+}
+
+impl FunscriptBuilder {
+    /// Adds an action for the stroke axis.
+    pub fn action(mut self, at: u32, pos: u32) -> Self {
+        self.actions.push(FunscriptAction { at, pos });
+        self
+    }
+
+    /// Adds actions for the stroke axis.
+    pub fn actions(mut self, actions: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        self.actions.extend(
+            actions
+                .into_iter()
+                .map(|(at, pos)| FunscriptAction { at, pos }),
+        );
+        self
+    }
+
+    /// Whether positions run from `range` down to 0 instead.
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// The highest position; zero leaves it for [`Funscript::fixup`] to work out.
+    pub fn range(mut self, range: u32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Adds the actions for another axis, making this a multiscript
+    /// (see [`Funscript::merge_axes`]).
+    pub fn axis(mut self, axis: AxisKind, actions: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        let actions = actions
+            .into_iter()
+            .map(|(at, pos)| FunscriptAction { at, pos })
+            .collect();
+        self.axes.push((axis, actions));
+        self
+    }
+
+    /// Builds the funscript and applies [`Funscript::fixup`], so the actions are sorted and
+    /// within the range.
+    ///
+    /// Fails if an axis is given twice (including the stroke axis, as both actions and an
+    /// axis) or has no T-Code name. Whether the script is any use is left to
+    /// [`Funscript::sanity_check`], so that scripts with few actions can be built too.
+    pub fn build(self) -> eyre::Result<Funscript> {
+        let mut funscript = self.build_without_fixup()?;
+        funscript.fixup();
+        Ok(funscript)
+    }
+
+    /// Like [`Self::build`], but leaves the funscript exactly as given,
+    /// e.g. to test what happens to funscripts that need repairing.
+    pub fn build_without_fixup(self) -> eyre::Result<Funscript> {
+        let mut funscript = Funscript::new(self.actions);
+        funscript.inverted = self.inverted;
+        funscript.range = self.range;
+        for (axis, actions) in self.axes {
+            funscript.merge_axes(axis, actions)?;
         }
+        Ok(funscript)
     }
 }
 
 /// One datapoint on the 'curve' that the funscript represents
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// Some exporters write the numbers as fractions (`"at": 1234.5`) or as strings
+/// (`"at": "1234"`); both are accepted and rounded to the nearest whole number.
+/// Positions that are fractions of 1 by convention are scaled up when reading a [`Funscript`]
+/// instead; see [`PositionConvention`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FunscriptAction {
     /// Timestamp in milliseconds relative to the start of the video
+    #[serde(deserialize_with = "deserialize_rounded")]
     pub at: u32,
 
     /// The position of the movement at this point in time
+    #[serde(deserialize_with = "deserialize_rounded")]
     pub pos: u32,
 }
+
+/// Deserialises a whole number that may be written as a fraction or a string,
+/// rounding to the nearest.
+///
+/// Anything outside the range of a `u32` is an error: there's no way to report a repair here,
+/// unlike for the main actions (see [`crate::repair::actions_from_raw`]).
+fn deserialize_rounded<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    struct RoundedVisitor;
+
+    impl<'de> Visitor<'de> for RoundedVisitor {
+        type Value = u32;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a non-negative number, or a string containing one")
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<u32, E> {
+            u32::try_from(value).map_err(|_| E::invalid_value(Unexpected::Unsigned(value), &self))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<u32, E> {
+            u32::try_from(value).map_err(|_| E::invalid_value(Unexpected::Signed(value), &self))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<u32, E> {
+            let rounded = value.round();
+            if !(0.0..=u32::MAX as f64).contains(&rounded) {
+                return Err(E::invalid_value(Unexpected::Float(value), &self));
+            }
+            Ok(rounded as u32)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<u32, E> {
+            let number: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| E::invalid_value(Unexpected::Str(value), &self))?;
+            self.visit_f64(number)
+        }
+    }
+
+    deserializer.deserialize_any(RoundedVisitor)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+    use strokers_core::AxisKind;
+
+    use crate::{
+        processing::{normalised_from_funscript, resample, NormalisedAction},
+        repair::Repair,
+        FunscriptError,
+    };
+
+    use super::{
+        ChapterSpan, Funscript, FunscriptAction, MetadataTime, PositionConvention, SaveOptions,
+    };
+
+    const WITH_METADATA: &str = include_str!("../fixtures/with_metadata.funscript");
+    const MILLISECOND_METADATA: &str = include_str!("../fixtures/millisecond_metadata.funscript");
+    const PER_AXIS_INVERTED: &str = include_str!("../fixtures/per_axis_inverted.funscript");
+
+    #[test]
+    fn test_round_trip_with_metadata() {
+        let mut funscript: Funscript = serde_json::from_str(WITH_METADATA).unwrap();
+        funscript.fixup();
+        let saved = funscript.to_json_string().unwrap();
+
+        let original: Value = serde_json::from_str(WITH_METADATA).unwrap();
+        let saved: Value = serde_json::from_str(&saved).unwrap();
+        assert_eq!(original, saved);
+    }
+
+    #[test]
+    fn test_computed_range_not_written() {
+        let text = r#"{"actions":[{"at":0,"pos":10},{"at":100,"pos":90}],"version":"1.0"}"#;
+        let mut funscript: Funscript = serde_json::from_str(text).unwrap();
+        funscript.fixup();
+        assert_eq!(funscript.range, 100);
+
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(saved, serde_json::from_str::<Value>(text).unwrap());
+
+        let saved: Value = serde_json::from_str(
+            &funscript
+                .to_json_string_with_options(&SaveOptions {
+                    write_computed_range: true,
+                })
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(saved["range"], 100);
+    }
+
+    #[test]
+    fn test_known_fields_win() {
+        let mut funscript: Funscript =
+            serde_json::from_str(r#"{"actions":[],"range":90}"#).unwrap();
+        funscript.unknown["range"] = Value::from(50);
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(saved["range"], 90);
+    }
+
+    #[test]
+    fn test_metadata_text_times() {
+        let funscript: Funscript = serde_json::from_str(WITH_METADATA).unwrap();
+        let metadata = funscript.metadata.as_ref().unwrap();
+        assert_eq!(metadata.creator.as_deref(), Some("someone"));
+        assert_eq!(
+            metadata.duration.as_ref().and_then(|d| d.as_f64()),
+            Some(2.0)
+        );
+        assert!(metadata.unknown.contains_key("video_url"));
+        assert_eq!(
+            funscript.chapters(),
+            vec![ChapterSpan {
+                name: Some("Warm up".to_owned()),
+                range: 0..1800,
+            }]
+        );
+        let bookmarks = metadata.bookmarks.as_ref().unwrap();
+        assert_eq!(bookmarks[1].time.milliseconds().unwrap(), 1333);
+    }
+
+    #[test]
+    fn test_metadata_millisecond_times() {
+        let funscript: Funscript = serde_json::from_str(MILLISECOND_METADATA).unwrap();
+        assert_eq!(
+            funscript.chapters(),
+            vec![
+                ChapterSpan {
+                    name: Some("Slow".to_owned()),
+                    range: 0..61_500,
+                },
+                ChapterSpan {
+                    name: None,
+                    range: 61_500..3_723_456,
+                },
+            ]
+        );
+        let bookmarks = funscript
+            .metadata
+            .as_ref()
+            .unwrap()
+            .bookmarks
+            .as_ref()
+            .unwrap();
+        assert_eq!(bookmarks[0].time, MetadataTime::Milliseconds(30_000));
+
+        // Round trip keeps each time in its original form
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(
+            saved,
+            serde_json::from_str::<Value>(MILLISECOND_METADATA).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_no_metadata() {
+        let funscript: Funscript =
+            serde_json::from_str(r#"{"actions":[{"at":0,"pos":10}]}"#).unwrap();
+        assert!(funscript.metadata.is_none());
+        assert!(funscript.chapters().is_empty());
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert!(saved.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_metadata_time_text() {
+        let millis = |text: &str| MetadataTime::Text(text.to_owned()).milliseconds();
+        assert_eq!(millis("00:01:23.456").unwrap(), 83_456);
+        assert_eq!(millis("01:23.4").unwrap(), 83_400);
+        assert_eq!(millis("1:00:00").unwrap(), 3_600_000);
+        assert_eq!(millis("1500").unwrap(), 1500);
+        assert!(millis("1:2:3:4").is_err());
+        assert!(millis("soon").is_err());
+        // Too long to be a time, rather than wrapping around
+        assert!(millis("99999999:00").is_err());
+        assert!(millis("4294967:00").is_err());
+
+        let number = |value: Value| serde_json::from_value::<MetadataTime>(value).unwrap();
+        assert_eq!(number(json!(1500.4)).milliseconds().unwrap(), 1500);
+        assert!(number(json!(-5)).milliseconds().is_err());
+        assert!(number(json!(true)).milliseconds().is_err());
+    }
+
+    #[test]
+    fn test_bad_chapters() {
+        let text = r#"{
+            "actions": [{"at": 0, "pos": 10}],
+            "metadata": {"chapters": [
+                {"name": "No end", "startTime": 0},
+                {"name": "Fractional", "startTime": 1000.5, "endTime": "00:00:02.5"},
+                {"name": "Nonsense", "startTime": null, "endTime": {"at": 5}},
+                {"name": "Too long", "startTime": 0, "endTime": "99999999:00:00"}
+            ]}
+        }"#;
+        let funscript: Funscript = serde_json::from_str(text).unwrap();
+        assert_eq!(
+            funscript.chapters(),
+            vec![ChapterSpan {
+                name: Some("Fractional".to_owned()),
+                range: 1001..2500,
+            }]
+        );
+
+        // Kept as they were, nonetheless
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(
+            saved["metadata"]["chapters"][2],
+            json!({"name": "Nonsense", "endTime": {"at": 5}})
+        );
+        assert_eq!(saved["metadata"]["chapters"][1]["startTime"], json!(1000.5));
+    }
+
+    #[test]
+    fn test_shift_multiscript() {
+        let text = r#"{
+            "actions": [{"at": 100, "pos": 0}, {"at": 300, "pos": 100}],
+            "axes": [{"id": "R0", "actions": [{"at": 50, "pos": 50}, {"at": 250, "pos": 0}]}]
+        }"#;
+        let mut funscript: Funscript = serde_json::from_str(text).unwrap();
+        funscript.shift_ms(-150);
+
+        let times = |actions: &[FunscriptAction]| -> Vec<(u32, u32)> {
+            actions.iter().map(|a| (a.at, a.pos)).collect()
+        };
+        assert_eq!(times(&funscript.actions), vec![(0, 0), (150, 100)]);
+        let axes = funscript.axes.as_ref().unwrap();
+        assert_eq!(axes[0].id, "R0");
+        assert_eq!(times(&axes[0].actions), vec![(0, 50), (100, 0)]);
+
+        // Axes survive a round trip
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(saved["axes"][0]["actions"][1]["at"], 100);
+    }
+
+    #[test]
+    fn test_per_axis_inverted() {
+        let mut funscript: Funscript = serde_json::from_str(PER_AXIS_INVERTED).unwrap();
+        funscript.fixup();
+        let axes = funscript.get_axes_funscripts();
+
+        let positions = |axis_kind: AxisKind| -> Vec<f32> {
+            axes[&axis_kind]
+                .normalised()
+                .map(|action| action.norm_pos)
+                .collect()
+        };
+        // The stroke is inverted, but the twist says it isn't
+        assert_eq!(positions(AxisKind::Stroke), vec![1.0, 0.0]);
+        assert_eq!(positions(AxisKind::Twist), vec![0.25, 0.75]);
+        // The roll doesn't say, so follows the stroke
+        assert_eq!(positions(AxisKind::Roll), vec![0.75, 0.25]);
+        // The surge has its own range, and was clamped to it rather than the stroke's
+        assert_eq!(axes[&AxisKind::Surge].range, 50);
+        assert_eq!(positions(AxisKind::Surge), vec![0.5, 0.0]);
+
+        // The per-axis fields survive a round trip, and are left out where absent
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        assert_eq!(saved["axes"][0]["inverted"], false);
+        assert!(saved["axes"][1].get("inverted").is_none());
+        assert_eq!(saved["axes"][2]["range"], 50);
+    }
+
+    #[test]
+    fn test_build_multiscript() {
+        let actions = |positions: &[u32]| -> Vec<FunscriptAction> {
+            positions
+                .iter()
+                .enumerate()
+                .map(|(idx, &pos)| FunscriptAction {
+                    at: idx as u32 * 100,
+                    pos,
+                })
+                .collect()
+        };
+
+        let mut funscript = Funscript::new(Vec::new());
+        funscript
+            .set_axis_actions(AxisKind::Stroke, actions(&[0, 100]))
+            .unwrap();
+        funscript
+            .set_axis_actions(AxisKind::Twist, actions(&[10, 20]))
+            .unwrap();
+        funscript
+            .set_axis_actions(AxisKind::Vibration, actions(&[50]))
+            .unwrap();
+        // Replaces the earlier twist rather than adding another
+        funscript
+            .set_axis_actions(AxisKind::Twist, actions(&[30, 40, 50]))
+            .unwrap();
+        funscript.fixup();
+
+        let saved: Value = serde_json::from_str(&funscript.to_json_string().unwrap()).unwrap();
+        let ids: Vec<&str> = saved["axes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|axis| axis["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["R0", "V0"]);
+
+        let mut reloaded: Funscript = serde_json::from_value(saved).unwrap();
+        reloaded.fixup();
+        let axes = reloaded.get_axes_funscripts();
+        assert_eq!(
+            axes.keys().copied().collect::<Vec<_>>(),
+            vec![AxisKind::Stroke, AxisKind::Twist, AxisKind::Vibration]
+        );
+        assert_eq!(axes[&AxisKind::Stroke].actions, actions(&[0, 100]));
+        assert_eq!(axes[&AxisKind::Twist].actions, actions(&[30, 40, 50]));
+        assert_eq!(axes[&AxisKind::Vibration].actions, actions(&[50]));
+        assert!(axes
+            .values()
+            .all(|axis| axis.range == 100 && !axis.inverted));
+    }
+
+    #[test]
+    fn test_large_multiscript_not_copied() {
+        let actions: Vec<FunscriptAction> = (0..500_000)
+            .map(|idx| FunscriptAction {
+                at: idx * 50,
+                pos: (idx % 2) * 100,
+            })
+            .collect();
+        let mut funscript = Funscript::new(actions.clone());
+        funscript.merge_axes(AxisKind::Twist, actions).unwrap();
+        funscript.fixup();
+
+        let axes = funscript.get_axes_funscripts();
+        assert!(std::ptr::eq(
+            axes[&AxisKind::Stroke].actions,
+            funscript.actions.as_slice()
+        ));
+        assert!(std::ptr::eq(
+            axes[&AxisKind::Twist].actions,
+            funscript.axes.as_ref().unwrap()[0].actions.as_slice()
+        ));
+
+        // Lazily normalised, the same as all at once
+        let eager = normalised_from_funscript(&funscript);
+        assert_eq!(axes[&AxisKind::Stroke].normalised().count(), 500_000);
+        assert!(axes[&AxisKind::Twist]
+            .normalised()
+            .zip(&eager)
+            .all(|(lazy, eager)| lazy.at == eager.at && lazy.norm_pos == eager.norm_pos));
+    }
+
+    #[test]
+    fn test_slice_chapter() {
+        let mut funscript: Funscript = serde_json::from_str(WITH_METADATA).unwrap();
+        funscript.fixup();
+        let chapter = funscript.chapters()[0].range.clone();
+        let sliced = funscript.slice_range(chapter.start, chapter.end);
+
+        assert_eq!(sliced.actions.first().unwrap().at, 0);
+        assert_eq!(
+            sliced.actions.last().unwrap().at,
+            chapter.end - chapter.start
+        );
+        assert!(sliced.chapters().is_empty());
+        assert_eq!(sliced.range, funscript.range);
+
+        let text = r#"{
+            "actions": [{"at": 0, "pos": 0}, {"at": 1000, "pos": 100}, {"at": 2000, "pos": 0}],
+            "axes": [{"id": "R0", "actions": [{"at": 0, "pos": 50}, {"at": 2000, "pos": 100}]}]
+        }"#;
+        let mut funscript: Funscript = serde_json::from_str(text).unwrap();
+        let sliced = funscript.slice_range(500, 1500);
+        let positions = |actions: &[FunscriptAction]| -> Vec<(u32, u32)> {
+            actions.iter().map(|a| (a.at, a.pos)).collect()
+        };
+        assert_eq!(
+            positions(&sliced.actions),
+            vec![(0, 50), (500, 100), (1000, 50)]
+        );
+        assert_eq!(
+            positions(&sliced.axes.as_ref().unwrap()[0].actions),
+            vec![(0, 63), (1000, 88)]
+        );
+
+        funscript.exclude_ranges(&[0..250, 750..1250]);
+        assert_eq!(
+            positions(&funscript.actions),
+            vec![(0, 0), (250, 25), (750, 75), (1250, 75), (2000, 0)]
+        );
+        assert_eq!(
+            positions(&funscript.axes.as_ref().unwrap()[0].actions),
+            vec![(0, 50), (250, 56), (750, 69), (1250, 81), (2000, 100)]
+        );
+    }
+
+    fn repaired(text: &str) -> (Funscript, Vec<Repair>) {
+        let mut funscript: Funscript = serde_json::from_str(text).unwrap();
+        let repairs = funscript
+            .validate_and_repair()
+            .into_iter()
+            .map(|report| report.repair)
+            .collect();
+        (funscript, repairs)
+    }
+
+    fn positions(funscript: &Funscript) -> Vec<(u32, u32)> {
+        funscript.actions.iter().map(|a| (a.at, a.pos)).collect()
+    }
+
+    #[test]
+    fn test_repair_unsorted() {
+        let (funscript, repairs) =
+            repaired(include_str!("../fixtures/malformed/unsorted.funscript"));
+        assert_eq!(repairs, vec![Repair::Sorted]);
+        assert_eq!(
+            positions(&funscript),
+            vec![(0, 0), (500, 100), (1000, 0), (1500, 100)]
+        );
+    }
+
+    #[test]
+    fn test_repair_duplicates() {
+        let (funscript, repairs) =
+            repaired(include_str!("../fixtures/malformed/duplicates.funscript"));
+        assert_eq!(
+            repairs,
+            vec![
+                // Duplicates needn't be next to each other
+                Repair::Sorted,
+                Repair::MergedDuplicates { at: 500, count: 3 },
+                Repair::MergedDuplicates { at: 1000, count: 2 },
+            ]
+        );
+        assert_eq!(positions(&funscript), vec![(0, 0), (500, 80), (1000, 10)]);
+    }
+
+    #[test]
+    fn test_repair_out_of_range() {
+        let (funscript, repairs) =
+            repaired(include_str!("../fixtures/malformed/out_of_range.funscript"));
+        assert_eq!(
+            repairs,
+            vec![
+                Repair::ClampedPosition {
+                    at: 0,
+                    pos: -10.0,
+                    clamped_to: 0
+                },
+                Repair::ClampedPosition {
+                    at: 500,
+                    pos: 120.0,
+                    clamped_to: 100
+                },
+            ]
+        );
+        assert_eq!(positions(&funscript), vec![(0, 0), (500, 100), (1000, 50)]);
+    }
+
+    #[test]
+    fn test_repair_garbage() {
+        let (funscript, repairs) =
+            repaired(include_str!("../fixtures/malformed/garbage.funscript"));
+        let dropped: Vec<usize> = repairs
+            .iter()
+            .map(|repair| match repair {
+                Repair::DroppedInvalidAction { index, .. } => *index,
+                other => panic!("unexpected repair {other:?}"),
+            })
+            .collect();
+        assert_eq!(dropped, vec![1, 2, 3, 5]);
+        // Numbers written as strings are fine, as are fractions
+        assert_eq!(positions(&funscript), vec![(0, 10), (1000, 90), (1500, 51)]);
+    }
+
+    #[test]
+    fn test_timestamp_representations() {
+        let parse = |text: &str| {
+            let funscript = Funscript::from_slice(text.as_bytes()).unwrap();
+            let axis = funscript.axes.as_ref().unwrap()[0].actions.clone();
+            (positions(&funscript), axis)
+        };
+        let integer = parse(include_str!("../fixtures/timestamps/integer.funscript"));
+        assert_eq!(integer.0, vec![(0, 10), (1235, 90), (2500, 51)]);
+        // Fractions round half away from zero
+        assert_eq!(
+            parse(include_str!("../fixtures/timestamps/float.funscript")),
+            integer
+        );
+        assert_eq!(
+            parse(include_str!("../fixtures/timestamps/string.funscript")),
+            integer
+        );
+
+        for bad in [r#"-1"#, r#"4294967296"#, r#""soon""#, r#"null"#, r#"1e12"#] {
+            let text = format!(r#"{{"at": {bad}, "pos": 0}}"#);
+            assert!(
+                serde_json::from_str::<FunscriptAction>(&text).is_err(),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_repair_multiscript_axes() {
+        let text = r#"{
+            "actions": [{"at": 0, "pos": 0}],
+            "range": 100,
+            "axes": [{"id": "R0", "actions": [{"at": 500, "pos": 200}, {"at": 0, "pos": 50}]}]
+        }"#;
+        let mut funscript: Funscript = serde_json::from_str(text).unwrap();
+        let reports = funscript.validate_and_repair();
+        assert_eq!(reports.len(), 2);
+        assert!(reports
+            .iter()
+            .all(|report| report.axis.as_deref() == Some("R0")));
+        let axis = &funscript.axes.as_ref().unwrap()[0];
+        assert_eq!(
+            axis.actions,
+            vec![
+                FunscriptAction { at: 0, pos: 50 },
+                FunscriptAction { at: 500, pos: 100 }
+            ]
+        );
+    }
+
+    /// The normalised positions of each axis, after fixing up.
+    fn normalised_axes(text: &str) -> Vec<(AxisKind, Vec<f32>)> {
+        let mut funscript = Funscript::from_slice(text.as_bytes()).unwrap();
+        funscript.fixup();
+        funscript
+            .get_axes_funscripts()
+            .into_iter()
+            .map(|(kind, axis)| (kind, axis.normalised().map(|a| a.norm_pos).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_position_conventions() {
+        const V1: &str = include_str!("../fixtures/versions/v1.funscript");
+        const V2: &str = include_str!("../fixtures/versions/v2.funscript");
+        assert_eq!(normalised_axes(V1), normalised_axes(V2));
+        assert_eq!(
+            normalised_axes(V2)[0],
+            (AxisKind::Stroke, vec![0.0, 0.25, 1.0])
+        );
+
+        let v2 = Funscript::from_slice(V2.as_bytes()).unwrap();
+        assert_eq!(v2.position_convention(), PositionConvention::Normalised);
+        let v2_json: Value = serde_json::from_str(&v2.to_json_string().unwrap()).unwrap();
+        assert_eq!(v2_json["actions"][1]["pos"], 0.25);
+        assert_eq!(v2_json["axes"][0]["actions"][0]["pos"], 0.5);
+        assert_eq!(v2_json["version"], "2.0");
+        assert!(v2_json.get("range").is_none());
+        assert!(v2_json["axes"][0].get("range").is_none());
+
+        let v1 = Funscript::from_slice(V1.as_bytes()).unwrap();
+        assert_eq!(v1.position_convention(), PositionConvention::Integer);
+        let v1_json: Value = serde_json::from_str(&v1.to_json_string().unwrap()).unwrap();
+        assert_eq!(v1_json["actions"][1]["pos"], 25);
+        assert_eq!(v1_json["range"], 100);
+    }
+
+    #[test]
+    fn test_mixed_position_conventions() {
+        for text in [
+            include_str!("../fixtures/versions/mixed.funscript"),
+            include_str!("../fixtures/versions/mixed_v2.funscript"),
+        ] {
+            let axes = normalised_axes(text);
+            let axis = |kind| axes.iter().find(|(k, _)| *k == kind).unwrap().1.clone();
+            assert_eq!(axis(AxisKind::Stroke), vec![0.0, 0.25, 1.0], "{text}");
+            assert_eq!(
+                axis(AxisKind::from_tcode_name("R0").unwrap()),
+                vec![0.5, 0.75]
+            );
+            assert_eq!(
+                axis(AxisKind::from_tcode_name("R1").unwrap()),
+                vec![0.1, 0.9]
+            );
+
+            // Each axis is saved as it came, and reads back the same
+            let funscript = Funscript::from_slice(text.as_bytes()).unwrap();
+            let saved = funscript.to_json_string().unwrap();
+            let json: Value = serde_json::from_str(&saved).unwrap();
+            assert_eq!(json["axes"][0]["actions"][0]["pos"], 0.5, "{saved}");
+            assert_eq!(json["axes"][1]["actions"][0]["pos"], 10, "{saved}");
+            assert_eq!(normalised_axes(&saved), axes);
+        }
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let contents = include_bytes!("../fixtures/malformed/lenient.funscript");
+        assert!(Funscript::from_slice(contents).is_err());
+
+        let mut funscript = Funscript::from_slice_lenient(contents).unwrap();
+        let repairs: Vec<Repair> = funscript
+            .validate_and_repair()
+            .into_iter()
+            .map(|report| report.repair)
+            .collect();
+        assert_eq!(
+            repairs,
+            vec![
+                Repair::StrippedByteOrderMark,
+                Repair::RemovedComments { count: 1 },
+                Repair::RemovedTrailingCommas { count: 3 },
+                Repair::ReplacedNonFiniteNumbers { count: 1 },
+                Repair::DroppedInvalidAction {
+                    index: 1,
+                    reason: "bad position null".to_owned()
+                },
+            ]
+        );
+        assert_eq!(positions(&funscript), vec![(0, 0), (1000, 100), (1500, 0)]);
+
+        // Valid JSON needs nothing tolerated
+        let mut strict = Funscript::from_slice_lenient(WITH_METADATA.as_bytes()).unwrap();
+        assert!(strict.validate_and_repair().is_empty());
+    }
+
+    #[test]
+    fn test_well_formed_needs_no_repairs() {
+        let (_, repairs) = repaired(WITH_METADATA);
+        assert!(repairs.is_empty());
+    }
+
+    #[test]
+    fn test_sanity_check() {
+        let funscript = |positions: &[(u32, u32)]| {
+            Funscript::builder()
+                .actions(positions.iter().copied())
+                .build_without_fixup()
+                .unwrap()
+        };
+
+        assert_eq!(
+            funscript(&[]).sanity_check(None),
+            vec![FunscriptError::Empty]
+        );
+        assert_eq!(
+            funscript(&[(0, 50)]).sanity_check(None),
+            vec![FunscriptError::SingleAction]
+        );
+        assert_eq!(
+            funscript(&[(0, 50), (100, 50)]).sanity_check(None),
+            vec![FunscriptError::NoMovement { pos: 50 }]
+        );
+
+        // A little beyond the end of the video is fine; hours beyond isn't
+        let script = funscript(&[(0, 0), (3_600_000, 100)]);
+        assert!(script.sanity_check(Some(3_590_000)).is_empty());
+        assert_eq!(
+            script.sanity_check(Some(600_000)),
+            vec![FunscriptError::ExtendsBeyondVideo { by_ms: 3_000_000 }]
+        );
+        assert_eq!(
+            FunscriptError::ExtendsBeyondVideo { by_ms: 3_000_000 }.to_string(),
+            "funscript goes on for 3000 s beyond the end of the video"
+        );
+
+        // A multiscript with only other axes isn't empty, but those axes count for the duration
+        let multiscript = Funscript::builder()
+            .axis(AxisKind::Twist, [(0, 0), (7_200_000, 100)])
+            .build_without_fixup()
+            .unwrap();
+        assert!(multiscript.sanity_check(None).is_empty());
+        assert_eq!(
+            multiscript.sanity_check(Some(0)),
+            vec![FunscriptError::ExtendsBeyondVideo { by_ms: 7_200_000 }]
+        );
+
+        let mut well_formed: Funscript = serde_json::from_str(WITH_METADATA).unwrap();
+        well_formed.fixup();
+        assert!(well_formed.sanity_check(None).is_empty());
+    }
+
+    #[test]
+    fn test_builder() {
+        let funscript = Funscript::builder()
+            .action(1000, 120)
+            .actions([(0, 0), (500, 50)])
+            .range(100)
+            .axis(AxisKind::Roll, [(500, 10), (0, 90)])
+            .build()
+            .unwrap();
+        assert_eq!(
+            funscript.actions,
+            vec![
+                FunscriptAction { at: 0, pos: 0 },
+                FunscriptAction { at: 500, pos: 50 },
+                FunscriptAction { at: 1000, pos: 100 },
+            ]
+        );
+        let axes = funscript.get_axes_funscripts();
+        assert_eq!(
+            axes[&AxisKind::Roll].actions,
+            vec![
+                FunscriptAction { at: 0, pos: 90 },
+                FunscriptAction { at: 500, pos: 10 },
+            ]
+        );
+
+        // Each axis only once
+        assert!(Funscript::builder()
+            .action(0, 0)
+            .axis(AxisKind::Stroke, [(0, 0)])
+            .build()
+            .is_err());
+        assert!(Funscript::builder()
+            .axis(AxisKind::Twist, [(0, 0)])
+            .axis(AxisKind::Twist, [(100, 100)])
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_normalised_round_trip() {
+        let mut funscript: Funscript = serde_json::from_str(WITH_METADATA).unwrap();
+        funscript.fixup();
+        let within_rounding = |a: &[NormalisedAction], b: &[NormalisedAction], range: u32| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| {
+                    a.at == b.at && (a.norm_pos - b.norm_pos).abs() <= 0.5 / range as f32 + 1e-6
+                })
+        };
+
+        let processed = resample(&normalised_from_funscript(&funscript), 100);
+        for range in [100, 255, 1000] {
+            let converted = Funscript::from_normalised(&processed, range);
+            assert_eq!((converted.range, converted.inverted), (range, false));
+            assert!(converted.metadata.is_none());
+            let once = normalised_from_funscript(&converted);
+            assert!(within_rounding(&processed, &once, range), "range {range}");
+            // and no further drift once rounded
+            let twice = Funscript::from_normalised(&once, range);
+            assert_eq!(twice.actions, converted.actions, "range {range}");
+        }
+
+        // In place, keeping the rest of the file, and its inversion
+        for inverted in [false, true] {
+            let mut replaced = funscript.clone();
+            replaced.inverted = inverted;
+            replaced.replace_actions_from_normalised(&processed);
+            assert!(within_rounding(
+                &processed,
+                &normalised_from_funscript(&replaced),
+                100
+            ));
+            assert_eq!(replaced.inverted, inverted);
+            let json = replaced.to_json_value(&SaveOptions::default()).unwrap();
+            assert_eq!(json["metadata"]["title"], "with metadata");
+            assert_eq!(json["version"], "1.0");
+            assert_eq!(
+                json["actions"][1],
+                serde_json::json!({"at": 100, "pos": if inverted { 40 } else { 60 }})
+            );
+        }
+    }
+}
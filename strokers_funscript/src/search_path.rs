@@ -1,23 +1,152 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    path::Path,
+};
 
+use eyre::Context;
 use strokers_core::AxisKind;
+use tracing::warn;
 
+use crate::{archive::load_funscript, schema::Funscript};
+
+/// Suffixes (before `.funscript`) that mark a funscript for a particular axis,
+/// including aliases used by other tools.
+/// Funscripts without one of these are for [`AxisKind::Stroke`].
 pub const EXTENSIONS_TO_AXIS_KINDS: &[(&str, AxisKind)] = &[
+    (".stroke", AxisKind::Stroke),
+    (".raw", AxisKind::Stroke),
     (".surge", AxisKind::Surge),
     (".sway", AxisKind::Sway),
     (".twist", AxisKind::Twist),
+    (".rotate", AxisKind::Twist),
     (".roll", AxisKind::Roll),
     (".pitch", AxisKind::Pitch),
-    // TODO others.
+    (".vib", AxisKind::Vibration),
+    (".vibe", AxisKind::Vibration),
+    (".valve", AxisKind::Valve),
+    (".suck", AxisKind::Suction),
+    (".suction", AxisKind::Suction),
+    (".lube", AxisKind::Lubricant),
 ];
 
 /// All discovered funscripts related to a given video.
 /// There is a 'main' cluster and possibly one or more 'override' clusters,
 /// letting you switch in alternative funscripts at will.
-#[derive(Clone, Debug)]
+///
+/// Override clusters are named by what follows the video's name in their file names,
+/// tidied up by [`normalise_override_name`]: `MyVideo.soft.funscript`,
+/// `MyVideo (Soft).funscript` and `MyVideo - soft.funscript` are all in the `soft` cluster.
+#[derive(Clone, Debug, Default)]
 pub struct FunscriptScan {
     pub main: FunscriptCluster,
     pub overrides: BTreeMap<String, FunscriptCluster>,
+    /// How closely the funscripts' names match the video's: the loosest match of any of them.
+    pub match_kind: MatchKind,
+}
+
+/// How a funscript's name was matched to the video's, from closest to loosest.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The funscript's name starts with the video's name (without its extension).
+    #[default]
+    Exact,
+    /// The names are the same once tidied up by [`normalise_video_name`].
+    FuzzyEqual,
+    /// Once tidied up, the funscript's name is the start of the video's,
+    /// e.g. `My Video.funscript` for `My.Video.Directors.Cut.mkv`.
+    FuzzyPrefix,
+}
+
+/// Words in video file names that describe the encoding rather than the video,
+/// so are ignored by [`normalise_video_name`].
+const ENCODING_WORDS: &[&str] = &[
+    "4k", "5k", "6k", "8k", "uhd", "fhd", "hd", "sd", "hdr", "hdr10", "sdr", "10bit", "8bit",
+    "h264", "h265", "x264", "x265", "hevc", "avc", "av1", "vp9", "aac", "web", "webdl", "webrip",
+];
+
+/// Whether a word is a resolution, like `2160p` or `1080i`.
+fn is_resolution(word: &str) -> bool {
+    word.strip_suffix(['p', 'i'])
+        .is_some_and(|digits| digits.len() >= 3 && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+impl FunscriptScan {
+    /// Adds the funscripts of `other` for any axes of any clusters that this scan doesn't
+    /// already have a funscript for.
+    pub fn merge_missing(&mut self, other: FunscriptScan) {
+        let clusters = std::iter::once((None, other.main)).chain(
+            other
+                .overrides
+                .into_iter()
+                .map(|(name, cluster)| (Some(name), cluster)),
+        );
+        for (name, cluster) in clusters {
+            if cluster.scripts.is_empty() {
+                continue;
+            }
+            let into = match name {
+                None => &mut self.main,
+                Some(name) => self.overrides.entry(name).or_default(),
+            };
+            for (axis_kind, filename) in cluster.scripts {
+                if let Entry::Vacant(entry) = into.scripts.entry(axis_kind) {
+                    entry.insert(filename);
+                    self.match_kind = self.match_kind.max(other.match_kind);
+                }
+            }
+        }
+    }
+
+    /// The names of the override clusters, in order.
+    pub fn override_names(&self) -> impl Iterator<Item = &str> {
+        self.overrides.keys().map(String::as_str)
+    }
+
+    /// The funscripts to use for the named override cluster, or the main cluster for `None`.
+    ///
+    /// Names are matched as normalised by [`normalise_override_name`], so `"Soft"` finds
+    /// the `soft` cluster. An override cluster uses the main cluster's funscripts for any
+    /// axes it doesn't have its own for. If there is no such cluster, the main cluster is
+    /// used, with a warning.
+    pub fn cluster(&self, name: Option<&str>) -> FunscriptCluster {
+        let Some(name) = name else {
+            return self.main.clone();
+        };
+        let name = normalise_override_name(name);
+        let Some(cluster) = self.overrides.get(&name) else {
+            warn!("no funscripts for {name:?}; using the main ones");
+            return self.main.clone();
+        };
+
+        let mut cluster = cluster.clone();
+        for (&axis_kind, filename) in &self.main.scripts {
+            cluster
+                .scripts
+                .entry(axis_kind)
+                .or_insert_with(|| filename.clone());
+        }
+        cluster
+    }
+}
+
+/// Tidies up the name of an override cluster as found in a file name, trimming the
+/// separators and brackets around it and making it lowercase, e.g. ` (Soft)` becomes `soft`.
+pub fn normalise_override_name(name: &str) -> String {
+    name.trim_matches(|c: char| c.is_whitespace() || ".-_()[]{}".contains(c))
+        .to_lowercase()
+}
+
+/// Tidies up a video's or funscript's name (without its extension) for fuzzy matching:
+/// lowercase, with punctuation turned into spaces and words describing the encoding
+/// (`2160p`, `4k`, `h265`, …) removed, e.g. `My.Video.2160p.h265` becomes `my video`.
+pub fn normalise_video_name(name: &str) -> String {
+    let lowercase = name.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .filter(|word| !ENCODING_WORDS.contains(word) && !is_resolution(word))
+        .collect();
+    words.join(" ")
 }
 
 /// A cluster of funscript files, one per axis.
@@ -26,6 +155,40 @@ pub struct FunscriptCluster {
     pub scripts: BTreeMap<AxisKind, String>,
 }
 
+impl FunscriptCluster {
+    /// Loads the funscripts of this cluster from `dir` (or archives in it)
+    /// and assembles them into one multiscript.
+    ///
+    /// Positions are standardised to a range of 100, not inverted.
+    /// The metadata and unknown fields of the stroke funscript, if any, are kept.
+    pub async fn into_multiscript(self, dir: &Path) -> eyre::Result<Funscript> {
+        let mut multiscript: Option<Funscript> = None;
+        let mut axes = Vec::with_capacity(self.scripts.len());
+
+        for (axis_kind, filename) in self.scripts {
+            let mut funscript = load_funscript(dir, &filename).await?;
+            funscript.fixup();
+            let actions = funscript.standardised_actions();
+            if axis_kind == AxisKind::Stroke {
+                funscript.actions = Vec::new();
+                funscript.axes = None;
+                multiscript = Some(funscript);
+            }
+            axes.push((axis_kind, actions, filename));
+        }
+
+        let mut multiscript = multiscript.unwrap_or_else(|| Funscript::new(Vec::new()));
+        multiscript.range = 100;
+        multiscript.inverted = false;
+        for (axis_kind, actions, filename) in axes {
+            multiscript
+                .merge_axes(axis_kind, actions)
+                .with_context(|| format!("failed to merge {filename:?}"))?;
+        }
+        Ok(multiscript)
+    }
+}
+
 /// Given a list of filenames in the directory alongside the video,
 /// search for funscripts that likely match the video.
 pub fn scan_for_funscripts(
@@ -35,35 +198,26 @@ pub fn scan_for_funscripts(
     let video_without_extension = video_name
         .rsplit_once('.')
         .map(|(a, _)| a)
-        .unwrap_or(&video_name);
+        .unwrap_or(video_name);
 
-    let mut scan = FunscriptScan {
-        main: Default::default(),
-        overrides: Default::default(),
-    };
+    let mut scan = FunscriptScan::default();
 
     for file in dir_listing_of_files {
         let Some(unextended) = file.strip_prefix(video_without_extension) else {
             continue;
         };
 
-        let Some(mut unextended) = unextended.strip_suffix(".funscript") else {
+        let Some(unextended) = strip_suffix_ignore_case(unextended, ".funscript") else {
             continue;
         };
 
-        let mut axis = AxisKind::Stroke;
+        let (unextended, axis) = split_axis_suffix(unextended, EXTENSIONS_TO_AXIS_KINDS);
+        let override_name = normalise_override_name(unextended);
 
-        for (axis_suffix, axis_kind) in EXTENSIONS_TO_AXIS_KINDS {
-            if let Some(new_unextended) = unextended.strip_suffix(axis_suffix) {
-                axis = *axis_kind;
-                unextended = new_unextended;
-            }
-        }
-
-        let cluster_to_add_to = if unextended.is_empty() {
+        let cluster_to_add_to = if override_name.is_empty() {
             &mut scan.main
         } else {
-            scan.overrides.entry(unextended.to_owned()).or_default()
+            scan.overrides.entry(override_name).or_default()
         };
 
         cluster_to_add_to.scripts.insert(axis, file.clone());
@@ -71,3 +225,711 @@ pub fn scan_for_funscripts(
 
     Ok(scan)
 }
+
+/// Like [`scan_for_funscripts`], but matching names loosely, for funscripts that are named
+/// a little differently to the video, e.g. `My Video.funscript` for `My.Video.2160p.h265.mkv`.
+///
+/// Names are compared once tidied up by [`normalise_video_name`]. A funscript matches if
+/// its name is the same as the video's, or the start of it (in whole words).
+/// If there are several for the same axis, the longest name wins, so a funscript that
+/// names the video exactly is preferred. Everything found is in the main cluster.
+///
+/// If there are funscripts matching exactly, they are returned instead, so this is only
+/// looser when it needs to be.
+pub fn scan_for_funscripts_fuzzy(
+    dir_listing_of_files: &Vec<String>,
+    video_name: &str,
+) -> eyre::Result<FunscriptScan> {
+    let scan = scan_for_funscripts(dir_listing_of_files, video_name)?;
+    if !scan.main.scripts.is_empty() || !scan.overrides.is_empty() {
+        return Ok(scan);
+    }
+
+    let video_without_extension = video_name
+        .rsplit_once('.')
+        .map(|(a, _)| a)
+        .unwrap_or(video_name);
+    let video_words = normalise_video_name(video_without_extension);
+
+    // The best funscript for each axis so far: (name length in words, match kind, file)
+    let mut best: BTreeMap<AxisKind, (usize, MatchKind, &String)> = BTreeMap::new();
+    for file in dir_listing_of_files {
+        let Some(unextended) = strip_suffix_ignore_case(file, ".funscript") else {
+            continue;
+        };
+        let (unextended, axis) = split_axis_suffix(unextended, EXTENSIONS_TO_AXIS_KINDS);
+        let words = normalise_video_name(unextended);
+        let match_kind = if words.is_empty() {
+            continue;
+        } else if words == video_words {
+            MatchKind::FuzzyEqual
+        } else if video_words
+            .strip_prefix(&words)
+            .is_some_and(|rest| rest.starts_with(' '))
+        {
+            MatchKind::FuzzyPrefix
+        } else {
+            continue;
+        };
+        let word_count = words.split(' ').count();
+        if best
+            .get(&axis)
+            .map_or(true, |&(best_count, _, _)| word_count > best_count)
+        {
+            best.insert(axis, (word_count, match_kind, file));
+        }
+    }
+
+    let mut scan = FunscriptScan::default();
+    for (axis, (_, match_kind, file)) in best {
+        scan.main.scripts.insert(axis, file.clone());
+        scan.match_kind = scan.match_kind.max(match_kind);
+    }
+    Ok(scan)
+}
+
+/// Like [`scan_for_funscripts`], but across several directories.
+///
+/// `listings` holds the files in each directory, as a path relative to the video's directory
+/// (empty for the video's own directory) and the list of filenames in it.
+/// The filenames in the returned scan are relative to the video's directory,
+/// e.g. `funscripts/video.funscript`.
+///
+/// If the same axis of the same cluster is found in more than one directory,
+/// the directory that comes first in `listings` wins.
+pub fn scan_for_funscripts_in_dirs(
+    listings: &[(String, Vec<String>)],
+    video_name: &str,
+) -> eyre::Result<FunscriptScan> {
+    scan_dirs(listings, video_name, scan_for_funscripts)
+}
+
+/// Like [`scan_for_funscripts_fuzzy`], but across several directories
+/// as for [`scan_for_funscripts_in_dirs`].
+///
+/// Each directory is scanned on its own, so funscripts matching exactly in one directory
+/// don't stop looser matches being found in another.
+pub fn scan_for_funscripts_in_dirs_fuzzy(
+    listings: &[(String, Vec<String>)],
+    video_name: &str,
+) -> eyre::Result<FunscriptScan> {
+    scan_dirs(listings, video_name, scan_for_funscripts_fuzzy)
+}
+
+/// Like [`scan_for_funscripts_in_dirs`] (or [`scan_for_funscripts_in_dirs_fuzzy`], if `fuzzy`),
+/// but for a library of funscripts kept apart from the videos, e.g. organised by studio.
+///
+/// The directories in `listings` can be anywhere, so are given as full paths.
+/// Rather than preferring earlier directories, the funscripts whose names match the video's
+/// most closely (see [`MatchKind`]) win, then those in directories earlier in `listings`.
+pub fn scan_library(
+    listings: &[(String, Vec<String>)],
+    video_name: &str,
+    fuzzy: bool,
+) -> eyre::Result<FunscriptScan> {
+    let scan_dir: fn(&Vec<String>, &str) -> eyre::Result<FunscriptScan> = if fuzzy {
+        scan_for_funscripts_fuzzy
+    } else {
+        scan_for_funscripts
+    };
+
+    let mut dir_scans = Vec::new();
+    for listing in listings {
+        let dir_scan = scan_dirs(std::slice::from_ref(listing), video_name, scan_dir)?;
+        if !dir_scan.main.scripts.is_empty() || !dir_scan.overrides.is_empty() {
+            dir_scans.push(dir_scan);
+        }
+    }
+    // Stable, so directories with equally good matches stay in order
+    dir_scans.sort_by_key(|dir_scan| dir_scan.match_kind);
+
+    let mut scan = FunscriptScan::default();
+    for dir_scan in dir_scans {
+        scan.merge_missing(dir_scan);
+    }
+    Ok(scan)
+}
+
+fn scan_dirs(
+    listings: &[(String, Vec<String>)],
+    video_name: &str,
+    scan_dir: impl Fn(&Vec<String>, &str) -> eyre::Result<FunscriptScan>,
+) -> eyre::Result<FunscriptScan> {
+    let mut scan = FunscriptScan::default();
+
+    for (relative_dir, filenames) in listings {
+        let mut dir_scan = scan_dir(filenames, video_name)?;
+        if !relative_dir.is_empty() {
+            let relative_dir = relative_dir.trim_end_matches('/');
+            let clusters =
+                std::iter::once(&mut dir_scan.main).chain(dir_scan.overrides.values_mut());
+            for cluster in clusters {
+                for filename in cluster.scripts.values_mut() {
+                    *filename = format!("{relative_dir}/{filename}");
+                }
+            }
+        }
+        scan.merge_missing(dir_scan);
+    }
+
+    Ok(scan)
+}
+
+/// Splits the axis suffix (e.g. `.twist` or `_twist`, ignoring case) off a funscript's name
+/// (without `.funscript`), returning the rest of the name and the axis.
+///
+/// The longest matching suffix is used, so that a short suffix can't shadow a longer one
+/// that ends the same way.
+fn split_axis_suffix<'a>(
+    unextended: &'a str,
+    suffixes: &[(&str, AxisKind)],
+) -> (&'a str, AxisKind) {
+    let mut best: Option<(usize, &'a str, AxisKind)> = None;
+    for &(axis_suffix, axis_kind) in suffixes {
+        let token = axis_suffix.trim_start_matches('.');
+        if best.is_some_and(|(best_len, _, _)| token.len() <= best_len) {
+            continue;
+        }
+        let Some(before_token) = strip_suffix_ignore_case(unextended, token) else {
+            continue;
+        };
+        if let Some(rest) = before_token
+            .strip_suffix('.')
+            .or_else(|| before_token.strip_suffix('_'))
+        {
+            best = Some((token.len(), rest, axis_kind));
+        }
+    }
+    match best {
+        Some((_, rest, axis_kind)) => (rest, axis_kind),
+        None => (unextended, AxisKind::Stroke),
+    }
+}
+
+/// Strips `suffix` from the end of `text`, ignoring ASCII case.
+fn strip_suffix_ignore_case<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let split_at = text.len().checked_sub(suffix.len())?;
+    if !text.is_char_boundary(split_at) {
+        return None;
+    }
+    let (rest, tail) = text.split_at(split_at);
+    tail.eq_ignore_ascii_case(suffix).then_some(rest)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use strokers_core::AxisKind;
+
+    use crate::schema::{Funscript, FunscriptAction, SaveOptions};
+
+    use super::{
+        normalise_video_name, scan_for_funscripts, scan_for_funscripts_fuzzy,
+        scan_for_funscripts_in_dirs, scan_for_funscripts_in_dirs_fuzzy, scan_library,
+        split_axis_suffix, FunscriptScan, MatchKind, EXTENSIONS_TO_AXIS_KINDS,
+    };
+
+    fn cluster_dir() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/cluster"))
+    }
+
+    async fn scan_cluster_dir(dir: &Path) -> FunscriptScan {
+        let mut listing = Vec::new();
+        let mut read_dir = tokio::fs::read_dir(dir).await.unwrap();
+        while let Some(entry) = read_dir.next_entry().await.unwrap() {
+            listing.push(entry.file_name().into_string().unwrap());
+        }
+        scan_for_funscripts(&listing, "video.mp4").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cluster_into_multiscript() {
+        let scan = scan_cluster_dir(cluster_dir()).await;
+        let cluster = scan.main.clone();
+        assert_eq!(cluster.scripts.len(), 3);
+
+        let multiscript = cluster
+            .clone()
+            .into_multiscript(cluster_dir())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = multiscript
+            .axes
+            .iter()
+            .flatten()
+            .map(|axis| axis.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["R0", "R1"]);
+        assert_eq!(
+            multiscript.metadata.as_ref().unwrap().creator.as_deref(),
+            Some("stroke author")
+        );
+
+        // Round trip through the writer, then split back into a file per axis and re-scan
+        let out_dir =
+            std::env::temp_dir().join(format!("strokers_multiscript_{}", std::process::id()));
+        tokio::fs::create_dir_all(&out_dir).await.unwrap();
+        let multiscript_path = out_dir.join("multiscript.funscript");
+        multiscript
+            .save_to_path(&multiscript_path, &SaveOptions::default())
+            .await
+            .unwrap();
+        let reloaded = Funscript::load_from_path(&multiscript_path).await.unwrap();
+        tokio::fs::remove_file(&multiscript_path).await.unwrap();
+
+        for (axis_kind, axis) in reloaded.get_axes_funscripts() {
+            let suffix = match axis_kind {
+                AxisKind::Stroke => "",
+                _ => {
+                    EXTENSIONS_TO_AXIS_KINDS
+                        .iter()
+                        .find(|(_, kind)| *kind == axis_kind)
+                        .unwrap()
+                        .0
+                }
+            };
+            let mut funscript = Funscript::new(axis.actions.to_vec());
+            funscript.inverted = axis.inverted;
+            funscript.range = axis.range;
+            funscript
+                .save_to_path(
+                    &out_dir.join(format!("video{suffix}.funscript")),
+                    &SaveOptions::default(),
+                )
+                .await
+                .unwrap();
+        }
+        let rescanned = scan_cluster_dir(&out_dir).await.main;
+        let remerged = rescanned.clone().into_multiscript(&out_dir).await;
+        tokio::fs::remove_dir_all(&out_dir).await.unwrap();
+        let remerged = remerged.unwrap();
+
+        assert_eq!(rescanned.scripts, cluster.scripts);
+        let axes = remerged.get_axes_funscripts();
+        for (axis_kind, filename) in &cluster.scripts {
+            let mut original = Funscript::load_from_path(&cluster_dir().join(filename))
+                .await
+                .unwrap();
+            original.fixup();
+            assert_eq!(
+                axes[axis_kind].actions,
+                original.standardised_actions(),
+                "{axis_kind:?}"
+            );
+        }
+        // The twist script has range 50, so is rescaled
+        assert_eq!(
+            axes[&AxisKind::Twist].actions[1],
+            FunscriptAction { at: 500, pos: 100 }
+        );
+    }
+
+    #[test]
+    fn test_naming_variants() {
+        // (file name, override cluster or "" for main, axis)
+        let table = [
+            // OFS
+            ("Video.funscript", "", AxisKind::Stroke),
+            ("Video.twist.funscript", "", AxisKind::Twist),
+            ("Video.roll.funscript", "", AxisKind::Roll),
+            // ScriptPlayer and friends, with odd capitalisation
+            ("Video.Twist.funscript", "", AxisKind::Twist),
+            ("Video.TWIST.Funscript", "", AxisKind::Twist),
+            ("Video.pitch.FUNSCRIPT", "", AxisKind::Pitch),
+            // XBVR-style underscores
+            ("Video_surge.funscript", "", AxisKind::Surge),
+            ("Video_Sway.funscript", "", AxisKind::Sway),
+            // Alternative scripts go in override clusters, whatever the axis naming
+            ("Video (hard).funscript", "hard", AxisKind::Stroke),
+            ("Video (hard).Roll.funscript", "hard", AxisKind::Roll),
+            ("Video (hard)_roll.funscript", "hard", AxisKind::Roll),
+            ("Video.soft.funscript", "soft", AxisKind::Stroke),
+            ("Video (Soft).twist.funscript", "soft", AxisKind::Twist),
+            ("Video - Soft.funscript", "soft", AxisKind::Stroke),
+            ("Video [soft]_surge.funscript", "soft", AxisKind::Surge),
+            ("Video_soft.funscript", "soft", AxisKind::Stroke),
+            ("Video (Hard Mode).funscript", "hard mode", AxisKind::Stroke),
+            // Aliases
+            ("Video.stroke.funscript", "", AxisKind::Stroke),
+            ("Video_raw.funscript", "", AxisKind::Stroke),
+            ("Video.rotate.funscript", "", AxisKind::Twist),
+            ("Video.vib.funscript", "", AxisKind::Vibration),
+            ("Video_Vibe.funscript", "", AxisKind::Vibration),
+            ("Video.valve.funscript", "", AxisKind::Valve),
+            ("Video.suck.funscript", "", AxisKind::Suction),
+            ("Video.suction.funscript", "", AxisKind::Suction),
+            ("Video.lube.funscript", "", AxisKind::Lubricant),
+            // Only the last suffix counts
+            ("Video.roll.twist.funscript", "roll", AxisKind::Twist),
+            // Not axis suffixes
+            ("Video.funtwist.funscript", "funtwist", AxisKind::Stroke),
+            ("Videotwist.funscript", "twist", AxisKind::Stroke),
+        ];
+
+        for (file, cluster, axis) in table {
+            let scan = scan_for_funscripts(&vec![file.to_owned()], "Video.mp4").unwrap();
+            let found = if cluster.is_empty() {
+                &scan.main
+            } else {
+                &scan.overrides[cluster]
+            };
+            assert_eq!(
+                found.scripts.get(&axis).map(String::as_str),
+                Some(file),
+                "{file}: {scan:?}"
+            );
+        }
+
+        // Neither other videos nor other files
+        let scan = scan_for_funscripts(
+            &vec![
+                "Other.funscript".to_owned(),
+                "Video.twist.json".to_owned(),
+                "Video.mp4".to_owned(),
+            ],
+            "Video.mp4",
+        )
+        .unwrap();
+        assert!(scan.main.scripts.is_empty() && scan.overrides.is_empty());
+    }
+
+    #[test]
+    fn test_normalise_video_name() {
+        let table = [
+            ("My.Video.2160p.h265", "my video"),
+            ("My Video", "my video"),
+            ("My_Video_[4K]_HEVC", "my video"),
+            ("my-video-1080p-x264-10bit", "my video"),
+            ("Studio - Scene 12 (2023) 720p", "studio scene 12 2023"),
+            ("Café.Scène.UHD", "café scène"),
+            // Only whole words are encoding words
+            ("Hdream.720", "hdream 720"),
+            ("2160p", ""),
+        ];
+        for (name, expected) in table {
+            assert_eq!(normalise_video_name(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_matching() {
+        // (video, files beside it, expected stroke funscript, match kind)
+        let table = [
+            (
+                "My.Video.2160p.h265.mkv",
+                vec!["My Video.funscript"],
+                Some("My Video.funscript"),
+                MatchKind::FuzzyEqual,
+            ),
+            (
+                "my_video_4k.mp4",
+                vec!["My.Video.funscript", "My.Video.twist.funscript"],
+                Some("My.Video.funscript"),
+                MatchKind::FuzzyEqual,
+            ),
+            (
+                "Studio - Scene Name - 1080p.mp4",
+                vec!["Studio Scene Name.funscript"],
+                Some("Studio Scene Name.funscript"),
+                MatchKind::FuzzyEqual,
+            ),
+            // The funscript only names the start of the video
+            (
+                "My.Video.Directors.Cut.2160p.mkv",
+                vec!["My Video.funscript"],
+                Some("My Video.funscript"),
+                MatchKind::FuzzyPrefix,
+            ),
+            // The closest name wins
+            (
+                "My.Video.Part.2.mkv",
+                vec![
+                    "My.funscript",
+                    "My Video Part 2.funscript",
+                    "My Video.funscript",
+                ],
+                Some("My Video Part 2.funscript"),
+                MatchKind::FuzzyEqual,
+            ),
+            // Exact matches come first, even if a fuzzy match would be closer
+            (
+                "My.Video.2160p.mkv",
+                vec!["My Video 2160p.funscript", "My.Video.2160p.funscript"],
+                Some("My.Video.2160p.funscript"),
+                MatchKind::Exact,
+            ),
+            // Whole words only
+            (
+                "My.Videos.mkv",
+                vec!["My Video.funscript"],
+                None,
+                MatchKind::Exact,
+            ),
+            (
+                "My.Video.mkv",
+                vec!["My Video Part 2.funscript", "4K.funscript"],
+                None,
+                MatchKind::Exact,
+            ),
+        ];
+
+        for (video, files, expected, match_kind) in table {
+            let files: Vec<String> = files.into_iter().map(str::to_owned).collect();
+            let scan = scan_for_funscripts_fuzzy(&files, video).unwrap();
+            assert_eq!(
+                scan.main.scripts.get(&AxisKind::Stroke).map(String::as_str),
+                expected,
+                "{video}: {scan:?}"
+            );
+            assert_eq!(scan.match_kind, match_kind, "{video}: {scan:?}");
+        }
+
+        // Axes are matched fuzzily too
+        let scan = scan_for_funscripts_fuzzy(
+            &vec![
+                "My Video.funscript".to_owned(),
+                "my_video.roll.funscript".to_owned(),
+            ],
+            "My.Video.720p.mp4",
+        )
+        .unwrap();
+        assert_eq!(
+            scan.main.scripts[&AxisKind::Roll],
+            "my_video.roll.funscript"
+        );
+
+        // Across directories, the loosest match is reported
+        let listings = vec![
+            (String::new(), vec!["My.Video.funscript".to_owned()]),
+            (
+                "scripts".to_owned(),
+                vec!["My Video.twist.funscript".to_owned()],
+            ),
+        ];
+        let scan = scan_for_funscripts_in_dirs_fuzzy(&listings, "My.Video.Extended.mp4").unwrap();
+        assert_eq!(
+            scan.main.scripts[&AxisKind::Twist],
+            "scripts/My Video.twist.funscript"
+        );
+        assert_eq!(scan.match_kind, MatchKind::FuzzyPrefix);
+    }
+
+    #[test]
+    fn test_choose_cluster() {
+        let scan = scan_for_funscripts(
+            &vec![
+                "Video.funscript".to_owned(),
+                "Video.twist.funscript".to_owned(),
+                "Video (Soft).funscript".to_owned(),
+                "Video.hard.funscript".to_owned(),
+                "Video.hard.twist.funscript".to_owned(),
+            ],
+            "Video.mp4",
+        )
+        .unwrap();
+        assert_eq!(
+            scan.override_names().collect::<Vec<_>>(),
+            vec!["hard", "soft"]
+        );
+
+        let scripts = |name| {
+            scan.cluster(name)
+                .scripts
+                .into_iter()
+                .collect::<Vec<(AxisKind, String)>>()
+        };
+        let owned = |pairs: &[(AxisKind, &str)]| {
+            pairs
+                .iter()
+                .map(|&(axis, file)| (axis, file.to_owned()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            scripts(None),
+            owned(&[
+                (AxisKind::Stroke, "Video.funscript"),
+                (AxisKind::Twist, "Video.twist.funscript")
+            ])
+        );
+        // The main twist fills in for the soft cluster's missing one
+        assert_eq!(
+            scripts(Some("Soft")),
+            owned(&[
+                (AxisKind::Stroke, "Video (Soft).funscript"),
+                (AxisKind::Twist, "Video.twist.funscript")
+            ])
+        );
+        assert_eq!(
+            scripts(Some(" (hard) ")),
+            owned(&[
+                (AxisKind::Stroke, "Video.hard.funscript"),
+                (AxisKind::Twist, "Video.hard.twist.funscript")
+            ])
+        );
+        // Unknown clusters fall back to main
+        assert_eq!(scripts(Some("extreme")), scripts(None));
+    }
+
+    #[test]
+    fn test_scan_subdirectories() {
+        let listings = vec![
+            (
+                String::new(),
+                vec!["Video.mp4".to_owned(), "Video.funscript".to_owned()],
+            ),
+            (
+                "funscripts".to_owned(),
+                vec![
+                    "Video.funscript".to_owned(),
+                    "Video.twist.funscript".to_owned(),
+                    "Video (hard).funscript".to_owned(),
+                ],
+            ),
+            (
+                "scripts".to_owned(),
+                vec![
+                    "Video.twist.funscript".to_owned(),
+                    "Video.roll.funscript".to_owned(),
+                ],
+            ),
+        ];
+        let scan = scan_for_funscripts_in_dirs(&listings, "Video.mp4").unwrap();
+
+        // Beside the video beats subdirectories, and earlier subdirectories beat later ones
+        let main: Vec<(AxisKind, &str)> = scan
+            .main
+            .scripts
+            .iter()
+            .map(|(axis, filename)| (*axis, filename.as_str()))
+            .collect();
+        assert_eq!(
+            main,
+            vec![
+                (AxisKind::Stroke, "Video.funscript"),
+                (AxisKind::Twist, "funscripts/Video.twist.funscript"),
+                (AxisKind::Roll, "scripts/Video.roll.funscript"),
+            ]
+        );
+        assert_eq!(
+            scan.overrides["hard"].scripts[&AxisKind::Stroke],
+            "funscripts/Video (hard).funscript"
+        );
+    }
+
+    #[test]
+    fn test_scan_library() {
+        let listings = vec![
+            (
+                "/scripts/Studio A".to_owned(),
+                vec![
+                    "Other Video.funscript".to_owned(),
+                    "My Video.funscript".to_owned(),
+                    "My Video.twist.funscript".to_owned(),
+                ],
+            ),
+            (
+                "/scripts/Studio B/2024".to_owned(),
+                vec![
+                    "My.Video.2160p.funscript".to_owned(),
+                    "My.Video.2160p.roll.funscript".to_owned(),
+                ],
+            ),
+            ("/scripts/Empty".to_owned(), Vec::new()),
+        ];
+
+        // Exact matches beat fuzzy ones from earlier directories,
+        // which only fill in axes the exact ones don't have
+        let scan = scan_library(&listings, "My.Video.2160p.mkv", true).unwrap();
+        let main: Vec<(AxisKind, &str)> = scan
+            .main
+            .scripts
+            .iter()
+            .map(|(axis, filename)| (*axis, filename.as_str()))
+            .collect();
+        assert_eq!(
+            main,
+            vec![
+                (
+                    AxisKind::Stroke,
+                    "/scripts/Studio B/2024/My.Video.2160p.funscript"
+                ),
+                (
+                    AxisKind::Twist,
+                    "/scripts/Studio A/My Video.twist.funscript"
+                ),
+                (
+                    AxisKind::Roll,
+                    "/scripts/Studio B/2024/My.Video.2160p.roll.funscript"
+                ),
+            ]
+        );
+        assert_eq!(scan.match_kind, MatchKind::FuzzyEqual);
+
+        // Without fuzzy matching, only exact names are found
+        let scan = scan_library(&listings, "My Video.mp4", false).unwrap();
+        assert_eq!(
+            scan.main.scripts[&AxisKind::Stroke],
+            "/scripts/Studio A/My Video.funscript"
+        );
+        assert_eq!(scan.match_kind, MatchKind::Exact);
+        assert!(scan_library(&listings, "Another.mp4", true)
+            .unwrap()
+            .main
+            .scripts
+            .is_empty());
+    }
+
+    #[test]
+    fn test_longest_suffix_wins() {
+        // Whichever order the table is in, the longer suffix wins
+        let suffixes = [
+            (".roll", AxisKind::Roll),
+            (".reverse.roll", AxisKind::Twist),
+        ];
+        let reversed = [suffixes[1], suffixes[0]];
+        for suffixes in [&suffixes, &reversed] {
+            assert_eq!(
+                split_axis_suffix("video.reverse.roll", suffixes),
+                ("video", AxisKind::Twist)
+            );
+            assert_eq!(
+                split_axis_suffix("video_roll", suffixes),
+                ("video", AxisKind::Roll)
+            );
+            assert_eq!(
+                split_axis_suffix("video.other", suffixes),
+                ("video.other", AxisKind::Stroke)
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let mut funscript = Funscript::builder()
+            .action(0, 0)
+            .build_without_fixup()
+            .unwrap();
+        let actions = vec![FunscriptAction { at: 0, pos: 50 }];
+        assert!(funscript
+            .merge_axes(AxisKind::Stroke, actions.clone())
+            .is_err());
+        funscript
+            .merge_axes(AxisKind::Roll, actions.clone())
+            .unwrap();
+        assert!(funscript
+            .merge_axes(AxisKind::Roll, actions.clone())
+            .is_err());
+
+        funscript
+            .set_axis_actions(AxisKind::Stroke, actions.clone())
+            .unwrap();
+        funscript
+            .set_axis_actions(AxisKind::Roll, Vec::new())
+            .unwrap();
+        assert_eq!(funscript.actions, actions);
+        assert!(funscript.get_axes_funscripts()[&AxisKind::Roll]
+            .actions
+            .is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+//! Cutting time ranges out of actions, e.g. to play a single chapter or skip an intro.
+//!
+//! See [`Funscript::slice_range`](crate::schema::Funscript::slice_range) and
+//! [`Funscript::exclude_ranges`](crate::schema::Funscript::exclude_ranges).
+
+use std::ops::Range;
+
+use crate::schema::FunscriptAction;
+
+/// The position at the given time, interpolating linearly between the actions either side.
+/// Before the first action or after the last, the position is held.
+///
+/// `point` gives the time and position of an action, so that this works for any kind of action
+/// (e.g. [`action_point`] for a [`FunscriptAction`]). `None` if there are no actions.
+pub(crate) fn position_at<A>(
+    actions: &[A],
+    at: u32,
+    point: impl Fn(&A) -> (u32, f64),
+) -> Option<f64> {
+    let idx = actions.partition_point(|action| point(action).0 <= at);
+    let Some((previous_at, previous_pos)) = idx.checked_sub(1).map(|idx| point(&actions[idx]))
+    else {
+        return actions.first().map(|first| point(first).1);
+    };
+    let Some((next_at, next_pos)) = actions.get(idx).map(&point) else {
+        return Some(previous_pos);
+    };
+
+    let progress = (at - previous_at) as f64 / (next_at - previous_at) as f64;
+    Some(previous_pos + (next_pos - previous_pos) * progress)
+}
+
+/// The time and position of a funscript action, for [`position_at`].
+pub(crate) fn action_point(action: &FunscriptAction) -> (u32, f64) {
+    (action.at, action.pos as f64)
+}
+
+/// The actions from `start_ms` to `end_ms` (inclusive), re-based so that `start_ms` is zero.
+///
+/// There is always an action at each end, interpolated from the actions either side
+/// if there isn't one there already, so the position is right from the first millisecond.
+pub(crate) fn slice_actions(
+    actions: &[FunscriptAction],
+    start_ms: u32,
+    end_ms: u32,
+) -> Vec<FunscriptAction> {
+    let rounded_position_at =
+        |at: u32| position_at(actions, at, action_point).map(|pos| pos.round() as u32);
+    let (Some(start_pos), Some(end_pos)) =
+        (rounded_position_at(start_ms), rounded_position_at(end_ms))
+    else {
+        return Vec::new();
+    };
+    if end_ms < start_ms {
+        return Vec::new();
+    }
+
+    let mut out = vec![FunscriptAction {
+        at: 0,
+        pos: start_pos,
+    }];
+    out.extend(
+        actions
+            .iter()
+            .filter(|action| start_ms < action.at && action.at < end_ms)
+            .map(|action| FunscriptAction {
+                at: action.at - start_ms,
+                pos: action.pos,
+            }),
+    );
+    if end_ms > start_ms {
+        out.push(FunscriptAction {
+            at: end_ms - start_ms,
+            pos: end_pos,
+        });
+    }
+    out
+}
+
+/// Removes the actions strictly inside `range`, keeping all times as they are.
+///
+/// Where the range overlaps the actions, actions are added at its edges with the positions
+/// that were there, so the axis glides from one edge to the other instead of jumping.
+pub(crate) fn exclude_range(actions: &mut Vec<FunscriptAction>, range: &Range<u32>) {
+    let (Some(first), Some(last)) = (actions.first(), actions.last()) else {
+        return;
+    };
+    if range.end <= range.start || range.end < first.at || last.at < range.start {
+        return;
+    }
+
+    let edge = |at: u32| -> Option<FunscriptAction> {
+        let within_script = first.at <= at && at <= last.at;
+        let already_there = actions.iter().any(|action| action.at == at);
+        (within_script && !already_there).then(|| FunscriptAction {
+            at,
+            pos: position_at(actions, at, action_point).map_or(0, |pos| pos.round() as u32),
+        })
+    };
+    let start_edge = edge(range.start);
+    let end_edge = edge(range.end);
+
+    actions.retain(|action| !(range.start < action.at && action.at < range.end));
+    actions.extend(start_edge.into_iter().chain(end_edge));
+    actions.sort_by_key(|action| action.at);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::schema::FunscriptAction;
+
+    use super::{action_point, exclude_range, position_at, slice_actions};
+
+    fn actions(pairs: &[(u32, u32)]) -> Vec<FunscriptAction> {
+        pairs
+            .iter()
+            .map(|&(at, pos)| FunscriptAction { at, pos })
+            .collect()
+    }
+
+    #[test]
+    fn test_position_at() {
+        let script = actions(&[(1000, 0), (2000, 100), (3000, 50)]);
+        assert_eq!(position_at(&script, 0, action_point), Some(0.0));
+        assert_eq!(position_at(&script, 1000, action_point), Some(0.0));
+        assert_eq!(position_at(&script, 1250, action_point), Some(25.0));
+        assert_eq!(position_at(&script, 2000, action_point), Some(100.0));
+        assert_eq!(position_at(&script, 2500, action_point), Some(75.0));
+        assert_eq!(position_at(&script, 9999, action_point), Some(50.0));
+        assert_eq!(position_at(&[], 0, action_point), None);
+    }
+
+    #[test]
+    fn test_slice_interpolates_boundaries() {
+        let script = actions(&[(1000, 0), (2000, 100), (3000, 50), (4000, 0)]);
+
+        // Both ends between actions
+        assert_eq!(
+            slice_actions(&script, 1500, 3500),
+            actions(&[(0, 50), (500, 100), (1500, 50), (2000, 25)])
+        );
+        // Both ends on actions: nothing synthetic
+        assert_eq!(
+            slice_actions(&script, 2000, 3000),
+            actions(&[(0, 100), (1000, 50)])
+        );
+        // Beyond the actions, positions are held
+        assert_eq!(
+            slice_actions(&script, 0, 1500),
+            actions(&[(0, 0), (1000, 0), (1500, 50)])
+        );
+        assert_eq!(
+            slice_actions(&script, 5000, 6000),
+            actions(&[(0, 0), (1000, 0)])
+        );
+        // Degenerate ranges
+        assert_eq!(slice_actions(&script, 2500, 2500), actions(&[(0, 75)]));
+        assert!(slice_actions(&script, 3000, 2000).is_empty());
+        assert!(slice_actions(&[], 0, 1000).is_empty());
+    }
+
+    #[test]
+    fn test_exclude_stitches_edges() {
+        let mut script = actions(&[(0, 0), (1000, 100), (2000, 0), (3000, 100), (4000, 0)]);
+        exclude_range(&mut script, &(500..2500));
+        assert_eq!(
+            script,
+            actions(&[(0, 0), (500, 50), (2500, 50), (3000, 100), (4000, 0)])
+        );
+
+        // Edges on existing actions aren't duplicated
+        let mut script = actions(&[(0, 0), (1000, 100), (2000, 0), (3000, 100)]);
+        exclude_range(&mut script, &(1000..3000));
+        assert_eq!(script, actions(&[(0, 0), (1000, 100), (3000, 100)]));
+
+        // An intro before the actions start only needs the far edge
+        let mut script = actions(&[(1000, 0), (2000, 100), (3000, 0)]);
+        exclude_range(&mut script, &(0..1500));
+        assert_eq!(script, actions(&[(1500, 50), (2000, 100), (3000, 0)]));
+
+        // Ranges that miss the actions, or are empty, change nothing
+        let mut script = actions(&[(1000, 0), (2000, 100)]);
+        exclude_range(&mut script, &(3000..4000));
+        exclude_range(&mut script, &(1500..1500));
+        assert_eq!(script, actions(&[(1000, 0), (2000, 100)]));
+    }
+}
@@ -0,0 +1,446 @@
+//! Summaries of how a funscript moves, e.g. its speeds, intensity over time and heatmap.
+
+use serde::{Deserialize, Serialize};
+
+use crate::processing::NormalisedAction;
+
+/// Milliseconds in a minute, for [`Stats::intensity_per_minute`].
+const MINUTE_MS: u32 = 60_000;
+
+/// Summary statistics of a funscript, as shown by funscript sites.
+///
+/// Speeds are in full-scales per second, between consecutive actions.
+/// Actions at the same time as the previous one are ignored for speeds, as they
+/// would be infinitely fast.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Time from the first action to the last, in milliseconds.
+    pub duration_ms: u32,
+    pub action_count: usize,
+    /// Total distance travelled divided by the duration.
+    pub average_speed: f32,
+    pub peak_speed: f32,
+    /// Speeds of the movements between actions, weighted by how long each movement lasts.
+    pub speed_percentiles: SpeedPercentiles,
+    /// Number of times the direction of movement changes.
+    pub stroke_count: usize,
+    /// Distance travelled (in full-scales) in each minute since the start of the video.
+    pub intensity_per_minute: Vec<f32>,
+}
+
+/// Percentiles of speed, in full-scales per second.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpeedPercentiles {
+    pub p50: f32,
+    pub p90: f32,
+    pub p99: f32,
+}
+
+impl Stats {
+    /// Computes statistics for the given actions, which must be sorted by time.
+    ///
+    /// With fewer than 2 actions, everything but the action count is zero.
+    pub fn from_actions(actions: &[NormalisedAction]) -> Stats {
+        let mut stats = Stats {
+            action_count: actions.len(),
+            ..Default::default()
+        };
+        let (Some(first), Some(last)) = (actions.first(), actions.last()) else {
+            return stats;
+        };
+        stats.duration_ms = last.at.saturating_sub(first.at);
+
+        // (speed, duration in ms) of each movement
+        let mut movements: Vec<(f32, u32)> = Vec::with_capacity(actions.len());
+        let mut total_distance = 0.0;
+        let mut last_direction = 0.0;
+        for pair in actions.windows(2) {
+            let [from, to] = [pair[0], pair[1]];
+            let delta = to.norm_pos - from.norm_pos;
+            let duration_ms = to.at.saturating_sub(from.at);
+
+            if delta != 0.0 {
+                let direction = delta.signum();
+                if last_direction != 0.0 && direction != last_direction {
+                    stats.stroke_count += 1;
+                }
+                last_direction = direction;
+            }
+
+            total_distance += delta.abs();
+            add_intensity(&mut stats.intensity_per_minute, from, to);
+            if duration_ms > 0 {
+                movements.push((delta.abs() * 1000.0 / duration_ms as f32, duration_ms));
+            }
+        }
+
+        if stats.duration_ms > 0 {
+            stats.average_speed = total_distance * 1000.0 / stats.duration_ms as f32;
+        }
+        stats.peak_speed = movements
+            .iter()
+            .map(|&(speed, _)| speed)
+            .fold(0.0, f32::max);
+
+        movements.sort_by(|a, b| a.0.total_cmp(&b.0));
+        stats.speed_percentiles = SpeedPercentiles {
+            p50: weighted_percentile(&movements, 0.5),
+            p90: weighted_percentile(&movements, 0.9),
+            p99: weighted_percentile(&movements, 0.99),
+        };
+
+        stats
+    }
+}
+
+/// Adds the distance of the movement between two actions to the minutes it happens in.
+fn add_intensity(intensity: &mut Vec<f32>, from: NormalisedAction, to: NormalisedAction) {
+    let last_minute = (to.at / MINUTE_MS) as usize;
+    if intensity.len() <= last_minute {
+        intensity.resize(last_minute + 1, 0.0);
+    }
+    spread_movement(intensity, 0.0, MINUTE_MS as f64, from, to);
+}
+
+/// Adds the distance of the movement between two actions to the buckets it happens in,
+/// splitting it in proportion to the time spent in each bucket.
+///
+/// Bucket `n` covers `start_ms + n * width_ms` up to the next bucket; times beyond the last
+/// bucket count towards it. Instantaneous movements count towards the bucket they happen in.
+fn spread_movement(
+    buckets: &mut [f32],
+    start_ms: f64,
+    width_ms: f64,
+    from: NormalisedAction,
+    to: NormalisedAction,
+) {
+    let Some(last_bucket) = buckets.len().checked_sub(1) else {
+        return;
+    };
+    let bucket_of = |at: f64| (((at - start_ms) / width_ms).max(0.0) as usize).min(last_bucket);
+
+    let distance = (to.norm_pos - from.norm_pos).abs();
+    let (from_at, to_at) = (from.at as f64, to.at as f64);
+    if to_at <= from_at {
+        buckets[bucket_of(to_at)] += distance;
+        return;
+    }
+
+    let mut at = from_at;
+    while at < to_at {
+        let bucket = bucket_of(at);
+        let bucket_end = if bucket == last_bucket {
+            to_at
+        } else {
+            (start_ms + (bucket + 1) as f64 * width_ms).min(to_at)
+        };
+        buckets[bucket] += (distance as f64 * (bucket_end - at) / (to_at - from_at)) as f32;
+        at = bucket_end;
+    }
+}
+
+/// Divides the time from the first action to the last into `bucket_count` equal buckets
+/// and computes the intensity (position change per second) of each,
+/// normalised so that the most intense bucket is 1.0.
+///
+/// Movements spanning several buckets are shared between them in proportion to time.
+/// If nothing moves, all buckets are 0.0.
+pub fn heatmap(actions: &[NormalisedAction], bucket_count: usize) -> Vec<f32> {
+    let mut buckets = vec![0.0; bucket_count];
+    let (Some(first), Some(last)) = (actions.first(), actions.last()) else {
+        return buckets;
+    };
+    let duration_ms = last.at.saturating_sub(first.at);
+    if bucket_count == 0 || duration_ms == 0 {
+        return buckets;
+    }
+
+    // Buckets may be shorter than a millisecond for short scripts, so work in fractions.
+    let width_ms = duration_ms as f64 / bucket_count as f64;
+    for pair in actions.windows(2) {
+        spread_movement(&mut buckets, first.at as f64, width_ms, pair[0], pair[1]);
+    }
+
+    // All buckets are the same width, so distance is proportional to distance per second.
+    let max = buckets.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        for bucket in &mut buckets {
+            *bucket /= max;
+        }
+    }
+    buckets
+}
+
+/// The energy around `time_ms`: the mean absolute position change per second within the
+/// `window_ms` window centred on it.
+///
+/// Movements are shared out in proportion to the time they overlap the window, and
+/// instantaneous movements count if they happen within it. Before the first action and after
+/// the last nothing moves, so the energy falls off towards the ends of the script.
+///
+/// This looks at every action; to query the same actions many times, use an [`EnergyProfile`].
+pub fn energy_at(actions: &[NormalisedAction], time_ms: u32, window_ms: u32) -> f32 {
+    let Some((start, end)) = window(time_ms, window_ms) else {
+        return 0.0;
+    };
+    let mut distance = 0.0;
+    for pair in actions.windows(2) {
+        let [from, to] = [pair[0], pair[1]];
+        let delta = (to.norm_pos - from.norm_pos).abs() as f64;
+        let (from_at, to_at) = (from.at as f64, to.at as f64);
+        if to_at <= from_at {
+            if start < to_at && to_at <= end {
+                distance += delta;
+            }
+            continue;
+        }
+        let overlap = to_at.min(end) - from_at.max(start);
+        if overlap > 0.0 {
+            distance += delta * overlap / (to_at - from_at);
+        }
+    }
+    (distance * 1000.0 / window_ms as f64) as f32
+}
+
+/// The window (exclusive start, inclusive end) in milliseconds centred on `time_ms`.
+fn window(time_ms: u32, window_ms: u32) -> Option<(f64, f64)> {
+    let start = time_ms as f64 - window_ms as f64 / 2.0;
+    (window_ms > 0).then_some((start, start + window_ms as f64))
+}
+
+/// The total movement of some actions up to each of them, built once so that
+/// [`energy_at`](EnergyProfile::energy_at) can be answered in O(log n) for any window,
+/// e.g. for adaptive playback or drawing a heatmap as the video plays.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnergyProfile {
+    /// Times of the actions, in milliseconds.
+    times: Vec<u32>,
+    /// Distance travelled (in full-scales) from the first action up to and including each one.
+    cumulative: Vec<f64>,
+}
+
+impl EnergyProfile {
+    /// Builds the profile for the given actions, which must be sorted by time.
+    pub fn new(actions: &[NormalisedAction]) -> EnergyProfile {
+        let mut total = 0.0;
+        let cumulative = actions
+            .iter()
+            .enumerate()
+            .map(|(idx, action)| {
+                if let Some(previous) = idx.checked_sub(1).map(|idx| actions[idx]) {
+                    total += (action.norm_pos - previous.norm_pos).abs() as f64;
+                }
+                total
+            })
+            .collect();
+        EnergyProfile {
+            times: actions.iter().map(|action| action.at).collect(),
+            cumulative,
+        }
+    }
+
+    /// The same as [`energy_at`] on the actions the profile was built from.
+    pub fn energy_at(&self, time_ms: u32, window_ms: u32) -> f32 {
+        let Some((start, end)) = window(time_ms, window_ms) else {
+            return 0.0;
+        };
+        let distance = self.distance_until(end) - self.distance_until(start);
+        (distance * 1000.0 / window_ms as f64) as f32
+    }
+
+    /// The distance travelled up to and including the given time.
+    fn distance_until(&self, at: f64) -> f64 {
+        let idx = self.times.partition_point(|&time| time as f64 <= at);
+        let Some(previous) = idx.checked_sub(1) else {
+            return 0.0;
+        };
+        let Some(&next_at) = self.times.get(idx) else {
+            return self.cumulative[previous];
+        };
+        // `next_at` is strictly later, so there's nothing instantaneous to split
+        let previous_at = self.times[previous] as f64;
+        let progress = (at - previous_at) / (next_at as f64 - previous_at);
+        self.cumulative[previous] + (self.cumulative[idx] - self.cumulative[previous]) * progress
+    }
+}
+
+/// The speed below which `fraction` of the total time is spent.
+/// `movements` must be sorted by speed.
+fn weighted_percentile(movements: &[(f32, u32)], fraction: f32) -> f32 {
+    let total: u64 = movements.iter().map(|&(_, ms)| ms as u64).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let threshold = total as f64 * fraction as f64;
+    let mut seen = 0u64;
+    for &(speed, ms) in movements {
+        seen += ms as u64;
+        if seen as f64 >= threshold {
+            return speed;
+        }
+    }
+    movements.last().map(|&(speed, _)| speed).unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::processing::NormalisedAction;
+
+    use super::{energy_at, heatmap, EnergyProfile, SpeedPercentiles, Stats};
+
+    fn action(at: u32, norm_pos: f32) -> NormalisedAction {
+        NormalisedAction { at, norm_pos }
+    }
+
+    #[test]
+    fn test_degenerate() {
+        assert_eq!(Stats::from_actions(&[]), Stats::default());
+        let stats = Stats::from_actions(&[action(500, 0.5)]);
+        assert_eq!(stats.action_count, 1);
+        assert_eq!(stats.duration_ms, 0);
+        assert_eq!(stats.peak_speed, 0.0);
+        assert_eq!(stats.intensity_per_minute, Vec::<f32>::new());
+    }
+
+    #[test]
+    fn test_stats() {
+        let actions = [
+            action(1000, 0.0),
+            // 1 full-scale per second for 1 s
+            action(2000, 1.0),
+            // 4 full-scales per second for 0.25 s
+            action(2250, 0.0),
+            // Held still for 0.75 s
+            action(3000, 0.0),
+            // Instantaneous: ignored for speeds
+            action(3000, 0.5),
+            // Slowly, over a minute, 57 s of it in the first minute
+            action(63_000, 0.0),
+        ];
+        let stats = Stats::from_actions(&actions);
+        assert_eq!(stats.action_count, 6);
+        assert_eq!(stats.duration_ms, 62_000);
+        assert_eq!(stats.peak_speed, 4.0);
+        // Up, down, (hold), up, down
+        assert_eq!(stats.stroke_count, 3);
+        assert_close(stats.average_speed, 3.0 / 62.0);
+
+        // Most of the time is spent in the slow movement
+        let SpeedPercentiles { p50, p90, p99 } = stats.speed_percentiles;
+        assert_close(p50, 1.0 / 120.0);
+        assert_close(p90, 1.0 / 120.0);
+        assert_close(p99, 1.0);
+
+        assert_eq!(stats.intensity_per_minute.len(), 2);
+        assert_close(stats.intensity_per_minute[0], 2.975);
+        assert_close(stats.intensity_per_minute[1], 0.025);
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+    }
+
+    fn assert_all_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (&a, &e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_heatmap() {
+        // 500 ms buckets: 0.5, 0.5, 0 and 1.0 full-scales moved, so 1, 1, 0 and 2 per second
+        let actions = [
+            action(0, 0.0),
+            action(1000, 1.0),
+            action(1500, 1.0),
+            action(2000, 0.0),
+        ];
+        assert_all_close(&heatmap(&actions, 4), &[0.5, 0.5, 0.0, 1.0]);
+
+        // The first movement spans the boundary at 500 ms: 0.5 before it and 0.25 after
+        let actions = [action(0, 0.0), action(750, 0.75), action(1000, 0.75)];
+        assert_all_close(&heatmap(&actions, 2), &[1.0, 0.5]);
+
+        // Buckets are relative to the first action, and instantaneous moves count too
+        let actions = [
+            action(10_000, 0.0),
+            action(10_000, 1.0),
+            action(11_000, 1.0),
+            action(12_000, 0.5),
+        ];
+        assert_all_close(&heatmap(&actions, 2), &[1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_heatmap_degenerate() {
+        // Shorter than the bucket count: buckets are fractions of a millisecond
+        assert_all_close(
+            &heatmap(&[action(0, 0.0), action(2, 1.0)], 4),
+            &[1.0, 1.0, 1.0, 1.0],
+        );
+        assert_all_close(&heatmap(&[], 3), &[0.0, 0.0, 0.0]);
+        assert_all_close(&heatmap(&[action(5, 0.5)], 2), &[0.0, 0.0]);
+        assert_all_close(
+            &heatmap(&[action(0, 0.5), action(1000, 0.5)], 2),
+            &[0.0, 0.0],
+        );
+        assert!(heatmap(&[action(0, 0.0), action(1000, 1.0)], 0).is_empty());
+    }
+
+    #[test]
+    fn test_energy_at() {
+        let actions = [
+            action(1000, 0.0),
+            action(2000, 1.0),
+            action(2000, 0.5),
+            action(3000, 0.5),
+        ];
+        // Within the first movement: 1 full-scale per second
+        assert_close(energy_at(&actions, 1400, 800), 1.0);
+        // Half of it, plus the instantaneous move, over 1 s
+        assert_close(energy_at(&actions, 2000, 1000), 1.0);
+        // Holding still, or before the actions start
+        assert_close(energy_at(&actions, 2600, 500), 0.0);
+        assert_close(energy_at(&actions, 200, 400), 0.0);
+        // Windows reaching before zero are still divided by their full length
+        assert_close(energy_at(&actions, 1000, 4000), 0.375);
+        assert_close(energy_at(&actions, 1500, 0), 0.0);
+        assert_close(energy_at(&[], 1500, 1000), 0.0);
+    }
+
+    #[test]
+    fn test_energy_profile_matches_brute_force() {
+        let mut actions = Vec::new();
+        let mut at = 300;
+        for idx in 0..400u32 {
+            let norm_pos = (idx * 37 % 101) as f32 / 100.0;
+            actions.push(action(at, norm_pos));
+            // Some instantaneous moves, some long holds
+            at += [0, 40, 130, 275, 1200][(idx % 5) as usize];
+        }
+        let profile = EnergyProfile::new(&actions);
+        let end = actions.last().unwrap().at + 2000;
+        for window_ms in [1, 50, 333, 1000, 10_000] {
+            for time_ms in (0..end).step_by(97) {
+                let expected = energy_at(&actions, time_ms, window_ms);
+                let actual = profile.energy_at(time_ms, window_ms);
+                assert!(
+                    (actual - expected).abs() < 1e-3 * expected.max(1.0),
+                    "{time_ms} ms, {window_ms} ms window: {actual} != {expected}"
+                );
+            }
+        }
+
+        // Serialisable, e.g. for the OSD heatmap
+        let json = serde_json::to_string(&profile).unwrap();
+        let deserialised: EnergyProfile = serde_json::from_str(&json).unwrap();
+        for time_ms in (0..end).step_by(1009) {
+            assert_close(
+                deserialised.energy_at(time_ms, 1000),
+                profile.energy_at(time_ms, 1000),
+            );
+        }
+        assert_eq!(EnergyProfile::new(&[]).energy_at(1000, 1000), 0.0);
+    }
+}